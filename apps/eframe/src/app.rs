@@ -2,14 +2,15 @@ use std::sync::{Arc, RwLock};
 
 use eframe::Storage;
 use egui::ViewportCommand;
-use gb_core::{GameBoy, constants::DeviceModel, utils::button::Button};
+use gb_core::{GameBoy, constants::DeviceModel, rewind::RewindConfig, utils::button::Button};
 
 use crate::{
     audio::Audio,
     file_manager::{FileInfo, FileManager},
     gameboy_task::GameBoyTask,
     gui::{Event, Gui},
-    key_mappings::EguiKeyMappings,
+    key_mappings::{EguiKeyMappings, REWIND_KEY},
+    recorder::Recorder,
 };
 
 pub struct App {
@@ -17,6 +18,7 @@ pub struct App {
     file_manager: FileManager,
 
     audio: Option<Audio>,
+    recorder: Recorder,
     gui: Gui,
 }
 
@@ -27,14 +29,20 @@ impl App {
         device_model: DeviceModel,
         file_manager: Option<FileManager>,
     ) -> Self {
-        let gb_task = GameBoyTask::new(Arc::new(RwLock::new(GameBoy::new(device_model))));
+        let mut gb = GameBoy::new(device_model);
+        gb.enable_rewind(RewindConfig::default());
+
+        let gb_task = GameBoyTask::new(Arc::new(RwLock::new(gb)));
         let running = gb_task.running.clone();
+        let sync_mode = gb_task.sync_mode.clone();
+        let recorder = Recorder::default();
 
         let mut app = Self {
             gb_task,
             file_manager: file_manager.unwrap_or_default(),
             audio: None,
-            gui: Gui::new(&cc.egui_ctx, running),
+            recorder: recorder.clone(),
+            gui: Gui::new(&cc.egui_ctx, running, sync_mode, recorder),
         };
 
         if let Some(rom_file) = &app.file_manager.rom {
@@ -52,6 +60,8 @@ impl App {
                 ui.send_viewport_cmd(ViewportCommand::Close);
             }
 
+            *self.gb_task.rewinding.lock().unwrap() = i.key_down(REWIND_KEY);
+
             let mut gb = self.gb_task.gb.write().unwrap();
             for button in Button::ALL_CASES {
                 let key = button.mapped_to();
@@ -71,6 +81,23 @@ impl App {
                 }
 
                 Event::RomSelected(file) => self.load_from_file(storage, file),
+
+                #[cfg(not(target_arch = "wasm32"))]
+                Event::LinkConnected(link) => {
+                    self.gb_task
+                        .gb
+                        .write()
+                        .unwrap()
+                        .connect_link_port(Box::new(link));
+                    self.gui.link.set_status("Connected");
+                }
+
+                #[cfg(not(target_arch = "wasm32"))]
+                Event::LinkFailed(error) => {
+                    self.gui
+                        .link
+                        .set_status(format!("Unable to connect: {error}"));
+                }
             }
         }
     }
@@ -80,15 +107,25 @@ impl App {
 
         let rom = file.data.clone();
 
-        let audio = Audio::new();
+        let mut audio = Audio::new();
 
         let mut gb = self.gb_task.gb.write().unwrap();
 
-        gb.add_audio_callback(audio.get_callback());
+        let playback = audio.take_callback();
+        let recorder = self.recorder.clone();
+
+        gb.add_audio_callback(Box::new(move |samples| {
+            if let Some(playback) = &playback {
+                playback(samples);
+            }
+            recorder.record_mix(samples);
+        }));
+        gb.set_audio_sample_rate(audio.sample_rate() as usize);
         gb.load(bootrom, rom).unwrap();
         FileManager::load_battery(&mut gb, storage, &file);
         drop(gb);
 
+        *self.gb_task.audio_level.lock().unwrap() = Some(audio.level());
         self.audio = Some(audio);
         self.file_manager.rom = Some(file);
     }
@@ -1,5 +1,3 @@
-use std::sync::mpsc;
-
 use cpal::{
     Device,
     FromSample,
@@ -14,64 +12,99 @@ use cpal::{
     U24,
     traits::{DeviceTrait, HostTrait, StreamTrait},
 };
-use gb_core::components::apu::{AUDIO_BUFFER_SIZE, AUDIO_SAMPLE_RATE, Callback, StereoSample};
+use gb_core::components::apu::{Callback, StereoSample};
 use tracing::{error, info};
 
-type Sender = mpsc::SyncSender<StereoSample>;
-type Receiver = mpsc::Receiver<StereoSample>;
+pub use self::ring_buffer::Level;
+use self::ring_buffer::{Consumer, Producer, ring_buffer};
+
+mod ring_buffer;
+
+/// Samples that can be queued, in seconds.
+const BUFFER_DURATION: f64 = 0.25;
+
+/// Samples that should be queued, in seconds. This is the latency of the audio.
+pub const TARGET_LATENCY: f64 = 0.05;
+
+/// On underrun, the last sample fades out by this factor for each frame instead of dropping
+/// to silence at once, which would click.
+const UNDERRUN_FADE: f32 = 0.995;
 
 pub struct Audio {
     _stream: Stream,
-    sender: Sender,
+    producer: Option<Producer>,
+    level: Level,
+    sample_rate: u32,
 }
 
 impl Audio {
     pub fn new() -> Self {
-        let (stream, sender) = stream_setup_for();
+        let (stream, producer, sample_rate) = stream_setup_for();
         stream.play().unwrap();
 
         Self {
             _stream: stream,
-            sender,
+            level: producer.level(),
+            producer: Some(producer),
+            sample_rate,
         }
     }
 
-    pub fn get_callback(&self) -> Box<Callback> {
-        let sender = self.sender.clone();
+    /// Rate of the output device, the Game Boy has to produce samples at this rate.
+    pub const fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    /// Samples queued for the stream, to pace the emulation and adjust its rate.
+    pub fn level(&self) -> Level {
+        self.level.clone()
+    }
 
-        Box::new(move |buffer| {
-            for chunk in buffer.chunks_exact(2) {
-                #[cfg(not(target_arch = "wasm32"))]
-                let _ = sender.send([chunk[0], chunk[1]]);
+    /// The buffer has a single producer, so this returns `None` after the first call.
+    pub fn take_callback(&mut self) -> Option<Box<Callback>> {
+        let producer = self.producer.take()?;
 
-                // Bad
-                #[cfg(target_arch = "wasm32")]
-                let _ = sender.try_send([chunk[0], chunk[1]]);
+        Some(Box::new(move |buffer| {
+            for chunk in buffer.chunks_exact(2) {
+                // Overruns only happen if the emulation runs ahead, dropping is the best option.
+                producer.push([chunk[0], chunk[1]]);
             }
-        })
+        }))
     }
 }
 
-fn stream_setup_for() -> (Stream, Sender) {
-    let (_host, device, config) = host_device_setup();
+/// Samples of `duration` seconds at `sample_rate`.
+#[allow(
+    clippy::cast_precision_loss,
+    clippy::cast_possible_truncation,
+    clippy::cast_sign_loss
+)]
+pub fn samples_for(duration: f64, sample_rate: u32) -> usize {
+    (duration * sample_rate as f64) as usize
+}
 
-    let (stream, sender) = match config.sample_format() {
-        SampleFormat::I8 => make_stream::<i8>(&device, config.into()),
-        SampleFormat::I16 => make_stream::<i16>(&device, config.into()),
-        SampleFormat::I24 => make_stream::<I24>(&device, config.into()),
-        SampleFormat::I32 => make_stream::<i32>(&device, config.into()),
-        SampleFormat::I64 => make_stream::<i64>(&device, config.into()),
-        SampleFormat::U8 => make_stream::<u8>(&device, config.into()),
-        SampleFormat::U16 => make_stream::<u16>(&device, config.into()),
-        SampleFormat::U24 => make_stream::<U24>(&device, config.into()),
-        SampleFormat::U32 => make_stream::<u32>(&device, config.into()),
-        SampleFormat::U64 => make_stream::<u64>(&device, config.into()),
-        SampleFormat::F32 => make_stream::<f32>(&device, config.into()),
-        SampleFormat::F64 => make_stream::<f64>(&device, config.into()),
+fn stream_setup_for() -> (Stream, Producer, u32) {
+    let (_host, device, config) = host_device_setup();
+    let sample_rate = config.sample_rate();
+    let (producer, consumer) = ring_buffer(samples_for(BUFFER_DURATION, sample_rate));
+
+    let stream = match config.sample_format() {
+        SampleFormat::I8 => make_stream::<i8>(&device, &config.into(), consumer),
+        SampleFormat::I16 => make_stream::<i16>(&device, &config.into(), consumer),
+        SampleFormat::I24 => make_stream::<I24>(&device, &config.into(), consumer),
+        SampleFormat::I32 => make_stream::<i32>(&device, &config.into(), consumer),
+        SampleFormat::I64 => make_stream::<i64>(&device, &config.into(), consumer),
+        SampleFormat::U8 => make_stream::<u8>(&device, &config.into(), consumer),
+        SampleFormat::U16 => make_stream::<u16>(&device, &config.into(), consumer),
+        SampleFormat::U24 => make_stream::<U24>(&device, &config.into(), consumer),
+        SampleFormat::U32 => make_stream::<u32>(&device, &config.into(), consumer),
+        SampleFormat::U64 => make_stream::<u64>(&device, &config.into(), consumer),
+        SampleFormat::F32 => make_stream::<f32>(&device, &config.into(), consumer),
+        SampleFormat::F64 => make_stream::<f64>(&device, &config.into(), consumer),
         sample_format => panic!("Unsupported sample format '{sample_format}'"),
     };
 
-    (stream, sender)
+    (stream, producer, sample_rate)
 }
 
 fn host_device_setup() -> (Host, Device, SupportedStreamConfig) {
@@ -80,55 +113,52 @@ fn host_device_setup() -> (Host, Device, SupportedStreamConfig) {
     let device = host.default_output_device().unwrap();
     info!("Output device : {}", device.id().unwrap());
 
-    let mut configs = device.supported_output_configs().unwrap();
-
-    let config = configs
-        .find(|c| c.channels() == 2)
-        .unwrap()
-        .with_sample_rate(AUDIO_SAMPLE_RATE as u32);
+    // Devices don't all support 44100 Hz, the Game Boy follows the rate of the device instead.
+    let config = device.default_output_config().unwrap();
 
     info!("Output config : {config:?}");
 
     (host, device, config)
 }
 
-fn make_stream<T>(device: &Device, config: StreamConfig) -> (Stream, Sender)
+fn make_stream<T>(device: &Device, config: &StreamConfig, consumer: Consumer) -> Stream
 where
     T: SizedSample + FromSample<f32>,
 {
     let num_channels = config.channels as usize;
+    let mut last_sample = [0.0; 2];
 
-    let (sender, receiver) = mpsc::sync_channel::<StereoSample>(AUDIO_BUFFER_SIZE);
-
-    let stream = device
+    device
         .build_output_stream(
-            &config,
-            move |output: &mut [T], _| process_frame(output, num_channels, &receiver),
+            config,
+            move |output: &mut [T], _| {
+                process_frame(output, num_channels, &consumer, &mut last_sample);
+            },
             |err| error!("Unable to build output sound stream: {err}"),
             None,
         )
-        .unwrap();
-
-    (stream, sender)
+        .unwrap()
 }
 
-fn process_frame<SampleType>(output: &mut [SampleType], num_channels: usize, receiver: &Receiver)
-where
+fn process_frame<SampleType>(
+    output: &mut [SampleType],
+    num_channels: usize,
+    consumer: &Consumer,
+    last_sample: &mut StereoSample,
+) where
     SampleType: Sample + FromSample<f32>,
 {
     for frame in output.chunks_mut(num_channels) {
-        if let Ok(stereo_sample) = receiver.try_recv() {
-            let min_supported_channels = num_channels.min(stereo_sample.len());
-
-            for (i, sample) in stereo_sample
-                .into_iter()
-                .take(min_supported_channels)
-                .enumerate()
-            {
-                frame[i] = SampleType::from_sample(sample);
-            }
-        } else {
-            frame.fill(SampleType::from_sample(0.0));
+        let stereo_sample = consumer
+            .pop()
+            .unwrap_or_else(|| last_sample.map(|sample| sample * UNDERRUN_FADE));
+
+        *last_sample = stereo_sample;
+
+        frame.fill(SampleType::from_sample(0.0));
+
+        for (output, sample) in frame.iter_mut().zip(stereo_sample) {
+            *output = SampleType::from_sample(sample);
         }
     }
 }
@@ -0,0 +1,223 @@
+use std::sync::{
+    Arc,
+    atomic::{AtomicU64, AtomicUsize, Ordering},
+};
+
+use gb_core::components::apu::StereoSample;
+
+/// Creates a queue of samples between the emulation and the audio stream.
+///
+/// It never blocks: the producer drops samples when it's full and the consumer gets nothing
+/// when it's empty, so neither side waits for the other.
+pub fn ring_buffer(capacity: usize) -> (Producer, Consumer) {
+    let capacity = capacity.next_power_of_two();
+
+    let shared = Arc::new(Shared {
+        slots: (0..capacity).map(|_| AtomicU64::new(0)).collect(),
+        read: AtomicUsize::new(0),
+        written: AtomicUsize::new(0),
+    });
+
+    (
+        Producer {
+            shared: shared.clone(),
+        },
+        Consumer { shared },
+    )
+}
+
+struct Shared {
+    /// Both halves of each sample are packed together, so a slot is a single atomic.
+    slots: Box<[AtomicU64]>,
+
+    /// Samples read and written so far, wrapping around.
+    read: AtomicUsize,
+    written: AtomicUsize,
+}
+
+impl Shared {
+    fn capacity(&self) -> usize {
+        self.slots.len()
+    }
+
+    fn len(&self) -> usize {
+        // Loading `read` first keeps it behind `written`.
+        let read = self.read.load(Ordering::Acquire);
+        let written = self.written.load(Ordering::Acquire);
+
+        written.wrapping_sub(read).min(self.capacity())
+    }
+
+    fn slot(&self, position: usize) -> &AtomicU64 {
+        // The capacity is a power of two, so the positions can wrap around.
+        &self.slots[position & (self.capacity() - 1)]
+    }
+}
+
+pub struct Producer {
+    shared: Arc<Shared>,
+}
+
+impl Producer {
+    /// Returns `false` if the buffer is full, the sample is dropped.
+    pub fn push(&self, sample: StereoSample) -> bool {
+        let written = self.shared.written.load(Ordering::Relaxed);
+        let read = self.shared.read.load(Ordering::Acquire);
+
+        if written.wrapping_sub(read) == self.shared.capacity() {
+            return false;
+        }
+
+        self.shared
+            .slot(written)
+            .store(pack(sample), Ordering::Relaxed);
+        self.shared
+            .written
+            .store(written.wrapping_add(1), Ordering::Release);
+
+        true
+    }
+
+    pub fn level(&self) -> Level {
+        Level {
+            shared: self.shared.clone(),
+        }
+    }
+}
+
+pub struct Consumer {
+    shared: Arc<Shared>,
+}
+
+impl Consumer {
+    pub fn pop(&self) -> Option<StereoSample> {
+        let read = self.shared.read.load(Ordering::Relaxed);
+        let written = self.shared.written.load(Ordering::Acquire);
+
+        if read == written {
+            return None;
+        }
+
+        let sample = unpack(self.shared.slot(read).load(Ordering::Relaxed));
+        self.shared
+            .read
+            .store(read.wrapping_add(1), Ordering::Release);
+
+        Some(sample)
+    }
+}
+
+/// Watches how full the buffer is, from any thread.
+#[derive(Clone)]
+pub struct Level {
+    shared: Arc<Shared>,
+}
+
+impl Level {
+    /// Samples waiting to be played.
+    pub fn queued(&self) -> usize {
+        self.shared.len()
+    }
+}
+
+fn pack([left, right]: StereoSample) -> u64 {
+    (u64::from(right.to_bits()) << 32) | u64::from(left.to_bits())
+}
+
+fn unpack(bits: u64) -> StereoSample {
+    [
+        f32::from_bits(bits as u32),
+        f32::from_bits((bits >> 32) as u32),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use super::*;
+
+    #[allow(clippy::cast_precision_loss)]
+    fn sample(index: usize) -> StereoSample {
+        [index as f32, -(index as f32)]
+    }
+
+    #[test]
+    fn test_fifo_order_across_wraparound() {
+        let (producer, consumer) = ring_buffer(4);
+
+        // Each round starts further into the slots, so both positions wrap around.
+        for round in 0..10 {
+            for index in 0..3 {
+                assert!(producer.push(sample(round * 3 + index)));
+            }
+
+            for index in 0..3 {
+                assert_eq!(consumer.pop(), Some(sample(round * 3 + index)));
+            }
+        }
+
+        assert_eq!(consumer.pop(), None);
+    }
+
+    #[test]
+    fn test_full_and_empty() {
+        // Rounded up to a power of two.
+        let (producer, consumer) = ring_buffer(3);
+        let level = producer.level();
+
+        assert_eq!(consumer.pop(), None);
+        assert_eq!(level.queued(), 0);
+
+        for index in 0..4 {
+            assert!(producer.push(sample(index)));
+        }
+
+        // Full, the new sample is dropped and the queued ones are kept.
+        assert!(!producer.push(sample(4)));
+        assert_eq!(level.queued(), 4);
+
+        assert_eq!(consumer.pop(), Some(sample(0)));
+        assert!(producer.push(sample(4)));
+
+        for index in 1..5 {
+            assert_eq!(consumer.pop(), Some(sample(index)));
+        }
+
+        assert_eq!(consumer.pop(), None);
+        assert_eq!(level.queued(), 0);
+    }
+
+    /// The samples are copied bit for bit, so they compare exactly.
+    #[allow(clippy::float_cmp)]
+    #[test]
+    fn test_producer_consumer_threads() {
+        const SAMPLES: usize = 100_000;
+
+        let (producer, consumer) = ring_buffer(64);
+
+        let producing = thread::spawn(move || {
+            for index in 0..SAMPLES {
+                while !producer.push(sample(index)) {
+                    thread::yield_now();
+                }
+            }
+        });
+
+        // Every sample arrives once, in order, and with both halves from the same push.
+        let mut expected = 0;
+
+        while expected < SAMPLES {
+            match consumer.pop() {
+                Some(popped) => {
+                    assert_eq!(popped, sample(expected));
+                    expected += 1;
+                }
+                None => thread::yield_now(),
+            }
+        }
+
+        producing.join().unwrap();
+        assert_eq!(consumer.pop(), None);
+    }
+}
@@ -1,15 +1,59 @@
-use std::sync::{Arc, Mutex, RwLock};
+use std::{
+    fmt,
+    sync::{Arc, Mutex, RwLock},
+};
 
 use gb_core::{
     GameBoy,
+    components::apu::MAX_RATE_ADJUSTMENT,
     constants::{CPU_APPROX_M_CYCLES_PER_FRAME, CPU_CLOCK_RATE},
 };
+use tracing::error;
+
+use crate::{
+    audio::{Level, TARGET_LATENCY, samples_for},
+    sys::time::{Duration, Instant},
+};
+
+/// How long the audio stream can stop taking samples before the frames are timed without it,
+/// e.g. while the browser keeps the audio suspended until the page is clicked.
+const AUDIO_STALL_TIMEOUT: Duration = Duration::from_millis(200);
+
+/// How long to wait before checking the audio buffer again.
+const AUDIO_POLL_INTERVAL: Duration = Duration::from_millis(1);
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum SyncMode {
+    /// Runs a frame whenever the audio buffer runs low, so the audio never underruns.
+    #[default]
+    Audio,
+
+    /// Runs the frames at the refresh rate of the Game Boy,
+    /// nudging the audio rate to keep the buffer around its target.
+    Video,
+}
+
+impl SyncMode {
+    pub const ALL_CASES: [Self; 2] = [Self::Audio, Self::Video];
+}
+
+impl fmt::Display for SyncMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::Audio => "audio",
+            Self::Video => "video",
+        };
 
-use crate::sys::time::{Duration, Instant};
+        write!(f, "{name}")
+    }
+}
 
 pub struct GameBoyTask {
     pub gb: Arc<RwLock<GameBoy>>,
     pub running: Arc<Mutex<bool>>,
+    pub rewinding: Arc<Mutex<bool>>,
+    pub sync_mode: Arc<Mutex<SyncMode>>,
+    pub audio_level: Arc<Mutex<Option<Level>>>,
 }
 
 impl GameBoyTask {
@@ -17,6 +61,9 @@ impl GameBoyTask {
         let task = Self {
             gb,
             running: Arc::new(Mutex::new(false)),
+            rewinding: Arc::new(Mutex::new(false)),
+            sync_mode: Arc::new(Mutex::new(SyncMode::default())),
+            audio_level: Arc::new(Mutex::new(None)),
         };
 
         task.start();
@@ -27,6 +74,9 @@ impl GameBoyTask {
     fn start(&self) {
         let gb = self.gb.clone();
         let running = self.running.clone();
+        let rewinding = self.rewinding.clone();
+        let sync_mode = self.sync_mode.clone();
+        let audio_level = self.audio_level.clone();
 
         #[allow(clippy::cast_precision_loss)]
         let frame_time = Duration::from_secs_f64(
@@ -37,25 +87,100 @@ impl GameBoyTask {
             *running.lock().unwrap() = true;
             let mut next_frame = Instant::now();
 
+            let mut last_queued = 0;
+            let mut last_drained = Instant::now();
+
             loop {
-                if *running.lock().unwrap() {
+                let now = Instant::now();
+                let level = audio_level.lock().unwrap().clone();
+
+                if let Some(level) = &level {
+                    if level.queued() < last_queued {
+                        last_drained = now;
+                    }
+                }
+
+                let rewinding = *rewinding.lock().unwrap();
+
+                // Rewinding makes no samples, so it can't be paced by the audio.
+                let audio_clocked = *sync_mode.lock().unwrap() == SyncMode::Audio
+                    && level.is_some()
+                    && !rewinding
+                    && now - last_drained < AUDIO_STALL_TIMEOUT;
+
+                let due = audio_clocked || now >= next_frame;
+
+                if due && *running.lock().unwrap() {
                     let mut gb = gb.write().unwrap();
 
-                    if gb.cartridge_inserted() {
-                        gb.run_frame();
+                    // Waiting for a link partner would block inside the frame, with the lock held.
+                    if gb.cartridge_inserted() && gb.poll_link_port() {
+                        if rewinding {
+                            if let Err(err) = gb.rewind_frames(1) {
+                                error!("Unable to rewind: {err}");
+                            }
+                        } else if let Some(level) = &level {
+                            let target = samples_for(TARGET_LATENCY, gb.audio_sample_rate() as u32);
+
+                            if audio_clocked {
+                                gb.set_audio_rate_adjustment(0.0);
+
+                                // A single frame per poll, so the lock is released between frames
+                                // while the buffer fills up.
+                                if level.queued() < target {
+                                    gb.run_frame();
+                                    gb.flush_audio();
+                                }
+                            } else {
+                                gb.set_audio_rate_adjustment(rate_adjustment(
+                                    level.queued(),
+                                    target,
+                                ));
+
+                                gb.run_frame();
+                                gb.flush_audio();
+                            }
+                        } else {
+                            gb.run_frame();
+                        }
                     }
+                } else if due {
+                    // Lets a link partner know that this side is paused, not gone.
+                    gb.write().unwrap().poll_link_port();
                 }
 
-                next_frame += frame_time;
-                let now = Instant::now();
-                let remaining = next_frame - now;
+                if let Some(level) = &level {
+                    last_queued = level.queued();
+                }
 
-                if next_frame > now {
-                    crate::sys::thread::sleep(remaining).await;
+                if audio_clocked {
+                    // Keeps the timer from catching up on frames when it takes over.
+                    next_frame = Instant::now() + frame_time;
+                    crate::sys::thread::sleep(AUDIO_POLL_INTERVAL).await;
                 } else {
-                    next_frame = now;
+                    if due {
+                        next_frame += frame_time;
+                    }
+
+                    let now = Instant::now();
+                    let remaining = next_frame - now;
+
+                    if next_frame > now {
+                        crate::sys::thread::sleep(remaining).await;
+                    } else {
+                        next_frame = now;
+                    }
                 }
             }
         });
     }
 }
+
+/// Makes more samples when the buffer is below its target and fewer when it's above,
+/// so that it neither underruns nor overruns when the frames aren't paced by the audio.
+#[allow(clippy::cast_precision_loss)]
+fn rate_adjustment(queued: usize, target: usize) -> f64 {
+    let error = (target as f64 - queued as f64) / target as f64;
+
+    error.clamp(-1.0, 1.0) * MAX_RATE_ADJUSTMENT
+}
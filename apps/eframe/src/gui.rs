@@ -6,9 +6,14 @@ use std::sync::{
 
 use egui::{CentralPanel, MenuBar, Panel, ViewportCommand};
 use gb_core::GameBoy;
+#[cfg(not(target_arch = "wasm32"))]
+use gb_core::components::serial::link::tcp::TcpLink;
 
+#[cfg(not(target_arch = "wasm32"))]
+use self::link::Link;
 use self::{
     control::Control,
+    disassembly::Disassembly,
     palettes::Palettes,
     screen_area::ScreenArea,
     state::State,
@@ -16,12 +21,19 @@ use self::{
 };
 use crate::{
     file_manager::{FileInfo, FileType, file_picker_async},
+    gameboy_task::SyncMode,
     gui::{audio::Audio, rom_drop_area::RomDropArea},
+    recorder::Recorder,
 };
 
 pub enum Event {
     BootromSelected(FileInfo),
     RomSelected(FileInfo),
+
+    #[cfg(not(target_arch = "wasm32"))]
+    LinkConnected(TcpLink),
+    #[cfg(not(target_arch = "wasm32"))]
+    LinkFailed(String),
 }
 
 pub struct Gui {
@@ -30,6 +42,9 @@ pub struct Gui {
 
     pub audio: Audio,
     pub control: Control,
+    pub disassembly: Disassembly,
+    #[cfg(not(target_arch = "wasm32"))]
+    pub link: Link,
     pub palettes: Palettes,
     pub state: State,
     pub tiles: Tiles,
@@ -39,14 +54,22 @@ pub struct Gui {
 }
 
 impl Gui {
-    pub fn new(egui_ctx: &egui::Context, running: Arc<Mutex<bool>>) -> Self {
+    pub fn new(
+        egui_ctx: &egui::Context,
+        running: Arc<Mutex<bool>>,
+        sync_mode: Arc<Mutex<SyncMode>>,
+        recorder: Recorder,
+    ) -> Self {
         let (event_sender, event_receiver) = std::sync::mpsc::channel();
 
         Self {
             event_receiver,
             event_sender,
-            audio: Audio::default(),
+            audio: Audio::new(sync_mode, recorder),
             control: Control::new(running),
+            disassembly: Disassembly::default(),
+            #[cfg(not(target_arch = "wasm32"))]
+            link: Link::default(),
             palettes: Palettes::default(),
             state: State::default(),
             tiles: Tiles::new(egui_ctx),
@@ -77,13 +100,21 @@ impl Gui {
                         gb_ctx.reset();
                     }
 
+                    if ui.button("Power cycle").clicked() {
+                        gb_ctx.power_cycle();
+                    }
+
                     if ui.button("Quit").clicked() {
                         ui.send_viewport_cmd(ViewportCommand::Close);
                     }
                 });
 
+                #[cfg(not(target_arch = "wasm32"))]
+                Link::draw_menu(self, ui, gb_ctx);
+
                 Control::draw_manual_control_button(self, ui);
                 Control::draw_widget_toggle_button(self, ui);
+                Disassembly::draw_widget_toggle_button(self, ui);
                 State::draw_widget_toggle_button(self, ui);
                 Tiles::draw_widget_toggle_button(self, ui);
                 Palettes::draw_widget_toggle_button(self, ui);
@@ -92,6 +123,7 @@ impl Gui {
         });
 
         Control::draw(self, ui, gb_ctx);
+        Disassembly::draw(self, ui, gb_ctx);
         State::draw(self, ui, gb_ctx);
         Tiles::draw(self, ui, gb_ctx);
         Palettes::draw(self, ui, gb_ctx);
@@ -113,6 +145,9 @@ impl Gui {
 mod audio;
 mod components;
 mod control;
+mod disassembly;
+#[cfg(not(target_arch = "wasm32"))]
+mod link;
 mod palettes;
 mod rom_drop_area;
 mod screen_area;
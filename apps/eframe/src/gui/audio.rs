@@ -1,17 +1,35 @@
+use std::sync::{Arc, Mutex};
+
 use egui::Window;
 use gb_core::{
     GameBoy,
     components::{apu::Channels, memory::MemoryInterface},
 };
 
-use crate::gui::Gui;
+use crate::{
+    gameboy_task::SyncMode,
+    gui::Gui,
+    recorder::{Recorder, save_recording_async, save_vgm_async},
+};
 
 #[derive(Debug, Default)]
 pub struct Audio {
     opened: bool,
+    sync_mode: Arc<Mutex<SyncMode>>,
+    recorder: Recorder,
+    record_stems: bool,
 }
 
 impl Audio {
+    pub fn new(sync_mode: Arc<Mutex<SyncMode>>, recorder: Recorder) -> Self {
+        Self {
+            opened: false,
+            sync_mode,
+            recorder,
+            record_stems: false,
+        }
+    }
+
     pub fn draw_widget_toggle_button(ctx: &mut Gui, ui: &mut egui::Ui) {
         if ui.button("Audio").clicked() {
             ctx.audio.opened = !ctx.audio.opened;
@@ -26,6 +44,54 @@ impl Audio {
         Window::new("Audio")
             .open(&mut ctx.audio.opened)
             .show(ui, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label("Sync");
+
+                    let mut sync_mode = ctx.audio.sync_mode.lock().unwrap();
+
+                    for option in SyncMode::ALL_CASES {
+                        ui.radio_value(&mut *sync_mode, option, option.to_string());
+                    }
+                });
+
+                ui.separator();
+
+                ui.horizontal(|ui| {
+                    let recorder = &ctx.audio.recorder;
+
+                    if recorder.is_recording() {
+                        if ui.button("Stop recording").clicked()
+                            && let Some(recording) = recorder.stop(gb_ctx)
+                        {
+                            save_recording_async(recording);
+                        }
+                    } else {
+                        if ui.button("Record").clicked() {
+                            recorder.start(gb_ctx, ctx.audio.record_stems);
+                        }
+
+                        ui.checkbox(&mut ctx.audio.record_stems, "Channel stems");
+                    }
+                });
+
+                ui.horizontal(|ui| {
+                    if gb_ctx.is_recording_vgm() {
+                        if ui.button("Stop VGM").clicked()
+                            && let Some(vgm) = gb_ctx.stop_vgm_recording()
+                        {
+                            save_vgm_async(vgm);
+                        }
+
+                        if ui.button("Mark loop").clicked() {
+                            gb_ctx.mark_vgm_loop();
+                        }
+                    } else if ui.button("Record VGM").clicked() {
+                        gb_ctx.start_vgm_recording();
+                    }
+                });
+
+                ui.separator();
+
                 let apu = gb_ctx.memory_mut().apu_mut();
                 let channels = &mut apu.ui_channel_overrides;
 
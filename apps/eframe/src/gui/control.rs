@@ -1,7 +1,7 @@
 use std::sync::{Arc, Mutex};
 
 use egui::Window;
-use gb_core::GameBoy;
+use gb_core::{GameBoy, components::ppu::renderer::Renderer};
 
 use crate::gui::Gui;
 
@@ -55,6 +55,22 @@ impl Control {
                         gb_ctx.run_frame();
                     }
                 });
+
+                ui.separator();
+
+                ui.horizontal(|ui| {
+                    ui.label("Renderer");
+
+                    let mut renderer = gb_ctx.ppu_renderer();
+
+                    for option in Renderer::ALL_CASES {
+                        ui.radio_value(&mut renderer, option, option.to_string());
+                    }
+
+                    if renderer != gb_ctx.ppu_renderer() {
+                        gb_ctx.set_ppu_renderer(renderer);
+                    }
+                });
             });
     }
 }
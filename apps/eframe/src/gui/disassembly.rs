@@ -0,0 +1,92 @@
+use egui::{Align, Color32, FontId, RichText, ScrollArea, Window};
+use gb_core::{GameBoy, components::memory::MemoryInterface as _};
+use gb_opcode_info::disassembler::disassemble_around;
+
+use crate::gui::Gui;
+
+const LINES_BEFORE_PC: usize = 16;
+const LINES_AFTER_PC: usize = 48;
+
+#[derive(Debug)]
+pub struct Disassembly {
+    opened: bool,
+    follow_pc: bool,
+}
+
+impl Default for Disassembly {
+    fn default() -> Self {
+        Self {
+            opened: false,
+            follow_pc: true,
+        }
+    }
+}
+
+impl Disassembly {
+    pub fn draw_widget_toggle_button(ctx: &mut Gui, ui: &mut egui::Ui) {
+        if ui.button("Disassembly").clicked() {
+            ctx.disassembly.opened = !ctx.disassembly.opened;
+        }
+    }
+
+    pub fn draw(ctx: &mut Gui, ui: &egui::Ui, gb_ctx: &GameBoy) {
+        if !ctx.disassembly.opened {
+            return;
+        }
+
+        let follow_pc = &mut ctx.disassembly.follow_pc;
+
+        Window::new("Disassembly")
+            .open(&mut ctx.disassembly.opened)
+            .show(ui, |ui| {
+                ui.checkbox(follow_pc, "Follow PC");
+                ui.separator();
+
+                let memory = gb_ctx.memory();
+                let pc = gb_ctx.cpu().registers().pc;
+
+                let instructions =
+                    disassemble_around(pc, LINES_BEFORE_PC, LINES_AFTER_PC, |address| {
+                        memory.read(address)
+                    });
+
+                ScrollArea::vertical().max_height(400.0).show(ui, |ui| {
+                    for instruction in instructions {
+                        let bank = memory
+                            .rom_bank(instruction.address)
+                            .map_or_else(|| "  ".to_owned(), |bank| format!("{bank:02X}"));
+
+                        let bytes = instruction
+                            .bytes
+                            .iter()
+                            .map(|byte| format!("{byte:02X}"))
+                            .collect::<Vec<_>>()
+                            .join(" ");
+
+                        let text = format!(
+                            "{bank}:{address:04X}  {bytes:<8}  {instruction}",
+                            address = instruction.address
+                        );
+
+                        let mut text = RichText::new(text).font(FontId::monospace(14.0));
+
+                        let is_pc = instruction.address == pc;
+
+                        if is_pc {
+                            text = text.color(Color32::YELLOW);
+                        }
+
+                        let response = ui.label(text);
+
+                        if is_pc && *follow_pc {
+                            response.scroll_to_me(Some(Align::Center));
+                        }
+
+                        if let Some(target) = instruction.target() {
+                            response.on_hover_text(format!("Target: {target:#06X}"));
+                        }
+                    }
+                });
+            });
+    }
+}
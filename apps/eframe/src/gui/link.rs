@@ -0,0 +1,67 @@
+use std::io;
+
+use gb_core::{GameBoy, components::serial::link::tcp::TcpLink};
+
+use crate::gui::{Event, Gui};
+
+const DEFAULT_ADDRESS: &str = "127.0.0.1:5123";
+
+#[derive(Debug)]
+pub struct Link {
+    address: String,
+    status: String,
+}
+
+impl Default for Link {
+    fn default() -> Self {
+        Self {
+            address: DEFAULT_ADDRESS.to_owned(),
+            status: "Not connected".to_owned(),
+        }
+    }
+}
+
+impl Link {
+    pub fn set_status(&mut self, status: impl Into<String>) {
+        self.status = status.into();
+    }
+
+    pub fn draw_menu(ctx: &mut Gui, ui: &mut egui::Ui, gb_ctx: &mut GameBoy) {
+        ui.menu_button("Link", |ui| {
+            ui.label(&ctx.link.status);
+            ui.text_edit_singleline(&mut ctx.link.address);
+
+            if ui.button("Host link").clicked() {
+                ctx.link
+                    .set_status(format!("Waiting for a partner on {}...", ctx.link.address));
+                Self::connect(ctx, TcpLink::host);
+            }
+
+            if ui.button("Join link").clicked() {
+                ctx.link
+                    .set_status(format!("Connecting to {}...", ctx.link.address));
+                Self::connect(ctx, TcpLink::join);
+            }
+
+            if ui.button("Disconnect").clicked() {
+                gb_ctx.disconnect_link_port();
+                ctx.link.set_status("Not connected");
+            }
+        });
+    }
+
+    /// Connecting blocks, so it's done in the background.
+    fn connect(ctx: &Gui, connect: fn(String) -> io::Result<TcpLink>) {
+        let address = ctx.link.address.clone();
+        let event_sender = ctx.event_sender.clone();
+
+        std::thread::spawn(move || {
+            let event = match connect(address) {
+                Ok(link) => Event::LinkConnected(link),
+                Err(error) => Event::LinkFailed(error.to_string()),
+            };
+
+            event_sender.send(event).unwrap();
+        });
+    }
+}
@@ -1,6 +1,9 @@
 use egui::Key;
 use gb_core::utils::button::Button;
 
+/// Rewinds while held.
+pub const REWIND_KEY: Key = Key::R;
+
 pub trait EguiKeyMappings {
     fn mapped_to(self) -> Key;
 }
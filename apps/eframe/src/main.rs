@@ -117,5 +117,6 @@ mod file_manager;
 mod gameboy_task;
 mod gui;
 mod key_mappings;
+mod recorder;
 mod sys;
 mod utils;
@@ -0,0 +1,163 @@
+use std::sync::{Arc, Mutex};
+
+use gb_core::{
+    GameBoy,
+    components::apu::{CHANNEL_COUNT, wav},
+};
+use tracing::error;
+
+/// Records the audio into memory, to save it as WAV once it stops.
+#[derive(Debug, Default, Clone)]
+pub struct Recorder {
+    recording: Arc<Mutex<Option<Recording>>>,
+}
+
+#[derive(Debug)]
+pub struct Recording {
+    sample_rate: u32,
+    mix: Vec<f32>,
+    /// Interleaved like the channels callback, if the stems are recorded.
+    stems: Option<Vec<f32>>,
+}
+
+impl Recorder {
+    pub fn is_recording(&self) -> bool {
+        self.recording.lock().unwrap().is_some()
+    }
+
+    /// The mix is recorded by [`Recorder::record_mix`], called along with the playback.
+    pub fn start(&self, gb: &mut GameBoy, stems: bool) {
+        gb.flush_audio();
+
+        *self.recording.lock().unwrap() = Some(Recording {
+            sample_rate: gb.audio_sample_rate() as u32,
+            mix: Vec::new(),
+            stems: stems.then(Vec::new),
+        });
+
+        if stems {
+            let recording = self.recording.clone();
+
+            gb.add_audio_channels_callback(Box::new(move |samples| {
+                let mut recording = recording.lock().unwrap();
+
+                if let Some(Recording {
+                    stems: Some(stems), ..
+                }) = recording.as_mut()
+                {
+                    stems.extend_from_slice(samples);
+                }
+            }));
+        }
+    }
+
+    pub fn stop(&self, gb: &mut GameBoy) -> Option<Recording> {
+        gb.flush_audio();
+        gb.remove_audio_channels_callback();
+
+        self.recording.lock().unwrap().take()
+    }
+
+    pub fn record_mix(&self, samples: &[f32]) {
+        let mut recording = self.recording.lock().unwrap();
+
+        if let Some(recording) = recording.as_mut() {
+            recording.mix.extend_from_slice(samples);
+        }
+    }
+}
+
+impl Recording {
+    /// The mix first, then the stems from CH1 to CH4.
+    fn into_files(self) -> Vec<(String, Vec<u8>)> {
+        let mut files = vec![(String::new(), encode(&self.mix, 2, self.sample_rate))];
+
+        if let Some(stems) = self.stems {
+            for (index, stem) in wav::deinterleave(&stems, CHANNEL_COUNT).iter().enumerate() {
+                let suffix = format!("-ch{}", index + 1);
+                files.push((suffix, encode(stem, 1, self.sample_rate)));
+            }
+        }
+
+        files
+    }
+}
+
+fn encode(samples: &[f32], channels: u16, sample_rate: u32) -> Vec<u8> {
+    let mut data = Vec::new();
+    wav::write_wav(&mut data, samples, channels, sample_rate).unwrap();
+
+    data
+}
+
+/// Asks where to save the mix, the stems are saved next to it with the channel as a suffix.
+pub fn save_recording_async(recording: Recording) {
+    let task = rfd::AsyncFileDialog::new()
+        .add_filter("WAV", &["wav"])
+        .set_file_name("recording.wav")
+        .save_file();
+
+    crate::sys::thread::spawn(async move {
+        let Some(file_handle) = task.await else {
+            return;
+        };
+
+        let files = recording.into_files();
+
+        #[cfg(not(target_arch = "wasm32"))]
+        for (suffix, data) in files {
+            let path = file_handle.path();
+            let name = path.file_stem().unwrap_or_default().to_string_lossy();
+            let path = path.with_file_name(format!("{name}{suffix}.wav"));
+
+            if let Err(err) = std::fs::write(&path, data) {
+                error!("Unable to save {}: {err}", path.display());
+            }
+        }
+
+        // Browsers only save the files one download at a time.
+        #[cfg(target_arch = "wasm32")]
+        {
+            let mut file_handle = Some(file_handle);
+
+            for (suffix, data) in files {
+                let file_handle = match file_handle.take() {
+                    Some(file_handle) => file_handle,
+                    None => {
+                        let Some(file_handle) = rfd::AsyncFileDialog::new()
+                            .set_file_name(format!("recording{suffix}.wav"))
+                            .save_file()
+                            .await
+                        else {
+                            return;
+                        };
+
+                        file_handle
+                    }
+                };
+
+                if let Err(err) = file_handle.write(&data).await {
+                    error!("Unable to save the recording: {err}");
+                }
+            }
+        }
+    });
+}
+
+/// Asks where to save the writes to the APU, see [`GameBoy::start_vgm_recording`].
+pub fn save_vgm_async(vgm: Vec<u8>) {
+    let task = rfd::AsyncFileDialog::new()
+        .add_filter("VGM", &["vgm"])
+        .set_file_name("recording.vgm")
+        .save_file();
+
+    crate::sys::thread::spawn(async move {
+        let Some(file_handle) = task.await else {
+            return;
+        };
+
+        if let Err(err) = file_handle.write(&vgm).await {
+            error!("Unable to save the VGM: {err}");
+        }
+    });
+}
@@ -0,0 +1,201 @@
+use std::path::PathBuf;
+
+use clap::Parser;
+use gb_core::{
+    components::apu::AUDIO_SAMPLE_RATE,
+    debugger::{
+        Condition,
+        stop::{MemoryPattern, StopConditions},
+    },
+};
+
+/// Runs a ROM without a window or audio device, then dumps the results.
+///
+/// The run stops after `--frames` frames, or as soon as any of the `--until-*` conditions is met.
+#[derive(Debug, Parser)]
+#[command(author, version, about, long_about = None)]
+pub struct Cli {
+    /// Set the device model to DMG
+    #[arg(short, long, default_value_t = false)]
+    pub dmg: bool,
+
+    /// Optional bootrom path
+    #[arg(short, long)]
+    pub bootrom: Option<PathBuf>,
+
+    /// Optional battery (cartridge RAM) to load before running
+    #[arg(long)]
+    pub battery: Option<PathBuf>,
+
+    /// ROM path
+    pub rom: PathBuf,
+
+    /// Maximum number of frames to run
+    #[arg(short, long, default_value_t = 60 * 60)]
+    pub frames: u64,
+
+    /// Stop when the serial output contains this text
+    #[arg(long, value_name = "TEXT")]
+    pub until_serial: Option<String>,
+
+    /// Stop before the instruction at this address is executed
+    #[arg(long, value_name = "ADDRESS", value_parser = parse_u16)]
+    pub until_pc: Vec<u16>,
+
+    /// Stop before an instruction with this opcode is executed, e.g. 0x40 for `LD B,B`
+    #[arg(long, value_name = "OPCODE", value_parser = parse_u8)]
+    pub until_opcode: Vec<u8>,
+
+    /// Stop when the memory starting at an address matches the bytes, e.g. 0xA001=DE,B0,61
+    #[arg(long, value_name = "ADDRESS=BYTES", value_parser = parse_memory_pattern)]
+    pub until_memory: Vec<MemoryPattern>,
+
+    /// Stop at the end of a frame where the expression holds, e.g. "[0xA000] != 0x80 && a == 0"
+    #[arg(long, value_name = "EXPR", value_parser = parse_condition)]
+    pub until_condition: Vec<Condition>,
+
+    /// Stop when the CPU gets stuck in an infinite loop (`JR -2`)
+    #[arg(long, default_value_t = false)]
+    pub until_loop: bool,
+
+    /// Run every M-cycle of HALT and of idle loops instead of skipping them
+    #[arg(long, default_value_t = false)]
+    pub no_idle_skip: bool,
+
+    /// Save the final screen as PNG
+    #[arg(long, value_name = "PATH")]
+    pub screenshot: Option<PathBuf>,
+
+    /// Save the audio as WAV
+    #[arg(long, value_name = "PATH")]
+    pub audio: Option<PathBuf>,
+
+    /// Save each channel as a mono WAV in this directory (ch1.wav to ch4.wav)
+    #[arg(long, value_name = "DIR")]
+    pub stems: Option<PathBuf>,
+
+    /// Sample rate of the saved audio
+    #[arg(
+        long,
+        value_name = "HZ",
+        default_value_t = AUDIO_SAMPLE_RATE as u32,
+        value_parser = clap::value_parser!(u32).range(1..)
+    )]
+    pub sample_rate: u32,
+
+    /// Save the writes to the APU as VGM, for chiptune players
+    #[arg(long, value_name = "PATH")]
+    pub vgm: Option<PathBuf>,
+
+    /// Make the VGM loop back to the start of this frame
+    #[arg(long, value_name = "FRAME", requires = "vgm")]
+    pub vgm_loop: Option<u64>,
+
+    /// Save the serial output
+    #[arg(long, value_name = "PATH")]
+    pub serial: Option<PathBuf>,
+
+    /// Save the battery (cartridge RAM) after running
+    #[arg(long, value_name = "PATH")]
+    pub save_battery: Option<PathBuf>,
+}
+
+impl Cli {
+    pub fn conditions(&self) -> StopConditions {
+        StopConditions {
+            serial: self.until_serial.iter().cloned().collect(),
+            pc: self.until_pc.clone(),
+            opcodes: self.until_opcode.clone(),
+            memory: self.until_memory.clone(),
+            expressions: self.until_condition.clone(),
+            infinite_loop: self.until_loop,
+        }
+    }
+}
+
+pub fn parse_args() -> Cli {
+    Cli::parse()
+}
+
+/// Accepts `0x` and `$` prefixed hexadecimal numbers, or plain decimal ones.
+fn parse_number(value: &str) -> Result<u32, String> {
+    let result = if let Some(hex) = value
+        .strip_prefix("0x")
+        .or_else(|| value.strip_prefix("0X"))
+        .or_else(|| value.strip_prefix('$'))
+    {
+        u32::from_str_radix(hex, 16)
+    } else {
+        value.parse()
+    };
+
+    result.map_err(|_| format!("invalid number: {value}"))
+}
+
+fn parse_u16(value: &str) -> Result<u16, String> {
+    let number = parse_number(value)?;
+    u16::try_from(number).map_err(|_| format!("out of range: {value}"))
+}
+
+fn parse_u8(value: &str) -> Result<u8, String> {
+    let number = parse_number(value)?;
+    u8::try_from(number).map_err(|_| format!("out of range: {value}"))
+}
+
+/// Parses `ADDRESS=BYTES`, where the bytes are hexadecimal and separated by commas.
+fn parse_memory_pattern(value: &str) -> Result<MemoryPattern, String> {
+    let (address, bytes) = value
+        .split_once('=')
+        .ok_or_else(|| format!("expected ADDRESS=BYTES: {value}"))?;
+
+    let address = parse_u16(address)?;
+    let bytes = bytes
+        .split(',')
+        .map(|byte| {
+            u8::from_str_radix(byte.trim(), 16).map_err(|_| format!("invalid byte: {byte}"))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    if address as usize + bytes.len() > 0x10000 {
+        return Err(format!("the pattern goes past 0xFFFF: {value}"));
+    }
+
+    Ok(MemoryPattern { address, bytes })
+}
+
+fn parse_condition(value: &str) -> Result<Condition, String> {
+    Condition::parse(value).map_err(|err| err.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_numbers() {
+        assert_eq!(parse_u16("0xC000"), Ok(0xC000));
+        assert_eq!(parse_u16("$0150"), Ok(0x0150));
+        assert_eq!(parse_u8("64"), Ok(64));
+        assert!(parse_u8("0x100").is_err());
+    }
+
+    #[test]
+    fn test_parse_memory_pattern() {
+        assert_eq!(
+            parse_memory_pattern("0xA001=DE,B0,61"),
+            Ok(MemoryPattern {
+                address: 0xA001,
+                bytes: vec![0xDE, 0xB0, 0x61],
+            })
+        );
+        assert!(parse_memory_pattern("0xA001").is_err());
+        assert!(parse_memory_pattern("0xA001=XY").is_err());
+        assert!(parse_memory_pattern("0xFFFF=00,00").is_err());
+    }
+
+    #[test]
+    fn test_parse_condition() {
+        assert!(parse_condition("[0xA000] != 0x80").is_ok());
+        assert!(parse_condition("[0xA000] !=").is_err());
+    }
+}
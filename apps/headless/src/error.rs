@@ -0,0 +1,19 @@
+use std::{io, path::PathBuf};
+
+use gb_core::error::GameBoyError;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("Failed to read \"{path}\": {source}.")]
+    Read { path: PathBuf, source: io::Error },
+
+    #[error(transparent)]
+    Load(#[from] GameBoyError),
+
+    #[error("Failed to write the output: {0}.")]
+    Io(#[from] io::Error),
+
+    #[error("Failed to save the screenshot: {0}.")]
+    Image(#[from] image::ImageError),
+}
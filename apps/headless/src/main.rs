@@ -0,0 +1,107 @@
+use std::{path::Path, process::ExitCode};
+
+use gb_core::{GameBoy, constants::DeviceModel};
+use tracing::{error, info, warn};
+
+use crate::{
+    cli::{Cli, parse_args},
+    error::Error,
+    runner::Runner,
+};
+
+/// Returned when the frame limit is reached before any of the conditions is met.
+const EXIT_FRAME_LIMIT: u8 = 2;
+
+fn main() -> ExitCode {
+    tracing_subscriber::fmt()
+        .with_max_level(tracing::Level::INFO)
+        .without_time()
+        .with_writer(std::io::stderr)
+        .init();
+
+    let args = parse_args();
+
+    match run(&args) {
+        Ok(code) => code,
+        Err(error) => {
+            error!("{error}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(args: &Cli) -> Result<ExitCode, Error> {
+    let device_model = if args.dmg {
+        DeviceModel::Dmg
+    } else {
+        DeviceModel::Cgb
+    };
+
+    let bootrom = args.bootrom.as_deref().map(read).transpose()?;
+    let rom = read(&args.rom)?;
+
+    let mut gb = GameBoy::new(device_model);
+    gb.load(bootrom.map(Into::into), rom.into())?;
+    gb.set_idle_skipping(!args.no_idle_skip);
+    gb.set_audio_sample_rate(args.sample_rate as usize);
+
+    if let Some(path) = &args.battery {
+        gb.load_battery(read(path)?);
+    }
+
+    let mut runner = Runner::new(gb, args);
+    let outcome = runner.run(args.frames);
+
+    info!("Stopped after {} frames: {outcome}.", runner.frames());
+
+    if let Some(path) = &args.screenshot {
+        output::save_screenshot(runner.gb(), path)?;
+    }
+
+    if let Some(path) = &args.audio {
+        output::save_wav(path, &runner.audio(), 2, args.sample_rate)?;
+    }
+
+    if let Some(dir) = &args.stems {
+        output::save_stems(dir, &runner.stems(), args.sample_rate)?;
+    }
+
+    if let Some(path) = &args.vgm
+        && let Some(vgm) = runner.take_vgm()
+    {
+        std::fs::write(path, vgm)?;
+    }
+
+    if let Some(path) = &args.serial {
+        std::fs::write(path, runner.serial_output())?;
+    }
+
+    if let Some(path) = &args.save_battery {
+        if let Some(battery) = runner.gb().get_battery() {
+            std::fs::write(path, battery)?;
+        } else {
+            warn!("The cartridge has no battery, nothing to save.");
+        }
+    }
+
+    // Without conditions, running every frame is the expected outcome.
+    if outcome.is_match() || args.conditions().is_empty() {
+        Ok(ExitCode::SUCCESS)
+    } else {
+        Ok(ExitCode::from(EXIT_FRAME_LIMIT))
+    }
+}
+
+fn read(path: &Path) -> Result<Vec<u8>, Error> {
+    std::fs::read(path).map_err(|source| {
+        Error::Read {
+            path: path.to_owned(),
+            source,
+        }
+    })
+}
+
+mod cli;
+mod error;
+mod output;
+mod runner;
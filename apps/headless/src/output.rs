@@ -0,0 +1,62 @@
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+    path::Path,
+};
+
+use gb_core::{
+    GameBoy,
+    components::apu::{CHANNEL_COUNT, wav},
+    constants::{SCREEN_HEIGHT, SCREEN_PIXELS_SIZE, SCREEN_WIDTH},
+};
+
+use crate::error::Error;
+
+pub fn save_screenshot(gb: &GameBoy, path: &Path) -> Result<(), Error> {
+    #[allow(clippy::large_stack_arrays)]
+    let mut frame = [0; SCREEN_PIXELS_SIZE];
+
+    gb.draw_into_frame_rgba8888(&mut frame);
+
+    image::save_buffer_with_format(
+        path,
+        &frame,
+        SCREEN_WIDTH as u32,
+        SCREEN_HEIGHT as u32,
+        image::ColorType::Rgba8,
+        image::ImageFormat::Png,
+    )?;
+
+    Ok(())
+}
+
+/// Saves interleaved samples as 16-bit PCM.
+pub fn save_wav(
+    path: &Path,
+    samples: &[f32],
+    channels: u16,
+    sample_rate: u32,
+) -> Result<(), Error> {
+    let mut writer = BufWriter::new(File::create(path)?);
+
+    wav::write_wav(&mut writer, samples, channels, sample_rate)?;
+    writer.flush()?;
+
+    Ok(())
+}
+
+/// Saves each channel as a mono WAV, `ch1.wav` to `ch4.wav`, in `dir`.
+pub fn save_stems(dir: &Path, samples: &[f32], sample_rate: u32) -> Result<(), Error> {
+    std::fs::create_dir_all(dir)?;
+
+    for (index, stem) in wav::deinterleave(samples, CHANNEL_COUNT).iter().enumerate() {
+        save_wav(
+            &dir.join(format!("ch{}.wav", index + 1)),
+            stem,
+            1,
+            sample_rate,
+        )?;
+    }
+
+    Ok(())
+}
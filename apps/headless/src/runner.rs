@@ -0,0 +1,169 @@
+use std::sync::{Arc, Mutex};
+
+use gb_core::{
+    GameBoy,
+    debugger::stop::{ConditionRunner, Outcome},
+};
+
+use crate::cli::Cli;
+
+pub struct Runner {
+    gb: GameBoy,
+    conditions: ConditionRunner,
+
+    audio: Arc<Mutex<Vec<f32>>>,
+    stems: Arc<Mutex<Vec<f32>>>,
+    vgm_loop: Option<u64>,
+}
+
+impl Runner {
+    pub fn new(mut gb: GameBoy, args: &Cli) -> Self {
+        let conditions = ConditionRunner::new(&mut gb, args.conditions());
+
+        let audio = Arc::new(Mutex::new(Vec::new()));
+
+        if args.audio.is_some() {
+            let audio = audio.clone();
+
+            gb.add_audio_callback(Box::new(move |samples| {
+                audio.lock().unwrap().extend_from_slice(samples);
+            }));
+        }
+
+        let stems = Arc::new(Mutex::new(Vec::new()));
+
+        if args.stems.is_some() {
+            let stems = stems.clone();
+
+            gb.add_audio_channels_callback(Box::new(move |samples| {
+                stems.lock().unwrap().extend_from_slice(samples);
+            }));
+        }
+
+        if args.vgm.is_some() {
+            gb.start_vgm_recording();
+        }
+
+        Self {
+            gb,
+            conditions,
+            audio,
+            stems,
+            vgm_loop: args.vgm_loop,
+        }
+    }
+
+    pub fn gb(&self) -> &GameBoy {
+        &self.gb
+    }
+
+    pub fn frames(&self) -> u64 {
+        self.conditions.frames()
+    }
+
+    pub fn serial_output(&self) -> &[u8] {
+        self.conditions.serial_output()
+    }
+
+    /// Interleaved stereo samples at [`GameBoy::audio_sample_rate`].
+    pub fn audio(&self) -> Vec<f32> {
+        self.audio.lock().unwrap().clone()
+    }
+
+    /// Samples of each channel, interleaved like [`gb_core::components::apu::ChannelsCallback`].
+    pub fn stems(&self) -> Vec<f32> {
+        self.stems.lock().unwrap().clone()
+    }
+
+    /// The VGM file, if it was recording.
+    pub fn take_vgm(&mut self) -> Option<Vec<u8>> {
+        self.gb.stop_vgm_recording()
+    }
+
+    /// Runs until one of the conditions is met, or for `max_frames` frames.
+    pub fn run(&mut self, max_frames: u64) -> Outcome {
+        let mut outcome = Outcome::FrameLimit;
+
+        while self.conditions.frames() < max_frames {
+            if self.vgm_loop == Some(self.conditions.frames()) {
+                self.gb.mark_vgm_loop();
+            }
+
+            if let Some(stop) = self.conditions.run_frame(&mut self.gb) {
+                outcome = stop;
+                break;
+            }
+        }
+
+        // The last samples don't fill the audio buffers.
+        self.gb.flush_audio();
+
+        outcome
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::Parser as _;
+    use gb_core::{constants::DeviceModel, test_rom::TestRom};
+
+    use super::*;
+
+    /// `LD A, 0x42; LD (0xC000), A; LDH (0x01), A; LD A, 0x81; LDH (0x02), A; JR -2`,
+    /// which sends 'B' through the serial port.
+    fn new_runner(args: &[&str]) -> Runner {
+        let rom = TestRom::new()
+            .program(&[
+                0x3E, 0x42, 0xEA, 0x00, 0xC0, 0xE0, 0x01, 0x3E, 0x81, 0xE0, 0x02, 0x18, 0xFE,
+            ])
+            .build();
+
+        let mut gb = GameBoy::new(DeviceModel::Dmg);
+        gb.load(None, rom).unwrap();
+
+        let args = Cli::parse_from(["gb-headless", "test.gb"].iter().chain(args));
+
+        Runner::new(gb, &args)
+    }
+
+    #[test]
+    fn test_run_until_conditions() {
+        let mut runner = new_runner(&["--until-loop"]);
+        assert_eq!(runner.run(10), Outcome::InfiniteLoop(0x015B));
+        assert_eq!(runner.frames(), 0);
+
+        let mut runner = new_runner(&["--until-serial", "B"]);
+        assert_eq!(runner.run(10), Outcome::Serial("B".to_owned()));
+        assert_eq!(runner.serial_output(), b"B");
+
+        let mut runner = new_runner(&["--until-condition", "[0xC000] == 0x42"]);
+        assert!(matches!(runner.run(10), Outcome::Expression(_)));
+
+        // The prohibited area reads as an open bus.
+        let mut runner = new_runner(&["--until-memory", "0xFEA0=FF"]);
+        assert_eq!(runner.run(10), Outcome::Memory(0xFEA0));
+    }
+
+    #[test]
+    fn test_run_frame_limit() {
+        let mut runner = new_runner(&["--until-memory", "0xC000=43"]);
+
+        assert_eq!(runner.run(5), Outcome::FrameLimit);
+        assert_eq!(runner.frames(), 5);
+    }
+
+    #[test]
+    fn test_vgm_loop() {
+        let mut runner = new_runner(&["--vgm", "test.vgm", "--vgm-loop", "2"]);
+        runner.run(5);
+
+        let vgm = runner.take_vgm().unwrap();
+        let read_u32 =
+            |offset: usize| u32::from_le_bytes(vgm[offset..offset + 4].try_into().unwrap());
+
+        // 3 of the 5 frames loop.
+        let ratio = f64::from(read_u32(0x20)) / f64::from(read_u32(0x18));
+        assert!(read_u32(0x1C) != 0);
+        assert!((ratio - 0.6).abs() < 0.1, "{ratio}");
+    }
+}
@@ -1,6 +1,21 @@
+use std::{
+    cell::RefCell,
+    path::Path,
+    sync::{Arc, Mutex},
+};
+
 use gb_core::{
     GameBoy,
-    constants::{DeviceModel, SCREEN_HEIGHT, SCREEN_PIXELS_SIZE, SCREEN_WIDTH, ScreenPixels},
+    components::apu::AUDIO_SAMPLE_RATE,
+    constants::{
+        CPU_APPROX_M_CYCLES_PER_FRAME,
+        CPU_CLOCK_RATE,
+        DeviceModel,
+        SCREEN_HEIGHT,
+        SCREEN_PIXELS_SIZE,
+        SCREEN_WIDTH,
+        ScreenPixels,
+    },
     utils::button::Button,
 };
 use key_mappings::LibretroKeyMappings;
@@ -15,26 +30,52 @@ use libretro_rs::{
     RetroSystemInfo,
     RetroVideoInfo,
     libretro_core,
-    sys::RETRO_MEMORY_SAVE_RAM,
+    sys::RETRO_ENVIRONMENT_SET_SUPPORT_ACHIEVEMENTS,
 };
+use memory_map::MemoryMirror;
+use options::CoreOptions;
 use tracing::error;
 
+#[allow(clippy::cast_precision_loss)]
+const FRAME_RATE: f64 = CPU_CLOCK_RATE as f64 / CPU_APPROX_M_CYCLES_PER_FRAME as f64;
+
+#[allow(clippy::cast_precision_loss)]
+const SAMPLE_RATE: f64 = AUDIO_SAMPLE_RATE as f64;
+
 struct Emulator {
-    gb: GameBoy,
+    /// Borrowed mutably by `serialize`, which only gets `&self`.
+    gb: RefCell<GameBoy>,
 
     pixels: Box<ScreenPixels>,
+
+    /// Interleaved stereo samples of the current frame.
+    audio: Arc<Mutex<Vec<i16>>>,
+
+    /// Created when a game is loaded.
+    memory: Option<MemoryMirror>,
+    state_size: usize,
+
+    reload_battery: bool,
 }
 
 impl RetroCore for Emulator {
-    fn init(_env: &RetroEnvironment) -> Self {
+    fn init(env: &RetroEnvironment) -> Self {
         tracing_subscriber::fmt::init();
 
+        CoreOptions::register(env);
+
+        let audio = Arc::new(Mutex::new(Vec::new()));
+
         Self {
-            gb: GameBoy::new(DeviceModel::Cgb),
+            gb: RefCell::new(new_gameboy(DeviceModel::Cgb, &audio)),
             pixels: vec![0; SCREEN_PIXELS_SIZE]
                 .into_boxed_slice()
                 .try_into()
                 .unwrap(),
+            audio,
+            memory: None,
+            state_size: 0,
+            reload_battery: false,
         }
     }
 
@@ -44,19 +85,39 @@ impl RetroCore for Emulator {
     }
 
     fn reset(&mut self, _env: &RetroEnvironment) {
-        self.gb.reset();
+        self.gb.get_mut().reset();
+        self.pull_memory();
     }
 
-    fn run(&mut self, _env: &RetroEnvironment, runtime: &RetroRuntime) {
+    fn run(&mut self, env: &RetroEnvironment, runtime: &RetroRuntime) {
+        if CoreOptions::updated(env) {
+            self.apply_options(CoreOptions::read(*env));
+        }
+
+        if let Some(memory) = &self.memory {
+            // The frontend writes the save RAM after loading the game,
+            // so the RTC footer has to be parsed before the first frame.
+            if self.reload_battery {
+                memory.load_battery(self.gb.get_mut());
+                self.reload_battery = false;
+            } else {
+                memory.push(self.gb.get_mut());
+            }
+        }
+
+        let gb = self.gb.get_mut();
+
         for button in Button::ALL_CASES {
             let key = button.mapped_to();
             let value = runtime.is_joypad_button_pressed(0, key);
 
-            self.gb.set_joypad_button(button, value);
+            gb.set_joypad_button(button, value);
         }
 
-        self.gb.run_frame();
-        self.gb.draw_into_frame_bgra8888(&mut self.pixels);
+        gb.run_frame();
+        gb.flush_audio();
+        gb.draw_into_frame_bgra8888(&mut self.pixels);
+        self.pull_memory();
 
         runtime.upload_video_frame(
             self.pixels.as_ref(),
@@ -64,9 +125,61 @@ impl RetroCore for Emulator {
             SCREEN_HEIGHT as u32,
             SCREEN_WIDTH * 4,
         );
+
+        let mut audio = self.audio.lock().unwrap();
+        runtime.upload_audio_frame(&audio);
+        audio.clear();
+    }
+
+    fn serialize_size(&self, _env: &RetroEnvironment) -> usize {
+        self.state_size
+    }
+
+    fn serialize(&self, _env: &RetroEnvironment, data: *mut (), size: usize) -> bool {
+        let mut gb = self.gb.borrow_mut();
+
+        // Cheats and achievements might have written to the mirror since the last frame.
+        if let Some(memory) = &self.memory {
+            memory.push(&mut gb);
+        }
+
+        // SAFETY: the frontend provides a buffer of `size` bytes.
+        let buffer = unsafe { std::slice::from_raw_parts_mut(data.cast::<u8>(), size) };
+
+        if !save_state::write(&gb.save_state(), buffer) {
+            error!("The save state doesn't fit in {size} bytes.");
+
+            return false;
+        }
+
+        true
+    }
+
+    fn unserialize(&mut self, _env: &RetroEnvironment, data: *const (), size: usize) -> bool {
+        // SAFETY: the frontend provides a buffer of `size` bytes.
+        let buffer = unsafe { std::slice::from_raw_parts(data.cast::<u8>(), size) };
+
+        let Some(state) = save_state::read(buffer) else {
+            return false;
+        };
+
+        match self.gb.get_mut().load_state(state) {
+            Ok(()) => {
+                self.pull_memory();
+
+                // The state already holds the save RAM and the RTC.
+                self.reload_battery = false;
+
+                true
+            }
+            Err(err) => {
+                error!("{err}");
+                false
+            }
+        }
     }
 
-    fn load_game(&mut self, _env: &RetroEnvironment, game: RetroGame) -> RetroLoadGameResult {
+    fn load_game(&mut self, env: &RetroEnvironment, game: RetroGame) -> RetroLoadGameResult {
         let rom = match game {
             RetroGame::None { .. } => return RetroLoadGameResult::Failure,
             RetroGame::Data { data, .. } => data.to_vec(),
@@ -84,43 +197,107 @@ impl RetroCore for Emulator {
             }
         };
 
-        let result = self.gb.load(None, rom.into());
+        let options = CoreOptions::read(*env);
+
+        *self.gb.get_mut() = new_gameboy(options.device_model, &self.audio);
+        self.apply_options(options);
+
+        let bootrom = env
+            .get_system_directory()
+            .and_then(|directory| read_bootrom(Path::new(directory), options.device_model));
+
+        let result = self.gb.get_mut().load(bootrom.map(Into::into), rom.into());
 
         match result {
             Ok(()) => {
+                let mut memory = MemoryMirror::new(self.gb.get_mut());
+                memory.pull(self.gb.get_mut());
+                memory.set_memory_maps(env, options.device_model);
+                env.set_bool(RETRO_ENVIRONMENT_SET_SUPPORT_ACHIEVEMENTS, true);
+
+                self.memory = Some(memory);
+                self.state_size = save_state::size(self.gb.get_mut());
+                self.reload_battery = true;
+
                 RetroLoadGameResult::Success {
-                    audio: RetroAudioInfo::new(44100.0),
-                    video: RetroVideoInfo::new(4_194_304.0 / 70224.0, 160, 144)
-                        .with_pixel_format(RetroPixelFormat::XRGB8888),
+                    audio: RetroAudioInfo::new(SAMPLE_RATE),
+                    video: RetroVideoInfo::new(
+                        FRAME_RATE,
+                        SCREEN_WIDTH as u32,
+                        SCREEN_HEIGHT as u32,
+                    )
+                    .with_pixel_format(RetroPixelFormat::XRGB8888),
                 }
             }
-            Err(_) => RetroLoadGameResult::Failure,
+            Err(err) => {
+                error!("{err}");
+
+                RetroLoadGameResult::Failure
+            }
         }
     }
 
-    #[allow(clippy::as_ptr_cast_mut)]
     fn get_memory_data(&mut self, _env: &RetroEnvironment, id: u32) -> *mut () {
-        match id {
-            // This is horrible. Maybe try to find a better way.
-            RETRO_MEMORY_SAVE_RAM => {
-                self.gb
-                    .get_battery()
-                    .map_or(std::ptr::null_mut(), |ram| ram.as_ptr() as *mut ())
-            }
-
-            _ => std::ptr::null_mut(),
-        }
+        self.memory
+            .as_mut()
+            .and_then(|memory| memory.data(id))
+            .map_or(std::ptr::null_mut(), |data| data.as_mut_ptr().cast())
     }
 
     fn get_memory_size(&self, _env: &RetroEnvironment, id: u32) -> usize {
-        match id {
-            RETRO_MEMORY_SAVE_RAM => self.gb.get_battery().map_or(0, <[u8]>::len),
+        self.memory.as_ref().map_or(0, |memory| memory.size(id))
+    }
+}
+
+impl Emulator {
+    /// The device model only changes when a game is loaded.
+    fn apply_options(&mut self, options: CoreOptions) {
+        let gb = self.gb.get_mut();
 
-            _ => 0,
+        gb.set_dmg_palette(options.dmg_palette);
+        gb.set_color_correction(options.color_correction);
+    }
+
+    fn pull_memory(&mut self) {
+        if let Some(memory) = self.memory.as_mut() {
+            memory.pull(self.gb.get_mut());
         }
     }
 }
 
+fn new_gameboy(device_model: DeviceModel, audio: &Arc<Mutex<Vec<i16>>>) -> GameBoy {
+    let mut gb = GameBoy::new(device_model);
+    let audio = audio.clone();
+
+    gb.add_audio_callback(Box::new(move |samples| {
+        audio
+            .lock()
+            .unwrap()
+            .extend(samples.iter().map(|sample| sample_to_i16(*sample)));
+    }));
+
+    gb
+}
+
+/// Looks for the bootrom in the frontend's system directory, under the usual names.
+fn read_bootrom(directory: &Path, device_model: DeviceModel) -> Option<Vec<u8>> {
+    let names = match device_model {
+        DeviceModel::Dmg => ["dmg_boot.bin", "gb_bios.bin"],
+        DeviceModel::Cgb => ["cgb_boot.bin", "gbc_bios.bin"],
+    };
+
+    names
+        .iter()
+        .find_map(|name| std::fs::read(directory.join(name)).ok())
+}
+
+fn sample_to_i16(sample: f32) -> i16 {
+    (sample.clamp(-1.0, 1.0) * f32::from(i16::MAX)) as i16
+}
+
 libretro_core!(Emulator);
 
 mod key_mappings;
+mod memory_map;
+mod options;
+mod save_state;
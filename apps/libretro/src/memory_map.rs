@@ -0,0 +1,288 @@
+use gb_core::{GameBoy, components::memory::MemoryRegion, constants::DeviceModel};
+use libretro_rs::{
+    RetroEnvironment,
+    sys::{
+        RETRO_ENVIRONMENT_SET_MEMORY_MAPS,
+        RETRO_MEMDESC_SAVE_RAM,
+        RETRO_MEMDESC_SYSTEM_RAM,
+        RETRO_MEMDESC_VIDEO_RAM,
+        RETRO_MEMORY_RTC,
+        RETRO_MEMORY_SAVE_RAM,
+        RETRO_MEMORY_SYSTEM_RAM,
+        RETRO_MEMORY_VIDEO_RAM,
+        retro_memory_descriptor,
+        retro_memory_map,
+    },
+};
+
+const WRAM_BANK_SIZE: usize = 0x1000;
+const VRAM_BANK_SIZE: usize = 0x2000;
+const CARTRIDGE_RAM_BANK_SIZE: usize = 0x2000;
+const HRAM_SIZE: usize = 0x7F;
+
+/// Copies of the emulated memory with stable addresses, handed out to the frontend.
+///
+/// The core reallocates its memory on every reset and save state load, so the frontend can't
+/// point into it directly. The copies are written into the core before every frame and save state,
+/// and read back after every frame and loaded state, which keeps frontend writes (cheats, save RAM
+/// loading) working.
+pub struct MemoryMirror {
+    work_ram: Box<[u8]>,
+    high_ram: Box<[u8]>,
+    video_ram: Box<[u8]>,
+
+    /// Saved by the frontend separately, they make up the battery file together.
+    cartridge_ram: Box<[u8]>,
+    rtc: Box<[u8]>,
+}
+
+impl MemoryMirror {
+    pub fn new(gb: &GameBoy) -> Self {
+        let memory = gb.memory();
+        let region = |region| memory.region(region).unwrap_or_default().into();
+        let cleared = |region| vec![0; memory.region(region).map_or(0, <[u8]>::len)].into();
+
+        Self {
+            work_ram: region(MemoryRegion::WorkRam),
+            high_ram: region(MemoryRegion::HighRam),
+            video_ram: region(MemoryRegion::VideoRam),
+            cartridge_ram: cleared(MemoryRegion::CartridgeRam),
+            rtc: cleared(MemoryRegion::Rtc),
+        }
+    }
+
+    /// Copies the emulated memory into the mirror.
+    pub fn pull(&mut self, gb: &GameBoy) {
+        let memory = gb.memory();
+
+        copy(memory.region(MemoryRegion::WorkRam), &mut self.work_ram);
+        copy(memory.region(MemoryRegion::HighRam), &mut self.high_ram);
+        copy(memory.region(MemoryRegion::VideoRam), &mut self.video_ram);
+
+        copy(
+            memory.region(MemoryRegion::CartridgeRam),
+            &mut self.cartridge_ram,
+        );
+        copy(memory.region(MemoryRegion::Rtc), &mut self.rtc);
+    }
+
+    /// Copies the mirror into the emulated memory. The RTC is read-only.
+    pub fn push(&self, gb: &mut GameBoy) {
+        let memory = gb.memory_mut();
+
+        copy_into(&self.work_ram, memory.region_mut(MemoryRegion::WorkRam));
+        copy_into(&self.high_ram, memory.region_mut(MemoryRegion::HighRam));
+        copy_into(&self.video_ram, memory.region_mut(MemoryRegion::VideoRam));
+        copy_into(
+            &self.cartridge_ram,
+            memory.region_mut(MemoryRegion::CartridgeRam),
+        );
+    }
+
+    /// Loads the save RAM and the RTC written by the frontend, as one battery file.
+    pub fn load_battery(&self, gb: &mut GameBoy) {
+        let mut battery = self.cartridge_ram.to_vec();
+
+        // Left cleared when the frontend has no RTC file, the clock then starts from zero
+        // instead of catching up with the time since 1970.
+        if self.rtc.iter().any(|&byte| byte != 0) {
+            battery.extend_from_slice(&self.rtc);
+        }
+
+        if !battery.is_empty() {
+            gb.load_battery(battery);
+        }
+    }
+
+    pub fn data(&mut self, id: u32) -> Option<&mut [u8]> {
+        let data = match id {
+            RETRO_MEMORY_SAVE_RAM => &mut self.cartridge_ram[..],
+            RETRO_MEMORY_RTC => &mut self.rtc[..],
+            RETRO_MEMORY_SYSTEM_RAM => &mut self.work_ram[..],
+            RETRO_MEMORY_VIDEO_RAM => &mut self.video_ram[..],
+            _ => return None,
+        };
+
+        (!data.is_empty()).then_some(data)
+    }
+
+    pub fn size(&self, id: u32) -> usize {
+        match id {
+            RETRO_MEMORY_SAVE_RAM => self.cartridge_ram.len(),
+            RETRO_MEMORY_RTC => self.rtc.len(),
+            RETRO_MEMORY_SYSTEM_RAM => self.work_ram.len(),
+            RETRO_MEMORY_VIDEO_RAM => self.video_ram.len(),
+            _ => 0,
+        }
+    }
+
+    /// Describes the CPU address space for achievements and cheats.
+    ///
+    /// Only the first bank of the VRAM is mapped. The banks that don't fit in the address space
+    /// are placed after it: the CGB WRAM banks 2-7 at 0x10000, and the cartridge RAM banks from
+    /// 1 on at 0x16000, on both models.
+    pub fn set_memory_maps(&mut self, env: &RetroEnvironment, device_model: DeviceModel) {
+        let descriptors = self.descriptors(device_model);
+
+        let map = retro_memory_map {
+            descriptors: descriptors.as_ptr(),
+            num_descriptors: descriptors.len() as u32,
+        };
+
+        // SAFETY: the frontend copies the descriptors, and the buffers live as long as the game.
+        unsafe {
+            env.set_raw(RETRO_ENVIRONMENT_SET_MEMORY_MAPS, &raw const map);
+        }
+    }
+
+    fn descriptors(&mut self, device_model: DeviceModel) -> Vec<retro_memory_descriptor> {
+        let mut descriptors = vec![
+            descriptor(
+                RETRO_MEMDESC_VIDEO_RAM,
+                &mut self.video_ram,
+                0,
+                0x8000,
+                VRAM_BANK_SIZE,
+            ),
+            descriptor(
+                RETRO_MEMDESC_SYSTEM_RAM,
+                &mut self.work_ram,
+                0,
+                0xC000,
+                WRAM_BANK_SIZE,
+            ),
+            descriptor(
+                RETRO_MEMDESC_SYSTEM_RAM,
+                &mut self.work_ram,
+                WRAM_BANK_SIZE,
+                0xD000,
+                WRAM_BANK_SIZE,
+            ),
+            descriptor(0, &mut self.high_ram, 0, 0xFF80, HRAM_SIZE),
+        ];
+
+        if !self.cartridge_ram.is_empty() {
+            let len = self.cartridge_ram.len().min(CARTRIDGE_RAM_BANK_SIZE);
+            descriptors.push(descriptor(
+                RETRO_MEMDESC_SAVE_RAM,
+                &mut self.cartridge_ram,
+                0,
+                0xA000,
+                len,
+            ));
+        }
+
+        if self.cartridge_ram.len() > CARTRIDGE_RAM_BANK_SIZE {
+            let len = self.cartridge_ram.len() - CARTRIDGE_RAM_BANK_SIZE;
+            descriptors.push(descriptor(
+                RETRO_MEMDESC_SAVE_RAM,
+                &mut self.cartridge_ram,
+                CARTRIDGE_RAM_BANK_SIZE,
+                0x16000,
+                len,
+            ));
+        }
+
+        if device_model.is_cgb() {
+            descriptors.push(descriptor(
+                RETRO_MEMDESC_SYSTEM_RAM,
+                &mut self.work_ram,
+                2 * WRAM_BANK_SIZE,
+                0x10000,
+                6 * WRAM_BANK_SIZE,
+            ));
+        }
+
+        descriptors
+    }
+}
+
+fn descriptor(
+    flags: u32,
+    data: &mut [u8],
+    offset: usize,
+    start: usize,
+    len: usize,
+) -> retro_memory_descriptor {
+    retro_memory_descriptor {
+        flags: u64::from(flags),
+        ptr: data.as_mut_ptr().cast(),
+        offset,
+        start,
+        select: 0,
+        disconnect: 0,
+        len,
+        addrspace: std::ptr::null(),
+    }
+}
+
+fn copy(source: Option<&[u8]>, destination: &mut [u8]) {
+    if let Some(source) = source {
+        destination.copy_from_slice(source);
+    }
+}
+
+fn copy_into(source: &[u8], destination: Option<&mut [u8]>) {
+    if let Some(destination) = destination {
+        destination.copy_from_slice(source);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use gb_core::test_rom::TestRom;
+
+    use super::*;
+
+    /// MBC3+TIMER+RAM+BATTERY, with 4 banks of RAM.
+    fn mbc3_gameboy() -> GameBoy {
+        let rom = TestRom::new().mbc(0x10).ram_size(0x03).build();
+
+        let mut gb = GameBoy::new(DeviceModel::Cgb);
+        gb.load(None, rom).unwrap();
+
+        gb
+    }
+
+    #[test]
+    fn test_mirror_round_trip() {
+        let mut gb = mbc3_gameboy();
+        let mut memory = MemoryMirror::new(&gb);
+
+        let work_ram = gb.memory_mut().region_mut(MemoryRegion::WorkRam).unwrap();
+        work_ram[0x7000] = 0x42;
+        memory.pull(&gb);
+        assert_eq!(memory.data(RETRO_MEMORY_SYSTEM_RAM).unwrap()[0x7000], 0x42);
+
+        memory.data(RETRO_MEMORY_SYSTEM_RAM).unwrap()[0x7000] = 0x24;
+        memory.data(RETRO_MEMORY_VIDEO_RAM).unwrap()[0x2000] = 0x42;
+        memory.data(RETRO_MEMORY_SAVE_RAM).unwrap()[0x6000] = 0x42;
+        memory.push(&mut gb);
+
+        let region = |region| gb.memory().region(region).unwrap();
+        assert_eq!(region(MemoryRegion::WorkRam)[0x7000], 0x24);
+        assert_eq!(region(MemoryRegion::VideoRam)[0x2000], 0x42);
+        assert_eq!(region(MemoryRegion::CartridgeRam)[0x6000], 0x42);
+
+        // The RTC is saved separately from the save RAM.
+        assert_eq!(memory.size(RETRO_MEMORY_SAVE_RAM), 0x8000);
+        assert_eq!(
+            memory.size(RETRO_MEMORY_RTC),
+            region(MemoryRegion::Rtc).len()
+        );
+    }
+
+    #[test]
+    fn test_every_cartridge_ram_bank_is_mapped() {
+        let mut memory = MemoryMirror::new(&mbc3_gameboy());
+
+        let mapped: Vec<_> = memory
+            .descriptors(DeviceModel::Cgb)
+            .iter()
+            .filter(|descriptor| descriptor.flags == u64::from(RETRO_MEMDESC_SAVE_RAM))
+            .map(|descriptor| (descriptor.start, descriptor.offset, descriptor.len))
+            .collect();
+
+        assert_eq!(mapped, [(0xA000, 0, 0x2000), (0x16000, 0x2000, 0x6000)]);
+    }
+}
@@ -0,0 +1,112 @@
+use std::ffi::CStr;
+
+use gb_core::{
+    constants::DeviceModel,
+    utils::color::{ColorCorrection, DmgPalette},
+};
+use libretro_rs::{
+    RetroEnvironment,
+    sys::{
+        RETRO_ENVIRONMENT_GET_VARIABLE,
+        RETRO_ENVIRONMENT_GET_VARIABLE_UPDATE,
+        RETRO_ENVIRONMENT_SET_VARIABLES,
+        retro_variable,
+    },
+};
+
+const DEVICE_MODEL: &CStr = c"gameboy_emulator_device_model";
+const DMG_PALETTE: &CStr = c"gameboy_emulator_dmg_palette";
+const COLOR_CORRECTION: &CStr = c"gameboy_emulator_color_correction";
+
+/// `"Description; Default|Other values"`, as expected by `RETRO_ENVIRONMENT_SET_VARIABLES`.
+const VARIABLES: [(&CStr, &CStr); 3] = [
+    (DEVICE_MODEL, c"Device model (restart); CGB|DMG"),
+    (DMG_PALETTE, c"DMG palette; Green|Grey"),
+    (COLOR_CORRECTION, c"Color correction; Accurate|Disabled"),
+];
+
+#[derive(Debug, Clone, Copy)]
+pub struct CoreOptions {
+    /// Only applied when a game is loaded.
+    pub device_model: DeviceModel,
+    pub dmg_palette: DmgPalette,
+    pub color_correction: ColorCorrection,
+}
+
+impl CoreOptions {
+    pub fn register(env: &RetroEnvironment) {
+        let variables = VARIABLES
+            .iter()
+            .map(|(key, value)| {
+                retro_variable {
+                    key: key.as_ptr(),
+                    value: value.as_ptr(),
+                }
+            })
+            .chain(std::iter::once(retro_variable {
+                key: std::ptr::null(),
+                value: std::ptr::null(),
+            }))
+            .collect::<Vec<_>>();
+
+        // SAFETY: the list is terminated by a null entry and the strings are static.
+        unsafe {
+            env.set_raw(RETRO_ENVIRONMENT_SET_VARIABLES, variables.as_ptr());
+        }
+    }
+
+    pub fn read(env: RetroEnvironment) -> Self {
+        let device_model = match get_variable(&env, DEVICE_MODEL).as_deref() {
+            Some("DMG") => DeviceModel::Dmg,
+            _ => DeviceModel::Cgb,
+        };
+
+        let dmg_palette = match get_variable(&env, DMG_PALETTE).as_deref() {
+            Some("Grey") => DmgPalette::Grey,
+            _ => DmgPalette::Green,
+        };
+
+        let color_correction = match get_variable(&env, COLOR_CORRECTION).as_deref() {
+            Some("Disabled") => ColorCorrection::Disabled,
+            _ => ColorCorrection::Accurate,
+        };
+
+        Self {
+            device_model,
+            dmg_palette,
+            color_correction,
+        }
+    }
+
+    /// Whether any option changed since the last [`CoreOptions::read`].
+    pub fn updated(env: &RetroEnvironment) -> bool {
+        let mut updated = false;
+
+        // SAFETY: the frontend writes a `bool`.
+        let supported =
+            unsafe { env.set_raw(RETRO_ENVIRONMENT_GET_VARIABLE_UPDATE, &raw mut updated) };
+
+        supported && updated
+    }
+}
+
+fn get_variable(env: &RetroEnvironment, key: &CStr) -> Option<String> {
+    let mut variable = retro_variable {
+        key: key.as_ptr(),
+        value: std::ptr::null(),
+    };
+
+    // SAFETY: the frontend fills in `value`, which is only valid until the next call.
+    unsafe {
+        if !env.set_raw(RETRO_ENVIRONMENT_GET_VARIABLE, &raw mut variable)
+            || variable.value.is_null()
+        {
+            return None;
+        }
+
+        CStr::from_ptr(variable.value)
+            .to_str()
+            .ok()
+            .map(str::to_owned)
+    }
+}
@@ -0,0 +1,122 @@
+use gb_core::GameBoy;
+
+/// Room for the parts of the save states that change in size, like the sprites of the current
+/// line. The frontends expect the size to stay the same while the game runs.
+const STATE_SLACK: usize = 1024;
+
+/// The state is prefixed with its length, since the rest of the buffer is padding.
+const STATE_LENGTH_SIZE: usize = size_of::<u32>();
+
+/// The size reported to the frontend, which has to fit every later state of the game.
+pub fn size(gb: &GameBoy) -> usize {
+    STATE_LENGTH_SIZE + gb.save_state().len() + STATE_SLACK
+}
+
+/// Writes `state` prefixed with its length, and pads the rest of `buffer` with zeros.
+pub fn write(state: &[u8], buffer: &mut [u8]) -> bool {
+    if STATE_LENGTH_SIZE + state.len() > buffer.len() {
+        return false;
+    }
+
+    let (length, rest) = buffer.split_at_mut(STATE_LENGTH_SIZE);
+
+    length.copy_from_slice(&(state.len() as u32).to_le_bytes());
+    rest[..state.len()].copy_from_slice(state);
+    rest[state.len()..].fill(0);
+
+    true
+}
+
+/// The state written by [`write`], without the padding.
+pub fn read(buffer: &[u8]) -> Option<&[u8]> {
+    let (length, rest) = buffer.split_first_chunk::<STATE_LENGTH_SIZE>()?;
+
+    rest.get(..u32::from_le_bytes(*length) as usize)
+}
+
+#[cfg(test)]
+mod tests {
+    use gb_core::{
+        components::{memory::MemoryInterface, ppu::renderer::Renderer},
+        constants::{CPU_APPROX_M_CYCLES_PER_FRAME, DeviceModel},
+        test_rom::TestRom,
+    };
+
+    use super::*;
+
+    /// Fills the OAM with 40 sprites at the same position, so 10 of them are on lines 16-23.
+    fn sprites_gameboy(renderer: Renderer) -> GameBoy {
+        let rom = TestRom::new()
+            .program(&[
+                0xAF, // XOR A
+                0xE0, 0x40, // LDH (0x40), A
+                0x21, 0x00, 0xFE, // LD HL, 0xFE00
+                0x3E, 0x20, // LD A, 0x20
+                0x06, 0xA0, // LD B, 0xA0
+                0x22, // LD (HL+), A
+                0x05, // DEC B
+                0x20, 0xFC, // JR NZ, -4
+                0x3E, 0x93, // LD A, 0x93
+                0xE0, 0x40, // LDH (0x40), A
+                0x18, 0xFE, // JR -2
+            ])
+            .build();
+
+        let mut gb = GameBoy::new(DeviceModel::Cgb);
+        gb.set_ppu_renderer(renderer);
+        gb.load(None, rom).unwrap();
+
+        gb
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let mut gb = sprites_gameboy(Renderer::Scanline);
+        gb.run_frame();
+
+        let state = gb.save_state();
+        let mut buffer = vec![0xFF; size(&gb)];
+
+        assert!(write(&state, &mut buffer));
+        assert_eq!(read(&buffer), Some(state.as_slice()));
+        assert!(
+            buffer[STATE_LENGTH_SIZE + state.len()..]
+                .iter()
+                .all(|&byte| byte == 0)
+        );
+
+        assert!(!write(&state, &mut buffer[..state.len()]));
+        assert_eq!(read(&buffer[..state.len()]), None);
+    }
+
+    #[test]
+    fn test_size_fits_every_state() {
+        for renderer in [Renderer::Scanline, Renderer::Fifo] {
+            let mut gb = sprites_gameboy(renderer);
+
+            // Computed right after loading the game, like the frontend does.
+            let size = size(&gb);
+
+            let mut smallest = usize::MAX;
+            let mut largest = 0;
+
+            // Two frames, `JR -2` takes 12 cycles.
+            for _ in 0..2 * CPU_APPROX_M_CYCLES_PER_FRAME / 12 {
+                gb.step();
+
+                // Saving the whole state after every step is slow, only the lines with sprites
+                // are of interest.
+                if !(16..24).contains(&gb.memory().read(0xFF44)) {
+                    continue;
+                }
+
+                let length = gb.save_state().len();
+                smallest = smallest.min(length);
+                largest = largest.max(length);
+            }
+
+            assert!(STATE_LENGTH_SIZE + largest <= size, "{renderer:?}");
+            assert!(largest - smallest <= STATE_SLACK, "{renderer:?}");
+        }
+    }
+}
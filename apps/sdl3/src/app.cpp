@@ -2,6 +2,7 @@
 
 #include <array>
 #include <span>
+#include <string>
 #include <stdexcept>
 #include <string_view>
 #include <vector>
@@ -33,7 +34,13 @@ auto App::run() -> void {
   auto rom = read_binary_file(args[1]);
 
   auto* gb = gameboy_new(true);
-  gameboy_load(gb, {.data = nullptr, .size = 0}, {.data = rom.data(), .size = rom.size()});
+
+  if (gameboy_load(gb, {.data = nullptr, .size = 0}, {.data = rom.data(), .size = rom.size()}) != GB_OK) {
+    auto error = std::string{gameboy_last_error(gb)};
+    gameboy_destroy(gb);
+
+    throw std::runtime_error(error);
+  }
 
   std::array<u8, FRAMEBUFFER_SIZE> framebuffer = {};
 
@@ -16,7 +16,7 @@ final class GameBoyContext {
     func load(_ url: URL) throws {
         let rom = try [UInt8](Data(contentsOf: url))
 
-        gb.load(bootrom: nil, rom: rom)
+        try gb.load(bootrom: nil, rom: rom)
 
         timer = Timer.publish(every: 1 / 60, on: .current, in: .default)
             .autoconnect()
@@ -1,6 +1,10 @@
 import CGameBoyCore
 import Foundation
 
+public struct GameBoyError: Error, CustomStringConvertible {
+    public let description: String
+}
+
 public final class GameBoy {
     private let gb: OpaquePointer
 
@@ -15,14 +19,16 @@ public final class GameBoy {
         gameboy_destroy(gb)
     }
 
-    public func load(bootrom: [UInt8]?, rom: [UInt8]) {
+    public func load(bootrom: [UInt8]?, rom: [UInt8]) throws {
         let bootromPointer = bootrom?.withUnsafeBufferPointer { $0.baseAddress }
         let romPointer = rom.withUnsafeBufferPointer { $0.baseAddress }
 
         let gbBootrom = Bootrom(data: bootromPointer, size: bootrom?.count ?? 0)
         let gbRom = Rom(data: romPointer, size: rom.count)
 
-        gameboy_load(gb, gbBootrom, gbRom)
+        if gameboy_load(gb, gbBootrom, gbRom) != GB_OK {
+            throw GameBoyError(description: String(cString: gameboy_last_error(gb)))
+        }
     }
 
     public func runFrame() {
@@ -31,6 +31,29 @@ enum Button {
   DOWN = 7,
 };
 
+enum DeviceModel {
+  DMG = 0,
+  CGB = 1,
+};
+
+enum Status {
+  GB_OK = 0,
+  GB_ERROR_INVALID_ARGUMENT = 1,
+  GB_ERROR_INVALID_ROM = 2,
+  GB_ERROR_INVALID_BOOTROM = 3,
+  GB_ERROR_INVALID_STATE = 4,
+  GB_ERROR_BUFFER_TOO_SMALL = 5,
+  GB_ERROR_NO_CARTRIDGE = 6,
+};
+
+enum MbcType {
+  MBC_TYPE_NONE = 0,
+  MBC_TYPE_MBC1 = 1,
+  MBC_TYPE_MBC2 = 2,
+  MBC_TYPE_MBC3 = 3,
+  MBC_TYPE_MBC5 = 5,
+};
+
 struct GameBoy;
 
 struct Bootrom {
@@ -43,16 +66,59 @@ struct Rom {
   size_t size;
 };
 
+struct CartridgeInfo {
+  char title[17];
+  enum MbcType mbc_type;
+  size_t rom_banks;
+  size_t ram_banks;
+  bool has_battery;
+  bool has_rtc;
+  bool cgb_support;
+  bool cgb_only;
+  bool sgb_support;
+  uint8_t old_licensee_code;
+};
+
+struct Registers {
+  uint8_t a;
+  uint8_t f;
+  uint8_t b;
+  uint8_t c;
+  uint8_t d;
+  uint8_t e;
+  uint8_t h;
+  uint8_t l;
+  uint16_t sp;
+  uint16_t pc;
+  bool ime;
+};
+
 struct GameBoy* gameboy_new(bool is_cgb);
+struct GameBoy* gameboy_new_with_device_model(enum DeviceModel device_model);
 void gameboy_destroy(struct GameBoy* gb_ptr);
 void gameboy_reset(struct GameBoy* gb_ptr);
-bool gameboy_load(struct GameBoy* gb_ptr, struct Bootrom bootrom, struct Rom rom);
+void gameboy_power_cycle(struct GameBoy* gb_ptr);
+enum Status gameboy_load(struct GameBoy* gb_ptr, struct Bootrom bootrom, struct Rom rom);
 void gameboy_run_frame(struct GameBoy* gb_ptr);
 void gameboy_set_joypad_button(struct GameBoy* gb_ptr, enum Button button, bool value);
 void gameboy_joypad_button_up(struct GameBoy* gb_ptr, enum Button button);
 void gameboy_joypad_button_down(struct GameBoy* gb_ptr, enum Button button);
 void gameboy_draw_into_frame_rgba8888(struct GameBoy* gb_ptr, uint8_t* frame);
+void gameboy_draw_into_frame_bgra8888(struct GameBoy* gb_ptr, uint8_t* frame);
 void gameboy_add_audio_callback(struct GameBoy* gb_ptr, void* userdata, void (*callback)(void*, const float*, size_t));
+enum Status gameboy_set_audio_sample_rate(struct GameBoy* gb_ptr, uint32_t sample_rate);
+enum Status gameboy_set_audio_rate_adjustment(struct GameBoy* gb_ptr, double adjustment);
+const char* gameboy_last_error(struct GameBoy* gb_ptr);
+enum DeviceModel gameboy_device_model(struct GameBoy* gb_ptr);
+enum Status gameboy_get_battery(struct GameBoy* gb_ptr, uint8_t* buffer, size_t* size);
+enum Status gameboy_load_battery(struct GameBoy* gb_ptr, const uint8_t* data, size_t size);
+enum Status gameboy_save_state(struct GameBoy* gb_ptr, uint8_t* buffer, size_t* size);
+enum Status gameboy_load_state(struct GameBoy* gb_ptr, const uint8_t* data, size_t size);
+enum Status gameboy_get_cartridge_info(struct GameBoy* gb_ptr, struct CartridgeInfo* info);
+void gameboy_get_registers(struct GameBoy* gb_ptr, struct Registers* registers);
+uint8_t gameboy_peek(struct GameBoy* gb_ptr, uint16_t address);
+enum Status gameboy_poke(struct GameBoy* gb_ptr, uint16_t address, uint8_t value);
+void gameboy_set_serial_callback(struct GameBoy* gb_ptr, void* userdata, void (*callback)(void*, uint8_t));
 #ifdef __cplusplus
 }
 #endif
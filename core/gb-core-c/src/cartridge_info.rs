@@ -0,0 +1,72 @@
+use std::ffi::c_char;
+
+use gb_core::components::cartridge::info::{
+    Info,
+    cgb_flag::CgbFlag,
+    extra_features::ExtraFeature,
+    mbc_type::MbcType as CoreMbcType,
+    title::TITLE_SIZE,
+};
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub enum MbcType {
+    None = 0,
+    Mbc1 = 1,
+    Mbc2 = 2,
+    Mbc3 = 3,
+    Mbc5 = 5,
+}
+
+impl From<CoreMbcType> for MbcType {
+    fn from(value: CoreMbcType) -> Self {
+        match value {
+            CoreMbcType::NoMbc => Self::None,
+            CoreMbcType::Mbc1 => Self::Mbc1,
+            CoreMbcType::Mbc2 => Self::Mbc2,
+            CoreMbcType::Mbc3 => Self::Mbc3,
+            CoreMbcType::Mbc5 => Self::Mbc5,
+        }
+    }
+}
+
+#[repr(C)]
+pub struct CartridgeInfo {
+    /// Null-terminated ASCII.
+    pub title: [c_char; TITLE_SIZE + 1],
+    pub mbc_type: MbcType,
+    pub rom_banks: usize,
+    pub ram_banks: usize,
+    pub has_battery: bool,
+    pub has_rtc: bool,
+    pub cgb_support: bool,
+    pub cgb_only: bool,
+    pub sgb_support: bool,
+    pub old_licensee_code: u8,
+}
+
+impl From<&Info> for CartridgeInfo {
+    fn from(value: &Info) -> Self {
+        let mut title = [0; TITLE_SIZE + 1];
+
+        for (c, byte) in title[..TITLE_SIZE]
+            .iter_mut()
+            .zip(value.title.to_string().bytes())
+        {
+            *c = byte as c_char;
+        }
+
+        Self {
+            title,
+            mbc_type: value.mbc_type.into(),
+            rom_banks: value.rom_banks,
+            ram_banks: value.ram_banks,
+            has_battery: value.extra_features.contains(&ExtraFeature::Battery),
+            has_rtc: value.extra_features.contains(&ExtraFeature::Timer),
+            cgb_support: value.cgb_flag.has_cgb_support(),
+            cgb_only: value.cgb_flag == CgbFlag::CgbOnly,
+            sgb_support: value.sgb_flag,
+            old_licensee_code: value.licensee_code.old_code(),
+        }
+    }
+}
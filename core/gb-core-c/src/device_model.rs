@@ -0,0 +1,26 @@
+use gb_core::constants::DeviceModel as CoreDeviceModel;
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub enum DeviceModel {
+    DMG = 0,
+    CGB = 1,
+}
+
+impl From<DeviceModel> for CoreDeviceModel {
+    fn from(value: DeviceModel) -> Self {
+        match value {
+            DeviceModel::DMG => Self::Dmg,
+            DeviceModel::CGB => Self::Cgb,
+        }
+    }
+}
+
+impl From<CoreDeviceModel> for DeviceModel {
+    fn from(value: CoreDeviceModel) -> Self {
+        match value {
+            CoreDeviceModel::Dmg => Self::DMG,
+            CoreDeviceModel::Cgb => Self::CGB,
+        }
+    }
+}
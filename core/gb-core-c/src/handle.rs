@@ -0,0 +1,70 @@
+use std::{
+    ffi::{CString, c_void},
+    fmt::Display,
+    sync::mpsc,
+};
+
+use crate::status::Status;
+
+/// The opaque handle given to C, the core plus the state that only the C API needs.
+pub struct GameBoy {
+    pub core: gb_core::GameBoy,
+
+    serial: Option<SerialOutput>,
+    last_error: Option<CString>,
+}
+
+struct SerialOutput {
+    receiver: mpsc::Receiver<u8>,
+    userdata: *mut c_void,
+    callback: extern "C" fn(*mut c_void, u8),
+}
+
+impl GameBoy {
+    #[must_use]
+    pub fn new(core: gb_core::GameBoy) -> Self {
+        Self {
+            core,
+            serial: None,
+            last_error: None,
+        }
+    }
+
+    #[must_use]
+    pub fn last_error(&self) -> Option<&CString> {
+        self.last_error.as_ref()
+    }
+
+    /// Records the message for [`crate::gameboy_last_error`] and returns `status`.
+    pub fn fail(&mut self, status: Status, message: impl Display) -> Status {
+        self.last_error = CString::new(message.to_string()).ok();
+
+        status
+    }
+
+    pub fn set_serial_callback(
+        &mut self,
+        userdata: *mut c_void,
+        callback: extern "C" fn(*mut c_void, u8),
+    ) {
+        let (sender, receiver) = mpsc::channel();
+        self.core.add_serial_channel(sender);
+
+        self.serial = Some(SerialOutput {
+            receiver,
+            userdata,
+            callback,
+        });
+    }
+
+    /// Passes the bytes sent through the serial port since the last call to the callback.
+    pub fn flush_serial(&self) {
+        let Some(serial) = &self.serial else {
+            return;
+        };
+
+        for byte in serial.receiver.try_iter() {
+            (serial.callback)(serial.userdata, byte);
+        }
+    }
+}
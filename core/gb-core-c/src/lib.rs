@@ -1,25 +1,34 @@
-use std::ffi::{c_float, c_void};
+use std::ffi::{c_char, c_float, c_void};
 
 use button::Button;
+use cartridge_info::CartridgeInfo;
+use device_model::DeviceModel;
 use gb_core::{
-    GameBoy,
-    constants::{DeviceModel, SCREEN_HEIGHT, SCREEN_WIDTH, ScreenPixels},
+    components::memory::MemoryRegion,
+    constants::{SCREEN_HEIGHT, SCREEN_WIDTH, ScreenPixels},
 };
+use handle::GameBoy;
+use registers::Registers;
+use status::Status;
 use types::{Bootrom, Rom, ToSlice as _};
 
 #[unsafe(no_mangle)]
 pub extern "C" fn gameboy_new(is_cgb: bool) -> *mut GameBoy {
     let device_model = if is_cgb {
-        DeviceModel::Cgb
+        DeviceModel::CGB
     } else {
-        DeviceModel::Dmg
+        DeviceModel::DMG
     };
 
-    let gb = GameBoy::new(device_model);
+    gameboy_new_with_device_model(device_model)
+}
+
+#[unsafe(no_mangle)]
+pub extern "C" fn gameboy_new_with_device_model(device_model: DeviceModel) -> *mut GameBoy {
+    let gb = GameBoy::new(gb_core::GameBoy::new(device_model.into()));
 
     Box::into_raw(Box::new(gb))
 }
-
 /// # Safety
 ///
 /// The memory for the Game Boy core has to be allocated and valid
@@ -40,11 +49,21 @@ pub unsafe extern "C" fn gameboy_destroy(gb_ptr: *mut GameBoy) {
 /// The Game Boy core pointer cannot be null.
 #[unsafe(no_mangle)]
 pub unsafe extern "C" fn gameboy_reset(gb_ptr: *mut GameBoy) {
-    let gb = unsafe { &mut *gb_ptr };
+    let gb = unsafe { &mut (*gb_ptr).core };
 
     gb.reset();
 }
 
+/// # Safety
+///
+/// The Game Boy core pointer cannot be null.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn gameboy_power_cycle(gb_ptr: *mut GameBoy) {
+    let gb = unsafe { &mut (*gb_ptr).core };
+
+    gb.power_cycle();
+}
+
 /// # Safety
 ///
 /// 1. The Game Boy core pointer cannot be null.
@@ -52,19 +71,20 @@ pub unsafe extern "C" fn gameboy_reset(gb_ptr: *mut GameBoy) {
 /// 3. The allocated size for the ROM has to be equal to `rom.size`.
 /// 4. The bootrom is optional, but if provided, its allocated size has to be equal to `bootrom.size`.
 #[unsafe(no_mangle)]
-pub unsafe extern "C" fn gameboy_load(gb_ptr: *mut GameBoy, bootrom: Bootrom, rom: Rom) -> bool {
+pub unsafe extern "C" fn gameboy_load(gb_ptr: *mut GameBoy, bootrom: Bootrom, rom: Rom) -> Status {
     let gb = unsafe { &mut *gb_ptr };
 
     let rom = unsafe { rom.to_slice() };
     let bootrom = unsafe { bootrom.to_slice() };
 
     let Some(rom) = rom else {
-        return false;
+        return gb.fail(Status::InvalidArgument, "The ROM is missing.");
     };
 
-    gb.load(bootrom.map(Into::into), rom.into()).unwrap();
-
-    true
+    match gb.core.load(bootrom.map(Into::into), rom.into()) {
+        Ok(()) => Status::Ok,
+        Err(err) => gb.fail((&err).into(), err),
+    }
 }
 
 /// # Safety
@@ -74,7 +94,8 @@ pub unsafe extern "C" fn gameboy_load(gb_ptr: *mut GameBoy, bootrom: Bootrom, ro
 pub unsafe extern "C" fn gameboy_run_frame(gb_ptr: *mut GameBoy) {
     let gb = unsafe { &mut *gb_ptr };
 
-    gb.run_frame();
+    gb.core.run_frame();
+    gb.flush_serial();
 }
 
 /// # Safety
@@ -86,7 +107,7 @@ pub unsafe extern "C" fn gameboy_set_joypad_button(
     button: Button,
     value: bool,
 ) {
-    let gb = unsafe { &mut *gb_ptr };
+    let gb = unsafe { &mut (*gb_ptr).core };
 
     gb.set_joypad_button(button.into(), value);
 }
@@ -96,7 +117,7 @@ pub unsafe extern "C" fn gameboy_set_joypad_button(
 /// The Game Boy core pointer cannot be null.
 #[unsafe(no_mangle)]
 pub unsafe extern "C" fn gameboy_joypad_button_up(gb_ptr: *mut GameBoy, button: Button) {
-    let gb = unsafe { &mut *gb_ptr };
+    let gb = unsafe { &mut (*gb_ptr).core };
 
     gb.joypad_button_up(button.into());
 }
@@ -106,7 +127,7 @@ pub unsafe extern "C" fn gameboy_joypad_button_up(gb_ptr: *mut GameBoy, button:
 /// The Game Boy core pointer cannot be null.
 #[unsafe(no_mangle)]
 pub unsafe extern "C" fn gameboy_joypad_button_down(gb_ptr: *mut GameBoy, button: Button) {
-    let gb = unsafe { &mut *gb_ptr };
+    let gb = unsafe { &mut (*gb_ptr).core };
 
     gb.joypad_button_down(button.into());
 }
@@ -118,7 +139,7 @@ pub unsafe extern "C" fn gameboy_joypad_button_down(gb_ptr: *mut GameBoy, button
 /// 3. The allocated size for the frame has to be equal to `SCREEN_WIDTH * SCREEN_HEIGHT * 4`.
 #[unsafe(no_mangle)]
 pub unsafe extern "C" fn gameboy_draw_into_frame_rgba8888(gb_ptr: *mut GameBoy, frame: *mut u8) {
-    let gb = unsafe { &mut *gb_ptr };
+    let gb = unsafe { &mut (*gb_ptr).core };
 
     let slice: &mut ScreenPixels = unsafe {
         std::slice::from_raw_parts_mut(frame, SCREEN_WIDTH * SCREEN_HEIGHT * 4)
@@ -129,6 +150,268 @@ pub unsafe extern "C" fn gameboy_draw_into_frame_rgba8888(gb_ptr: *mut GameBoy,
     gb.draw_into_frame_rgba8888(slice);
 }
 
+/// # Safety
+///
+/// 1. The Game Boy core pointer cannot be null.
+/// 2. The frame array pointer cannot be null.
+/// 3. The allocated size for the frame has to be equal to `SCREEN_WIDTH * SCREEN_HEIGHT * 4`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn gameboy_draw_into_frame_bgra8888(gb_ptr: *mut GameBoy, frame: *mut u8) {
+    let gb = unsafe { &mut (*gb_ptr).core };
+
+    let slice: &mut ScreenPixels = unsafe {
+        std::slice::from_raw_parts_mut(frame, SCREEN_WIDTH * SCREEN_HEIGHT * 4)
+            .try_into()
+            .unwrap_unchecked()
+    };
+
+    gb.draw_into_frame_bgra8888(slice);
+}
+
+/// Returns the message of the last error, or null if nothing failed yet.
+///
+/// # Safety
+///
+/// 1. The Game Boy core pointer cannot be null.
+/// 2. The message is only valid until the next call that fails.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn gameboy_last_error(gb_ptr: *mut GameBoy) -> *const c_char {
+    let gb = unsafe { &*gb_ptr };
+
+    gb.last_error()
+        .map_or(std::ptr::null(), |message| message.as_ptr())
+}
+
+/// # Safety
+///
+/// The Game Boy core pointer cannot be null.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn gameboy_device_model(gb_ptr: *mut GameBoy) -> DeviceModel {
+    let gb = unsafe { &(*gb_ptr).core };
+
+    gb.device_model.into()
+}
+
+/// Copies the battery (cartridge RAM and RTC) into `buffer`.
+///
+/// `size` holds the size of `buffer` and is set to the size of the battery.
+/// Pass a null `buffer` to only query the size.
+///
+/// # Safety
+///
+/// 1. The Game Boy core pointer cannot be null.
+/// 2. `size` cannot be null.
+/// 3. The buffer is optional, but if provided, its allocated size has to be at least `*size`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn gameboy_get_battery(
+    gb_ptr: *mut GameBoy,
+    buffer: *mut u8,
+    size: *mut usize,
+) -> Status {
+    let gb = unsafe { &mut *gb_ptr };
+
+    if !gb.core.cartridge_inserted() {
+        return gb.fail(Status::NoCartridge, "No cartridge inserted.");
+    }
+
+    let battery = gb.core.get_battery().unwrap_or_default().to_vec();
+
+    unsafe { write_buffer(gb, &battery, buffer, size) }
+}
+
+/// # Safety
+///
+/// 1. The Game Boy core pointer cannot be null.
+/// 2. The data pointer cannot be null.
+/// 3. The allocated size for the data has to be equal to `size`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn gameboy_load_battery(
+    gb_ptr: *mut GameBoy,
+    data: *const u8,
+    size: usize,
+) -> Status {
+    let gb = unsafe { &mut *gb_ptr };
+
+    let Some(battery) = gb.core.get_battery().map(<[u8]>::len) else {
+        return gb.fail(Status::NoCartridge, "The cartridge has no battery.");
+    };
+
+    // The RTC footer is optional.
+    let ram = gb
+        .core
+        .memory()
+        .region(MemoryRegion::CartridgeRam)
+        .map_or(0, <[u8]>::len);
+
+    if size != battery && size != ram {
+        return gb.fail(
+            Status::InvalidArgument,
+            format!("Expected a battery of {battery} bytes, got {size}."),
+        );
+    }
+
+    let data = unsafe { std::slice::from_raw_parts(data, size) };
+    gb.core.load_battery(data.to_vec());
+
+    Status::Ok
+}
+
+/// Serializes the whole machine state into `buffer`.
+///
+/// `size` holds the size of `buffer` and is set to the size of the state.
+/// Pass a null `buffer` to only query the size, which can change between frames.
+///
+/// # Safety
+///
+/// 1. The Game Boy core pointer cannot be null.
+/// 2. `size` cannot be null.
+/// 3. The buffer is optional, but if provided, its allocated size has to be at least `*size`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn gameboy_save_state(
+    gb_ptr: *mut GameBoy,
+    buffer: *mut u8,
+    size: *mut usize,
+) -> Status {
+    let gb = unsafe { &mut *gb_ptr };
+
+    if !gb.core.cartridge_inserted() {
+        return gb.fail(Status::NoCartridge, "No cartridge inserted.");
+    }
+
+    let state = gb.core.save_state();
+
+    unsafe { write_buffer(gb, &state, buffer, size) }
+}
+
+/// The current state is left untouched if the save state is rejected.
+///
+/// # Safety
+///
+/// 1. The Game Boy core pointer cannot be null.
+/// 2. The data pointer cannot be null.
+/// 3. The allocated size for the data has to be equal to `size`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn gameboy_load_state(
+    gb_ptr: *mut GameBoy,
+    data: *const u8,
+    size: usize,
+) -> Status {
+    let gb = unsafe { &mut *gb_ptr };
+
+    let state = unsafe { std::slice::from_raw_parts(data, size) };
+
+    match gb.core.load_state(state) {
+        Ok(()) => Status::Ok,
+        Err(err) => gb.fail((&err).into(), err),
+    }
+}
+
+/// # Safety
+///
+/// 1. The Game Boy core pointer cannot be null.
+/// 2. The info pointer cannot be null.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn gameboy_get_cartridge_info(
+    gb_ptr: *mut GameBoy,
+    info: *mut CartridgeInfo,
+) -> Status {
+    let gb = unsafe { &mut *gb_ptr };
+
+    let Some(cartridge_info) = gb.core.cartridge_info() else {
+        return gb.fail(Status::NoCartridge, "No cartridge inserted.");
+    };
+
+    unsafe {
+        info.write(cartridge_info.into());
+    }
+
+    Status::Ok
+}
+
+/// # Safety
+///
+/// 1. The Game Boy core pointer cannot be null.
+/// 2. The registers pointer cannot be null.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn gameboy_get_registers(gb_ptr: *mut GameBoy, registers: *mut Registers) {
+    let gb = unsafe { &(*gb_ptr).core };
+
+    unsafe {
+        registers.write(gb.cpu().registers().into());
+    }
+}
+
+/// Reads the memory as seen by the CPU, without side effects.
+/// Unmapped memory, like the cartridge without a cartridge, reads as 0xFF.
+///
+/// # Safety
+///
+/// The Game Boy core pointer cannot be null.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn gameboy_peek(gb_ptr: *mut GameBoy, address: u16) -> u8 {
+    let gb = unsafe { &(*gb_ptr).core };
+
+    gb.memory().peek(address)
+}
+
+/// Writes to the memory as the CPU would, so writes to the ROM area still switch banks.
+///
+/// # Safety
+///
+/// The Game Boy core pointer cannot be null.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn gameboy_poke(gb_ptr: *mut GameBoy, address: u16, value: u8) -> Status {
+    let gb = unsafe { &mut *gb_ptr };
+
+    match gb.core.memory_mut().poke(address, value) {
+        Ok(()) => Status::Ok,
+        Err(err) => gb.fail(Status::NoCartridge, err),
+    }
+}
+
+/// `callback` is called with every byte sent through the serial port, at the end of each frame.
+///
+/// # Safety
+///
+/// 1. The Game Boy core pointer cannot be null.
+/// 2. `userdata` should always be valid.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn gameboy_set_serial_callback(
+    gb_ptr: *mut GameBoy,
+    userdata: *mut c_void,
+    callback: extern "C" fn(*mut c_void, u8),
+) {
+    let gb = unsafe { &mut *gb_ptr };
+
+    gb.set_serial_callback(userdata, callback);
+}
+
+/// # Safety
+///
+/// 1. `size` cannot be null.
+/// 2. The buffer is optional, but if provided, its allocated size has to be at least `*size`.
+unsafe fn write_buffer(gb: &mut GameBoy, data: &[u8], buffer: *mut u8, size: *mut usize) -> Status {
+    let size = unsafe { &mut *size };
+    let capacity = std::mem::replace(size, data.len());
+
+    if buffer.is_null() {
+        return Status::Ok;
+    }
+
+    if capacity < data.len() {
+        return gb.fail(
+            Status::BufferTooSmall,
+            format!("Expected a buffer of {} bytes, got {capacity}.", data.len()),
+        );
+    }
+
+    unsafe {
+        std::ptr::copy_nonoverlapping(data.as_ptr(), buffer, data.len());
+    }
+
+    Status::Ok
+}
+
 #[derive(Copy, Clone)]
 struct Userdata(*mut c_void);
 unsafe impl Send for Userdata {}
@@ -149,7 +432,7 @@ pub unsafe extern "C" fn gameboy_add_audio_callback(
     userdata: *mut c_void,
     callback: extern "C" fn(*mut c_void, *const c_float, usize),
 ) {
-    let gb = unsafe { &mut *gb_ptr };
+    let gb = unsafe { &mut (*gb_ptr).core };
 
     let userdata = Userdata(userdata);
     let callback = Callback(callback);
@@ -161,5 +444,152 @@ pub unsafe extern "C" fn gameboy_add_audio_callback(
     }));
 }
 
+/// # Safety
+///
+/// The Game Boy core pointer cannot be null.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn gameboy_set_audio_sample_rate(
+    gb_ptr: *mut GameBoy,
+    sample_rate: u32,
+) -> Status {
+    let gb = unsafe { &mut *gb_ptr };
+
+    if sample_rate == 0 {
+        return gb.fail(Status::InvalidArgument, "The sample rate cannot be 0.");
+    }
+
+    gb.core.set_audio_sample_rate(sample_rate as usize);
+
+    Status::Ok
+}
+
+/// # Safety
+///
+/// The Game Boy core pointer cannot be null.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn gameboy_set_audio_rate_adjustment(
+    gb_ptr: *mut GameBoy,
+    adjustment: f64,
+) -> Status {
+    let gb = unsafe { &mut *gb_ptr };
+
+    if !adjustment.is_finite() {
+        return gb.fail(
+            Status::InvalidArgument,
+            "The rate adjustment has to be finite.",
+        );
+    }
+
+    gb.core.set_audio_rate_adjustment(adjustment);
+
+    Status::Ok
+}
+
 pub mod button;
+pub mod cartridge_info;
+pub mod device_model;
+pub mod handle;
+pub mod registers;
+pub mod status;
 pub mod types;
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::CStr;
+
+    use gb_core::test_rom::TestRom;
+
+    use super::*;
+
+    #[test]
+    fn test_peek_poke_without_cartridge() {
+        let gb_ptr = gameboy_new(true);
+
+        unsafe {
+            // Open bus instead of a panic.
+            assert_eq!(gameboy_peek(gb_ptr, 0xFEA0), 0xFF);
+            assert_eq!(gameboy_peek(gb_ptr, 0x0150), 0xFF);
+            assert_eq!(gameboy_peek(gb_ptr, 0xA000), 0xFF);
+
+            assert_eq!(gameboy_poke(gb_ptr, 0x2000, 0x01), Status::NoCartridge);
+            assert_eq!(gameboy_poke(gb_ptr, 0xB000, 0x01), Status::NoCartridge);
+
+            let message = CStr::from_ptr(gameboy_last_error(gb_ptr));
+            assert_eq!(message.to_str(), Ok("No cartridge inserted."));
+
+            assert_eq!(gameboy_poke(gb_ptr, 0xFEA0, 0x42), Status::Ok);
+            assert_eq!(gameboy_poke(gb_ptr, 0xC000, 0x42), Status::Ok);
+            assert_eq!(gameboy_peek(gb_ptr, 0xC000), 0x42);
+
+            gameboy_destroy(gb_ptr);
+        }
+    }
+
+    #[test]
+    fn test_invalid_sample_rate() {
+        let gb_ptr = gameboy_new(true);
+
+        unsafe {
+            assert_eq!(
+                gameboy_set_audio_sample_rate(gb_ptr, 0),
+                Status::InvalidArgument
+            );
+
+            let message = CStr::from_ptr(gameboy_last_error(gb_ptr));
+            assert_eq!(message.to_str(), Ok("The sample rate cannot be 0."));
+
+            assert_eq!(gameboy_set_audio_sample_rate(gb_ptr, 48000), Status::Ok);
+
+            gameboy_destroy(gb_ptr);
+        }
+    }
+
+    #[test]
+    fn test_invalid_rate_adjustment() {
+        let gb_ptr = gameboy_new(true);
+
+        unsafe {
+            assert_eq!(
+                gameboy_set_audio_rate_adjustment(gb_ptr, f64::NAN),
+                Status::InvalidArgument
+            );
+
+            let message = CStr::from_ptr(gameboy_last_error(gb_ptr));
+            assert_eq!(
+                message.to_str(),
+                Ok("The rate adjustment has to be finite.")
+            );
+
+            assert_eq!(gameboy_set_audio_rate_adjustment(gb_ptr, 0.001), Status::Ok);
+
+            gameboy_destroy(gb_ptr);
+        }
+    }
+
+    #[test]
+    fn test_buffer_too_small() {
+        let gb_ptr = gameboy_new(true);
+        let rom = TestRom::new().mbc(0x03).ram_size(0x02).build();
+
+        unsafe {
+            (*gb_ptr).core.load(None, rom).unwrap();
+
+            let mut buffer = [0; 0x1000];
+            let mut size = buffer.len();
+
+            assert_eq!(
+                gameboy_get_battery(gb_ptr, buffer.as_mut_ptr(), &raw mut size),
+                Status::BufferTooSmall
+            );
+            assert_eq!(size, 0x2000);
+
+            let message = CStr::from_ptr(gameboy_last_error(gb_ptr));
+            assert_eq!(
+                message.to_str(),
+                Ok("Expected a buffer of 8192 bytes, got 4096.")
+            );
+
+            gameboy_destroy(gb_ptr);
+        }
+    }
+}
@@ -0,0 +1,34 @@
+use gb_core::components::cpu::registers::Registers as CoreRegisters;
+
+#[repr(C)]
+pub struct Registers {
+    pub a: u8,
+    pub f: u8,
+    pub b: u8,
+    pub c: u8,
+    pub d: u8,
+    pub e: u8,
+    pub h: u8,
+    pub l: u8,
+    pub sp: u16,
+    pub pc: u16,
+    pub ime: bool,
+}
+
+impl From<&CoreRegisters> for Registers {
+    fn from(value: &CoreRegisters) -> Self {
+        Self {
+            a: value.a,
+            f: value.f.bits(),
+            b: value.b,
+            c: value.c,
+            d: value.d,
+            e: value.e,
+            h: value.h,
+            l: value.l,
+            sp: value.sp,
+            pc: value.pc,
+            ime: value.ime.is_enabled(),
+        }
+    }
+}
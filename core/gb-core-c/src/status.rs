@@ -0,0 +1,32 @@
+use gb_core::{error::GameBoyError, save_state::SaveStateError};
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Status {
+    Ok = 0,
+    InvalidArgument = 1,
+    InvalidRom = 2,
+    InvalidBootrom = 3,
+    InvalidState = 4,
+    BufferTooSmall = 5,
+    NoCartridge = 6,
+}
+
+impl From<&GameBoyError> for Status {
+    fn from(value: &GameBoyError) -> Self {
+        match value {
+            GameBoyError::BootromError(_) => Self::InvalidBootrom,
+            GameBoyError::CartridgeError(_) => Self::InvalidRom,
+            GameBoyError::SaveStateError(err) => err.into(),
+        }
+    }
+}
+
+impl From<&SaveStateError> for Status {
+    fn from(value: &SaveStateError) -> Self {
+        match value {
+            SaveStateError::NoCartridge => Self::NoCartridge,
+            _ => Self::InvalidState,
+        }
+    }
+}
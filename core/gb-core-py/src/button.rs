@@ -0,0 +1,30 @@
+use gb_core::utils::button::Button as CoreButton;
+use pyo3::prelude::*;
+
+#[pyclass(eq, eq_int, frozen, from_py_object, module = "gameboy")]
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Button {
+    A,
+    B,
+    Select,
+    Start,
+    Right,
+    Left,
+    Up,
+    Down,
+}
+
+impl From<Button> for CoreButton {
+    fn from(value: Button) -> Self {
+        match value {
+            Button::A => Self::A,
+            Button::B => Self::B,
+            Button::Select => Self::Select,
+            Button::Start => Self::Start,
+            Button::Right => Self::Right,
+            Button::Left => Self::Left,
+            Button::Up => Self::Up,
+            Button::Down => Self::Down,
+        }
+    }
+}
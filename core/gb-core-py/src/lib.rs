@@ -0,0 +1,190 @@
+use std::sync::Arc;
+
+use button::Button;
+use gb_core::{constants::DeviceModel, utils::button::Button as CoreButton};
+use pyo3::{
+    exceptions::{PyRuntimeError, PyValueError},
+    prelude::*,
+    types::PyBytes,
+};
+use screen::Screen;
+
+/// A Game Boy (Color) that runs as fast as the host allows.
+#[pyclass(module = "gameboy")]
+pub struct GameBoy {
+    gb: gb_core::GameBoy,
+    screen: Py<Screen>,
+}
+
+#[pymethods]
+impl GameBoy {
+    #[new]
+    #[pyo3(signature = (cgb = true))]
+    fn new(py: Python<'_>, cgb: bool) -> PyResult<Self> {
+        let device_model = if cgb {
+            DeviceModel::Cgb
+        } else {
+            DeviceModel::Dmg
+        };
+
+        Ok(Self {
+            gb: gb_core::GameBoy::new(device_model),
+            screen: Py::new(py, Screen::new())?,
+        })
+    }
+
+    /// Inserts the cartridge and powers the system on. Raises `ValueError` if the ROM or the
+    /// bootrom are invalid.
+    #[pyo3(signature = (rom, bootrom = None))]
+    fn load(&mut self, rom: &[u8], bootrom: Option<&[u8]>) -> PyResult<()> {
+        let bootrom = bootrom.map(Arc::from);
+
+        self.gb
+            .load(bootrom, rom.into())
+            .map_err(|err| PyValueError::new_err(err.to_string()))
+    }
+
+    fn reset(&mut self) {
+        self.gb.reset();
+    }
+
+    /// Runs `frames` frames while holding `buttons`, then updates the screen.
+    ///
+    /// Buttons that are not listed are released.
+    #[pyo3(signature = (frames = 1, buttons = Vec::new()))]
+    fn run_frames(&mut self, py: Python<'_>, frames: usize, buttons: Vec<Button>) {
+        for button in CoreButton::ALL_CASES {
+            self.gb.set_joypad_button(button, false);
+        }
+
+        for button in buttons {
+            self.gb.set_joypad_button(button.into(), true);
+        }
+
+        // Other Python threads can run in the meantime.
+        let gb = &mut self.gb;
+        py.detach(|| {
+            for _ in 0..frames {
+                gb.run_frame();
+            }
+        });
+
+        self.gb
+            .draw_into_frame_rgba8888(self.screen.borrow_mut(py).pixels_mut());
+    }
+
+    /// The last frame, see `Screen`.
+    #[getter]
+    fn screen(&self, py: Python<'_>) -> Py<Screen> {
+        self.screen.clone_ref(py)
+    }
+
+    /// Reads the memory as seen by the CPU, without side effects.
+    /// Unmapped memory, like the cartridge without a cartridge, reads as 0xFF.
+    fn read_memory(&self, address: u16) -> u8 {
+        self.gb.memory().peek(address)
+    }
+
+    /// Reads `length` bytes starting at `address`. The range can't go past 0xFFFF.
+    fn read_memory_range<'py>(
+        &self,
+        py: Python<'py>,
+        address: u16,
+        length: usize,
+    ) -> PyResult<Bound<'py, PyBytes>> {
+        let end = address as usize + length;
+
+        if end > 0x10000 {
+            return Err(PyValueError::new_err("The range goes past 0xFFFF."));
+        }
+
+        let memory = self.gb.memory();
+        let bytes = (address as usize..end)
+            .map(|address| memory.peek(address as u16))
+            .collect::<Vec<_>>();
+
+        Ok(PyBytes::new(py, &bytes))
+    }
+
+    /// Writes to the memory as the CPU would, so writes to the ROM area still switch banks.
+    /// Raises `RuntimeError` if the write targets the cartridge before one is loaded.
+    fn write_memory(&mut self, address: u16, value: u8) -> PyResult<()> {
+        self.gb
+            .memory_mut()
+            .poke(address, value)
+            .map_err(|err| PyRuntimeError::new_err(err.to_string()))
+    }
+
+    fn save_state<'py>(&self, py: Python<'py>) -> Bound<'py, PyBytes> {
+        PyBytes::new(py, &self.gb.save_state())
+    }
+
+    /// Raises `ValueError` and leaves the current state untouched if the state is rejected.
+    fn load_state(&mut self, state: &[u8]) -> PyResult<()> {
+        self.gb
+            .load_state(state)
+            .map_err(|err| PyValueError::new_err(err.to_string()))
+    }
+}
+
+#[pymodule]
+fn gameboy(module: &Bound<'_, PyModule>) -> PyResult<()> {
+    module.add_class::<GameBoy>()?;
+    module.add_class::<Button>()?;
+    module.add_class::<Screen>()?;
+
+    Ok(())
+}
+
+mod button;
+mod screen;
+
+#[cfg(test)]
+mod tests {
+    use gb_core::test_rom::TestRom;
+    use pyo3::types::PyDict;
+
+    use super::*;
+
+    #[test]
+    fn test_smoke() {
+        // LD A, 0x42; LD (0xC000), A; JR -2
+        let rom = TestRom::new()
+            .program(&[0x3E, 0x42, 0xEA, 0x00, 0xC0, 0x18, 0xFE])
+            .build();
+
+        Python::initialize();
+        Python::attach(|py| {
+            let module = PyModule::new(py, "gameboy")?;
+            gameboy(&module)?;
+
+            let locals = PyDict::new(py);
+            locals.set_item("gameboy", module)?;
+            locals.set_item("rom", PyBytes::new(py, &rom))?;
+
+            py.run(
+                cr"
+gb = gameboy.GameBoy(cgb=False)
+gb.load(rom)
+gb.run_frames(2, [gameboy.Button.A])
+
+screen = memoryview(gb.screen)
+assert screen.shape == (144, 160, 4)
+assert screen.readonly
+assert screen[0, 0, 3] == 0xFF
+
+assert gb.read_memory(0xC000) == 0x42
+state = gb.save_state()
+
+gb.write_memory(0xC000, 0x00)
+gb.load_state(state)
+assert gb.read_memory(0xC000) == 0x42
+assert gb.save_state() == state
+",
+                None,
+                Some(&locals),
+            )
+        })
+        .unwrap();
+    }
+}
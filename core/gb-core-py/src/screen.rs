@@ -0,0 +1,103 @@
+use std::ffi::{c_int, c_void};
+
+use gb_core::constants::{SCREEN_HEIGHT, SCREEN_PIXELS_SIZE, SCREEN_WIDTH, ScreenPixels};
+use pyo3::{exceptions::PyBufferError, ffi, prelude::*};
+
+const CHANNELS: usize = 4;
+
+/// `(height, width, channels)`, as expected by image libraries.
+static SHAPE: [ffi::Py_ssize_t; 3] = [
+    SCREEN_HEIGHT as ffi::Py_ssize_t,
+    SCREEN_WIDTH as ffi::Py_ssize_t,
+    CHANNELS as ffi::Py_ssize_t,
+];
+
+static STRIDES: [ffi::Py_ssize_t; 3] = [
+    (SCREEN_WIDTH * CHANNELS) as ffi::Py_ssize_t,
+    CHANNELS as ffi::Py_ssize_t,
+    1,
+];
+
+/// The last frame in RGBA8888, exposed through the buffer protocol.
+///
+/// The buffer is a live view: `numpy.asarray(gb.screen)` is updated in place by every call to
+/// `GameBoy.run_frames`, without copying. Copy it to keep a frame around.
+#[pyclass(module = "gameboy")]
+pub struct Screen {
+    pixels: Box<ScreenPixels>,
+}
+
+impl Screen {
+    pub fn new() -> Self {
+        Self {
+            pixels: vec![0; SCREEN_PIXELS_SIZE]
+                .into_boxed_slice()
+                .try_into()
+                .unwrap(),
+        }
+    }
+
+    pub fn pixels_mut(&mut self) -> &mut ScreenPixels {
+        &mut self.pixels
+    }
+}
+
+#[pymethods]
+impl Screen {
+    /// # Safety
+    ///
+    /// `view` has to be valid, which is guaranteed by the Python interpreter.
+    unsafe fn __getbuffer__(
+        slf: Bound<'_, Self>,
+        view: *mut ffi::Py_buffer,
+        flags: c_int,
+    ) -> PyResult<()> {
+        if view.is_null() {
+            return Err(PyBufferError::new_err("View is null."));
+        }
+
+        if (flags & ffi::PyBUF_WRITABLE) == ffi::PyBUF_WRITABLE {
+            return Err(PyBufferError::new_err("The screen is read-only."));
+        }
+
+        // The pixels are boxed, so the pointer stays valid while the view keeps `slf` alive.
+        let buf = slf.borrow().pixels.as_ptr().cast::<c_void>().cast_mut();
+
+        unsafe {
+            (*view).obj = slf.into_any().into_ptr();
+            (*view).buf = buf;
+            (*view).len = SCREEN_PIXELS_SIZE as ffi::Py_ssize_t;
+            (*view).readonly = 1;
+            (*view).itemsize = 1;
+
+            (*view).format = if (flags & ffi::PyBUF_FORMAT) == ffi::PyBUF_FORMAT {
+                c"B".as_ptr().cast_mut()
+            } else {
+                std::ptr::null_mut()
+            };
+
+            (*view).ndim = SHAPE.len() as c_int;
+
+            (*view).shape = if (flags & ffi::PyBUF_ND) == ffi::PyBUF_ND {
+                SHAPE.as_ptr().cast_mut()
+            } else {
+                std::ptr::null_mut()
+            };
+
+            (*view).strides = if (flags & ffi::PyBUF_STRIDES) == ffi::PyBUF_STRIDES {
+                STRIDES.as_ptr().cast_mut()
+            } else {
+                std::ptr::null_mut()
+            };
+
+            (*view).suboffsets = std::ptr::null_mut();
+            (*view).internal = std::ptr::null_mut();
+        }
+
+        Ok(())
+    }
+
+    fn __len__(&self) -> usize {
+        SCREEN_PIXELS_SIZE
+    }
+}
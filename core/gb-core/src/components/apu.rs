@@ -2,19 +2,35 @@ use bitflags::bitflags;
 
 use self::channels::{Channel1, Channel2, Channel3, Channel4};
 use crate::{
-    components::apu::{frame_sequencer::FrameSequencer, high_pass_filter::HighPassFilter},
+    components::apu::{
+        blip_buffer::BlipBuffer,
+        channel_taps::ChannelTaps,
+        frame_sequencer::FrameSequencer,
+        high_pass_filter::HighPassFilter,
+    },
     constants::{CPU_CLOCK_RATE, DeviceModel},
+    save_state::{SaveState, SaveStateError, StateReader, StateWriter},
     utils::macros::device_is_cgb,
 };
 
+/// Default output rate, see [`Apu::set_sample_rate`].
 pub const AUDIO_SAMPLE_RATE: usize = 44100;
+/// How far [`Apu::set_rate_adjustment`] can nudge the output rate, relative to the sample rate.
+pub const MAX_RATE_ADJUSTMENT: f64 = 0.005;
 pub const AUDIO_BUFFER_SIZE: usize = 4096;
-const AUDIO_CYCLES_PER_SAMPLE: usize = CPU_CLOCK_RATE / AUDIO_SAMPLE_RATE; // 95
 
 pub type StereoSample = [f32; 2];
 pub type AudioBuffer = [f32; AUDIO_BUFFER_SIZE];
 pub type Callback = dyn Fn(&[f32]) + Send + Sync;
 
+/// Samples per frame sent to the [`ChannelsCallback`], one for each channel from CH1 to CH4.
+pub const CHANNEL_COUNT: usize = 4;
+/// Receives the output of each channel before panning and mixing, see [`CHANNEL_COUNT`].
+///
+/// The channels keep their level in the mix: at full volume and panned to both sides,
+/// they add up to the mixed samples.
+pub type ChannelsCallback = dyn Fn(&[f32]) + Send + Sync;
+
 bitflags! {
     #[derive(Debug, Copy, Clone)]
     pub struct Channels: u8 {
@@ -28,7 +44,6 @@ bitflags! {
 #[allow(clippy::struct_excessive_bools)]
 pub struct Apu {
     prev_system_div: u8,
-    internal_cycles: usize,
 
     frame_sequencer: FrameSequencer,
 
@@ -54,21 +69,27 @@ pub struct Apu {
     cgb_mode: bool,
     double_speed: bool,
 
+    /// Turns the output into samples, ticking at the single speed clock rate.
+    blip: BlipBuffer,
+    sample_rate: usize,
+    rate_adjustment: f64,
+
     hpf_left: HighPassFilter,
     hpf_right: HighPassFilter,
 
     buffer: AudioBuffer,
     buffer_position: usize,
     callback: Option<Box<Callback>>,
+    channel_taps: Option<ChannelTaps>,
 
     pub ui_channel_overrides: Channels,
 }
 
 impl Default for Apu {
+    #[allow(clippy::cast_precision_loss)]
     fn default() -> Self {
         Self {
             prev_system_div: 0,
-            internal_cycles: 0,
             frame_sequencer: FrameSequencer::default(),
             channel1: Channel1::default(),
             channel2: Channel2::default(),
@@ -84,11 +105,15 @@ impl Default for Apu {
             cgb_mode: false,
             device_model: DeviceModel::default(),
             double_speed: false,
-            hpf_left: HighPassFilter::default(),
-            hpf_right: HighPassFilter::default(),
+            blip: BlipBuffer::new(CPU_CLOCK_RATE, AUDIO_SAMPLE_RATE as f64),
+            sample_rate: AUDIO_SAMPLE_RATE,
+            rate_adjustment: 0.0,
+            hpf_left: HighPassFilter::with_sample_rate(AUDIO_SAMPLE_RATE),
+            hpf_right: HighPassFilter::with_sample_rate(AUDIO_SAMPLE_RATE),
             buffer: [0.0; AUDIO_BUFFER_SIZE],
             buffer_position: 0,
             callback: None,
+            channel_taps: None,
             ui_channel_overrides: Channels::all(),
         }
     }
@@ -121,6 +146,62 @@ impl Apu {
         self.double_speed = value;
     }
 
+    #[must_use]
+    pub fn sample_rate(&self) -> usize {
+        self.sample_rate
+    }
+
+    /// Changes the rate of the samples sent to the callback, e.g. to the one of the audio device.
+    ///
+    /// A sample rate of 0 is ignored, the current one is kept.
+    pub fn set_sample_rate(&mut self, sample_rate: usize) {
+        // The filters and the resampler divide by it.
+        if sample_rate == 0 {
+            return;
+        }
+
+        self.sample_rate = sample_rate;
+        self.hpf_left.set_sample_rate(sample_rate);
+        self.hpf_right.set_sample_rate(sample_rate);
+        self.update_blip_rate();
+    }
+
+    #[must_use]
+    pub fn rate_adjustment(&self) -> f64 {
+        self.rate_adjustment
+    }
+
+    /// Nudges the output rate, for dynamic rate control: 0.001 makes 0.1% more samples.
+    ///
+    /// Frontends paced by the audio device can derive it from the fill level of their buffer,
+    /// so that it neither underruns nor overruns. Clamped to [`MAX_RATE_ADJUSTMENT`],
+    /// a NaN or infinite adjustment is ignored (treated as 0).
+    pub fn set_rate_adjustment(&mut self, adjustment: f64) {
+        // A NaN would go through the clamp and stop the resampler for good.
+        let adjustment = if adjustment.is_finite() {
+            adjustment
+        } else {
+            0.0
+        };
+
+        self.rate_adjustment = adjustment.clamp(-MAX_RATE_ADJUSTMENT, MAX_RATE_ADJUSTMENT);
+        self.update_blip_rate();
+    }
+
+    #[allow(clippy::cast_precision_loss)]
+    fn adjusted_sample_rate(&self) -> f64 {
+        self.sample_rate as f64 * (1.0 + self.rate_adjustment)
+    }
+
+    fn update_blip_rate(&mut self) {
+        let adjusted_rate = self.adjusted_sample_rate();
+        self.blip.set_rates(CPU_CLOCK_RATE, adjusted_rate);
+
+        if let Some(taps) = &mut self.channel_taps {
+            taps.set_rates(self.sample_rate, adjusted_rate);
+        }
+    }
+
     pub fn skip_bootrom(&mut self) {
         self.channel1.write_nr10(0x80);
         self.channel1.write_nr11(0xBF);
@@ -208,11 +289,78 @@ impl Apu {
 
         self.prev_system_div = div;
 
-        if self.internal_cycles >= AUDIO_CYCLES_PER_SAMPLE {
-            self.internal_cycles = 0;
-            self.push_sample();
+        self.advance_blips(1);
+        self.update_output();
+    }
+
+    /// Same as calling [`Apu::tick`] `ticks` times, skipping over the ticks where nothing happens,
+    /// then sends the samples that are ready to the callback.
+    ///
+    /// `counter` is the timer's system counter seen by the last tick,
+    /// and it advances by `stride` between ticks (the DIV must not be reset in the meantime).
+    pub fn advance(&mut self, mut ticks: u64, counter: u16, stride: u16) {
+        if ticks == 0 {
+            return;
+        }
+
+        // Wrapping around is fine, the counter is 16 bits wide.
+        let mut counter = counter.wrapping_sub((stride as u64 * (ticks - 1)) as u16);
+
+        // The DIV might have been reset since the previous tick, so there's no shortcut here.
+        self.tick((counter >> 8) as u8);
+        ticks -= 1;
+
+        while ticks > 0 {
+            let until_step = if self.audio_on {
+                self.ticks_until_frame_sequencer_step(counter, stride)
+            } else {
+                u64::MAX
+            };
+
+            let idle_ticks = self
+                .ticks_until_output_change()
+                .min(until_step)
+                .min(ticks + 1)
+                - 1;
+
+            self.channel1.advance(idle_ticks);
+            self.channel2.advance(idle_ticks);
+            self.channel3.advance(idle_ticks);
+            self.channel4.advance(idle_ticks);
+
+            self.advance_blips(idle_ticks);
+            counter = counter.wrapping_add((stride as u64 * idle_ticks) as u16);
+            self.prev_system_div = (counter >> 8) as u8;
+            ticks -= idle_ticks;
+
+            if ticks > 0 {
+                counter = counter.wrapping_add(stride);
+                self.tick((counter >> 8) as u8);
+                ticks -= 1;
+            }
         }
-        self.internal_cycles += 1;
+
+        self.read_samples();
+    }
+
+    /// Ticks until the next step of the waveform of any channel that is playing.
+    ///
+    /// The output is constant in the meantime, unless the frame sequencer steps.
+    fn ticks_until_output_change(&self) -> u64 {
+        self.channel1
+            .ticks_until_step()
+            .min(self.channel2.ticks_until_step())
+            .min(self.channel3.ticks_until_step())
+            .min(self.channel4.ticks_until_step())
+    }
+
+    /// The frame sequencer steps when the DIV bit falls, that is, every time the counter goes
+    /// past a multiple of twice the bit.
+    fn ticks_until_frame_sequencer_step(&self, counter: u16, stride: u16) -> u64 {
+        let period: u64 = if self.double_speed { 1 << 14 } else { 1 << 13 };
+        let position = counter as u64 & (period - 1);
+
+        (period - position).div_ceil(stride as u64)
     }
 
     pub fn add_callback(&mut self, callback: Box<Callback>) {
@@ -223,6 +371,36 @@ impl Apu {
         self.callback.take()
     }
 
+    /// Also sends the output of each channel, at the same rate as the mixed samples.
+    pub fn add_channels_callback(&mut self, callback: Box<ChannelsCallback>) {
+        let mut taps = ChannelTaps::new(callback, self.sample_rate, self.adjusted_sample_rate());
+        taps.set_amplitudes(self.channel_outputs());
+
+        self.channel_taps = Some(taps);
+    }
+
+    pub fn take_channels_callback(&mut self) -> Option<Box<ChannelsCallback>> {
+        self.channel_taps.take().map(ChannelTaps::into_callback)
+    }
+
+    /// Sends the buffered samples to the callbacks, even if the buffers are not full yet.
+    pub fn flush(&mut self) {
+        self.read_samples();
+
+        if let Some(taps) = &mut self.channel_taps {
+            taps.flush();
+        }
+
+        let Some(callback) = &self.callback else {
+            return;
+        };
+
+        if self.buffer_position != 0 {
+            callback(&self.buffer[0..self.buffer_position]);
+            self.buffer_position = 0;
+        }
+    }
+
     #[must_use]
     pub fn read(&self, address: u16) -> u8 {
         match address {
@@ -348,6 +526,8 @@ impl Apu {
 
             _ => unreachable!("[apu.rs] Invalid write: ({address:#06x}) = {value:#04x}"),
         }
+
+        self.update_output();
     }
 
     fn read_nr50(&self) -> u8 {
@@ -410,21 +590,50 @@ impl Apu {
         }
     }
 
-    fn push_sample(&mut self) {
-        let [left, right] = self.mix();
+    fn advance_blips(&mut self, ticks: u64) {
+        self.blip.advance(ticks);
 
-        // Implies audio is enabled. Otherwise, skip adding samples to the buffer.
-        let Some(callback) = &self.callback else {
-            return;
-        };
+        if let Some(taps) = &mut self.channel_taps {
+            taps.advance(ticks);
+        }
+    }
 
-        self.buffer[self.buffer_position] = left;
-        self.buffer[self.buffer_position + 1] = right;
-        self.buffer_position += 2;
+    /// Records the current output, which takes effect at the current tick.
+    fn update_output(&mut self) {
+        let output = self.mix();
+        self.blip.set_amplitude(output);
 
-        if self.buffer_position >= AUDIO_BUFFER_SIZE {
-            callback(&self.buffer[0..self.buffer_position]);
-            self.buffer_position = 0;
+        if self.channel_taps.is_some() {
+            let outputs = self.channel_outputs();
+
+            if let Some(taps) = &mut self.channel_taps {
+                taps.set_amplitudes(outputs);
+            }
+        }
+    }
+
+    fn read_samples(&mut self) {
+        self.blip.read_samples(|[left, right]| {
+            let left = self.hpf_left.apply(left);
+            let right = self.hpf_right.apply(right);
+
+            // Implies audio is enabled. Otherwise, skip adding samples to the buffer.
+            let Some(callback) = &self.callback else {
+                return;
+            };
+
+            self.buffer[self.buffer_position] = left;
+            self.buffer[self.buffer_position + 1] = right;
+            self.buffer_position += 2;
+
+            if self.buffer_position >= AUDIO_BUFFER_SIZE {
+                callback(&self.buffer[0..self.buffer_position]);
+                self.buffer_position = 0;
+            }
+        });
+
+        if let Some(taps) = &mut self.channel_taps {
+            taps.read_samples();
         }
     }
 
@@ -434,59 +643,317 @@ impl Apu {
         ((prev & mask) != 0) && ((next & mask) == 0)
     }
 
-    // TODO: refactor this :')
-    fn mix(&mut self) -> [f32; 2] {
+    /// Normalized output of each channel, disabled channels are silent.
+    fn channel_outputs(&self) -> [f32; CHANNEL_COUNT] {
         if !self.audio_on {
-            return [0.0, 0.0];
+            return [0.0; CHANNEL_COUNT];
         }
 
-        let [left, right] = [
-            (Channels::CH1, self.channel1.digital_output()),
-            (Channels::CH2, self.channel2.digital_output()),
-            (Channels::CH3, self.channel3.digital_output()),
-            (Channels::CH4, self.channel4.digital_output()),
+        [
+            self.channel1.digital_output(),
+            self.channel2.digital_output(),
+            self.channel3.digital_output(),
+            self.channel4.digital_output(),
         ]
-        .into_iter()
-        // Remove channels disabled by the UI
-        .filter(|(channel, _)| self.ui_channel_overrides.contains(*channel))
-        // Remove disabled channels
-        .filter_map(|(ch, sample)| sample.map(|sample| (ch, sample)))
-        // Normalize
-        .map(|(ch, sample)| (ch, ((sample as f32) / 7.5) - 1.0))
-        // Stereo panning
-        .map(|(channel, sample)| {
-            let left = if self.left_panning.contains(channel) {
-                sample
-            } else {
-                0.0
-            };
+        .map(|sample| sample.map_or(0.0, |sample| ((sample as f32) / 7.5) - 1.0))
+    }
 
-            let right = if self.right_panning.contains(channel) {
-                sample
-            } else {
-                0.0
-            };
+    // TODO: refactor this :')
+    fn mix(&self) -> [f32; 2] {
+        if !self.audio_on {
+            return [0.0, 0.0];
+        }
 
-            [left, right]
-        })
-        // Accumulate
-        .fold([0.0, 0.0], |acc, sample| {
-            [acc[0] + sample[0], acc[1] + sample[1]]
-        })
-        // Average
-        .map(|sample| sample / 4.0);
+        let [left, right] = Channels::all()
+            .iter()
+            .zip(self.channel_outputs())
+            // Remove channels disabled by the UI
+            .filter(|(channel, _)| self.ui_channel_overrides.contains(*channel))
+            // Stereo panning
+            .map(|(channel, sample)| {
+                let left = if self.left_panning.contains(channel) {
+                    sample
+                } else {
+                    0.0
+                };
+
+                let right = if self.right_panning.contains(channel) {
+                    sample
+                } else {
+                    0.0
+                };
+
+                [left, right]
+            })
+            // Accumulate
+            .fold([0.0, 0.0], |acc, sample| {
+                [acc[0] + sample[0], acc[1] + sample[1]]
+            })
+            // Average
+            .map(|sample| sample / 4.0);
 
         // These registers should never completely mute the channel.
         let left_volume = (self.left_volume as f32 + 1.0) / 8.0;
         let right_volume = (self.right_volume as f32 + 1.0) / 8.0;
 
-        [
-            self.hpf_left.apply(left * left_volume),
-            self.hpf_right.apply(right * right_volume),
-        ]
+        [left * left_volume, right * right_volume]
     }
 }
 
+impl SaveState for Apu {
+    fn save_state(&self, writer: &mut StateWriter) {
+        writer.write_u8(self.prev_system_div);
+
+        self.frame_sequencer.save_state(writer);
+
+        self.channel1.save_state(writer);
+        self.channel2.save_state(writer);
+        self.channel3.save_state(writer);
+        self.channel4.save_state(writer);
+
+        writer.write_u8(self.read_nr50());
+        writer.write_u8(self.read_nr51());
+        writer.write_bool(self.audio_on);
+
+        writer.write_bool(self.cgb_mode);
+        writer.write_bool(self.double_speed);
+
+        self.blip.save_state(writer);
+        self.hpf_left.save_state(writer);
+        self.hpf_right.save_state(writer);
+    }
+
+    fn load_state(&mut self, reader: &mut StateReader) -> Result<(), SaveStateError> {
+        self.prev_system_div = reader.read_u8()?;
+
+        self.frame_sequencer.load_state(reader)?;
+
+        self.channel1.load_state(reader)?;
+        self.channel2.load_state(reader)?;
+        self.channel3.load_state(reader)?;
+        self.channel4.load_state(reader)?;
+
+        self.write_nr50(reader.read_u8()?);
+        self.write_nr51(reader.read_u8()?);
+        self.audio_on = reader.read_bool()?;
+
+        self.cgb_mode = reader.read_bool()?;
+        self.double_speed = reader.read_bool()?;
+
+        self.blip.load_state(reader)?;
+        self.hpf_left.load_state(reader)?;
+        self.hpf_right.load_state(reader)?;
+
+        // Samples that were not flushed yet belong to the previous timeline.
+        self.buffer_position = 0;
+
+        Ok(())
+    }
+}
+
+mod blip_buffer;
+mod channel_taps;
 mod channels;
 mod frame_sequencer;
 mod high_pass_filter;
+pub mod vgm;
+pub mod wav;
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+    use crate::{GameBoy, test_rom::TestRom};
+
+    fn save(apu: &Apu) -> Vec<u8> {
+        let mut writer = StateWriter::default();
+        apu.save_state(&mut writer);
+        writer.into_inner()
+    }
+
+    /// Every channel playing, with short lengths and envelopes.
+    fn test_apu(double_speed: bool) -> Apu {
+        let mut apu = Apu::with_device_model(DeviceModel::Cgb);
+        apu.set_double_speed(double_speed);
+
+        for (address, value) in [
+            (0xFF26, 0x80),
+            (0xFF24, 0x77),
+            (0xFF25, 0xFF),
+            (0xFF10, 0x11),
+            (0xFF11, 0xB0),
+            (0xFF12, 0xF1),
+            (0xFF14, 0xC7),
+            (0xFF16, 0x3F),
+            (0xFF17, 0xA3),
+            (0xFF19, 0xC6),
+            (0xFF1A, 0x80),
+            (0xFF1B, 0xF0),
+            (0xFF1C, 0x20),
+            (0xFF1E, 0xC7),
+            (0xFF21, 0xF2),
+            (0xFF22, 0x11),
+            (0xFF20, 0x38),
+            (0xFF23, 0xC0),
+        ] {
+            apu.write(address, value);
+        }
+
+        apu
+    }
+
+    #[test]
+    fn test_advance_matches_tick() {
+        for (double_speed, stride) in [(false, 1), (true, 2)] {
+            let mut ticked = test_apu(double_speed);
+            let mut advanced = test_apu(double_speed);
+            let mut counter: u16 = 0x1234;
+
+            // Long enough for the lengths and the envelopes to run out.
+            for ticks in [1, 3, 17, 250, 1000, 8192, 20000, 65536, 100_000, 200_000] {
+                for _ in 0..ticks {
+                    counter = counter.wrapping_add(stride);
+                    ticked.tick((counter >> 8) as u8);
+                }
+
+                ticked.read_samples();
+                advanced.advance(ticks, counter, stride);
+
+                assert_eq!(advanced.read(0xFF26), ticked.read(0xFF26));
+                assert_eq!(advanced.read(0xFF76), ticked.read(0xFF76));
+                assert_eq!(advanced.read(0xFF77), ticked.read(0xFF77));
+                assert!(save(&advanced) == save(&ticked));
+            }
+
+            // All the channels were stopped by their length counters.
+            assert_eq!(ticked.read(0xFF26) & 0x0F, 0);
+        }
+    }
+
+    /// Plays a square wave on CH2, then loops forever.
+    pub(super) fn tone_rom() -> Arc<[u8]> {
+        // LD A, 0xF0; LDH (0x17), A; LD A, 0x00; LDH (0x18), A; LD A, 0x87; LDH (0x19), A; JR -2
+        TestRom::new()
+            .program(&[
+                0x3E, 0xF0, 0xE0, 0x17, 0x3E, 0x00, 0xE0, 0x18, 0x3E, 0x87, 0xE0, 0x19, 0x18, 0xFE,
+            ])
+            .build()
+    }
+
+    /// MBC1+RAM+BATTERY, with 8 KiB of RAM.
+    fn silent_rom() -> Arc<[u8]> {
+        TestRom::new().mbc(0x03).ram_size(0x02).build()
+    }
+
+    #[test]
+    fn test_flush_audio_per_frame() {
+        let samples = Arc::new(Mutex::new(0));
+
+        let mut gb = GameBoy::new(DeviceModel::Cgb);
+        gb.load(None, silent_rom()).unwrap();
+        gb.add_audio_callback({
+            let samples = samples.clone();
+            Box::new(move |buffer| *samples.lock().unwrap() += buffer.len())
+        });
+
+        gb.run_frame();
+        gb.flush_audio();
+        *samples.lock().unwrap() = 0;
+
+        gb.run_frame();
+        gb.flush_audio();
+
+        // 70224 cycles per frame, at 44100 stereo samples per 4194304 cycles.
+        assert!((738 * 2..=739 * 2).contains(&*samples.lock().unwrap()));
+    }
+
+    #[test]
+    fn test_audio_sample_rate() {
+        let samples = Arc::new(Mutex::new(0));
+
+        let mut gb = GameBoy::new(DeviceModel::Cgb);
+        gb.load(None, silent_rom()).unwrap();
+        gb.set_audio_sample_rate(48000);
+        gb.add_audio_callback({
+            let samples = samples.clone();
+            Box::new(move |buffer| *samples.lock().unwrap() += buffer.len())
+        });
+
+        // Kept across power cycles.
+        gb.power_cycle();
+        assert_eq!(gb.audio_sample_rate(), 48000);
+
+        let count_frames = |gb: &mut GameBoy, frames: usize| {
+            *samples.lock().unwrap() = 0;
+
+            for _ in 0..frames {
+                gb.run_frame();
+            }
+
+            gb.flush_audio();
+            *samples.lock().unwrap() / 2
+        };
+
+        count_frames(&mut gb, 1);
+
+        // 70224 cycles per frame, at 48000 samples per 4194304 cycles.
+        assert!((8036..=8037).contains(&count_frames(&mut gb, 10)));
+
+        // Clamped to half a percent.
+        gb.set_audio_rate_adjustment(0.1);
+        assert!((8076..=8077).contains(&count_frames(&mut gb, 10)));
+
+        // Ignored instead of silencing the output.
+        gb.set_audio_rate_adjustment(f64::NAN);
+        assert!((8036..=8037).contains(&count_frames(&mut gb, 10)));
+
+        gb.set_audio_sample_rate(0);
+        assert_eq!(gb.audio_sample_rate(), 48000);
+        assert!((8036..=8037).contains(&count_frames(&mut gb, 10)));
+    }
+
+    #[test]
+    fn test_audio_channels_callback() {
+        let mixed = Arc::new(Mutex::new(Vec::new()));
+        let channels = Arc::new(Mutex::new(Vec::new()));
+
+        let mut gb = GameBoy::new(DeviceModel::Cgb);
+        gb.load(None, tone_rom()).unwrap();
+        gb.add_audio_callback({
+            let mixed = mixed.clone();
+            Box::new(move |buffer| mixed.lock().unwrap().extend_from_slice(buffer))
+        });
+        gb.add_audio_channels_callback({
+            let channels = channels.clone();
+            Box::new(move |buffer| channels.lock().unwrap().extend_from_slice(buffer))
+        });
+
+        // Kept across power cycles.
+        gb.power_cycle();
+
+        for _ in 0..10 {
+            gb.run_frame();
+        }
+
+        gb.flush_audio();
+
+        let mixed = mixed.lock().unwrap().len();
+        let channels = channels.lock().unwrap().clone();
+
+        // Same rate as the mixed samples.
+        assert_eq!(channels.len() / CHANNEL_COUNT, mixed / 2);
+
+        let peak = |channel: usize| {
+            channels
+                .iter()
+                .skip(channel)
+                .step_by(CHANNEL_COUNT)
+                .fold(0.0f32, |peak, sample| peak.max(sample.abs()))
+        };
+
+        assert!(peak(1) > 0.2);
+        assert!(peak(2) < 1e-3);
+        assert!(peak(3) < 1e-3);
+    }
+}
@@ -0,0 +1,271 @@
+use std::{f64::consts::PI, sync::LazyLock};
+
+use crate::save_state::{SaveState, SaveStateError, StateReader, StateWriter};
+
+/// Bits of the fixed point positions below an output sample.
+const FRACTION_BITS: u32 = 32;
+
+/// Steps are placed with a precision of 1/`PHASES` of an output sample.
+const PHASE_BITS: u32 = 5;
+const PHASES: usize = 1 << PHASE_BITS;
+
+/// Output samples covered by a step.
+const WIDTH: usize = 16;
+
+/// Highest frequency kept, relative to the output sample rate (just below Nyquist).
+const CUTOFF: f64 = 0.45;
+
+/// Band-limited impulse of each phase, as the difference between consecutive samples of a step.
+static KERNEL: LazyLock<[[f32; WIDTH]; PHASES]> = LazyLock::new(build_kernel);
+
+/// Resamples a signal made of steps, such as the output of the APU, without aliasing.
+///
+/// Instead of sampling the signal at the output rate, every change of amplitude is added
+/// at its exact clock as a band-limited step (windowed sinc), like `blip_buf` does.
+/// The steps are centered `WIDTH / 2` samples later, which is the latency of the buffer.
+///
+/// Each sample holds `N` signals that share the same clock, such as the left and right outputs.
+pub struct BlipBuffer<const N: usize = 2> {
+    /// Output samples per clock, as fixed point.
+    factor: u64,
+    /// Position of the current clock, as fixed point relative to the first unread sample.
+    position: u64,
+
+    /// Changes of each output sample, the output is their running sum.
+    deltas: Vec<[f32; N]>,
+    /// Running sum before the first unread sample.
+    sum: [f32; N],
+
+    amplitude: [f32; N],
+}
+
+impl<const N: usize> BlipBuffer<N> {
+    pub fn new(clock_rate: usize, sample_rate: f64) -> Self {
+        let mut blip = Self {
+            factor: 0,
+            position: 0,
+            deltas: Vec::new(),
+            sum: [0.0; N],
+            amplitude: [0.0; N],
+        };
+
+        blip.set_rates(clock_rate, sample_rate);
+
+        blip
+    }
+
+    /// Takes effect from the current clock on, the samples so far are kept.
+    #[allow(
+        clippy::cast_precision_loss,
+        clippy::cast_possible_truncation,
+        clippy::cast_sign_loss
+    )]
+    pub fn set_rates(&mut self, clock_rate: usize, sample_rate: f64) {
+        let samples_per_clock = sample_rate / clock_rate as f64;
+        self.factor = (samples_per_clock * (1u64 << FRACTION_BITS) as f64).round() as u64;
+    }
+
+    pub fn advance(&mut self, clocks: u64) {
+        self.position += clocks * self.factor;
+    }
+
+    /// Changes the amplitude at the current clock.
+    #[allow(clippy::float_cmp)]
+    pub fn set_amplitude(&mut self, amplitude: [f32; N]) {
+        if amplitude == self.amplitude {
+            return;
+        }
+
+        let delta: [f32; N] = std::array::from_fn(|i| amplitude[i] - self.amplitude[i]);
+
+        self.amplitude = amplitude;
+
+        let index = (self.position >> FRACTION_BITS) as usize;
+        let phase = (self.position >> (FRACTION_BITS - PHASE_BITS)) as usize & (PHASES - 1);
+
+        if self.deltas.len() < index + WIDTH {
+            self.deltas.resize(index + WIDTH, [0.0; N]);
+        }
+
+        for (output, tap) in self.deltas[index..].iter_mut().zip(KERNEL[phase]) {
+            for (output, delta) in output.iter_mut().zip(delta) {
+                *output = delta.mul_add(tap, *output);
+            }
+        }
+    }
+
+    /// Reads every sample before the current clock.
+    pub fn read_samples(&mut self, mut output: impl FnMut([f32; N])) {
+        let available = (self.position >> FRACTION_BITS) as usize;
+
+        if available == 0 {
+            return;
+        }
+
+        if self.deltas.len() < available + WIDTH {
+            self.deltas.resize(available + WIDTH, [0.0; N]);
+        }
+
+        for delta in self.deltas.drain(..available) {
+            for (sum, delta) in self.sum.iter_mut().zip(delta) {
+                *sum += delta;
+            }
+
+            output(self.sum);
+        }
+
+        self.position -= (available as u64) << FRACTION_BITS;
+    }
+}
+
+impl<const N: usize> SaveState for BlipBuffer<N> {
+    /// Unread samples are left out, like the samples that were not flushed yet.
+    fn save_state(&self, writer: &mut StateWriter) {
+        let index = (self.position >> FRACTION_BITS) as usize;
+        let mut deltas = self
+            .deltas
+            .iter()
+            .copied()
+            .chain(std::iter::repeat([0.0; N]));
+
+        let mut sum = self.sum;
+
+        for delta in deltas.by_ref().take(index) {
+            for (sum, delta) in sum.iter_mut().zip(delta) {
+                *sum += delta;
+            }
+        }
+
+        writer.write_u32(self.position as u32);
+
+        for value in sum.into_iter().chain(self.amplitude) {
+            writer.write_f32(value);
+        }
+
+        for delta in deltas.take(WIDTH) {
+            for value in delta {
+                writer.write_f32(value);
+            }
+        }
+    }
+
+    fn load_state(&mut self, reader: &mut StateReader) -> Result<(), SaveStateError> {
+        self.position = reader.read_u32()? as u64;
+
+        for value in self.sum.iter_mut().chain(&mut self.amplitude) {
+            *value = reader.read_f32()?;
+        }
+
+        self.deltas.clear();
+
+        for _ in 0..WIDTH {
+            let mut delta = [0.0; N];
+
+            for value in &mut delta {
+                *value = reader.read_f32()?;
+            }
+
+            self.deltas.push(delta);
+        }
+
+        Ok(())
+    }
+}
+
+#[allow(clippy::cast_precision_loss)]
+fn build_kernel() -> [[f32; WIDTH]; PHASES] {
+    let mut kernel = [[0.0; WIDTH]; PHASES];
+    let half_width = (WIDTH / 2) as f64;
+
+    for (phase, taps) in kernel.iter_mut().enumerate() {
+        let offset = phase as f64 / PHASES as f64;
+
+        for (i, tap) in taps.iter_mut().enumerate() {
+            // Distance between the middle of the output sample and the step.
+            let x = i as f64 + 0.5 - half_width - offset;
+
+            let sinc = if x == 0.0 {
+                1.0
+            } else {
+                let y = PI * 2.0 * CUTOFF * x;
+                y.sin() / y
+            };
+
+            let angle = PI * x / half_width;
+            let blackman = 0.08f64.mul_add((2.0 * angle).cos(), 0.5f64.mul_add(angle.cos(), 0.42));
+
+            *tap = (sinc * blackman) as f32;
+        }
+
+        // Every step has to add up to its exact height.
+        let total: f32 = taps.iter().sum();
+        for tap in taps {
+            *tap /= total;
+        }
+    }
+
+    kernel
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read_all(blip: &mut BlipBuffer) -> Vec<f32> {
+        let mut samples = Vec::new();
+        blip.read_samples(|[left, _]| samples.push(left));
+        samples
+    }
+
+    #[test]
+    fn test_step_settles_at_its_height() {
+        let mut blip = BlipBuffer::new(4, 1.0);
+
+        blip.advance(3);
+        blip.set_amplitude([1.0, -0.5]);
+        blip.advance(4 * 2 * WIDTH as u64);
+
+        let mut last = [0.0, 0.0];
+        blip.read_samples(|sample| last = sample);
+
+        assert!((last[0] - 1.0).abs() < 1e-5);
+        assert!((last[1] + 0.5).abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_samples_per_clock() {
+        let mut blip = BlipBuffer::new(4_194_304, 44100.0);
+
+        blip.advance(4_194_304 / 2);
+        assert_eq!(read_all(&mut blip).len(), 22050);
+
+        blip.advance(4_194_304 / 2);
+        assert_eq!(read_all(&mut blip).len(), 22050);
+    }
+
+    #[test]
+    fn test_save_state_keeps_pending_steps() {
+        let mut blip = BlipBuffer::new(3, 1.0);
+        blip.set_amplitude([0.25, 0.25]);
+        blip.advance(10);
+        blip.set_amplitude([-0.75, 0.5]);
+        blip.advance(1);
+
+        let mut writer = StateWriter::default();
+        blip.save_state(&mut writer);
+        let state = writer.into_inner();
+
+        let mut loaded = BlipBuffer::new(3, 1.0);
+        loaded.load_state(&mut StateReader::new(&state)).unwrap();
+
+        // The unread samples were dropped from the state.
+        read_all(&mut blip);
+
+        for _ in 0..WIDTH {
+            blip.advance(3);
+            loaded.advance(3);
+
+            assert_eq!(read_all(&mut blip), read_all(&mut loaded));
+        }
+    }
+}
@@ -0,0 +1,75 @@
+use crate::{
+    components::apu::{
+        AUDIO_BUFFER_SIZE,
+        CHANNEL_COUNT,
+        ChannelsCallback,
+        blip_buffer::BlipBuffer,
+        high_pass_filter::HighPassFilter,
+    },
+    constants::CPU_CLOCK_RATE,
+};
+
+/// Resamples the output of each channel on its own, for the channels callback.
+pub struct ChannelTaps {
+    blip: BlipBuffer<CHANNEL_COUNT>,
+    hpfs: [HighPassFilter; CHANNEL_COUNT],
+
+    buffer: Vec<f32>,
+    callback: Box<ChannelsCallback>,
+}
+
+impl ChannelTaps {
+    pub fn new(callback: Box<ChannelsCallback>, sample_rate: usize, adjusted_rate: f64) -> Self {
+        Self {
+            blip: BlipBuffer::new(CPU_CLOCK_RATE, adjusted_rate),
+            hpfs: std::array::from_fn(|_| HighPassFilter::with_sample_rate(sample_rate)),
+            buffer: Vec::with_capacity(AUDIO_BUFFER_SIZE),
+            callback,
+        }
+    }
+
+    pub fn into_callback(self) -> Box<ChannelsCallback> {
+        self.callback
+    }
+
+    pub fn set_rates(&mut self, sample_rate: usize, adjusted_rate: f64) {
+        for hpf in &mut self.hpfs {
+            hpf.set_sample_rate(sample_rate);
+        }
+
+        self.blip.set_rates(CPU_CLOCK_RATE, adjusted_rate);
+    }
+
+    pub fn advance(&mut self, clocks: u64) {
+        self.blip.advance(clocks);
+    }
+
+    /// Scales the channels like the mix does, so that they add up to it.
+    #[allow(clippy::cast_precision_loss)]
+    pub fn set_amplitudes(&mut self, amplitudes: [f32; CHANNEL_COUNT]) {
+        self.blip
+            .set_amplitude(amplitudes.map(|amplitude| amplitude / CHANNEL_COUNT as f32));
+    }
+
+    pub fn read_samples(&mut self) {
+        self.blip.read_samples(|samples| {
+            for (hpf, sample) in self.hpfs.iter_mut().zip(samples) {
+                self.buffer.push(hpf.apply(sample));
+            }
+
+            if self.buffer.len() >= AUDIO_BUFFER_SIZE {
+                (self.callback)(&self.buffer);
+                self.buffer.clear();
+            }
+        });
+    }
+
+    pub fn flush(&mut self) {
+        self.read_samples();
+
+        if !self.buffer.is_empty() {
+            (self.callback)(&self.buffer);
+            self.buffer.clear();
+        }
+    }
+}
@@ -1,4 +1,5 @@
 use super::units::{Envelope, LengthTimer, PeriodDivider, WaveDuty};
+use crate::save_state::{SaveState, SaveStateError, StateReader, StateWriter};
 
 /// Pulse channel 2 (`NR2x`)
 pub struct Channel2 {
@@ -40,6 +41,12 @@ impl Channel2 {
         }
     }
 
+    /// Same as calling `tick` `ticks` times.
+    pub fn advance(&mut self, ticks: u64) {
+        let expirations = self.period_divider.advance(ticks);
+        self.wave_duty.advance(expirations);
+    }
+
     pub fn tick_length_timer(&mut self) {
         self.length_timer.tick();
         if self.length_timer.expired() {
@@ -51,6 +58,15 @@ impl Channel2 {
         self.envelope.tick();
     }
 
+    /// Ticks until the output might change, with the next step of the waveform.
+    pub fn ticks_until_step(&self) -> u64 {
+        if self.digital_output().is_none() {
+            return u64::MAX;
+        }
+
+        self.period_divider.ticks_until_expired()
+    }
+
     pub fn enabled(&self) -> bool {
         self.enabled
     }
@@ -149,3 +165,25 @@ impl Channel2 {
         }
     }
 }
+
+impl SaveState for Channel2 {
+    fn save_state(&self, writer: &mut StateWriter) {
+        writer.write_bool(self.enabled);
+        writer.write_bool(self.dac_enabled);
+        self.wave_duty.save_state(writer);
+        self.envelope.save_state(writer);
+        self.length_timer.save_state(writer);
+        self.period_divider.save_state(writer);
+    }
+
+    fn load_state(&mut self, reader: &mut StateReader) -> Result<(), SaveStateError> {
+        self.enabled = reader.read_bool()?;
+        self.dac_enabled = reader.read_bool()?;
+        self.wave_duty.load_state(reader)?;
+        self.envelope.load_state(reader)?;
+        self.length_timer.load_state(reader)?;
+        self.period_divider.load_state(reader)?;
+
+        Ok(())
+    }
+}
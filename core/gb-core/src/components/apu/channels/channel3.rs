@@ -1,4 +1,5 @@
 use super::units::{LengthTimer, PeriodDivider};
+use crate::save_state::{SaveState, SaveStateError, StateReader, StateWriter};
 
 /// Wave channel (`NR3x`)
 pub struct Channel3 {
@@ -43,6 +44,12 @@ impl Channel3 {
         }
     }
 
+    /// Same as calling `tick` `ticks` times.
+    pub fn advance(&mut self, ticks: u64) {
+        let expirations = self.period_divider.advance(ticks);
+        self.wave_position = ((self.wave_position as u64 + expirations) % 32) as usize;
+    }
+
     pub fn tick_length_timer(&mut self) {
         self.length_timer.tick();
         if self.length_timer.expired() {
@@ -50,6 +57,15 @@ impl Channel3 {
         }
     }
 
+    /// Ticks until the output might change, with the next step of the waveform.
+    pub fn ticks_until_step(&self) -> u64 {
+        if self.digital_output().is_none() {
+            return u64::MAX;
+        }
+
+        self.period_divider.ticks_until_expired()
+    }
+
     pub fn enabled(&self) -> bool {
         self.enabled
     }
@@ -185,3 +201,27 @@ impl Channel3 {
         self.wave_ram[address] = value;
     }
 }
+
+impl SaveState for Channel3 {
+    fn save_state(&self, writer: &mut StateWriter) {
+        writer.write_bool(self.enabled);
+        writer.write_bool(self.dac_enabled);
+        writer.write_u8(self.output_level);
+        self.length_timer.save_state(writer);
+        self.period_divider.save_state(writer);
+        writer.write_bytes(&self.wave_ram);
+        writer.write_usize(self.wave_position);
+    }
+
+    fn load_state(&mut self, reader: &mut StateReader) -> Result<(), SaveStateError> {
+        self.enabled = reader.read_bool()?;
+        self.dac_enabled = reader.read_bool()?;
+        self.output_level = reader.read_u8()? & 0b11;
+        self.length_timer.load_state(reader)?;
+        self.period_divider.load_state(reader)?;
+        reader.read_bytes_into(&mut self.wave_ram)?;
+        self.wave_position = reader.read_usize()? % 32;
+
+        Ok(())
+    }
+}
@@ -1,4 +1,5 @@
 use super::units::{Envelope, LengthTimer, PeriodDivider};
+use crate::save_state::{SaveState, SaveStateError, StateReader, StateWriter};
 
 /// Noise channel (`NR4x`)
 pub struct Channel4 {
@@ -45,15 +46,34 @@ impl Channel4 {
             self.period_divider.set_period(new_frequency);
             self.period_divider.reload();
 
-            let shifted_lfsr = self.lfsr >> 1;
-            let xor_result = (self.lfsr & 0b1) ^ (shifted_lfsr & 0b1);
+            self.step_lfsr();
+        }
+    }
+
+    /// Same as calling `tick` `ticks` times.
+    pub fn advance(&mut self, ticks: u64) {
+        // The period is only updated when the divider expires.
+        if ticks >= self.period_divider.ticks_until_expired() {
+            let new_frequency = self.clock_divider() << self.clock_shift;
+            self.period_divider.set_period(new_frequency);
+        }
+
+        let expirations = self.period_divider.advance(ticks);
+
+        for _ in 0..expirations {
+            self.step_lfsr();
+        }
+    }
+
+    fn step_lfsr(&mut self) {
+        let shifted_lfsr = self.lfsr >> 1;
+        let xor_result = (self.lfsr & 0b1) ^ (shifted_lfsr & 0b1);
 
-            self.lfsr = (xor_result << 14) | shifted_lfsr;
+        self.lfsr = (xor_result << 14) | shifted_lfsr;
 
-            if self.short_width_mode {
-                self.lfsr &= !(1 << 6);
-                self.lfsr |= xor_result << 6;
-            }
+        if self.short_width_mode {
+            self.lfsr &= !(1 << 6);
+            self.lfsr |= xor_result << 6;
         }
     }
 
@@ -68,6 +88,15 @@ impl Channel4 {
         self.envelope.tick();
     }
 
+    /// Ticks until the output might change, with the next step of the waveform.
+    pub fn ticks_until_step(&self) -> u64 {
+        if self.digital_output().is_none() {
+            return u64::MAX;
+        }
+
+        self.period_divider.ticks_until_expired()
+    }
+
     pub fn enabled(&self) -> bool {
         self.enabled
     }
@@ -180,3 +209,31 @@ impl Channel4 {
         }
     }
 }
+
+impl SaveState for Channel4 {
+    fn save_state(&self, writer: &mut StateWriter) {
+        writer.write_bool(self.enabled);
+        writer.write_bool(self.dac_enabled);
+        self.envelope.save_state(writer);
+        writer.write_u8(self.clock_shift);
+        writer.write_bool(self.short_width_mode);
+        writer.write_u8(self.clock_divider_code);
+        writer.write_u16(self.lfsr);
+        self.length_timer.save_state(writer);
+        self.period_divider.save_state(writer);
+    }
+
+    fn load_state(&mut self, reader: &mut StateReader) -> Result<(), SaveStateError> {
+        self.enabled = reader.read_bool()?;
+        self.dac_enabled = reader.read_bool()?;
+        self.envelope.load_state(reader)?;
+        self.clock_shift = reader.read_u8()?;
+        self.short_width_mode = reader.read_bool()?;
+        self.clock_divider_code = reader.read_u8()?;
+        self.lfsr = reader.read_u16()?;
+        self.length_timer.load_state(reader)?;
+        self.period_divider.load_state(reader)?;
+
+        Ok(())
+    }
+}
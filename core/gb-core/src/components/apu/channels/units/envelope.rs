@@ -1,3 +1,5 @@
+use crate::save_state::{SaveState, SaveStateError, StateReader, StateWriter};
+
 #[derive(Debug, Default)]
 pub struct Envelope {
     initial_volume: u8,
@@ -82,3 +84,23 @@ impl From<bool> for Direction {
         }
     }
 }
+
+impl SaveState for Envelope {
+    fn save_state(&self, writer: &mut StateWriter) {
+        writer.write_u8(self.initial_volume);
+        writer.write_u8(self.direction as u8);
+        writer.write_u8(self.sweep_pace);
+        writer.write_u8(self.volume);
+        writer.write_u8(self.counter);
+    }
+
+    fn load_state(&mut self, reader: &mut StateReader) -> Result<(), SaveStateError> {
+        self.initial_volume = reader.read_u8()?;
+        self.direction = Direction::from(reader.read_bool()?);
+        self.sweep_pace = reader.read_u8()?;
+        self.volume = reader.read_u8()?;
+        self.counter = reader.read_u8()?;
+
+        Ok(())
+    }
+}
@@ -1,3 +1,5 @@
+use crate::save_state::{SaveState, SaveStateError, StateReader, StateWriter};
+
 pub struct LengthTimer {
     length: usize,
 
@@ -42,3 +44,21 @@ impl LengthTimer {
         self.enabled = enabled;
     }
 }
+
+impl SaveState for LengthTimer {
+    fn save_state(&self, writer: &mut StateWriter) {
+        writer.write_bool(self.enabled);
+        writer.write_usize(self.counter);
+    }
+
+    fn load_state(&mut self, reader: &mut StateReader) -> Result<(), SaveStateError> {
+        self.enabled = reader.read_bool()?;
+        self.counter = reader.read_usize()?;
+
+        if self.counter > self.length {
+            return Err(SaveStateError::InvalidData("length timer"));
+        }
+
+        Ok(())
+    }
+}
@@ -1,3 +1,5 @@
+use crate::save_state::{SaveState, SaveStateError, StateReader, StateWriter};
+
 #[derive(Debug, Default)]
 pub struct PeriodDivider<T: Fn(u16) -> u16> {
     // Only takes effect in the next trigger
@@ -24,6 +26,33 @@ impl<T: Fn(u16) -> u16> PeriodDivider<T> {
         self.counter -= 1;
     }
 
+    /// Same as calling `tick` `ticks` times, reloading whenever it expires.
+    ///
+    /// Returns how many times it expired.
+    pub fn advance(&mut self, ticks: u64) -> u64 {
+        let until_expired = self.ticks_until_expired();
+
+        if ticks < until_expired {
+            self.counter -= ticks as u16;
+            return 0;
+        }
+
+        self.reload();
+
+        // A period of 0 expires on every tick.
+        let period = self.counter.max(1) as u64;
+        let remaining = ticks - until_expired;
+
+        self.counter -= (remaining % period) as u16;
+
+        1 + (remaining / period)
+    }
+
+    /// An expired divider expires again on the next tick.
+    pub fn ticks_until_expired(&self) -> u64 {
+        self.counter.max(1) as u64
+    }
+
     pub fn reload(&mut self) {
         self.counter = (self.get_next_counter)(self.period);
     }
@@ -51,3 +80,17 @@ impl<T: Fn(u16) -> u16> PeriodDivider<T> {
         self.counter == 0
     }
 }
+
+impl<T: Fn(u16) -> u16> SaveState for PeriodDivider<T> {
+    fn save_state(&self, writer: &mut StateWriter) {
+        writer.write_u16(self.period);
+        writer.write_u16(self.counter);
+    }
+
+    fn load_state(&mut self, reader: &mut StateReader) -> Result<(), SaveStateError> {
+        self.period = reader.read_u16()?;
+        self.counter = reader.read_u16()?;
+
+        Ok(())
+    }
+}
@@ -1,4 +1,7 @@
-use crate::components::apu::channels::units::period_divider::PeriodDivider;
+use crate::{
+    components::apu::channels::units::period_divider::PeriodDivider,
+    save_state::{SaveState, SaveStateError, StateReader, StateWriter},
+};
 
 #[derive(Debug, Default)]
 pub struct Sweep {
@@ -119,3 +122,25 @@ impl From<bool> for Direction {
         }
     }
 }
+
+impl SaveState for Sweep {
+    fn save_state(&self, writer: &mut StateWriter) {
+        writer.write_u8(self.pace);
+        writer.write_u8(self.direction as u8);
+        writer.write_u8(self.individual_step);
+        writer.write_bool(self.enabled);
+        writer.write_u16(self.shadow_period);
+        writer.write_u8(self.counter);
+    }
+
+    fn load_state(&mut self, reader: &mut StateReader) -> Result<(), SaveStateError> {
+        self.pace = reader.read_u8()?;
+        self.direction = Direction::from(reader.read_bool()?);
+        self.individual_step = reader.read_u8()?;
+        self.enabled = reader.read_bool()?;
+        self.shadow_period = reader.read_u16()?;
+        self.counter = reader.read_u8()?;
+
+        Ok(())
+    }
+}
@@ -1,3 +1,5 @@
+use crate::save_state::{SaveState, SaveStateError, StateReader, StateWriter};
+
 #[derive(Debug, Default)]
 pub struct WaveDuty {
     duty_cycle: DutyCycle,
@@ -9,6 +11,10 @@ impl WaveDuty {
         self.position = (self.position + 1) & 0b111;
     }
 
+    pub fn advance(&mut self, steps: u64) {
+        self.position = ((self.position as u64 + steps) & 0b111) as u8;
+    }
+
     pub fn reset_position(&mut self) {
         self.position = 0;
     }
@@ -58,3 +64,18 @@ impl DutyCycle {
         }
     }
 }
+
+impl SaveState for WaveDuty {
+    fn save_state(&self, writer: &mut StateWriter) {
+        writer.write_u8(self.duty_cycle as u8);
+        writer.write_u8(self.position);
+    }
+
+    fn load_state(&mut self, reader: &mut StateReader) -> Result<(), SaveStateError> {
+        self.duty_cycle = DutyCycle::from_value(reader.read_u8()?)
+            .ok_or(SaveStateError::InvalidData("duty cycle"))?;
+        self.position = reader.read_u8()? & 0b111;
+
+        Ok(())
+    }
+}
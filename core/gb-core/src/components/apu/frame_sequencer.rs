@@ -1,3 +1,5 @@
+use crate::save_state::{SaveState, SaveStateError, StateReader, StateWriter};
+
 #[derive(Debug, Default)]
 pub struct FrameSequencer {
     step: usize,
@@ -12,3 +14,15 @@ impl FrameSequencer {
         next_step
     }
 }
+
+impl SaveState for FrameSequencer {
+    fn save_state(&self, writer: &mut StateWriter) {
+        writer.write_u8(self.step as u8);
+    }
+
+    fn load_state(&mut self, reader: &mut StateReader) -> Result<(), SaveStateError> {
+        self.step = (reader.read_u8()? & 0b111) as usize;
+
+        Ok(())
+    }
+}
@@ -1,17 +1,52 @@
-#[derive(Debug, Default)]
+use crate::{
+    constants::CPU_CLOCK_RATE,
+    save_state::{SaveState, SaveStateError, StateReader, StateWriter},
+};
+
+#[derive(Debug)]
 pub struct HighPassFilter {
     capacitor: f32,
+
+    /// How much charge the capacitor keeps between samples (not part of save states).
+    factor: f32,
 }
 
 impl HighPassFilter {
+    pub fn with_sample_rate(sample_rate: usize) -> Self {
+        let mut filter = Self {
+            capacitor: 0.0,
+            factor: 0.0,
+        };
+
+        filter.set_sample_rate(sample_rate);
+
+        filter
+    }
+
     // https://gbdev.io/pandocs/Audio_details.html#obscure-behavior
-    pub fn apply(&mut self, in_sample: f32) -> f32 {
-        // 0.999958 ^ (4194304 / AUDIO_SAMPLE_RATE)
-        const FACTOR: f32 = 0.996; // At 44.1kHz
+    #[allow(clippy::cast_precision_loss)]
+    pub fn set_sample_rate(&mut self, sample_rate: usize) {
+        // 0.999958 ^ (4194304 / sample_rate), e.g. 0.996 at 44.1kHz.
+        let cycles_per_sample = CPU_CLOCK_RATE as f64 / sample_rate as f64;
+        self.factor = 0.999_958_f64.powf(cycles_per_sample) as f32;
+    }
 
+    pub fn apply(&mut self, in_sample: f32) -> f32 {
         let out = in_sample - self.capacitor;
-        self.capacitor = out.mul_add(-FACTOR, in_sample); // in_sample - (out * FACTOR)
+        self.capacitor = out.mul_add(-self.factor, in_sample); // in_sample - (out * factor)
 
         out
     }
 }
+
+impl SaveState for HighPassFilter {
+    fn save_state(&self, writer: &mut StateWriter) {
+        writer.write_f32(self.capacitor);
+    }
+
+    fn load_state(&mut self, reader: &mut StateReader) -> Result<(), SaveStateError> {
+        self.capacitor = reader.read_f32()?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,275 @@
+use crate::{components::apu::Apu, constants::CPU_CLOCK_RATE};
+
+/// VGM files count time in samples at this rate, whatever the chip.
+pub const VGM_SAMPLE_RATE: u64 = 44100;
+
+/// Version 1.61 is the first one with the Game Boy DMG chip.
+const VERSION: u32 = 0x0000_0161;
+const HEADER_SIZE: usize = 0x100;
+
+const GAME_BOY_WRITE: u8 = 0xB3;
+const WAIT: u8 = 0x61;
+const WAIT_60HZ_FRAME: u8 = 0x62;
+const WAIT_50HZ_FRAME: u8 = 0x63;
+const WAIT_SHORT: u8 = 0x70;
+const END_OF_DATA: u8 = 0x66;
+
+const FIRST_REGISTER: u16 = 0xFF10;
+
+/// Logs the writes to the APU registers as VGM commands, for chiptune players.
+///
+/// Time is measured with the single speed clock, which keeps running at the same rate
+/// in double speed, and turned into waits between the writes.
+pub struct VgmRecorder {
+    data: Vec<u8>,
+
+    /// Clock of the last command, which can restart from 0 (see [`VgmRecorder::resync`]).
+    clock: u64,
+    /// Clocks recorded so far, the waits are rounded from it so that they don't drift.
+    elapsed: u64,
+    samples: u64,
+
+    /// Offset in `data` and samples before the point where the players loop back to.
+    loop_start: Option<(usize, u64)>,
+}
+
+impl VgmRecorder {
+    /// Starts at `clock`, from the current state of `apu`.
+    #[must_use]
+    pub fn new(clock: u64, apu: &Apu) -> Self {
+        let mut recorder = Self {
+            data: Vec::new(),
+            clock,
+            elapsed: 0,
+            samples: 0,
+            loop_start: None,
+        };
+
+        recorder.write_state(apu);
+
+        recorder
+    }
+
+    pub fn write(&mut self, clock: u64, address: u16, value: u8) {
+        self.wait_until(clock);
+
+        let register = (address - FIRST_REGISTER) as u8;
+        self.data.extend([GAME_BOY_WRITE, register, value]);
+    }
+
+    /// Makes the players loop back to `clock` once they reach the end.
+    pub fn mark_loop(&mut self, clock: u64) {
+        self.wait_until(clock);
+        self.loop_start = Some((self.data.len(), self.samples));
+    }
+
+    /// Continues from `clock` after the clock jumped, e.g. because a state was loaded,
+    /// then writes the state of `apu` as it can't be told from the writes so far.
+    pub fn resync(&mut self, clock: u64, apu: &Apu) {
+        self.clock = clock;
+        self.write_state(apu);
+    }
+
+    /// Ends the data at `clock` and returns the whole file.
+    #[must_use]
+    pub fn finish(mut self, clock: u64) -> Vec<u8> {
+        self.wait_until(clock);
+        self.data.push(END_OF_DATA);
+
+        let mut header = vec![0; HEADER_SIZE];
+
+        let mut set = |offset: usize, value: u32| {
+            header[offset..offset + 4].copy_from_slice(&value.to_le_bytes());
+        };
+
+        set(0x00, u32::from_le_bytes(*b"Vgm "));
+        set(0x04, (HEADER_SIZE + self.data.len() - 0x04) as u32);
+        set(0x08, VERSION);
+        set(0x18, self.samples as u32);
+
+        if let Some((offset, samples)) = self.loop_start {
+            set(0x1C, (HEADER_SIZE + offset - 0x1C) as u32);
+            set(0x20, (self.samples - samples) as u32);
+        }
+
+        set(0x34, (HEADER_SIZE - 0x34) as u32);
+        set(0x80, CPU_CLOCK_RATE as u32);
+
+        header.append(&mut self.data);
+
+        header
+    }
+
+    fn wait_until(&mut self, clock: u64) {
+        self.elapsed += clock.saturating_sub(self.clock);
+        self.clock = clock;
+
+        let samples = self.elapsed * VGM_SAMPLE_RATE / CPU_CLOCK_RATE as u64;
+        let mut wait = samples - self.samples;
+        self.samples = samples;
+
+        while wait > 0 {
+            let step = wait.min(u16::MAX as u64);
+
+            match step {
+                735 => self.data.push(WAIT_60HZ_FRAME),
+                882 => self.data.push(WAIT_50HZ_FRAME),
+                1..=16 => self.data.push(WAIT_SHORT + (step - 1) as u8),
+                _ => {
+                    self.data.push(WAIT);
+                    self.data.extend((step as u16).to_le_bytes());
+                }
+            }
+
+            wait -= step;
+        }
+    }
+
+    /// The registers that can be read back, in an order that a player accepts.
+    ///
+    /// The frequencies and lengths can't be read, and no channel is triggered,
+    /// so the notes that are already playing start at their next trigger.
+    fn write_state(&mut self, apu: &Apu) {
+        let nr52 = apu.read(0xFF26) & 0x80;
+
+        // Turning the APU off clears the other registers.
+        self.write(self.clock, 0xFF26, 0x00);
+
+        // The wave RAM is writable either way, as long as channel 3 is not playing.
+        for address in 0xFF30..=0xFF3F {
+            self.write(self.clock, address, apu.read(address));
+        }
+
+        if nr52 == 0 {
+            return;
+        }
+
+        self.write(self.clock, 0xFF26, nr52);
+
+        for (address, mask) in [
+            (0xFF24, 0xFF), // NR50
+            (0xFF25, 0xFF), // NR51
+            (0xFF10, 0xFF), // NR10
+            (0xFF11, 0xC0), // NR11 (duty)
+            (0xFF12, 0xFF), // NR12
+            (0xFF14, 0x40), // NR14 (length enable)
+            (0xFF16, 0xC0), // NR21 (duty)
+            (0xFF17, 0xFF), // NR22
+            (0xFF19, 0x40), // NR24 (length enable)
+            (0xFF1A, 0xFF), // NR30
+            (0xFF1C, 0xFF), // NR32
+            (0xFF1E, 0x40), // NR34 (length enable)
+            (0xFF21, 0xFF), // NR42
+            (0xFF22, 0xFF), // NR43
+            (0xFF23, 0x40), // NR44 (length enable)
+        ] {
+            self.write(self.clock, address, apu.read(address) & mask);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{GameBoy, components::apu::tests::tone_rom, constants::DeviceModel};
+
+    fn read_u32(data: &[u8], offset: usize) -> u32 {
+        u32::from_le_bytes(data[offset..offset + 4].try_into().unwrap())
+    }
+
+    /// The commands after the initial state.
+    fn commands(apu: &Apu, record: impl FnOnce(&mut VgmRecorder)) -> Vec<u8> {
+        let start = VgmRecorder::new(0, apu).data.len();
+
+        let mut recorder = VgmRecorder::new(0, apu);
+        record(&mut recorder);
+
+        recorder.data[start..].to_vec()
+    }
+
+    #[test]
+    fn test_waits() {
+        let apu = Apu::default();
+        let clock = |samples: u64| samples * CPU_CLOCK_RATE as u64 / VGM_SAMPLE_RATE + 1;
+
+        let data = commands(&apu, |recorder| {
+            recorder.write(clock(3), 0xFF24, 0x77);
+            recorder.write(clock(3 + 735), 0xFF25, 0xFF);
+            recorder.write(clock(3 + 735 + 70000), 0xFF26, 0x80);
+        });
+
+        assert_eq!(
+            data,
+            [
+                0x72, 0xB3, 0x14, 0x77, // Wait 3
+                0x62, 0xB3, 0x15, 0xFF, // Wait 735
+                0x61, 0xFF, 0xFF, 0x61, 0x71, 0x11, 0xB3, 0x16, 0x80, // Wait 70000
+            ]
+        );
+    }
+
+    #[test]
+    fn test_header() {
+        let apu = Apu::default();
+        let second = CPU_CLOCK_RATE as u64;
+
+        let mut recorder = VgmRecorder::new(100, &apu);
+        recorder.write(100 + second, 0xFF24, 0x77);
+        recorder.mark_loop(100 + second);
+        recorder.write(100 + second * 2, 0xFF25, 0xFF);
+        let loop_offset = HEADER_SIZE + recorder.loop_start.unwrap().0;
+
+        let vgm = recorder.finish(100 + second * 3);
+
+        assert_eq!(&vgm[0..4], b"Vgm ");
+        assert_eq!(read_u32(&vgm, 0x04) as usize, vgm.len() - 4);
+        assert_eq!(read_u32(&vgm, 0x08), 0x161);
+        assert_eq!(read_u32(&vgm, 0x18), 3 * 44100);
+        assert_eq!(read_u32(&vgm, 0x1C) as usize + 0x1C, loop_offset);
+        assert_eq!(read_u32(&vgm, 0x20), 2 * 44100);
+        assert_eq!(read_u32(&vgm, 0x34) as usize + 0x34, HEADER_SIZE);
+        assert_eq!(read_u32(&vgm, 0x80), 4194304);
+
+        assert_eq!(&vgm[loop_offset..loop_offset + 3], [0x61, 0x44, 0xAC]);
+        assert_eq!(vgm.last(), Some(&0x66));
+    }
+
+    #[test]
+    fn test_vgm_recording() {
+        let mut gb = GameBoy::new(DeviceModel::Cgb);
+        gb.load(None, tone_rom()).unwrap();
+
+        assert!(gb.stop_vgm_recording().is_none());
+
+        gb.start_vgm_recording();
+        gb.run_frame();
+        gb.mark_vgm_loop();
+
+        // Kept across power cycles.
+        gb.power_cycle();
+        assert!(gb.is_recording_vgm());
+
+        for _ in 0..10 {
+            gb.run_frame();
+        }
+
+        let vgm = gb.stop_vgm_recording().unwrap();
+        assert!(!gb.is_recording_vgm());
+
+        assert_eq!(&vgm[0..4], b"Vgm ");
+        assert_eq!(read_u32(&vgm, 0x04) as usize, vgm.len() - 4);
+        assert_eq!(vgm.last(), Some(&0x66));
+
+        // About 11 frames of 735 samples, and 10 of them loop.
+        assert!((8000..8200).contains(&read_u32(&vgm, 0x18)));
+        assert!((7300..7400).contains(&read_u32(&vgm, 0x20)));
+
+        // The writes to NR22, NR23 and NR24 are in the data, after the loop point.
+        let loop_offset = read_u32(&vgm, 0x1C) as usize + 0x1C;
+        let commands = &vgm[loop_offset..];
+
+        for write in [[0xB3, 0x07, 0xF0], [0xB3, 0x08, 0x00], [0xB3, 0x09, 0x87]] {
+            assert!(commands.windows(3).any(|command| command == write));
+        }
+    }
+}
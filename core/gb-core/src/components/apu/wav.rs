@@ -0,0 +1,85 @@
+use std::io::{self, Write};
+
+/// Writes interleaved samples, `channels` per frame, as 16-bit PCM.
+pub fn write_wav(
+    writer: &mut impl Write,
+    samples: &[f32],
+    channels: u16,
+    sample_rate: u32,
+) -> io::Result<()> {
+    const BITS_PER_SAMPLE: u16 = 16;
+    const FORMAT_PCM: u16 = 1;
+
+    let block_align = channels * BITS_PER_SAMPLE / 8;
+    let byte_rate = sample_rate * block_align as u32;
+    let data_size = (samples.len() * size_of::<i16>()) as u32;
+
+    writer.write_all(b"RIFF")?;
+    writer.write_all(&(36 + data_size).to_le_bytes())?;
+    writer.write_all(b"WAVE")?;
+
+    writer.write_all(b"fmt ")?;
+    writer.write_all(&16u32.to_le_bytes())?;
+    writer.write_all(&FORMAT_PCM.to_le_bytes())?;
+    writer.write_all(&channels.to_le_bytes())?;
+    writer.write_all(&sample_rate.to_le_bytes())?;
+    writer.write_all(&byte_rate.to_le_bytes())?;
+    writer.write_all(&block_align.to_le_bytes())?;
+    writer.write_all(&BITS_PER_SAMPLE.to_le_bytes())?;
+
+    writer.write_all(b"data")?;
+    writer.write_all(&data_size.to_le_bytes())?;
+
+    for sample in samples {
+        let sample = (sample.clamp(-1.0, 1.0) * f32::from(i16::MAX)) as i16;
+        writer.write_all(&sample.to_le_bytes())?;
+    }
+
+    Ok(())
+}
+
+/// Splits interleaved samples into one buffer for each of the `channels`.
+#[must_use]
+pub fn deinterleave(samples: &[f32], channels: usize) -> Vec<Vec<f32>> {
+    (0..channels)
+        .map(|channel| {
+            samples
+                .iter()
+                .skip(channel)
+                .step_by(channels)
+                .copied()
+                .collect()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_write_wav() {
+        let mut wav = Vec::new();
+        write_wav(&mut wav, &[0.0, 1.0, -1.0, 2.0], 2, 48000).unwrap();
+
+        assert_eq!(wav.len(), 44 + 4 * 2);
+        assert_eq!(&wav[0..4], b"RIFF");
+        assert_eq!(&wav[4..8], &44u32.to_le_bytes());
+        assert_eq!(&wav[22..24], &2u16.to_le_bytes());
+        assert_eq!(&wav[24..28], &48000u32.to_le_bytes());
+        assert_eq!(&wav[40..44], &8u32.to_le_bytes());
+
+        // Clamped to the range of the samples.
+        assert_eq!(&wav[44..], &[0, 0, 0xFF, 0x7F, 0x01, 0x80, 0xFF, 0x7F]);
+    }
+
+    #[test]
+    fn test_deinterleave() {
+        let samples = [1.0, 2.0, 3.0, 4.0, 5.0, 6.0];
+
+        assert_eq!(
+            deinterleave(&samples, 3),
+            [vec![1.0, 4.0], vec![2.0, 5.0], vec![3.0, 6.0]]
+        );
+    }
+}
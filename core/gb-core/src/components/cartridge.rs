@@ -4,6 +4,8 @@ use error::CartridgeError;
 use info::Info;
 use mbc::{Mbc, MbcInterface};
 
+use crate::save_state::{SaveState, SaveStateError, StateReader, StateWriter};
+
 pub struct Cartridge {
     pub rom: Arc<[u8]>,
     pub info: Info,
@@ -27,6 +29,27 @@ impl Cartridge {
         self.mbc.load_battery(file);
     }
 
+    /// Like [`Cartridge::load_battery`], but the clock does not catch up with the time that
+    /// passed since the file was saved. Used where the emulation must be deterministic.
+    pub fn restore_battery(&mut self, file: Vec<u8>) {
+        self.mbc.restore_battery(file);
+    }
+
+    /// Carries the battery backed RAM and the clock over from `previous`, like a soft reset.
+    pub(crate) fn keep_battery(&mut self, previous: Self) {
+        self.mbc.keep_battery(previous.mbc);
+    }
+
+    /// The RAM, without the RTC footer of the battery.
+    #[must_use]
+    pub fn ram(&self) -> &[u8] {
+        self.mbc.ram()
+    }
+
+    pub fn ram_mut(&mut self) -> &mut [u8] {
+        self.mbc.ram_mut()
+    }
+
     #[must_use]
     pub fn read_rom_bank_0(&self, address: u16) -> u8 {
         self.mbc.read_rom_bank_0(address)
@@ -37,6 +60,16 @@ impl Cartridge {
         self.mbc.read_rom_bank_x(address)
     }
 
+    /// The ROM bank mapped to `address` (0x0000 ~ 0x7FFF).
+    #[must_use]
+    pub fn rom_bank(&self, address: u16) -> usize {
+        if address < 0x4000 {
+            self.mbc.rom_bank_0()
+        } else {
+            self.mbc.rom_bank_x()
+        }
+    }
+
     #[must_use]
     pub fn read_ram(&self, address: u16) -> u8 {
         self.mbc.read_ram(address)
@@ -49,8 +82,65 @@ impl Cartridge {
     pub fn write_ram(&mut self, address: u16, value: u8) {
         self.mbc.write_ram(address, value);
     }
+
+    pub fn tick(&mut self, cycles: u32) {
+        self.mbc.tick(cycles);
+    }
+}
+
+impl SaveState for Cartridge {
+    fn save_state(&self, writer: &mut StateWriter) {
+        self.mbc.save_state(writer);
+    }
+
+    fn load_state(&mut self, reader: &mut StateReader) -> Result<(), SaveStateError> {
+        self.mbc.load_state(reader)
+    }
 }
 
 pub mod error;
 pub mod info;
 pub mod mbc;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{GameBoy, constants::DeviceModel, error::GameBoyError, test_rom::TestRom};
+
+    /// MBC1+RAM+BATTERY, with 8 KiB of RAM.
+    fn mbc1_rom() -> Arc<[u8]> {
+        TestRom::new().mbc(0x03).ram_size(0x02).build()
+    }
+
+    #[test]
+    fn test_load_rejects_invalid_rom() {
+        let mut gb = GameBoy::new(DeviceModel::Cgb);
+        gb.load(None, mbc1_rom()).unwrap();
+        gb.load_battery(vec![0x42; 0x2000]);
+
+        assert!(matches!(
+            gb.load(None, vec![0; 0x100].into()),
+            Err(GameBoyError::CartridgeError(_))
+        ));
+        assert!(matches!(
+            gb.load(Some(vec![0; 0x10].into()), mbc1_rom()),
+            Err(GameBoyError::BootromError(_))
+        ));
+
+        // The running game is left untouched.
+        assert_eq!(gb.get_battery(), Some([0x42; 0x2000].as_slice()));
+    }
+
+    #[test]
+    fn test_reset_keeps_cartridge_ram() {
+        let mut gb = GameBoy::new(DeviceModel::Cgb);
+        gb.load(None, mbc1_rom()).unwrap();
+        gb.load_battery(vec![0x42; 0x2000]);
+
+        gb.reset();
+        assert_eq!(gb.get_battery(), Some([0x42; 0x2000].as_slice()));
+
+        gb.power_cycle();
+        assert_eq!(gb.get_battery(), Some([0x00; 0x2000].as_slice()));
+    }
+}
@@ -2,19 +2,39 @@ use enum_dispatch::enum_dispatch;
 
 use self::{mbc1::Mbc1, mbc2::Mbc2, mbc3::Mbc3, mbc5::Mbc5, no_mbc::NoMbc};
 use super::info::{Info, mbc_type::MbcType};
+use crate::save_state::{SaveState, SaveStateError, StateReader, StateWriter};
 
 #[enum_dispatch]
 pub(crate) trait MbcInterface {
     fn get_battery(&self) -> &[u8];
     fn load_battery(&mut self, file: Vec<u8>);
 
+    /// Like [`MbcInterface::load_battery`], but the clock does not catch up with the time that
+    /// passed since the file was saved.
+    fn restore_battery(&mut self, file: Vec<u8>) {
+        self.load_battery(file);
+    }
+
+    /// All the RAM banks, even if the cartridge has no battery.
+    fn ram(&self) -> &[u8];
+    fn ram_mut(&mut self) -> &mut [u8];
+
     fn read_rom_bank_0(&self, address: u16) -> u8;
     fn read_rom_bank_x(&self, address: u16) -> u8;
 
+    /// The ROM banks currently mapped to 0x0000 ~ 0x3FFF and 0x4000 ~ 0x7FFF.
+    fn rom_bank_0(&self) -> usize {
+        0
+    }
+    fn rom_bank_x(&self) -> usize;
+
     fn read_ram(&self, address: u16) -> u8;
 
     fn write_rom(&mut self, address: u16, value: u8);
     fn write_ram(&mut self, address: u16, value: u8);
+
+    /// Advances time-dependent hardware by the given amount of (single speed) T-cycles.
+    fn tick(&mut self, _cycles: u32) {}
 }
 
 #[enum_dispatch(MbcInterface)]
@@ -36,6 +56,64 @@ impl Mbc {
             MbcType::Mbc5 => Mbc5::new(cartridge_info).into(),
         }
     }
+
+    /// Carries the battery backed RAM and the clock over from the same cartridge.
+    pub(crate) fn keep_battery(&mut self, previous: Self) {
+        match (self, previous) {
+            (Self::Mbc3(mbc), Self::Mbc3(previous)) => mbc.keep_battery(previous),
+
+            (mbc, previous) => {
+                if !previous.get_battery().is_empty() {
+                    mbc.restore_battery(previous.get_battery().to_vec());
+                }
+            }
+        }
+    }
+}
+
+impl SaveState for Mbc {
+    fn save_state(&self, writer: &mut StateWriter) {
+        match self {
+            Self::NoMbc(mbc) => {
+                writer.write_u8(0);
+                mbc.save_state(writer);
+            }
+
+            Self::Mbc1(mbc) => {
+                writer.write_u8(1);
+                mbc.save_state(writer);
+            }
+
+            Self::Mbc2(mbc) => {
+                writer.write_u8(2);
+                mbc.save_state(writer);
+            }
+
+            Self::Mbc3(mbc) => {
+                writer.write_u8(3);
+                mbc.save_state(writer);
+            }
+
+            Self::Mbc5(mbc) => {
+                writer.write_u8(5);
+                mbc.save_state(writer);
+            }
+        }
+    }
+
+    fn load_state(&mut self, reader: &mut StateReader) -> Result<(), SaveStateError> {
+        let tag = reader.read_u8()?;
+
+        match (self, tag) {
+            (Self::NoMbc(mbc), 0) => mbc.load_state(reader),
+            (Self::Mbc1(mbc), 1) => mbc.load_state(reader),
+            (Self::Mbc2(mbc), 2) => mbc.load_state(reader),
+            (Self::Mbc3(mbc), 3) => mbc.load_state(reader),
+            (Self::Mbc5(mbc), 5) => mbc.load_state(reader),
+
+            _ => Err(SaveStateError::InvalidData("MBC type")),
+        }
+    }
 }
 
 mod mbc1;
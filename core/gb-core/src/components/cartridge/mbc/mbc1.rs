@@ -6,6 +6,7 @@ use super::MbcInterface;
 use crate::{
     components::cartridge::info::{Info, ram_banks::RAM_BANK_SIZE, rom_banks::ROM_BANK_SIZE},
     constants::ONE_KIB,
+    save_state::{SaveState, SaveStateError, StateReader, StateWriter},
 };
 
 pub struct Mbc1 {
@@ -77,6 +78,14 @@ impl MbcInterface for Mbc1 {
         &self.ram
     }
 
+    fn ram(&self) -> &[u8] {
+        &self.ram
+    }
+
+    fn ram_mut(&mut self) -> &mut [u8] {
+        &mut self.ram
+    }
+
     fn load_battery(&mut self, file: Vec<u8>) {
         if self.ram.is_empty() {
             warn!("This cartridge does not have a battery backed RAM");
@@ -103,6 +112,14 @@ impl MbcInterface for Mbc1 {
         self.rom[address + offset]
     }
 
+    fn rom_bank_0(&self) -> usize {
+        self.rom_0x0000_0x3fff_offset() / ROM_BANK_SIZE
+    }
+
+    fn rom_bank_x(&self) -> usize {
+        self.rom_0x4000_0x7fff_offset() / ROM_BANK_SIZE
+    }
+
     fn read_ram(&self, address: u16) -> u8 {
         if !self.ram_enable || self.ram.is_empty() {
             return 0xFF;
@@ -143,3 +160,23 @@ impl MbcInterface for Mbc1 {
         self.ram[address + offset] = value;
     }
 }
+
+impl SaveState for Mbc1 {
+    fn save_state(&self, writer: &mut StateWriter) {
+        writer.write_bytes(&self.ram);
+        writer.write_bool(self.ram_enable);
+        writer.write_bool(self.mode);
+        writer.write_u8(self.bank_lo);
+        writer.write_u8(self.bank_hi);
+    }
+
+    fn load_state(&mut self, reader: &mut StateReader) -> Result<(), SaveStateError> {
+        reader.read_bytes_into(&mut self.ram)?;
+        self.ram_enable = reader.read_bool()?;
+        self.mode = reader.read_bool()?;
+        self.bank_lo = reader.read_u8()?;
+        self.bank_hi = reader.read_u8()?;
+
+        Ok(())
+    }
+}
@@ -3,7 +3,10 @@ use std::sync::Arc;
 use tracing::error;
 
 use super::MbcInterface;
-use crate::components::cartridge::info::{Info, rom_banks::ROM_BANK_SIZE};
+use crate::{
+    components::cartridge::info::{Info, rom_banks::ROM_BANK_SIZE},
+    save_state::{SaveState, SaveStateError, StateReader, StateWriter},
+};
 
 pub struct Mbc2 {
     rom: Arc<[u8]>,
@@ -42,6 +45,14 @@ impl MbcInterface for Mbc2 {
         self.ram.as_ref()
     }
 
+    fn ram(&self) -> &[u8] {
+        self.ram.as_ref()
+    }
+
+    fn ram_mut(&mut self) -> &mut [u8] {
+        self.ram.as_mut()
+    }
+
     fn load_battery(&mut self, file: Vec<u8>) {
         self.ram = if let Ok(file) = file.try_into() {
             file
@@ -63,6 +74,10 @@ impl MbcInterface for Mbc2 {
         self.rom[address + offset]
     }
 
+    fn rom_bank_x(&self) -> usize {
+        self.rom_0x4000_0x7fff_offset() / ROM_BANK_SIZE
+    }
+
     fn read_ram(&self, address: u16) -> u8 {
         if !self.ram_enable {
             return 0xFF;
@@ -100,3 +115,19 @@ impl MbcInterface for Mbc2 {
         self.ram[address & 0x01FF] = value;
     }
 }
+
+impl SaveState for Mbc2 {
+    fn save_state(&self, writer: &mut StateWriter) {
+        writer.write_bytes(self.ram.as_ref());
+        writer.write_bool(self.ram_enable);
+        writer.write_u8(self.rom_bank);
+    }
+
+    fn load_state(&mut self, reader: &mut StateReader) -> Result<(), SaveStateError> {
+        reader.read_bytes_into(self.ram.as_mut())?;
+        self.ram_enable = reader.read_bool()?;
+        self.rom_bank = reader.read_u8()?;
+
+        Ok(())
+    }
+}
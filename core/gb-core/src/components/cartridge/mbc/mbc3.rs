@@ -1,16 +1,28 @@
 use std::sync::Arc;
 
-use tracing::{error, info};
+use rtc::{RTC_FOOTER_SIZE, Rtc};
+use tracing::{error, info, warn};
 
 use super::MbcInterface;
 use crate::{
-    components::cartridge::info::{Info, ram_banks::RAM_BANK_SIZE, rom_banks::ROM_BANK_SIZE},
+    components::cartridge::info::{
+        Info,
+        extra_features::ExtraFeature,
+        ram_banks::RAM_BANK_SIZE,
+        rom_banks::ROM_BANK_SIZE,
+    },
     constants::ONE_KIB,
+    save_state::{SaveState, SaveStateError, StateReader, StateWriter},
 };
 
 pub struct Mbc3 {
     rom: Arc<[u8]>,
-    ram: Box<[u8]>,
+
+    /// RAM, followed by the RTC footer if the cartridge has a timer.
+    battery: Box<[u8]>,
+    ram_size: usize,
+
+    rtc: Option<Rtc>,
 
     ram_rtc_enable: bool,
 
@@ -27,11 +39,12 @@ enum RamRtcSelection {
 }
 
 impl Mbc3 {
-    // FIXME: need to check if the cartridge supports the timer
     pub fn new(cartridge_info: &Info) -> Self {
         let rom_banks = cartridge_info.rom_banks;
         let ram_banks = cartridge_info.ram_banks;
 
+        let has_timer = cartridge_info.extra_features.contains(&ExtraFeature::Timer);
+
         // ROM size > 2MiB or RAM size > 32KiB
         let is_mbc30 = rom_banks > 128 || ram_banks > 4;
 
@@ -39,9 +52,16 @@ impl Mbc3 {
             info!("MBC30 variant");
         }
 
-        Self {
+        let ram_size = ram_banks * (8 * ONE_KIB);
+        let footer_size = if has_timer { RTC_FOOTER_SIZE } else { 0 };
+
+        let mut mbc = Self {
             rom: cartridge_info.rom.clone(),
-            ram: vec![0; ram_banks * (8 * ONE_KIB)].into_boxed_slice(),
+
+            battery: vec![0; ram_size + footer_size].into_boxed_slice(),
+            ram_size,
+
+            rtc: has_timer.then(Rtc::default),
 
             ram_rtc_enable: false,
 
@@ -49,6 +69,16 @@ impl Mbc3 {
             ram_rtc_sel: Some(RamRtcSelection::RamBank(0)),
 
             is_mbc30,
+        };
+
+        mbc.update_rtc_footer();
+
+        mbc
+    }
+
+    fn update_rtc_footer(&mut self) {
+        if let Some(rtc) = &self.rtc {
+            rtc.write_footer(&mut self.battery[self.ram_size..]);
         }
     }
 
@@ -56,6 +86,55 @@ impl Mbc3 {
         ROM_BANK_SIZE * (self.rom_bank as usize)
     }
 
+    /// What a write of `value` to 0x4000 ~ 0x5FFF maps to 0xA000 ~ 0xBFFF.
+    fn ram_rtc_selection(&self, value: u8) -> Option<RamRtcSelection> {
+        use RamRtcSelection::{RamBank, RtcRegister};
+
+        match value {
+            0x00..=0x03 if !self.is_mbc30 => Some(RamBank(value)),
+            0x00..=0x07 if self.is_mbc30 => Some(RamBank(value)),
+            0x08..=0x0C => Some(RtcRegister(value)),
+
+            _ => None,
+        }
+    }
+
+    /// Takes over the RAM and the running clock of `previous`.
+    pub fn keep_battery(&mut self, previous: Self) {
+        self.battery = previous.battery;
+        self.rtc = previous.rtc;
+    }
+
+    fn read_battery(&mut self, file: &[u8], catch_up: bool) {
+        if self.battery.is_empty() {
+            error!("This cartridge does not have a battery backed RAM");
+            return;
+        } else if file.len() < self.ram_size {
+            error!("Size mismatch");
+            return;
+        }
+
+        let (ram, footer) = file.split_at(self.ram_size);
+
+        if let Some(rtc) = self.rtc.as_mut() {
+            let valid = if catch_up {
+                rtc.read_footer(footer)
+            } else {
+                rtc.restore_footer(footer).is_some()
+            };
+
+            if !valid {
+                warn!("Invalid or missing RTC data, the clock will start from zero");
+            }
+        } else if !footer.is_empty() {
+            error!("Size mismatch");
+            return;
+        }
+
+        self.ram_mut().copy_from_slice(ram);
+        self.update_rtc_footer();
+    }
+
     fn ram_offset(bank: u8) -> usize {
         // Bank is guaranteed to be between 0 and 7.
         RAM_BANK_SIZE * (bank as usize)
@@ -64,19 +143,31 @@ impl Mbc3 {
 
 impl MbcInterface for Mbc3 {
     fn get_battery(&self) -> &[u8] {
-        &self.ram
+        &self.battery
+    }
+
+    fn ram(&self) -> &[u8] {
+        &self.battery[..self.ram_size]
+    }
+
+    fn ram_mut(&mut self) -> &mut [u8] {
+        &mut self.battery[..self.ram_size]
     }
 
     fn load_battery(&mut self, file: Vec<u8>) {
-        if self.ram.is_empty() {
-            error!("This cartridge does not have a battery backed RAM");
-            return;
-        } else if self.ram.len() != file.len() {
-            error!("Size mismatch");
-            return;
-        }
+        self.read_battery(&file, true);
+    }
 
-        self.ram = file.into_boxed_slice();
+    fn restore_battery(&mut self, file: Vec<u8>) {
+        self.read_battery(&file, false);
+    }
+
+    fn tick(&mut self, cycles: u32) {
+        if let Some(rtc) = self.rtc.as_mut()
+            && rtc.tick(cycles)
+        {
+            self.update_rtc_footer();
+        }
     }
 
     fn read_rom_bank_0(&self, address: u16) -> u8 {
@@ -91,6 +182,10 @@ impl MbcInterface for Mbc3 {
         self.rom[address + offset]
     }
 
+    fn rom_bank_x(&self) -> usize {
+        self.rom_0x4000_0x7fff_offset() / ROM_BANK_SIZE
+    }
+
     fn read_ram(&self, address: u16) -> u8 {
         use RamRtcSelection::{RamBank, RtcRegister};
 
@@ -107,16 +202,14 @@ impl MbcInterface for Mbc3 {
                 let address = (address - 0xA000) as usize;
                 let offset = Self::ram_offset(bank);
 
-                self.ram[address + offset]
+                self.ram().get(address + offset).copied().unwrap_or(0xFF)
             }
 
-            RtcRegister(_register) => 0x00,
+            RtcRegister(register) => self.rtc.as_ref().map_or(0xFF, |rtc| rtc.read(register)),
         }
     }
 
     fn write_rom(&mut self, address: u16, value: u8) {
-        use RamRtcSelection::{RamBank, RtcRegister};
-
         match address {
             0x0000..=0x1FFF => self.ram_rtc_enable = (value & 0b1111) == 0x0A,
 
@@ -132,18 +225,14 @@ impl MbcInterface for Mbc3 {
                 }
             }
 
-            0x4000..=0x5FFF => {
-                self.ram_rtc_sel = match value {
-                    0x00..=0x03 if !self.is_mbc30 => Some(RamBank(value)),
-                    0x00..=0x07 if self.is_mbc30 => Some(RamBank(value)),
-                    0x08..=0x0C => Some(RtcRegister(value)),
+            0x4000..=0x5FFF => self.ram_rtc_sel = self.ram_rtc_selection(value),
 
-                    _ => None,
+            0x6000..=0x7FFF => {
+                if let Some(rtc) = self.rtc.as_mut() {
+                    rtc.latch(value);
                 }
             }
 
-            0x6000..=0x7FFF => { /* Latch RTC registers */ }
-
             _ => unreachable!("Invalid write: ({address:#06x}) = {value:#04x}"),
         }
     }
@@ -164,10 +253,174 @@ impl MbcInterface for Mbc3 {
                 let address = (address - 0xA000) as usize;
                 let offset = Self::ram_offset(bank);
 
-                self.ram[address + offset] = value;
+                if let Some(byte) = self.ram_mut().get_mut(address + offset) {
+                    *byte = value;
+                }
             }
 
-            RtcRegister(_register) => {}
+            RtcRegister(register) => {
+                if let Some(rtc) = self.rtc.as_mut() {
+                    rtc.write(register, value);
+                }
+
+                self.update_rtc_footer();
+            }
+        }
+    }
+}
+
+impl SaveState for Mbc3 {
+    fn save_state(&self, writer: &mut StateWriter) {
+        use RamRtcSelection::{RamBank, RtcRegister};
+
+        writer.write_bytes(self.ram());
+
+        if let Some(rtc) = &self.rtc {
+            rtc.save_state(writer);
+        }
+
+        writer.write_bool(self.ram_rtc_enable);
+        writer.write_u8(self.rom_bank);
+
+        let (selection, value) = match self.ram_rtc_sel {
+            None => (0, 0),
+            Some(RamBank(bank)) => (1, bank),
+            Some(RtcRegister(register)) => (2, register),
+        };
+
+        writer.write_u8(selection);
+        writer.write_u8(value);
+    }
+
+    fn load_state(&mut self, reader: &mut StateReader) -> Result<(), SaveStateError> {
+        use RamRtcSelection::{RamBank, RtcRegister};
+
+        reader.read_bytes_into(self.ram_mut())?;
+
+        if let Some(rtc) = self.rtc.as_mut() {
+            rtc.load_state(reader)?;
+        }
+
+        self.update_rtc_footer();
+
+        self.ram_rtc_enable = reader.read_bool()?;
+        self.rom_bank = reader.read_u8()?;
+
+        if self.rom_bank == 0 || self.rom_0x4000_0x7fff_offset() >= self.rom.len() {
+            return Err(SaveStateError::InvalidData("MBC3 ROM bank"));
+        }
+
+        let selection = reader.read_u8()?;
+        let value = reader.read_u8()?;
+
+        // Only what a write to the register could have selected.
+        self.ram_rtc_sel = match (selection, self.ram_rtc_selection(value)) {
+            (0, _) => None,
+            (1, Some(RamBank(bank))) => Some(RamBank(bank)),
+            (2, Some(RtcRegister(register))) => Some(RtcRegister(register)),
+            _ => return Err(SaveStateError::InvalidData("MBC3 RAM/RTC selection")),
+        };
+
+        Ok(())
+    }
+}
+
+mod rtc;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        GameBoy,
+        constants::DeviceModel,
+        movie::{Movie, MovieFrame, MovieStart},
+        test_rom::TestRom,
+    };
+
+    /// MBC3+TIMER+RAM+BATTERY, 32 KiB of ROM and 8 KiB of RAM.
+    fn mbc3() -> Mbc3 {
+        let rom = TestRom::new().mbc(0x10).ram_size(0x02).build();
+
+        Mbc3::new(&Info::new(rom).unwrap())
+    }
+
+    /// Saves a state, then replaces the ROM bank, the RAM/RTC selection and its value.
+    fn load_patched_state(rom_bank: u8, selection: u8, value: u8) -> Result<(), SaveStateError> {
+        let mut mbc = mbc3();
+
+        let mut writer = StateWriter::default();
+        mbc.save_state(&mut writer);
+
+        let mut state = writer.into_inner();
+        let length = state.len();
+        state[length - 3..].copy_from_slice(&[rom_bank, selection, value]);
+
+        mbc.load_state(&mut StateReader::new(&state))
+    }
+
+    #[test]
+    fn test_load_state() {
+        assert!(load_patched_state(0x01, 0, 0x00).is_ok());
+        assert!(load_patched_state(0x01, 1, 0x03).is_ok());
+        assert!(load_patched_state(0x01, 2, 0x08).is_ok());
+        assert!(load_patched_state(0x01, 2, 0x0C).is_ok());
+    }
+
+    #[test]
+    fn test_load_state_invalid_selection() {
+        for (rom_bank, selection, value) in [
+            // Only 2 banks of ROM, and bank 0 can't be mapped to 0x4000 ~ 0x7FFF.
+            (0x00, 0, 0x00),
+            (0x02, 0, 0x00),
+            // Only MBC30 has RAM banks 4 ~ 7.
+            (0x01, 1, 0x04),
+            (0x01, 1, 0x08),
+            (0x01, 2, 0x07),
+            (0x01, 2, 0x0D),
+            (0x01, 3, 0x00),
+        ] {
+            assert!(
+                matches!(
+                    load_patched_state(rom_bank, selection, value),
+                    Err(SaveStateError::InvalidData(_))
+                ),
+                "{rom_bank:#04x}, {selection}, {value:#04x}"
+            );
         }
     }
+
+    #[test]
+    fn test_movie_playback_with_rtc() {
+        // The footer was saved in 1970, the clock must not catch up with today.
+        let mut battery = vec![0; 0x2000 + 48];
+        battery[0x2004..0x2008].copy_from_slice(&42_u32.to_le_bytes());
+
+        let movie = Movie {
+            device_model: DeviceModel::Cgb,
+            rom: None,
+            start: MovieStart::CartridgeRam(battery),
+            frames: (0..70)
+                .map(|frame| {
+                    MovieFrame {
+                        buttons: 0,
+                        reset: frame == 30,
+                    }
+                })
+                .collect(),
+        };
+
+        let mut gb = GameBoy::new(DeviceModel::Cgb);
+        gb.load(None, TestRom::new().mbc(0x10).ram_size(0x02).build())
+            .unwrap();
+        gb.play_movie(movie).unwrap();
+
+        for _ in 0..70 {
+            gb.run_frame();
+        }
+
+        // Just over a second, counted across the reset. S and M are the first two registers.
+        let footer = &gb.get_battery().unwrap()[0x2000..];
+        assert_eq!(footer[0x00..0x08], [1, 0, 0, 0, 42, 0, 0, 0]);
+        assert_eq!(footer[0x0C..0x10], [0, 0, 0, 0]);
+    }
 }
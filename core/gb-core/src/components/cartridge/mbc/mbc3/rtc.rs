@@ -0,0 +1,437 @@
+use web_time::{SystemTime, UNIX_EPOCH};
+
+use crate::save_state::{SaveState, SaveStateError, StateReader, StateWriter};
+
+/// Size of the RTC footer appended to the battery file (VBA-M/BGB layout).
+///
+/// | Offset | Size | Field                                      |
+/// | ------ | ---- | ------------------------------------------ |
+/// | 0x00   | 20   | S, M, H, DL and DH (`u32` each)            |
+/// | 0x14   | 20   | Latched S, M, H, DL and DH (`u32` each)    |
+/// | 0x28   | 8    | UNIX timestamp of when the file was saved  |
+///
+/// Some emulators only store a 32-bit timestamp (44 bytes), those are also accepted.
+pub const RTC_FOOTER_SIZE: usize = 48;
+const RTC_FOOTER_SIZE_32_BIT_TIMESTAMP: usize = 44;
+
+/// The RTC is clocked by a 32768 Hz crystal, independent from the CPU speed.
+const CYCLES_PER_SECOND: u32 = 4_194_304;
+
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+struct Registers {
+    seconds: u8,
+    minutes: u8,
+    hours: u8,
+    days: u16,
+
+    halt: bool,
+    day_carry: bool,
+}
+
+impl Registers {
+    fn read(self, register: u8) -> u8 {
+        match register {
+            0x08 => self.seconds,
+            0x09 => self.minutes,
+            0x0A => self.hours,
+            0x0B => self.days as u8,
+            0x0C => self.read_dh(),
+
+            _ => unreachable!("Invalid RTC register: {register:#04x}"),
+        }
+    }
+
+    fn write(&mut self, register: u8, value: u8) {
+        match register {
+            0x08 => self.seconds = value & 0b0011_1111,
+            0x09 => self.minutes = value & 0b0011_1111,
+            0x0A => self.hours = value & 0b0001_1111,
+            0x0B => self.days = (self.days & 0x100) | value as u16,
+            0x0C => self.write_dh(value),
+
+            _ => unreachable!("Invalid RTC register: {register:#04x}"),
+        }
+    }
+
+    fn read_dh(self) -> u8 {
+        let day_high = (self.days >> 8) as u8 & 0b1;
+        let halt = (self.halt as u8) << 6;
+        let day_carry = (self.day_carry as u8) << 7;
+
+        day_high | halt | day_carry
+    }
+
+    fn write_dh(&mut self, value: u8) {
+        self.days = (self.days & 0xFF) | (((value & 0b1) as u16) << 8);
+        self.halt = value & 0b0100_0000 != 0;
+        self.day_carry = value & 0b1000_0000 != 0;
+    }
+
+    fn in_range(self) -> bool {
+        self.seconds < 60 && self.minutes < 60 && self.hours < 24
+    }
+
+    /// Out of range values keep counting until the register overflows, without carrying.
+    fn advance_second(&mut self) {
+        self.seconds = (self.seconds + 1) & 0b0011_1111;
+
+        if self.seconds != 60 {
+            return;
+        }
+
+        self.seconds = 0;
+        self.minutes = (self.minutes + 1) & 0b0011_1111;
+
+        if self.minutes != 60 {
+            return;
+        }
+
+        self.minutes = 0;
+        self.hours = (self.hours + 1) & 0b0001_1111;
+
+        if self.hours != 24 {
+            return;
+        }
+
+        self.hours = 0;
+        self.advance_day();
+    }
+
+    fn advance_day(&mut self) {
+        self.days += 1;
+
+        if self.days > 0x1FF {
+            self.days = 0;
+            self.day_carry = true;
+        }
+    }
+
+    fn advance(&mut self, mut seconds: u64) {
+        while seconds > 0 && !self.in_range() {
+            self.advance_second();
+            seconds -= 1;
+        }
+
+        if seconds == 0 {
+            return;
+        }
+
+        let time_of_day =
+            (self.hours as u64 * 60 + self.minutes as u64) * 60 + self.seconds as u64 + seconds;
+        let days = self.days as u64 + time_of_day / SECONDS_PER_DAY;
+        let time_of_day = time_of_day % SECONDS_PER_DAY;
+
+        self.seconds = (time_of_day % 60) as u8;
+        self.minutes = ((time_of_day / 60) % 60) as u8;
+        self.hours = (time_of_day / 3600) as u8;
+
+        if days > 0x1FF {
+            self.day_carry = true;
+        }
+
+        self.days = (days & 0x1FF) as u16;
+    }
+
+    fn write_footer(self, footer: &mut [u8]) {
+        let values = [
+            self.seconds,
+            self.minutes,
+            self.hours,
+            self.days as u8,
+            self.read_dh(),
+        ];
+
+        for (chunk, value) in footer.chunks_exact_mut(4).zip(values) {
+            chunk.copy_from_slice(&(value as u32).to_le_bytes());
+        }
+    }
+
+    fn read_footer(footer: &[u8]) -> Self {
+        let mut values = footer
+            .chunks_exact(4)
+            .map(|chunk| u32::from_le_bytes(chunk.try_into().unwrap()) as u8);
+
+        let mut registers = Self::default();
+
+        for register in 0x08..=0x0C {
+            registers.write(register, values.next().unwrap());
+        }
+
+        registers
+    }
+}
+
+impl SaveState for Registers {
+    fn save_state(&self, writer: &mut StateWriter) {
+        writer.write_u8(self.seconds);
+        writer.write_u8(self.minutes);
+        writer.write_u8(self.hours);
+        writer.write_u16(self.days);
+        writer.write_bool(self.halt);
+        writer.write_bool(self.day_carry);
+    }
+
+    fn load_state(&mut self, reader: &mut StateReader) -> Result<(), SaveStateError> {
+        self.seconds = reader.read_u8()?;
+        self.minutes = reader.read_u8()?;
+        self.hours = reader.read_u8()?;
+        self.days = reader.read_u16()? & 0x1FF;
+        self.halt = reader.read_bool()?;
+        self.day_carry = reader.read_bool()?;
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Rtc {
+    registers: Registers,
+    latched: Registers,
+
+    latch_armed: bool,
+    cycles: u32,
+}
+
+impl Rtc {
+    /// Advances the clock by the given amount of (single speed) T-cycles.
+    ///
+    /// Returns `true` if a second has passed.
+    pub fn tick(&mut self, cycles: u32) -> bool {
+        if self.registers.halt {
+            return false;
+        }
+
+        self.cycles += cycles;
+
+        if self.cycles < CYCLES_PER_SECOND {
+            return false;
+        }
+
+        self.cycles -= CYCLES_PER_SECOND;
+        self.registers.advance_second();
+
+        true
+    }
+
+    pub fn read(&self, register: u8) -> u8 {
+        self.latched.read(register)
+    }
+
+    pub fn write(&mut self, register: u8, value: u8) {
+        // Writing to the seconds register resets the sub-second counter.
+        if register == 0x08 {
+            self.cycles = 0;
+        }
+
+        self.registers.write(register, value);
+        self.latched.write(register, value);
+    }
+
+    /// Writing 0x00 and then 0x01 copies the clock into the latched registers.
+    pub fn latch(&mut self, value: u8) {
+        if self.latch_armed && value == 0x01 {
+            self.latched = self.registers;
+        }
+
+        self.latch_armed = value == 0x00;
+    }
+
+    pub fn write_footer(&self, footer: &mut [u8]) {
+        self.registers.write_footer(&mut footer[0x00..0x14]);
+        self.latched.write_footer(&mut footer[0x14..0x28]);
+
+        footer[0x28..0x30].copy_from_slice(&unix_timestamp().to_le_bytes());
+    }
+
+    /// Restores the clock from a footer, catching up with the time that passed since it was saved.
+    pub fn read_footer(&mut self, footer: &[u8]) -> bool {
+        let Some(timestamp) = self.restore_footer(footer) else {
+            return false;
+        };
+
+        if !self.registers.halt {
+            self.registers
+                .advance(unix_timestamp().saturating_sub(timestamp));
+        }
+
+        true
+    }
+
+    /// Restores the clock from a footer as it was saved, returning the timestamp.
+    pub fn restore_footer(&mut self, footer: &[u8]) -> Option<u64> {
+        let timestamp = match footer.len() {
+            RTC_FOOTER_SIZE => u64::from_le_bytes(footer[0x28..0x30].try_into().unwrap()),
+            RTC_FOOTER_SIZE_32_BIT_TIMESTAMP => {
+                u32::from_le_bytes(footer[0x28..0x2C].try_into().unwrap()) as u64
+            }
+
+            _ => return None,
+        };
+
+        self.registers = Registers::read_footer(&footer[0x00..0x14]);
+        self.latched = Registers::read_footer(&footer[0x14..0x28]);
+        self.cycles = 0;
+
+        Some(timestamp)
+    }
+}
+
+impl SaveState for Rtc {
+    fn save_state(&self, writer: &mut StateWriter) {
+        self.registers.save_state(writer);
+        self.latched.save_state(writer);
+        writer.write_bool(self.latch_armed);
+        writer.write_u32(self.cycles);
+    }
+
+    fn load_state(&mut self, reader: &mut StateReader) -> Result<(), SaveStateError> {
+        self.registers.load_state(reader)?;
+        self.latched.load_state(reader)?;
+        self.latch_armed = reader.read_bool()?;
+        self.cycles = reader.read_u32()?;
+
+        if self.cycles >= CYCLES_PER_SECOND {
+            return Err(SaveStateError::InvalidData("RTC cycles"));
+        }
+
+        Ok(())
+    }
+}
+
+fn unix_timestamp() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tick_seconds(rtc: &mut Rtc, seconds: u32) {
+        for _ in 0..seconds {
+            assert!(rtc.tick(CYCLES_PER_SECOND));
+        }
+    }
+
+    fn latch(rtc: &mut Rtc) {
+        rtc.latch(0x00);
+        rtc.latch(0x01);
+    }
+
+    #[test]
+    fn test_latch() {
+        let mut rtc = Rtc::default();
+
+        tick_seconds(&mut rtc, 5);
+        assert_eq!(rtc.read(0x08), 0);
+
+        // 0x01 alone does not latch.
+        rtc.latch(0x01);
+        assert_eq!(rtc.read(0x08), 0);
+
+        latch(&mut rtc);
+        assert_eq!(rtc.read(0x08), 5);
+
+        tick_seconds(&mut rtc, 1);
+        assert_eq!(rtc.read(0x08), 5);
+    }
+
+    #[test]
+    fn test_halt() {
+        let mut rtc = Rtc::default();
+
+        rtc.write(0x0C, 0b0100_0000);
+        assert!(!rtc.tick(CYCLES_PER_SECOND));
+
+        rtc.write(0x0C, 0);
+        tick_seconds(&mut rtc, 1);
+
+        latch(&mut rtc);
+        assert_eq!(rtc.read(0x08), 1);
+    }
+
+    #[test]
+    fn test_rollover() {
+        let mut rtc = Rtc::default();
+
+        rtc.write(0x08, 59);
+        rtc.write(0x09, 59);
+        rtc.write(0x0A, 23);
+        rtc.write(0x0B, 0xFF);
+        rtc.write(0x0C, 0b1);
+
+        tick_seconds(&mut rtc, 1);
+        latch(&mut rtc);
+
+        assert_eq!(rtc.read(0x08), 0);
+        assert_eq!(rtc.read(0x09), 0);
+        assert_eq!(rtc.read(0x0A), 0);
+        assert_eq!(rtc.read(0x0B), 0);
+        assert_eq!(rtc.read(0x0C), 0b1000_0000);
+    }
+
+    #[test]
+    fn test_out_of_range_values() {
+        let mut rtc = Rtc::default();
+
+        rtc.write(0x08, 63);
+        tick_seconds(&mut rtc, 1);
+        latch(&mut rtc);
+
+        assert_eq!(rtc.read(0x08), 0);
+        assert_eq!(rtc.read(0x09), 0);
+    }
+
+    #[test]
+    fn test_advance() {
+        let mut expected = Registers::default();
+        let mut registers = Registers {
+            seconds: 62,
+            ..Registers::default()
+        };
+        expected.seconds = 62;
+
+        let seconds = 3 * SECONDS_PER_DAY + 12345;
+
+        for _ in 0..seconds {
+            expected.advance_second();
+        }
+
+        registers.advance(seconds);
+
+        assert_eq!(registers, expected);
+    }
+
+    #[test]
+    fn test_footer() {
+        let mut rtc = Rtc::default();
+
+        rtc.write(0x09, 42);
+        rtc.write(0x0C, 0b0100_0000);
+
+        let mut footer = [0; RTC_FOOTER_SIZE];
+        rtc.write_footer(&mut footer);
+
+        let mut other = Rtc::default();
+        assert!(other.read_footer(&footer));
+
+        assert_eq!(other.registers, rtc.registers);
+        assert_eq!(other.latched, rtc.latched);
+
+        // Catch up from an old timestamp.
+        rtc.write(0x0C, 0);
+        rtc.write_footer(&mut footer);
+        footer[0x28..0x30].copy_from_slice(&(unix_timestamp() - 90).to_le_bytes());
+
+        assert!(other.read_footer(&footer));
+        assert_eq!(other.registers.minutes, 43);
+        assert!((30..=31).contains(&other.registers.seconds));
+
+        // Unless the time is restored as saved.
+        assert!(other.restore_footer(&footer).is_some());
+        assert_eq!(other.registers, rtc.registers);
+    }
+}
@@ -6,6 +6,7 @@ use super::MbcInterface;
 use crate::{
     components::cartridge::info::{Info, ram_banks::RAM_BANK_SIZE, rom_banks::ROM_BANK_SIZE},
     constants::ONE_KIB,
+    save_state::{SaveState, SaveStateError, StateReader, StateWriter},
 };
 
 pub struct Mbc5 {
@@ -53,6 +54,14 @@ impl MbcInterface for Mbc5 {
         &self.ram
     }
 
+    fn ram(&self) -> &[u8] {
+        &self.ram
+    }
+
+    fn ram_mut(&mut self) -> &mut [u8] {
+        &mut self.ram
+    }
+
     fn load_battery(&mut self, file: Vec<u8>) {
         if self.ram.is_empty() {
             error!("This cartridge does not have a battery backed RAM");
@@ -77,6 +86,10 @@ impl MbcInterface for Mbc5 {
         self.rom[address + offset]
     }
 
+    fn rom_bank_x(&self) -> usize {
+        self.rom_0x4000_0x7fff_offset() / ROM_BANK_SIZE
+    }
+
     fn read_ram(&self, address: u16) -> u8 {
         if !self.ram_enable {
             return 0xFF;
@@ -124,3 +137,21 @@ impl MbcInterface for Mbc5 {
         self.ram[address + offset] = value;
     }
 }
+
+impl SaveState for Mbc5 {
+    fn save_state(&self, writer: &mut StateWriter) {
+        writer.write_bytes(&self.ram);
+        writer.write_bool(self.ram_enable);
+        writer.write_u16(self.rom_bank);
+        writer.write_u8(self.ram_bank);
+    }
+
+    fn load_state(&mut self, reader: &mut StateReader) -> Result<(), SaveStateError> {
+        reader.read_bytes_into(&mut self.ram)?;
+        self.ram_enable = reader.read_bool()?;
+        self.rom_bank = reader.read_u16()?;
+        self.ram_bank = reader.read_u8()?;
+
+        Ok(())
+    }
+}
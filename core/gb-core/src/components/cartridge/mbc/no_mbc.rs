@@ -3,7 +3,11 @@ use std::sync::Arc;
 use tracing::error;
 
 use super::MbcInterface;
-use crate::{components::cartridge::info::Info, constants::ONE_KIB};
+use crate::{
+    components::cartridge::info::Info,
+    constants::ONE_KIB,
+    save_state::{SaveState, SaveStateError, StateReader, StateWriter},
+};
 
 pub struct NoMbc {
     rom: Arc<[u8]>,
@@ -26,6 +30,14 @@ impl MbcInterface for NoMbc {
         &self.ram
     }
 
+    fn ram(&self) -> &[u8] {
+        &self.ram
+    }
+
+    fn ram_mut(&mut self) -> &mut [u8] {
+        &mut self.ram
+    }
+
     fn load_battery(&mut self, file: Vec<u8>) {
         if self.ram.is_empty() {
             error!("This cartridge does not have a battery backed RAM");
@@ -48,6 +60,10 @@ impl MbcInterface for NoMbc {
         self.rom[address]
     }
 
+    fn rom_bank_x(&self) -> usize {
+        1
+    }
+
     fn read_ram(&self, address: u16) -> u8 {
         if self.ram.is_empty() {
             return 0xFF;
@@ -70,3 +86,13 @@ impl MbcInterface for NoMbc {
         self.ram[address] = value;
     }
 }
+
+impl SaveState for NoMbc {
+    fn save_state(&self, writer: &mut StateWriter) {
+        writer.write_bytes(&self.ram);
+    }
+
+    fn load_state(&mut self, reader: &mut StateReader) -> Result<(), SaveStateError> {
+        reader.read_bytes_into(&mut self.ram)
+    }
+}
@@ -1,9 +1,14 @@
 use bitflags::Flags;
+use tracing::error;
 
-use self::registers::{ImeState, Registers};
+use self::{
+    registers::{ImeState, Registers},
+    tracer::Tracer,
+};
 use crate::{
     DeviceModel,
     components::memory::MemoryInterface,
+    save_state::{SaveState, SaveStateError, StateReader, StateWriter},
     utils::{events::Events, macros::device_is_cgb},
 };
 
@@ -12,6 +17,17 @@ pub struct Cpu {
     registers: Registers,
     halt: bool,
 
+    /// Vector of the interrupt dispatched in the last step (not part of save states).
+    dispatched_interrupt: Option<u16>,
+
+    tracer: Option<Tracer>,
+
+    /// Lets the time pass at once while halted or stuck in a loop that waits for an event.
+    idle_skipping: bool,
+    /// Registers after the last backward jump, where an iteration of a loop starts
+    /// (not part of save states).
+    loop_head: Option<Registers>,
+
     device_model: DeviceModel,
 }
 
@@ -19,6 +35,7 @@ impl Cpu {
     #[must_use]
     pub fn with_device_model(device_model: DeviceModel) -> Self {
         Self {
+            idle_skipping: true,
             device_model,
             ..Default::default()
         }
@@ -29,6 +46,31 @@ impl Cpu {
         &self.registers
     }
 
+    pub(crate) fn halted(&self) -> bool {
+        self.halt
+    }
+
+    pub(crate) fn dispatched_interrupt(&self) -> Option<u16> {
+        self.dispatched_interrupt
+    }
+
+    pub(crate) fn set_tracer(&mut self, tracer: Tracer) {
+        self.tracer = Some(tracer);
+    }
+
+    pub(crate) fn take_tracer(&mut self) -> Option<Tracer> {
+        self.tracer.take()
+    }
+
+    pub(crate) fn idle_skipping(&self) -> bool {
+        self.idle_skipping
+    }
+
+    pub(crate) fn set_idle_skipping(&mut self, value: bool) {
+        self.idle_skipping = value;
+        self.loop_head = None;
+    }
+
     pub(crate) fn skip_bootrom(&mut self) {
         self.registers.pc = 0x0100;
         self.registers.sp = 0xFFFE;
@@ -50,6 +92,7 @@ impl Cpu {
         // Cache the value in case there's a change
         // TODO: can we do this better?
         let interrupts_are_enabled = self.registers.ime.is_enabled();
+        self.dispatched_interrupt = None;
 
         if !interrupts_are_enabled {
             // Takes effect in the next step
@@ -71,18 +114,76 @@ impl Cpu {
             return;
         }
 
+        if self.tracer.is_some() {
+            // The trace may include I/O registers, such as LY.
+            memory.sync();
+        }
+
+        if let Some(tracer) = &mut self.tracer
+            && let Err(err) = tracer.trace(&self.registers, memory)
+        {
+            error!("Unable to write the trace, disabling it: {err}");
+            self.tracer = None;
+        }
+
         let opcode = self.read_byte_operand(memory);
         self.run_instruction(memory, opcode);
     }
 
     pub(crate) fn run_frame(&mut self, memory: &mut impl MemoryInterface) {
+        // The frontend might have changed the memory between frames.
+        self.loop_head = None;
+
         while !memory.events().contains(Events::VBLANK) {
+            if self.idle_skipping {
+                self.skip_halt(memory);
+            }
+
+            let pc = self.registers.pc;
             self.step(memory);
+
+            // Skipped instructions would be missing from the trace.
+            if self.idle_skipping && self.tracer.is_none() {
+                self.skip_idle_loop(memory, pc);
+            }
         }
 
         memory.events_mut().clear();
     }
 
+    /// Skips the halted steps that would only cycle the memory until the next event.
+    fn skip_halt(&self, memory: &mut impl MemoryInterface) {
+        if self.halt
+            && self.registers.ime != ImeState::Pending
+            && !memory.interrupts().has_queued_irq()
+        {
+            memory.skip_m_cycles(memory.idle_m_cycles());
+        }
+    }
+
+    /// Skips the iterations of a loop that keeps doing the same until an event happens,
+    /// such as polling LY or waiting for an interrupt handler to set a flag.
+    ///
+    /// Taking a backward jump ends an iteration. If the registers are back to how they were
+    /// when the previous one ended, and the memory didn't see anything that could change
+    /// the outcome of the next one, every iteration until the next event is the same.
+    fn skip_idle_loop(&mut self, memory: &mut impl MemoryInterface, pc: u16) {
+        if self.registers.pc >= pc || self.dispatched_interrupt.is_some() {
+            return;
+        }
+
+        if self.loop_head.as_ref() == Some(&self.registers)
+            && let Some(length) = memory.idle_iteration()
+            && length > 0
+        {
+            let iterations = memory.idle_m_cycles() / length;
+            memory.skip_m_cycles(iterations * length);
+        }
+
+        self.loop_head = Some(self.registers.clone());
+        memory.watch_iteration();
+    }
+
     fn handle_interrupts(&mut self, memory: &mut impl MemoryInterface) {
         self.halt = false;
         self.registers.ime = ImeState::Disabled;
@@ -108,6 +209,7 @@ impl Cpu {
         };
 
         self.registers.pc = address;
+        self.dispatched_interrupt = Some(address);
     }
 
     fn cycle_memory(&self, memory: &mut impl MemoryInterface) {
@@ -186,9 +288,24 @@ impl Cpu {
     }
 }
 
+impl SaveState for Cpu {
+    fn save_state(&self, writer: &mut StateWriter) {
+        self.registers.save_state(writer);
+        writer.write_bool(self.halt);
+    }
+
+    fn load_state(&mut self, reader: &mut StateReader) -> Result<(), SaveStateError> {
+        self.registers.load_state(reader)?;
+        self.halt = reader.read_bool()?;
+
+        Ok(())
+    }
+}
+
 mod alu;
 mod instructions;
-mod registers;
+pub mod registers;
+pub mod tracer;
 
 #[cfg(test)]
 mod tests;
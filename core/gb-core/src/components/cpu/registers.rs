@@ -1,6 +1,7 @@
 pub use self::{flags::Flags, ime_state::ImeState};
+use crate::save_state::{SaveState, SaveStateError, StateReader, StateWriter};
 
-#[derive(Default)]
+#[derive(Default, Clone, PartialEq, Eq)]
 pub struct Registers {
     pub a: u8,
     pub f: Flags,
@@ -19,6 +20,7 @@ pub struct Registers {
 }
 
 impl Registers {
+    #[must_use]
     pub fn get_af(&self) -> u16 {
         let high = self.a as u16;
         let low = self.f.bits() as u16;
@@ -26,6 +28,7 @@ impl Registers {
         (high << 8) | low
     }
 
+    #[must_use]
     pub fn get_bc(&self) -> u16 {
         let high = self.b as u16;
         let low = self.c as u16;
@@ -33,6 +36,7 @@ impl Registers {
         (high << 8) | low
     }
 
+    #[must_use]
     pub fn get_de(&self) -> u16 {
         let high = self.d as u16;
         let low = self.e as u16;
@@ -40,6 +44,7 @@ impl Registers {
         (high << 8) | low
     }
 
+    #[must_use]
     pub fn get_hl(&self) -> u16 {
         let high = self.h as u16;
         let low = self.l as u16;
@@ -161,5 +166,42 @@ impl std::fmt::Display for Registers {
     }
 }
 
+impl SaveState for Registers {
+    fn save_state(&self, writer: &mut StateWriter) {
+        writer.write_u16(self.get_af());
+        writer.write_u16(self.get_bc());
+        writer.write_u16(self.get_de());
+        writer.write_u16(self.get_hl());
+
+        writer.write_u16(self.pc);
+        writer.write_u16(self.sp);
+
+        writer.write_u8(match self.ime {
+            ImeState::Disabled => 0,
+            ImeState::Enabled => 1,
+            ImeState::Pending => 2,
+        });
+    }
+
+    fn load_state(&mut self, reader: &mut StateReader) -> Result<(), SaveStateError> {
+        self.set_af(reader.read_u16()?);
+        self.set_bc(reader.read_u16()?);
+        self.set_de(reader.read_u16()?);
+        self.set_hl(reader.read_u16()?);
+
+        self.pc = reader.read_u16()?;
+        self.sp = reader.read_u16()?;
+
+        self.ime = match reader.read_u8()? {
+            0 => ImeState::Disabled,
+            1 => ImeState::Enabled,
+            2 => ImeState::Pending,
+            _ => return Err(SaveStateError::InvalidData("IME state")),
+        };
+
+        Ok(())
+    }
+}
+
 mod flags;
 mod ime_state;
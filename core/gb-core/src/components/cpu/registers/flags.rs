@@ -1,7 +1,7 @@
 use bitflags::bitflags;
 
 bitflags! {
-    #[derive(Default)]
+    #[derive(Default, Clone, Copy, PartialEq, Eq)]
     pub struct Flags: u8 {
         const ZERO = 1 << 7;
         const N_ADD_SUB = 1 << 6;
@@ -13,18 +13,22 @@ bitflags! {
 }
 
 impl Flags {
+    #[must_use]
     pub fn zero(&self) -> bool {
         self.contains(Self::ZERO)
     }
 
+    #[must_use]
     pub fn n_add_sub(&self) -> bool {
         self.contains(Self::N_ADD_SUB)
     }
 
+    #[must_use]
     pub fn half_carry(&self) -> bool {
         self.contains(Self::HALF_CARRY)
     }
 
+    #[must_use]
     pub fn carry(&self) -> bool {
         self.contains(Self::CARRY)
     }
@@ -7,6 +7,7 @@ pub enum ImeState {
 }
 
 impl ImeState {
+    #[must_use]
     pub fn is_enabled(self) -> bool {
         match self {
             Self::Disabled | Self::Pending => false,
@@ -0,0 +1,51 @@
+use std::sync::Arc;
+
+use crate::{
+    GameBoy,
+    components::memory::MemoryInterface as _,
+    constants::DeviceModel,
+    test_rom::TestRom,
+};
+
+/// Counts the frames in 0xC000 from the V-Blank handler, alternating between
+/// waiting in HALT and polling LY and a flag set by the handler.
+fn idle_rom() -> Arc<[u8]> {
+    TestRom::new()
+        // LD HL, 0xC000; INC (HL); LD A, 0x01; LDH (0x90), A; RETI
+        .code(
+            0x0040,
+            &[0x21, 0x00, 0xC0, 0x34, 0x3E, 0x01, 0xE0, 0x90, 0xD9],
+        )
+        // LD A, 0x01; LDH (0xFF), A; EI
+        // loop: HALT; NOP
+        //       LDH A, (0x44); CP 0x42; JR NZ, -6
+        //       XOR A; LDH (0x90), A
+        //       LDH A, (0x90); AND A; JR Z, -5
+        //       JR loop
+        .program(&[
+            0x3E, 0x01, 0xE0, 0xFF, 0xFB, 0x76, 0x00, 0xF0, 0x44, 0xFE, 0x42, 0x20, 0xFA, 0xAF,
+            0xE0, 0x90, 0xF0, 0x90, 0xA7, 0x28, 0xFB, 0x18, 0xEE,
+        ])
+        .build()
+}
+
+#[test]
+fn test_idle_skipping_keeps_results() {
+    for device_model in [DeviceModel::Dmg, DeviceModel::Cgb] {
+        let mut skipping = GameBoy::new(device_model);
+        let mut exact = GameBoy::new(device_model);
+
+        skipping.load(None, idle_rom()).unwrap();
+        exact.load(None, idle_rom()).unwrap();
+        exact.set_idle_skipping(false);
+
+        for _ in 0..30 {
+            skipping.run_frame();
+            exact.run_frame();
+
+            assert_eq!(skipping.save_state(), exact.save_state());
+        }
+
+        assert_eq!(skipping.memory().read(0xC000), 30);
+    }
+}
@@ -1 +1,2 @@
+mod idle_skipping;
 mod single_step_tests;
@@ -0,0 +1,198 @@
+use std::{fmt::Write as _, io};
+
+use super::registers::Registers;
+use crate::components::memory::MemoryInterface;
+
+const LY_ADDRESS: u16 = 0xFF44;
+
+pub type Callback = dyn FnMut(&str) + Send + Sync;
+
+/// Extra fields appended to the Gameboy Doctor line.
+///
+/// The default format is exactly the one expected by Gameboy Doctor.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct TraceFormat {
+    /// T-cycles elapsed since the system was turned on (`CY:`).
+    pub cycles: bool,
+    /// Current scanline (`LY:`).
+    pub ly: bool,
+    /// ROM bank mapped at PC (`BANK:`), `--` outside of the ROM.
+    pub rom_bank: bool,
+}
+
+enum Output {
+    Writer(Box<dyn io::Write + Send + Sync>),
+    Callback(Box<Callback>),
+}
+
+/// Emits one line per executed instruction, before it runs.
+pub struct Tracer {
+    format: TraceFormat,
+    output: Output,
+    line: String,
+}
+
+impl Tracer {
+    #[must_use]
+    pub fn with_writer(writer: Box<dyn io::Write + Send + Sync>) -> Self {
+        Self::with_output(Output::Writer(writer))
+    }
+
+    #[must_use]
+    pub fn with_callback(callback: Box<Callback>) -> Self {
+        Self::with_output(Output::Callback(callback))
+    }
+
+    fn with_output(output: Output) -> Self {
+        Self {
+            format: TraceFormat::default(),
+            output,
+            line: String::new(),
+        }
+    }
+
+    #[must_use]
+    pub fn format(mut self, format: TraceFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        match &mut self.output {
+            Output::Writer(writer) => writer.flush(),
+            Output::Callback(_) => Ok(()),
+        }
+    }
+
+    pub(crate) fn trace(
+        &mut self,
+        registers: &Registers,
+        memory: &impl MemoryInterface,
+    ) -> io::Result<()> {
+        self.line.clear();
+        self.format_line(registers, memory);
+
+        match &mut self.output {
+            Output::Writer(writer) => {
+                self.line.push('\n');
+                writer.write_all(self.line.as_bytes())
+            }
+            Output::Callback(callback) => {
+                callback(&self.line);
+                Ok(())
+            }
+        }
+    }
+
+    fn format_line(&mut self, registers: &Registers, memory: &impl MemoryInterface) {
+        let line = &mut self.line;
+        let pc = registers.pc;
+        let pc_mem = |offset| memory.read(pc.wrapping_add(offset));
+
+        // Writing to a `String` never fails.
+        let _ = write!(
+            line,
+            "A:{:02X} F:{:02X} B:{:02X} C:{:02X} D:{:02X} E:{:02X} H:{:02X} L:{:02X} \
+             SP:{:04X} PC:{:04X} PCMEM:{:02X},{:02X},{:02X},{:02X}",
+            registers.a,
+            registers.f.bits(),
+            registers.b,
+            registers.c,
+            registers.d,
+            registers.e,
+            registers.h,
+            registers.l,
+            registers.sp,
+            pc,
+            pc_mem(0),
+            pc_mem(1),
+            pc_mem(2),
+            pc_mem(3),
+        );
+
+        if self.format.cycles {
+            let _ = write!(line, " CY:{}", memory.elapsed_cycles());
+        }
+
+        if self.format.ly {
+            let _ = write!(line, " LY:{:02X}", memory.read(LY_ADDRESS));
+        }
+
+        if self.format.rom_bank {
+            match memory.rom_bank(pc) {
+                Some(bank) => {
+                    let _ = write!(line, " BANK:{bank:02X}");
+                }
+                None => line.push_str(" BANK:--"),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+    use crate::{DeviceModel, GameBoy, test_rom::TestRom};
+
+    fn rom() -> Arc<[u8]> {
+        // NOP; LD A,$42; JR -2
+        TestRom::new()
+            .code(0x0100, &[0x00, 0x3E, 0x42, 0x18, 0xFE])
+            .build()
+    }
+
+    fn collect_lines(gb: &mut GameBoy, format: TraceFormat, steps: usize) -> Vec<String> {
+        let lines = Arc::new(Mutex::new(Vec::new()));
+        let sink = lines.clone();
+
+        let tracer = Tracer::with_callback(Box::new(move |line| {
+            sink.lock().unwrap().push(line.to_owned());
+        }));
+
+        gb.set_tracer(tracer.format(format));
+
+        for _ in 0..steps {
+            gb.step();
+        }
+
+        drop(gb.take_tracer());
+
+        Arc::try_unwrap(lines).unwrap().into_inner().unwrap()
+    }
+
+    #[test]
+    fn test_gameboy_doctor_format() {
+        let mut gb = GameBoy::new(DeviceModel::Dmg);
+        gb.load(None, rom()).unwrap();
+
+        let lines = collect_lines(&mut gb, TraceFormat::default(), 3);
+
+        assert_eq!(
+            lines,
+            [
+                "A:01 F:B0 B:00 C:13 D:00 E:D8 H:01 L:4D SP:FFFE PC:0100 PCMEM:00,3E,42,18",
+                "A:01 F:B0 B:00 C:13 D:00 E:D8 H:01 L:4D SP:FFFE PC:0101 PCMEM:3E,42,18,FE",
+                "A:42 F:B0 B:00 C:13 D:00 E:D8 H:01 L:4D SP:FFFE PC:0103 PCMEM:18,FE,00,00",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_extended_format() {
+        let mut gb = GameBoy::new(DeviceModel::Dmg);
+        gb.load(None, rom()).unwrap();
+
+        let format = TraceFormat {
+            cycles: true,
+            ly: true,
+            rom_bank: true,
+        };
+
+        let lines = collect_lines(&mut gb, format, 2);
+
+        assert!(lines[0].ends_with("PCMEM:00,3E,42,18 CY:0 LY:00 BANK:00"));
+        assert!(lines[1].ends_with("PCMEM:3E,42,18,FE CY:4 LY:00 BANK:00"));
+    }
+}
@@ -1,5 +1,8 @@
 use self::line_selection::{JOYP_SELECTION_MASK, LineSelection};
-use crate::utils::button::Button;
+use crate::{
+    save_state::{SaveState, SaveStateError, StateReader, StateWriter},
+    utils::button::Button,
+};
 
 const JOYP_UNUSED_MASK: u8 = 0b1100_0000;
 const JOYP_BUTTONS_MASK: u8 = 0b0000_1111;
@@ -57,6 +60,17 @@ impl Joypad {
         self.update_joyp();
     }
 
+    /// Pressed buttons, one bit per [`Button`].
+    pub(crate) fn buttons(&self) -> u8 {
+        self.buttons
+    }
+
+    pub(crate) fn set_buttons(&mut self, buttons: u8) {
+        for button in Button::ALL_CASES {
+            self.set_joypad_button(button, buttons & (button as u8) != 0);
+        }
+    }
+
     fn update_joyp(&mut self) {
         let buttons_bits = match LineSelection::from_joyp_bits(self.joyp) {
             LineSelection::Both => (self.buttons | (self.buttons >> 4)) & 0b1111,
@@ -70,6 +84,22 @@ impl Joypad {
     }
 }
 
+impl SaveState for Joypad {
+    fn save_state(&self, writer: &mut StateWriter) {
+        writer.write_u8(self.joyp);
+        writer.write_u8(self.buttons);
+        writer.write_bool(self.irq);
+    }
+
+    fn load_state(&mut self, reader: &mut StateReader) -> Result<(), SaveStateError> {
+        self.joyp = reader.read_u8()?;
+        self.buttons = reader.read_u8()?;
+        self.irq = reader.read_bool()?;
+
+        Ok(())
+    }
+}
+
 mod line_selection;
 
 #[cfg(test)]
@@ -14,19 +14,35 @@ use super::cartridge::{Cartridge, error::CartridgeError};
 use crate::{
     DeviceModel,
     components::{
-        apu::Apu,
+        apu::{Apu, vgm::VgmRecorder},
         joypad::Joypad,
         memory::key0::Key0,
         ppu::Ppu,
         serial::Serial,
         timer::Timer,
     },
+    save_state::{SaveState, SaveStateError, StateReader, StateWriter},
     utils::{
         events::Events,
         macros::{device_is_cgb, in_cgb_mode},
+        scheduler::{Event, Scheduler},
     },
 };
 
+/// Roughly a millisecond, so that the audio callback keeps receiving samples regularly.
+const APU_SYNC_INTERVAL: u64 = 4096;
+
+/// What happened since the CPU started watching an iteration of a loop.
+#[derive(Debug, Default)]
+struct LoopWatch {
+    /// When the iteration started, in M-cycles.
+    start: u64,
+    /// M-cycles that could pass before any component reached its deadline when it started.
+    idle_m_cycles: u64,
+    /// Whether the iteration did anything that could change what the next one sees.
+    volatile: bool,
+}
+
 #[derive(Debug, Error)]
 pub enum Error {
     #[error("Unable to load the bootrom: {0}.")]
@@ -36,6 +52,27 @@ pub enum Error {
     CartridgeError(#[from] CartridgeError),
 }
 
+/// Returned by [`Memory::poke`] for writes to the cartridge when none is inserted.
+#[derive(Debug, Error)]
+#[error("No cartridge inserted.")]
+pub struct NoCartridge;
+
+/// Memory that frontends can access in bulk, e.g. for libretro memory maps or cheat tools.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MemoryRegion {
+    /// 0xC000 ~ 0xDFFF, every bank (8 KiB on DMG, 32 KiB on CGB).
+    WorkRam,
+    /// 0xFF80 ~ 0xFFFE.
+    HighRam,
+    /// 0x8000 ~ 0x9FFF, every bank (8 KiB on DMG, 16 KiB on CGB).
+    VideoRam,
+    /// 0xA000 ~ 0xBFFF, every bank.
+    CartridgeRam,
+    /// RTC of MBC3 cartridges with a timer, in the footer format used by the battery files.
+    /// Read-only, loading a battery is the only way to change it.
+    Rtc,
+}
+
 pub trait MemoryInterface {
     fn cycle(&mut self);
 
@@ -58,11 +95,50 @@ pub trait MemoryInterface {
 
     fn interrupts(&self) -> &Interrupts;
     fn interrupts_mut(&mut self) -> &mut Interrupts;
+
+    /// Catches up the components that are only advanced when needed,
+    /// so that their state can be inspected from the outside.
+    fn sync(&mut self) {}
+
+    /// T-cycles elapsed since the system was turned on, counted at single speed.
+    fn elapsed_cycles(&self) -> u64 {
+        0
+    }
+
+    /// ROM bank mapped at `address`, if any.
+    fn rom_bank(&self, _address: u16) -> Option<usize> {
+        None
+    }
+
+    /// M-cycles that can pass without anything but the clocks changing.
+    fn idle_m_cycles(&self) -> u64 {
+        0
+    }
+
+    /// Lets `m_cycles` M-cycles pass at once, up to [`MemoryInterface::idle_m_cycles`].
+    fn skip_m_cycles(&mut self, _m_cycles: u64) {}
+
+    /// Starts watching an iteration of a loop, see [`MemoryInterface::idle_iteration`].
+    fn watch_iteration(&mut self) {}
+
+    /// Length in M-cycles of the iteration being watched, if the next ones would see the same:
+    /// nothing was written, the reads were from memory that doesn't change by itself,
+    /// and no component reached its deadline.
+    fn idle_iteration(&self) -> Option<u64> {
+        None
+    }
 }
 
 pub struct Memory {
     events: Events,
 
+    /// Timing of the components that are caught up lazily (not part of save states).
+    scheduler: Scheduler,
+    /// Idle loop detection (not part of save states).
+    loop_watch: LoopWatch,
+    /// Logs the writes to the APU (not part of save states).
+    vgm_recorder: Option<VgmRecorder>,
+
     bootrom: Bootrom,
 
     wram: WorkRam,
@@ -206,6 +282,9 @@ impl MemoryInterface for Memory {
     }
 
     fn write(&mut self, address: u16, value: u8) {
+        self.sync_for(address);
+        self.loop_watch.volatile = true;
+
         match address {
             0x0000..=0x00FF if self.bootrom.mapped() => (),
             0x0200..=0x08FF if self.bootrom.mapped() && device_is_cgb!(self) => (),
@@ -241,10 +320,10 @@ impl MemoryInterface for Memory {
 
             0xFF0F => self.interrupts.write_flags(value),
 
-            0xFF10..=0xFF14 => self.apu.write(address, value),
-            0xFF16..=0xFF1E => self.apu.write(address, value),
-            0xFF20..=0xFF26 => self.apu.write(address, value),
-            0xFF30..=0xFF3F => self.apu.write(address, value),
+            0xFF10..=0xFF14 => self.write_apu(address, value),
+            0xFF16..=0xFF1E => self.write_apu(address, value),
+            0xFF20..=0xFF26 => self.write_apu(address, value),
+            0xFF30..=0xFF3F => self.write_apu(address, value),
 
             0xFF40 => self.ppu.write_lcdc(value),
             0xFF41 => self.ppu.write_stat(value),
@@ -313,9 +392,14 @@ impl MemoryInterface for Memory {
             | 0xFF71
             | 0xFF78..=0xFF7F => (),
         }
+
+        self.reschedule_for(address);
     }
 
     fn read_cycle(&mut self, address: u16) -> u8 {
+        self.sync_for(address);
+        self.loop_watch.volatile |= !Self::is_stable(address);
+
         // Reading/writing before cycling fixes `timer/rapid_toggle`
         let value = self.read(address);
         self.cycle();
@@ -337,6 +421,10 @@ impl MemoryInterface for Memory {
     }
 
     fn process_speed_switch(&mut self) {
+        // Both the PPU and the APU change clocks.
+        self.sync();
+        self.loop_watch.volatile = true;
+
         self.key1.process();
         self.apu.set_double_speed(self.key1.double_speed());
     }
@@ -364,6 +452,59 @@ impl MemoryInterface for Memory {
     fn interrupts_mut(&mut self) -> &mut Interrupts {
         &mut self.interrupts
     }
+
+    fn sync(&mut self) {
+        self.sync_timer();
+        self.sync_ppu();
+        self.sync_apu();
+    }
+
+    fn elapsed_cycles(&self) -> u64 {
+        self.scheduler.dots()
+    }
+
+    fn rom_bank(&self, address: u16) -> Option<usize> {
+        self.rom_bank(address)
+    }
+
+    fn idle_m_cycles(&self) -> u64 {
+        let dma_idle = self.ppu.oam_dma.is_idle() && self.ppu.vram_dma.is_waiting();
+
+        // A button press is only turned into an interrupt in the next M-cycle.
+        if !dma_idle || !self.serial.is_idle() || self.joypad.irq {
+            return 0;
+        }
+
+        // The APU deadline keeps this short enough for the RTC, which counts one second at a time.
+        self.scheduler.idle_m_cycles(self.key1.double_speed())
+    }
+
+    fn skip_m_cycles(&mut self, m_cycles: u64) {
+        debug_assert!(m_cycles <= self.idle_m_cycles());
+
+        let double_speed = self.key1.double_speed();
+        self.scheduler.advance_by(m_cycles, double_speed);
+
+        let cycles = if double_speed { 2 } else { 4 };
+
+        if let Some(cartridge) = self.cartridge.as_mut() {
+            cartridge.tick(m_cycles as u32 * cycles);
+        }
+    }
+
+    fn watch_iteration(&mut self) {
+        self.loop_watch = LoopWatch {
+            start: self.scheduler.m_cycles(),
+            idle_m_cycles: self.idle_m_cycles(),
+            volatile: false,
+        };
+    }
+
+    fn idle_iteration(&self) -> Option<u64> {
+        let length = self.scheduler.m_cycles() - self.loop_watch.start;
+
+        (!self.loop_watch.volatile && length <= self.loop_watch.idle_m_cycles).then_some(length)
+    }
 }
 
 impl Memory {
@@ -371,6 +512,9 @@ impl Memory {
     pub fn with_device_model(device_model: DeviceModel) -> Self {
         Self {
             events: Events::default(),
+            scheduler: Scheduler::default(),
+            loop_watch: LoopWatch::default(),
+            vgm_recorder: None,
             bootrom: Bootrom::default(),
             wram: WorkRam::with_device_model(device_model),
             hram: HighRam::default(),
@@ -389,6 +533,117 @@ impl Memory {
         }
     }
 
+    /// Starts logging the writes to the APU, from its current state.
+    pub(crate) fn start_vgm_recording(&mut self) {
+        self.sync_apu();
+        self.vgm_recorder = Some(VgmRecorder::new(self.scheduler.dots(), &self.apu));
+    }
+
+    pub(crate) fn mark_vgm_loop(&mut self) {
+        if let Some(recorder) = &mut self.vgm_recorder {
+            recorder.mark_loop(self.scheduler.dots());
+        }
+    }
+
+    pub(crate) fn stop_vgm_recording(&mut self) -> Option<Vec<u8>> {
+        let recorder = self.vgm_recorder.take()?;
+
+        Some(recorder.finish(self.scheduler.dots()))
+    }
+
+    pub(crate) fn is_recording_vgm(&self) -> bool {
+        self.vgm_recorder.is_some()
+    }
+
+    pub(crate) fn take_vgm_recorder(&mut self) -> Option<VgmRecorder> {
+        self.vgm_recorder.take()
+    }
+
+    /// Continues a recording from another memory, e.g. after loading a state.
+    pub(crate) fn continue_vgm_recording(&mut self, mut recorder: VgmRecorder) {
+        self.sync_apu();
+        recorder.resync(self.scheduler.dots(), &self.apu);
+
+        self.vgm_recorder = Some(recorder);
+    }
+
+    /// Reads `address` like the CPU, for tools and debuggers.
+    ///
+    /// Unlike [`MemoryInterface::read`], it never panics: the prohibited area
+    /// and the cartridge RAM without a cartridge read as an open bus (0xFF).
+    #[must_use]
+    pub fn peek(&self, address: u16) -> u8 {
+        match address {
+            0xFEA0..=0xFEFF => 0xFF,
+            0xA000..=0xBFFF if self.cartridge.is_none() => 0xFF,
+            _ => self.read(address),
+        }
+    }
+
+    /// Writes `value` to `address` like the CPU, for tools and debuggers,
+    /// so writes to the ROM area still switch banks.
+    ///
+    /// # Errors
+    ///
+    /// Fails instead of panicking when the write targets the cartridge and none is inserted.
+    pub fn poke(&mut self, address: u16, value: u8) -> Result<(), NoCartridge> {
+        let cartridge_space = matches!(address, 0x0000..=0x7FFF | 0xA000..=0xBFFF);
+
+        if cartridge_space && self.cartridge.is_none() {
+            return Err(NoCartridge);
+        }
+
+        self.write(address, value);
+
+        Ok(())
+    }
+
+    /// The cartridge ROM bank mapped to `address`, if any.
+    #[must_use]
+    pub fn rom_bank(&self, address: u16) -> Option<usize> {
+        let in_bootrom = self.bootrom.mapped()
+            && (address <= 0x00FF || (0x0200..=0x08FF).contains(&address) && device_is_cgb!(self));
+
+        if address >= 0x8000 || in_bootrom {
+            return None;
+        }
+
+        self.cartridge
+            .as_ref()
+            .map(|cartridge| cartridge.rom_bank(address))
+    }
+
+    /// `None` if the region is not available, e.g. a cartridge without RAM.
+    #[must_use]
+    pub fn region(&self, region: MemoryRegion) -> Option<&[u8]> {
+        let data = match region {
+            MemoryRegion::WorkRam => self.wram.data(),
+            MemoryRegion::HighRam => self.hram.data(),
+            MemoryRegion::VideoRam => self.ppu.vram.data(),
+            MemoryRegion::CartridgeRam => self.cartridge.as_ref()?.ram(),
+            MemoryRegion::Rtc => {
+                let cartridge = self.cartridge.as_ref()?;
+                &cartridge.get_battery()[cartridge.ram().len()..]
+            }
+        };
+
+        (!data.is_empty()).then_some(data)
+    }
+
+    /// Same as [`Memory::region`], but the RTC can't be written to.
+    #[must_use]
+    pub fn region_mut(&mut self, region: MemoryRegion) -> Option<&mut [u8]> {
+        let data = match region {
+            MemoryRegion::WorkRam => self.wram.data_mut(),
+            MemoryRegion::HighRam => self.hram.data_mut(),
+            MemoryRegion::VideoRam => self.ppu.vram.data_mut(),
+            MemoryRegion::CartridgeRam => self.cartridge.as_mut()?.ram_mut(),
+            MemoryRegion::Rtc => return None,
+        };
+
+        (!data.is_empty()).then_some(data)
+    }
+
     pub fn set_cgb_mode(&mut self, value: bool) {
         if value == self.cgb_mode {
             return;
@@ -427,18 +682,120 @@ impl Memory {
         self.perform_oam_dma();
         self.perform_vram_dma();
 
-        for i in 0..4 {
-            self.timer.tick();
+        let double_speed = self.key1.double_speed();
+        self.scheduler.advance(double_speed);
 
-            if !self.key1.double_speed() || i & 0b1 == 0 {
-                self.apu.tick(self.timer.read_div());
-                self.ppu.tick(&mut self.events);
-            }
+        if self.scheduler.pending() {
+            self.run_due_events();
+        }
+
+        let cycles = if double_speed { 2 } else { 4 };
+
+        self.serial.tick(cycles);
+
+        if let Some(cartridge) = self.cartridge.as_mut() {
+            cartridge.tick(cycles);
         }
 
         self.check_interrupts();
     }
 
+    fn run_due_events(&mut self) {
+        if self.scheduler.is_due(Event::Timer) {
+            self.sync_timer();
+        }
+
+        if self.scheduler.is_due(Event::Ppu) {
+            self.sync_ppu();
+        }
+
+        if self.scheduler.is_due(Event::Apu) {
+            self.sync_apu();
+        }
+    }
+
+    fn sync_timer(&mut self) {
+        let cycles = self.scheduler.take_elapsed(Event::Timer);
+        self.timer.advance(cycles);
+
+        self.scheduler
+            .schedule(Event::Timer, self.timer.cycles_until_event());
+    }
+
+    fn sync_ppu(&mut self) {
+        let dots = self.scheduler.take_elapsed(Event::Ppu);
+        self.ppu.advance(dots, &mut self.events);
+
+        self.scheduler
+            .schedule(Event::Ppu, self.ppu.dots_until_event());
+    }
+
+    /// The APU follows the DIV, so the timer is caught up as well.
+    fn sync_apu(&mut self) {
+        self.sync_timer();
+
+        let ticks = self.scheduler.take_elapsed(Event::Apu);
+        let counter = self.timer.system_counter();
+
+        // In double speed mode, the APU is ticked every other timer tick,
+        // and the last one was before the last timer tick.
+        if self.key1.double_speed() {
+            self.apu.advance(ticks, counter.wrapping_sub(1), 2);
+        } else {
+            self.apu.advance(ticks, counter, 1);
+        }
+
+        self.scheduler.schedule(Event::Apu, APU_SYNC_INTERVAL);
+    }
+
+    /// Whether `address` only changes through writes, DMA transfers and the events of the scheduler.
+    ///
+    /// The cartridge RAM is excluded because of the mappers with sensors or clocks.
+    fn is_stable(address: u16) -> bool {
+        matches!(
+            address,
+            0x0000..=0x9FFF
+                | 0xC000..=0xFE9F
+                | 0xFF0F
+                | 0xFF40..=0xFF4B
+                | 0xFF4F
+                | 0xFF70
+                | 0xFF80..=0xFFFF
+        )
+    }
+
+    fn write_apu(&mut self, address: u16, value: u8) {
+        self.apu.write(address, value);
+
+        if let Some(recorder) = &mut self.vgm_recorder {
+            recorder.write(self.scheduler.dots(), address, value);
+        }
+    }
+
+    /// Catches up the components that can be observed or changed through `address`.
+    fn sync_for(&mut self, address: u16) {
+        match address {
+            0x8000..=0x9FFF | 0xFE00..=0xFE9F | 0xFF40..=0xFF6F => self.sync_ppu(),
+            0xFF04..=0xFF07 | 0xFF10..=0xFF3F | 0xFF76 | 0xFF77 => self.sync_apu(),
+            _ => {}
+        }
+    }
+
+    /// A write to `address` might have moved the next event of its component.
+    fn reschedule_for(&mut self, address: u16) {
+        match address {
+            0x8000..=0x9FFF | 0xFE00..=0xFE9F | 0xFF40..=0xFF6F => {
+                self.scheduler
+                    .schedule(Event::Ppu, self.ppu.dots_until_event());
+            }
+            0xFF04..=0xFF07 => {
+                self.scheduler
+                    .schedule(Event::Timer, self.timer.cycles_until_event());
+            }
+            _ => {}
+        }
+    }
+
     fn check_interrupts(&mut self) {
         if self.ppu.vblank_irq {
             self.interrupts.request_vblank();
@@ -535,6 +892,81 @@ impl Memory {
     }
 }
 
+impl SaveState for Memory {
+    fn save_state(&self, writer: &mut StateWriter) {
+        writer.write_u8(self.events.bits());
+
+        writer.write_bool(self.bootrom.mapped());
+
+        self.wram.save_state(writer);
+        self.hram.save_state(writer);
+
+        writer.write_bool(self.cartridge.is_some());
+        if let Some(cartridge) = &self.cartridge {
+            cartridge.save_state(writer);
+        }
+
+        self.ppu.save_state(writer);
+        self.apu.save_state(writer);
+
+        self.joypad.save_state(writer);
+        self.serial.save_state(writer);
+        self.timer.save_state(writer);
+
+        self.key0.save_state(writer);
+        self.key1.save_state(writer);
+        self.undocumented_registers.save_state(writer);
+
+        self.interrupts.save_state(writer);
+
+        writer.write_bool(self.cgb_mode);
+    }
+
+    fn load_state(&mut self, reader: &mut StateReader) -> Result<(), SaveStateError> {
+        self.events = Events::from_bits_truncate(reader.read_u8()?);
+
+        // The bootrom itself is not a part of the save state, only whether it is still mapped.
+        let bootrom_mapped = reader.read_bool()?;
+
+        if bootrom_mapped && !self.bootrom.mapped() {
+            return Err(SaveStateError::MissingBootrom);
+        }
+
+        if !bootrom_mapped {
+            self.bootrom.unmap();
+        }
+
+        self.wram.load_state(reader)?;
+        self.hram.load_state(reader)?;
+
+        let has_cartridge = reader.read_bool()?;
+
+        match (self.cartridge.as_mut(), has_cartridge) {
+            (Some(cartridge), true) => cartridge.load_state(reader)?,
+            (None, false) => {}
+            (None, true) => return Err(SaveStateError::NoCartridge),
+            (Some(_), false) => return Err(SaveStateError::InvalidData("missing cartridge")),
+        }
+
+        self.ppu.load_state(reader)?;
+        self.apu.load_state(reader)?;
+
+        self.joypad.load_state(reader)?;
+        self.serial.load_state(reader)?;
+        self.timer.load_state(reader)?;
+
+        self.key0.load_state(reader)?;
+        self.key1.load_state(reader)?;
+        self.undocumented_registers.load_state(reader)?;
+
+        self.interrupts.load_state(reader)?;
+
+        self.cgb_mode = reader.read_bool()?;
+
+        Ok(())
+    }
+}
+
 pub(crate) mod bootrom;
 pub(crate) mod high_ram;
 pub(crate) mod interrupts;
@@ -542,3 +974,40 @@ pub(crate) mod key0;
 pub(crate) mod key1;
 pub(crate) mod undocumented_registers;
 pub(crate) mod work_ram;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{GameBoy, test_rom::TestRom};
+
+    #[test]
+    fn test_memory_regions() {
+        // MBC1+RAM+BATTERY, with 8 KiB of RAM
+        let rom = TestRom::new().mbc(0x03).ram_size(0x02).build();
+
+        let mut gb = GameBoy::new(DeviceModel::Cgb);
+        gb.load(None, rom).unwrap();
+
+        let memory = gb.memory_mut();
+
+        assert_eq!(
+            memory.region(MemoryRegion::WorkRam).map(<[u8]>::len),
+            Some(0x8000)
+        );
+        assert_eq!(
+            memory.region(MemoryRegion::HighRam).map(<[u8]>::len),
+            Some(0x7F)
+        );
+        assert_eq!(
+            memory.region(MemoryRegion::VideoRam).map(<[u8]>::len),
+            Some(0x4000)
+        );
+        assert_eq!(memory.region(MemoryRegion::Rtc), None);
+
+        memory.region_mut(MemoryRegion::CartridgeRam).unwrap()[0] = 0x42;
+        memory.region_mut(MemoryRegion::WorkRam).unwrap()[0x1000] = 0x99;
+
+        assert_eq!(gb.get_battery().unwrap()[0], 0x42);
+        assert_eq!(gb.memory().read(0xD000), 0x99);
+    }
+}
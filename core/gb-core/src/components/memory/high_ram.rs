@@ -1,3 +1,5 @@
+use crate::save_state::{SaveState, SaveStateError, StateReader, StateWriter};
+
 const HRAM_SIZE: usize = 0x80;
 
 pub struct HighRam {
@@ -22,4 +24,23 @@ impl HighRam {
     pub fn write(&mut self, address: u16, value: u8) {
         self.data[address as usize - 0xFF80] = value;
     }
+
+    /// The last byte is not mapped, 0xFFFF is IE.
+    pub fn data(&self) -> &[u8] {
+        &self.data[..HRAM_SIZE - 1]
+    }
+
+    pub fn data_mut(&mut self) -> &mut [u8] {
+        &mut self.data[..HRAM_SIZE - 1]
+    }
+}
+
+impl SaveState for HighRam {
+    fn save_state(&self, writer: &mut StateWriter) {
+        writer.write_bytes(&self.data);
+    }
+
+    fn load_state(&mut self, reader: &mut StateReader) -> Result<(), SaveStateError> {
+        reader.read_bytes_into(&mut self.data)
+    }
 }
@@ -1,6 +1,9 @@
 use bitflags::bitflags;
 
-use crate::utils::bits;
+use crate::{
+    save_state::{SaveState, SaveStateError, StateReader, StateWriter},
+    utils::bits,
+};
 
 bitflags! {
     #[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
@@ -104,6 +107,20 @@ impl Interrupts {
     }
 }
 
+impl SaveState for Interrupts {
+    fn save_state(&self, writer: &mut StateWriter) {
+        writer.write_u8(self.flags.bits());
+        writer.write_u8(self.enable.bits());
+    }
+
+    fn load_state(&mut self, reader: &mut StateReader) -> Result<(), SaveStateError> {
+        self.flags = InterruptBits::from_bits_truncate(reader.read_u8()?);
+        self.enable = InterruptBits::from_bits_truncate(reader.read_u8()?);
+
+        Ok(())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1,6 +1,10 @@
 use tracing::info;
 
-use crate::{constants::DeviceModel, utils::macros::device_is_cgb};
+use crate::{
+    constants::DeviceModel,
+    save_state::{SaveState, SaveStateError, StateReader, StateWriter},
+    utils::macros::device_is_cgb,
+};
 
 pub struct Key0 {
     pub cgb_mode: bool,
@@ -49,3 +53,17 @@ impl Key0 {
         self.cgb_mode = (value & 0b0000_0100) == 0;
     }
 }
+
+impl SaveState for Key0 {
+    fn save_state(&self, writer: &mut StateWriter) {
+        writer.write_bool(self.cgb_mode);
+        writer.write_bool(self.locked_bootrom);
+    }
+
+    fn load_state(&mut self, reader: &mut StateReader) -> Result<(), SaveStateError> {
+        self.cgb_mode = reader.read_bool()?;
+        self.locked_bootrom = reader.read_bool()?;
+
+        Ok(())
+    }
+}
@@ -1,6 +1,10 @@
 use tracing::info;
 
-use crate::{DeviceModel, utils::macros::in_cgb_mode};
+use crate::{
+    DeviceModel,
+    save_state::{SaveState, SaveStateError, StateReader, StateWriter},
+    utils::macros::in_cgb_mode,
+};
 
 #[derive(Debug, Default)]
 pub struct Key1 {
@@ -66,6 +70,22 @@ impl Key1 {
     }
 }
 
+impl SaveState for Key1 {
+    fn save_state(&self, writer: &mut StateWriter) {
+        writer.write_bool(self.double_speed);
+        writer.write_bool(self.armed);
+        writer.write_bool(self.cgb_mode);
+    }
+
+    fn load_state(&mut self, reader: &mut StateReader) -> Result<(), SaveStateError> {
+        self.double_speed = reader.read_bool()?;
+        self.armed = reader.read_bool()?;
+        self.cgb_mode = reader.read_bool()?;
+
+        Ok(())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
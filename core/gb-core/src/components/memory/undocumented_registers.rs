@@ -1,5 +1,6 @@
 use crate::{
     DeviceModel,
+    save_state::{SaveState, SaveStateError, StateReader, StateWriter},
     utils::macros::{device_is_cgb, in_cgb_mode},
 };
 
@@ -93,3 +94,23 @@ impl UndocumentedRegisters {
         self.reg_0xff75 = value & Self::REG_FF75_MASK;
     }
 }
+
+impl SaveState for UndocumentedRegisters {
+    fn save_state(&self, writer: &mut StateWriter) {
+        writer.write_u8(self.reg_0xff72);
+        writer.write_u8(self.reg_0xff73);
+        writer.write_u8(self.reg_0xff74);
+        writer.write_u8(self.reg_0xff75);
+        writer.write_bool(self.cgb_mode);
+    }
+
+    fn load_state(&mut self, reader: &mut StateReader) -> Result<(), SaveStateError> {
+        self.reg_0xff72 = reader.read_u8()?;
+        self.reg_0xff73 = reader.read_u8()?;
+        self.reg_0xff74 = reader.read_u8()?;
+        self.reg_0xff75 = reader.read_u8()?;
+        self.cgb_mode = reader.read_bool()?;
+
+        Ok(())
+    }
+}
@@ -1,4 +1,9 @@
-use crate::{DeviceModel, constants::ONE_KIB, utils::macros::in_cgb_mode_or_bootrom};
+use crate::{
+    DeviceModel,
+    constants::ONE_KIB,
+    save_state::{SaveState, SaveStateError, StateReader, StateWriter},
+    utils::macros::in_cgb_mode_or_bootrom,
+};
 
 const DMG_WRAM_BANKS: usize = 2;
 const CGB_WRAM_BANKS: usize = 8;
@@ -43,6 +48,14 @@ impl WorkRam {
         }
     }
 
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    pub fn data_mut(&mut self) -> &mut [u8] {
+        &mut self.data
+    }
+
     pub fn set_cgb_mode(&mut self, value: bool) {
         self.cgb_mode = value;
     }
@@ -106,6 +119,24 @@ impl WorkRam {
     }
 }
 
+impl SaveState for WorkRam {
+    fn save_state(&self, writer: &mut StateWriter) {
+        writer.write_bytes(&self.data);
+        writer.write_u8(self.svbk);
+        writer.write_bool(self.locked_bootrom);
+        writer.write_bool(self.cgb_mode);
+    }
+
+    fn load_state(&mut self, reader: &mut StateReader) -> Result<(), SaveStateError> {
+        reader.read_bytes_into(&mut self.data)?;
+        self.svbk = reader.read_u8()?;
+        self.locked_bootrom = reader.read_bool()?;
+        self.cgb_mode = reader.read_bool()?;
+
+        Ok(())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1,16 +1,20 @@
 use self::{
     color_ram::ColorRam,
+    fifo::PixelFifo,
     lcd_control::LcdControl,
     lcd_status::{LcdStatus, StatusMode},
     oam::Oam,
     oam_dma::OamDma,
+    renderer::Renderer,
     video_ram::VideoRam,
     vram_dma::VramDma,
 };
 use super::cartridge::Cartridge;
 use crate::{
     DeviceModel,
+    save_state::{SaveState, SaveStateError, StateReader, StateWriter},
     utils::{
+        color::{Color, ColorCorrection, DmgPalette},
         events::Events,
         macros::{device_is_cgb, in_cgb_mode, in_cgb_mode_or_bootrom, pure_read_write_methods_u8},
         screen::Screen,
@@ -53,6 +57,12 @@ pub struct Ppu {
     mode: StatusMode,
     mode_remaining_dots: usize,
 
+    renderer: Renderer,
+    fifo: PixelFifo,
+
+    dmg_palette: DmgPalette,
+    color_correction: ColorCorrection,
+
     locked_bootrom: bool,
     cgb_mode: bool,
     device_model: DeviceModel,
@@ -102,6 +112,10 @@ impl Ppu {
             vram_dma: VramDma::with_device_model(device_model),
             mode: StatusMode::default(),
             mode_remaining_dots: StatusMode::default().dots(),
+            renderer: Renderer::default(),
+            fifo: PixelFifo::default(),
+            dmg_palette: DmgPalette::default(),
+            color_correction: ColorCorrection::default(),
             locked_bootrom: false,
             cgb_mode: device_model.is_cgb(),
             device_model,
@@ -117,6 +131,55 @@ impl Ppu {
         self.cgb_mode = value;
     }
 
+    #[must_use]
+    pub fn renderer(&self) -> Renderer {
+        self.renderer
+    }
+
+    /// Takes effect on the next line.
+    pub fn set_renderer(&mut self, renderer: Renderer) {
+        self.renderer = renderer;
+    }
+
+    #[must_use]
+    pub fn dmg_palette(&self) -> DmgPalette {
+        self.dmg_palette
+    }
+
+    /// Takes effect on the next line.
+    pub fn set_dmg_palette(&mut self, dmg_palette: DmgPalette) {
+        self.dmg_palette = dmg_palette;
+    }
+
+    #[must_use]
+    pub fn color_correction(&self) -> ColorCorrection {
+        self.color_correction
+    }
+
+    /// Takes effect on the next line.
+    pub fn set_color_correction(&mut self, color_correction: ColorCorrection) {
+        self.color_correction = color_correction;
+    }
+
+    /// DMG color after going through a palette register (BGP, OBP0 or OBP1).
+    fn dmg_color(&self, color_id: u8, palette: u8) -> Color {
+        self.dmg_palette.colors()[Color::apply_dmg_palette(color_id, palette) as usize]
+    }
+
+    /// Warning: CGB model only.
+    fn cgb_color(&self, raw_color: u16) -> Color {
+        self.color_correction.convert(raw_color)
+    }
+
+    /// Shown when the LCD or the background is disabled.
+    fn blank_color(&self) -> Color {
+        if device_is_cgb!(self) {
+            self.cgb_color(0x7FFF)
+        } else {
+            self.dmg_palette.colors()[0]
+        }
+    }
+
     pub fn handle_locked_bootrom(&mut self) {
         self.locked_bootrom = true;
     }
@@ -277,11 +340,64 @@ impl Ppu {
         self.opri = (value & 0b1) != 0;
     }
 
+    /// Same as calling [`Ppu::tick`] `dots` times, skipping over the dots where nothing happens.
+    pub(crate) fn advance(&mut self, mut dots: u64, events: &mut Events) {
+        while dots > 0 && self.lcdc.get_lcd_enable() {
+            let idle_dots = self.idle_dots().min(dots);
+
+            self.mode_remaining_dots -= idle_dots as usize;
+            dots -= idle_dots;
+
+            if dots > 0 {
+                self.tick(events);
+                dots -= 1;
+            }
+        }
+    }
+
+    /// Dots until something observable happens, e.g. a mode change or a STAT interrupt.
+    pub(crate) fn dots_until_event(&self) -> u64 {
+        if !self.lcdc.get_lcd_enable() {
+            return u64::MAX;
+        }
+
+        self.idle_dots() + 1
+    }
+
+    /// Dots that only count down the current mode.
+    fn idle_dots(&self) -> u64 {
+        if self.mode == StatusMode::Drawing && self.fifo.is_active() {
+            return 0;
+        }
+
+        // The hblank interrupt is requested one dot before the end of mode 3.
+        let event_at = if self.mode == StatusMode::Drawing {
+            2
+        } else {
+            1
+        };
+
+        self.mode_remaining_dots.saturating_sub(event_at) as u64
+    }
+
     pub(crate) fn tick(&mut self, events: &mut Events) {
         if !self.lcdc.get_lcd_enable() {
             return;
         }
 
+        if self.mode == StatusMode::Drawing && self.fifo.is_active() {
+            if self.tick_fifo() {
+                self.finish_drawing();
+
+                // Mode 3 and H-Blank always add up to the same length.
+                self.mode_remaining_dots = (StatusMode::Drawing.dots() + StatusMode::Hblank.dots())
+                    .saturating_sub(self.fifo.mode_3_dots())
+                    .max(1);
+            }
+
+            return;
+        }
+
         self.mode_remaining_dots -= 1;
 
         // Quirk.
@@ -308,19 +424,20 @@ impl Ppu {
                     self.draw_line_dmg();
                 }
 
-                self.switch_mode(StatusMode::Hblank);
-
-                if in_cgb_mode!(self) {
-                    self.vram_dma.resume_hdma();
-                }
+                self.finish_drawing();
             }
 
             StatusMode::Hblank => {
-                if self.lcdc.get_win_enable()
-                    && self.wx < 166
-                    && self.wy < 143
-                    && self.wy <= self.ly
-                {
+                let window_rendered = if self.fifo.is_active() {
+                    self.fifo.window_rendered()
+                } else {
+                    self.lcdc.get_win_enable()
+                        && self.wx < 166
+                        && self.wy < 143
+                        && self.wy <= self.ly
+                };
+
+                if window_rendered {
                     self.window_internal_counter = self.window_internal_counter.wrapping_add(1);
                 }
 
@@ -342,6 +459,7 @@ impl Ppu {
                 if self.ly == 154 {
                     self.ly = 0;
                     self.window_internal_counter = 0;
+                    self.fifo.reset_frame();
                     self.switch_mode(StatusMode::OamScan);
                 } else {
                     self.mode_remaining_dots = StatusMode::Vblank.dots();
@@ -352,6 +470,14 @@ impl Ppu {
         }
     }
 
+    fn finish_drawing(&mut self) {
+        self.switch_mode(StatusMode::Hblank);
+
+        if in_cgb_mode!(self) {
+            self.vram_dma.resume_hdma();
+        }
+    }
+
     fn check_irq(&mut self) {
         if self.ly == self.lyc {
             self.stat.insert(LcdStatus::LY_COMPARE);
@@ -375,7 +501,9 @@ impl Ppu {
                 }
             }
 
-            StatusMode::Drawing => {}
+            StatusMode::Drawing => {
+                self.start_fifo_line();
+            }
 
             StatusMode::Hblank => {
                 // Handled elsewhere due to different timings.
@@ -393,13 +521,179 @@ impl Ppu {
     }
 }
 
+impl SaveState for Ppu {
+    fn save_state(&self, writer: &mut StateWriter) {
+        writer.write_u8(self.lcdc.bits());
+        writer.write_u8(self.stat.bits());
+        writer.write_u8(self.scy);
+        writer.write_u8(self.scx);
+        writer.write_u8(self.ly);
+        writer.write_u8(self.lyc);
+        writer.write_u8(self.bgp);
+        writer.write_u8(self.obp0);
+        writer.write_u8(self.obp1);
+        writer.write_u8(self.wy);
+        writer.write_u8(self.wx);
+        writer.write_u8(self.window_internal_counter);
+
+        writer.write_u8(self.bcps);
+        self.bg_cram.save_state(writer);
+        writer.write_u8(self.ocps);
+        self.obj_cram.save_state(writer);
+        writer.write_bool(self.opri);
+
+        writer.write_bool(self.stat_irq);
+        writer.write_bool(self.vblank_irq);
+
+        self.vram.save_state(writer);
+        self.oam.save_state(writer);
+
+        self.oam_dma.save_state(writer);
+        self.vram_dma.save_state(writer);
+
+        writer.write_u8(self.mode as u8);
+        writer.write_usize(self.mode_remaining_dots);
+        self.fifo.save_state(writer);
+
+        writer.write_bool(self.locked_bootrom);
+        writer.write_bool(self.cgb_mode);
+
+        self.screen.save_state(writer);
+        self.internal_screen.save_state(writer);
+    }
+
+    fn load_state(&mut self, reader: &mut StateReader) -> Result<(), SaveStateError> {
+        self.lcdc = LcdControl::from_bits_truncate(reader.read_u8()?);
+        self.stat = LcdStatus::from_bits_truncate(reader.read_u8()?);
+        self.scy = reader.read_u8()?;
+        self.scx = reader.read_u8()?;
+        self.ly = reader.read_u8()?;
+        self.lyc = reader.read_u8()?;
+        self.bgp = reader.read_u8()?;
+        self.obp0 = reader.read_u8()?;
+        self.obp1 = reader.read_u8()?;
+        self.wy = reader.read_u8()?;
+        self.wx = reader.read_u8()?;
+        self.window_internal_counter = reader.read_u8()?;
+
+        self.bcps = reader.read_u8()?;
+        self.bg_cram.load_state(reader)?;
+        self.ocps = reader.read_u8()?;
+        self.obj_cram.load_state(reader)?;
+        self.opri = reader.read_bool()?;
+
+        self.stat_irq = reader.read_bool()?;
+        self.vblank_irq = reader.read_bool()?;
+
+        self.vram.load_state(reader)?;
+        self.oam.load_state(reader)?;
+
+        self.oam_dma.load_state(reader)?;
+        self.vram_dma.load_state(reader)?;
+
+        self.mode = match reader.read_u8()? {
+            0b00 => StatusMode::Hblank,
+            0b01 => StatusMode::Vblank,
+            0b10 => StatusMode::OamScan,
+            0b11 => StatusMode::Drawing,
+            _ => return Err(SaveStateError::InvalidData("PPU mode")),
+        };
+        self.mode_remaining_dots = reader.read_usize()?;
+        self.fifo.load_state(reader)?;
+
+        self.locked_bootrom = reader.read_bool()?;
+        self.cgb_mode = reader.read_bool()?;
+
+        self.screen.load_state(reader)?;
+        self.internal_screen.load_state(reader)?;
+
+        Ok(())
+    }
+}
+
 pub mod color_ram;
 mod draw_line_cgb;
 mod draw_line_dmg;
+mod fifo;
 mod lcd_control;
 mod lcd_status;
 mod oam;
 mod oam_dma;
+pub mod renderer;
 mod sprite;
 mod video_ram;
 mod vram_dma;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{GameBoy, test_rom::TestRom};
+
+    fn save(ppu: &Ppu) -> Vec<u8> {
+        let mut writer = StateWriter::default();
+        ppu.save_state(&mut writer);
+        writer.into_inner()
+    }
+
+    #[test]
+    fn test_advance_matches_tick() {
+        for renderer in [Renderer::Scanline, Renderer::Fifo] {
+            let mut ticked = Ppu::with_device_model(DeviceModel::Cgb);
+            let mut advanced = Ppu::with_device_model(DeviceModel::Cgb);
+
+            for ppu in [&mut ticked, &mut advanced] {
+                ppu.set_renderer(renderer);
+                ppu.write_lyc(0x42);
+                // Every STAT interrupt source.
+                ppu.write_stat(0b0111_1000);
+                ppu.write_lcdc(0b1001_0011);
+            }
+
+            let mut ticked_events = Events::empty();
+            let mut advanced_events = Events::empty();
+
+            // Stops right before, on and after the mode boundaries of a line, then across frames.
+            for dots in [1, 3, 75, 1, 1, 170, 2, 203, 456, 1000, 65664, 4560, 70224] {
+                for _ in 0..dots {
+                    ticked.tick(&mut ticked_events);
+                }
+
+                advanced.advance(dots, &mut advanced_events);
+
+                assert_eq!(advanced.ly, ticked.ly);
+                assert_eq!(advanced.read_stat(), ticked.read_stat());
+                assert_eq!(advanced.stat_irq, ticked.stat_irq);
+                assert_eq!(advanced.vblank_irq, ticked.vblank_irq);
+                assert_eq!(advanced_events.bits(), ticked_events.bits());
+                assert!(save(&advanced) == save(&ticked));
+
+                // The interrupts are checked again on the next stop.
+                for ppu in [&mut ticked, &mut advanced] {
+                    ppu.stat_irq = false;
+                    ppu.vblank_irq = false;
+                }
+
+                ticked_events = Events::empty();
+                advanced_events = Events::empty();
+            }
+        }
+    }
+
+    #[test]
+    fn test_dmg_palette() {
+        let mut gb = GameBoy::new(DeviceModel::Dmg);
+        gb.load(None, TestRom::new().build()).unwrap();
+        gb.run_frame();
+
+        let pixel = gb.screen().pixels[0];
+        assert_eq!(pixel.to_rgb555(), Color::DMG_GREEN_PALETTE[0].to_rgb555());
+
+        // Survives a power cycle.
+        gb.set_dmg_palette(DmgPalette::Grey);
+        gb.power_cycle();
+        gb.run_frame();
+
+        let pixel = gb.screen().pixels[0];
+        assert_eq!(pixel.to_rgb555(), Color::DMG_GREY_PALETTE[0].to_rgb555());
+    }
+}
@@ -1,5 +1,7 @@
 use tracing::error;
 
+use crate::save_state::{SaveState, SaveStateError, StateReader, StateWriter};
+
 const CRAM_SIZE: usize = 64;
 
 pub struct ColorRam {
@@ -57,3 +59,13 @@ impl ColorRam {
         }
     }
 }
+
+impl SaveState for ColorRam {
+    fn save_state(&self, writer: &mut StateWriter) {
+        writer.write_bytes(&self.data);
+    }
+
+    fn load_state(&mut self, reader: &mut StateReader) -> Result<(), SaveStateError> {
+        reader.read_bytes_into(&mut self.data)
+    }
+}
@@ -5,7 +5,7 @@ use crate::{
 };
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
-enum Priority {
+pub(super) enum Priority {
     Object,
     OamAttribute,
     Background,
@@ -21,6 +21,10 @@ impl Ppu {
     }
 
     fn draw_tiles_cgb(&mut self, priority: &mut [Priority; SCREEN_WIDTH]) {
+        // The screen line borrows `self`.
+        let blank_color = self.blank_color();
+        let color_correction = self.color_correction;
+
         let screen_line = {
             let line_offset = SCREEN_WIDTH * (self.ly as usize);
 
@@ -56,7 +60,7 @@ impl Ppu {
 
                 (x, y, tile_map_base_address)
             } else {
-                let pixel = Color::CGB_SYSTEM_DEFAULT;
+                let pixel = blank_color;
 
                 screen_line[i as usize] = pixel;
 
@@ -148,7 +152,7 @@ impl Ppu {
                     priority[i as usize] = Priority::Background;
                 }
 
-                let pixel = Color::from_rgb555_accurate(raw_color);
+                let pixel = color_correction.convert(raw_color);
                 screen_line[i as usize] = pixel;
             } else {
                 let color_index = Color::apply_dmg_palette(color_id, self.bgp);
@@ -160,13 +164,15 @@ impl Ppu {
                     Priority::Background
                 };
 
-                let pixel = Color::from_rgb555_accurate(raw_color);
+                let pixel = color_correction.convert(raw_color);
                 screen_line[i as usize] = pixel;
             }
         }
     }
 
     fn draw_sprites_cgb(&mut self, priority: &[Priority; SCREEN_WIDTH]) {
+        let color_correction = self.color_correction;
+
         if !self.lcdc.get_obj_enable() {
             return;
         }
@@ -262,7 +268,7 @@ impl Ppu {
                         .obj_cram
                         .get_color_rgb555(sprite.flags.palette_number, color_id);
 
-                    let pixel = Color::from_rgb555_accurate(raw_color);
+                    let pixel = color_correction.convert(raw_color);
 
                     screen_line[mapped_x] = pixel;
                 } else {
@@ -273,7 +279,7 @@ impl Ppu {
                     let color_index = Color::apply_dmg_palette(color_id, selected_palette);
                     let raw_color = self.obj_cram.get_color_rgb555(0, color_index);
 
-                    let pixel = Color::from_rgb555_accurate(raw_color);
+                    let pixel = color_correction.convert(raw_color);
 
                     screen_line[mapped_x] = pixel;
                 }
@@ -10,6 +10,9 @@ impl Ppu {
     }
 
     fn draw_tiles_dmg(&mut self, bg_priority: &mut [bool; SCREEN_WIDTH]) {
+        // The screen line borrows `self`.
+        let colors = self.dmg_palette.colors();
+
         let screen_line = {
             let line_offset = SCREEN_WIDTH * (self.ly as usize);
 
@@ -45,7 +48,7 @@ impl Ppu {
 
                 (x, y, tile_map_base_address)
             } else {
-                let pixel = Color::DMG_SYSTEM_DEFAULT;
+                let pixel = colors[0];
 
                 screen_line[i as usize] = pixel;
 
@@ -96,7 +99,7 @@ impl Ppu {
 
             bg_priority[i as usize] = color_id != 0;
 
-            let pixel = Color::from_dmg_color_id_with_palette(color_id, self.bgp);
+            let pixel = colors[Color::apply_dmg_palette(color_id, self.bgp) as usize];
             screen_line[i as usize] = pixel;
         }
     }
@@ -106,6 +109,8 @@ impl Ppu {
             return;
         }
 
+        let colors = self.dmg_palette.colors();
+
         let screen_line = {
             let line_offset = SCREEN_WIDTH * (self.ly as usize);
 
@@ -179,7 +184,7 @@ impl Ppu {
                     continue;
                 }
 
-                let pixel = Color::from_dmg_color_id_with_palette(color_id, selected_palette);
+                let pixel = colors[Color::apply_dmg_palette(color_id, selected_palette) as usize];
 
                 screen_line[mapped_x] = pixel;
             }
@@ -0,0 +1,940 @@
+use arrayvec::ArrayVec;
+
+use super::{Ppu, draw_line_cgb::Priority, renderer::Renderer, sprite::SpriteObjectFlags};
+use crate::{
+    constants::SCREEN_WIDTH,
+    save_state::{SaveState, SaveStateError, StateReader, StateWriter},
+    utils::{
+        color::Color,
+        macros::{device_is_cgb, in_cgb_mode},
+    },
+};
+
+/// The first tile fetch of every line is thrown away.
+const STARTUP_DOTS: u8 = 6;
+
+/// Dots spent fetching a sprite, not counting the wait for the background fetcher.
+const SPRITE_FETCH_DOTS: u8 = 6;
+
+/// Objects with X = 0 always take this long, even though they are not visible.
+const HIDDEN_SPRITE_FETCH_DOTS: u8 = 11;
+
+const MAX_SPRITES_PER_LINE: usize = 10;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+enum FetcherStep {
+    #[default]
+    Tile,
+    DataLow,
+    DataHigh,
+    Push,
+}
+
+/// Background/window fetcher. Every step but `Push` takes 2 dots.
+#[derive(Debug, Default, Clone, Copy)]
+struct Fetcher {
+    step: FetcherStep,
+    second_dot: bool,
+
+    tile_x: u8,
+    row: u8,
+
+    tile_index: u8,
+    attributes: u8,
+    data_lo: u8,
+    data_hi: u8,
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+struct BgPixel {
+    color_id: u8,
+    palette_number: u8, // CGB only
+    bg_priority: bool,  // CGB only
+    window: bool,
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+struct ObjPixel {
+    color_id: u8,
+    palette_number: u8, // CGB only
+    obp1_selected: bool,
+    bg_priority: bool,
+
+    /// Position in the sprite buffer, used for the CGB OAM priority.
+    index: u8,
+}
+
+/// State of the pixel FIFO renderer for the current line.
+#[derive(Debug, Default)]
+pub(super) struct PixelFifo {
+    /// Whether the current line is being drawn by the FIFO renderer.
+    active: bool,
+
+    /// Dots spent in mode 3 so far.
+    dots: u16,
+
+    x: u8,
+    startup_dots: u8,
+    discard: u8,
+    stall_dots: u8,
+
+    /// WY matched LY at some point during this frame.
+    wy_triggered: bool,
+    in_window: bool,
+
+    /// Raw OAM entries, in OAM order.
+    sprites: ArrayVec<[u8; 4], MAX_SPRITES_PER_LINE>,
+    fetched_sprites: u16,
+    last_penalized_tile: Option<(bool, u8)>,
+
+    fetcher: Fetcher,
+
+    bg_fifo: [BgPixel; 8],
+    bg_len: u8,
+
+    obj_fifo: [ObjPixel; 8],
+}
+
+impl PixelFifo {
+    pub(super) fn is_active(&self) -> bool {
+        self.active
+    }
+
+    pub(super) fn window_rendered(&self) -> bool {
+        self.in_window
+    }
+
+    pub(super) fn reset_frame(&mut self) {
+        self.wy_triggered = false;
+    }
+
+    pub(super) fn mode_3_dots(&self) -> usize {
+        self.dots as usize
+    }
+
+    fn pop_bg(&mut self) -> BgPixel {
+        let pixel = self.bg_fifo[8 - self.bg_len as usize];
+        self.bg_len -= 1;
+
+        pixel
+    }
+
+    fn pop_obj(&mut self) -> ObjPixel {
+        let pixel = self.obj_fifo[0];
+
+        self.obj_fifo.rotate_left(1);
+        self.obj_fifo[7] = ObjPixel::default();
+
+        pixel
+    }
+
+    fn next_sprite_hit(&self) -> Option<usize> {
+        self.sprites.iter().enumerate().position(|(index, sprite)| {
+            let oam_x = sprite[1];
+
+            let pending = self.fetched_sprites & (1 << index) == 0;
+            let hit = if self.x == 0 {
+                oam_x <= 8
+            } else {
+                oam_x == self.x + 8
+            };
+
+            pending && hit
+        })
+    }
+}
+
+impl Ppu {
+    /// Called when mode 3 starts.
+    pub(super) fn start_fifo_line(&mut self) {
+        if self.ly == self.wy {
+            self.fifo.wy_triggered = true;
+        }
+
+        if self.renderer != Renderer::Fifo {
+            self.fifo.active = false;
+            return;
+        }
+
+        let obj_height = if self.lcdc.get_obj_size() { 16 } else { 8 };
+
+        self.fifo = PixelFifo {
+            active: true,
+            startup_dots: STARTUP_DOTS,
+            discard: self.scx % 8,
+            wy_triggered: self.fifo.wy_triggered,
+            sprites: self.oam.get_raw_sprites_in_line(self.ly, obj_height),
+            ..PixelFifo::default()
+        };
+    }
+
+    /// Runs the FIFO renderer for one dot.
+    ///
+    /// Returns `true` once the line is complete.
+    pub(super) fn tick_fifo(&mut self) -> bool {
+        self.fifo.dots += 1;
+
+        if self.fifo.startup_dots > 0 {
+            self.fifo.startup_dots -= 1;
+            return false;
+        }
+
+        if self.fifo.stall_dots > 0 {
+            self.fifo.stall_dots -= 1;
+            return false;
+        }
+
+        self.check_window_fifo();
+        self.tick_fetcher();
+
+        if self.fifo.bg_len == 0 {
+            return false;
+        }
+
+        if self.fifo.discard > 0 {
+            self.fifo.pop_bg();
+            self.fifo.discard -= 1;
+
+            return false;
+        }
+
+        if self.lcdc.get_obj_enable()
+            && let Some(index) = self.fifo.next_sprite_hit()
+        {
+            self.fetch_sprite(index);
+            return false;
+        }
+
+        self.output_pixel();
+        self.fifo.x += 1;
+
+        // Quirk.
+        // One cycle before the mode switch (Drawing -> Hblank).
+        if self.fifo.x as usize == SCREEN_WIDTH - 1 && self.stat.get_hblank_irq() {
+            self.stat_irq = true;
+        }
+
+        self.fifo.x as usize == SCREEN_WIDTH
+    }
+
+    fn check_window_fifo(&mut self) {
+        if self.fifo.in_window || !self.fifo.wy_triggered || !self.lcdc.get_win_enable() {
+            return;
+        }
+
+        if (self.fifo.x as u16) + 7 < self.wx as u16 {
+            return;
+        }
+
+        self.fifo.in_window = true;
+        self.fifo.bg_len = 0;
+        self.fifo.fetcher = Fetcher::default();
+
+        // With WX < 7, the pixels left of the screen are thrown away.
+        self.fifo.discard = if self.fifo.x == 0 {
+            7 - self.wx.min(7)
+        } else {
+            0
+        };
+    }
+
+    fn tick_fetcher(&mut self) {
+        let fetcher = &mut self.fifo.fetcher;
+
+        if fetcher.step == FetcherStep::Push {
+            if self.fifo.bg_len == 0 {
+                self.push_bg_pixels();
+            }
+
+            return;
+        }
+
+        if !fetcher.second_dot {
+            fetcher.second_dot = true;
+            return;
+        }
+
+        fetcher.second_dot = false;
+
+        match fetcher.step {
+            FetcherStep::Tile => {
+                self.fetch_tile();
+                self.fifo.fetcher.step = FetcherStep::DataLow;
+            }
+
+            FetcherStep::DataLow => {
+                self.fifo.fetcher.data_lo = self.read_tile_data(0);
+                self.fifo.fetcher.step = FetcherStep::DataHigh;
+            }
+
+            FetcherStep::DataHigh => {
+                self.fifo.fetcher.data_hi = self.read_tile_data(1);
+                self.fifo.fetcher.step = FetcherStep::Push;
+            }
+
+            FetcherStep::Push => unreachable!(),
+        }
+    }
+
+    fn fetch_tile(&mut self) {
+        let (tile_map_base_address, tile_col, y) = if self.fifo.in_window {
+            let tile_map_base_address = if self.lcdc.get_win_map() {
+                0x9C00
+            } else {
+                0x9800
+            };
+
+            (
+                tile_map_base_address,
+                self.fifo.fetcher.tile_x & 31,
+                self.window_internal_counter,
+            )
+        } else {
+            let tile_map_base_address = if self.lcdc.get_bg_map() {
+                0x9C00
+            } else {
+                0x9800
+            };
+
+            (
+                tile_map_base_address,
+                ((self.scx / 8).wrapping_add(self.fifo.fetcher.tile_x)) & 31,
+                self.scy.wrapping_add(self.ly),
+            )
+        };
+
+        let tile_map_address = tile_map_base_address + ((y as u16 / 8) * 32) + tile_col as u16;
+
+        let fetcher = &mut self.fifo.fetcher;
+
+        fetcher.row = y % 8;
+        fetcher.tile_index = self.vram.read_bank_0(tile_map_address);
+        fetcher.attributes = if in_cgb_mode!(self) {
+            self.vram.read_bank_1(tile_map_address)
+        } else {
+            0
+        };
+    }
+
+    fn read_tile_data(&self, offset: u16) -> u8 {
+        let fetcher = &self.fifo.fetcher;
+
+        let y_flip = (fetcher.attributes & 0b0100_0000) != 0;
+        let in_bank_1 = (fetcher.attributes & 0b0000_1000) != 0;
+
+        let tile_address = {
+            let tile_index = fetcher.tile_index as u16;
+
+            if self.lcdc.get_bg_win_addr() {
+                // Unsigned mapping.
+                0x8000 + (tile_index * 16) // Each tile has 16 bytes.
+            } else {
+                // Signed mapping.
+                if tile_index < 128 {
+                    0x9000 + (tile_index * 16)
+                } else {
+                    0x8800 + ((tile_index - 128) * 16)
+                }
+            }
+        };
+
+        let row = if y_flip { 7 - fetcher.row } else { fetcher.row } as u16;
+        let address = tile_address + (row * 2) + offset;
+
+        if in_bank_1 {
+            self.vram.read_bank_1(address)
+        } else {
+            self.vram.read_bank_0(address)
+        }
+    }
+
+    fn push_bg_pixels(&mut self) {
+        let in_window = self.fifo.in_window;
+        let fetcher = &mut self.fifo.fetcher;
+
+        let bg_priority = (fetcher.attributes & 0b1000_0000) != 0;
+        let x_flip = (fetcher.attributes & 0b0010_0000) != 0;
+        let palette_number = fetcher.attributes & 0b0000_0111;
+
+        for (i, pixel) in self.fifo.bg_fifo.iter_mut().enumerate() {
+            let bit = if x_flip { i } else { 7 - i };
+
+            let lo = (fetcher.data_lo >> bit) & 0b1;
+            let hi = (fetcher.data_hi >> bit) & 0b1;
+
+            *pixel = BgPixel {
+                color_id: (hi << 1) | lo,
+                palette_number,
+                bg_priority,
+                window: in_window,
+            };
+        }
+
+        self.fifo.bg_len = 8;
+
+        fetcher.tile_x = fetcher.tile_x.wrapping_add(1);
+        fetcher.step = FetcherStep::Tile;
+    }
+
+    fn fetch_sprite(&mut self, index: usize) {
+        let [y, oam_x, tile_index, flags] = self.fifo.sprites[index];
+        self.fifo.fetched_sprites |= 1 << index;
+
+        let penalty = if oam_x == 0 {
+            HIDDEN_SPRITE_FETCH_DOTS
+        } else {
+            // Wait for the background fetcher to finish the tile under the sprite,
+            // unless a previous sprite already did.
+            let in_window = self.fifo.in_window;
+            let offset = if in_window {
+                (self.fifo.x + 7).wrapping_sub(self.wx)
+            } else {
+                self.fifo.x.wrapping_add(self.scx)
+            };
+
+            let tile = (in_window, offset / 8);
+
+            let wait = if self.fifo.last_penalized_tile == Some(tile) {
+                0
+            } else {
+                self.fifo.last_penalized_tile = Some(tile);
+                (7 - (offset % 8)).saturating_sub(2)
+            };
+
+            SPRITE_FETCH_DOTS + wait
+        };
+
+        // The current dot is part of the penalty.
+        self.fifo.stall_dots = penalty - 1;
+
+        let obj_height = if self.lcdc.get_obj_size() { 16 } else { 8 };
+        let flags = SpriteObjectFlags::from_byte(flags);
+
+        let tile_index = if self.lcdc.get_obj_size() {
+            // 8x16
+            tile_index & !0b1
+        } else {
+            // 8x8
+            tile_index
+        } as u16;
+
+        let tile_address = {
+            // 16 bytes per tile.
+            let base_tile_address = 0x8000 + (tile_index * 16);
+
+            let tile_row = {
+                let row = self.ly.wrapping_sub(y.wrapping_sub(16)) & (obj_height - 1);
+
+                if flags.y_flip {
+                    obj_height - 1 - row
+                } else {
+                    row
+                }
+            } as u16;
+
+            base_tile_address + (tile_row * 2)
+        };
+
+        let (tile_data_lo, tile_data_hi) = if device_is_cgb!(self) && flags.in_bank_1 {
+            (
+                self.vram.read_bank_1(tile_address),
+                self.vram.read_bank_1(tile_address + 1),
+            )
+        } else {
+            (
+                self.vram.read_bank_0(tile_address),
+                self.vram.read_bank_0(tile_address + 1),
+            )
+        };
+
+        // Sprites partially to the left of the screen are cut off.
+        let hidden_pixels = if self.fifo.x == 0 && oam_x < 8 {
+            8 - oam_x as usize
+        } else {
+            0
+        };
+
+        let oam_priority = device_is_cgb!(self) && !self.opri;
+
+        for i in hidden_pixels..8 {
+            let color_id = {
+                let bit = if flags.x_flip { i } else { 7 - i };
+
+                let lo = (tile_data_lo >> bit) & 0b1;
+                let hi = (tile_data_hi >> bit) & 0b1;
+
+                (hi << 1) | lo
+            };
+
+            let slot = &mut self.fifo.obj_fifo[i - hidden_pixels];
+
+            // On the DMG, the first sprite to be fetched (lowest X) wins.
+            let replace = slot.color_id == 0 || (oam_priority && (index as u8) < slot.index);
+
+            if color_id == 0 || !replace {
+                continue;
+            }
+
+            *slot = ObjPixel {
+                color_id,
+                palette_number: flags.palette_number,
+                obp1_selected: flags.obp1_selected,
+                bg_priority: flags.bg_priority,
+                index: index as u8,
+            };
+        }
+    }
+
+    fn output_pixel(&mut self) {
+        let bg = self.fifo.pop_bg();
+        let mut obj = self.fifo.pop_obj();
+
+        if !self.lcdc.get_obj_enable() {
+            obj.color_id = 0;
+        }
+
+        let pixel = if device_is_cgb!(self) {
+            self.mix_pixel_cgb(bg, obj)
+        } else {
+            self.mix_pixel_dmg(bg, obj)
+        };
+
+        let index = (SCREEN_WIDTH * self.ly as usize) + self.fifo.x as usize;
+        self.internal_screen.pixels[index] = pixel;
+    }
+
+    fn mix_pixel_dmg(&self, bg: BgPixel, obj: ObjPixel) -> Color {
+        let (bg_color, bg_opaque) = if bg.window || self.lcdc.get_bg_enable() {
+            (self.dmg_color(bg.color_id, self.bgp), bg.color_id != 0)
+        } else {
+            (self.blank_color(), false)
+        };
+
+        if obj.color_id == 0 || (bg_opaque && obj.bg_priority) {
+            return bg_color;
+        }
+
+        let selected_palette = if obj.obp1_selected {
+            self.obp1
+        } else {
+            self.obp0
+        };
+
+        self.dmg_color(obj.color_id, selected_palette)
+    }
+
+    /// Warning: CGB model only.
+    fn mix_pixel_cgb(&self, bg: BgPixel, obj: ObjPixel) -> Color {
+        let cgb_mode = in_cgb_mode!(self);
+
+        let (bg_color, priority) = if !(bg.window || cgb_mode || self.lcdc.get_bg_enable()) {
+            (self.blank_color(), Priority::Object)
+        } else if cgb_mode {
+            let raw_color = self
+                .bg_cram
+                .get_color_rgb555(bg.palette_number, bg.color_id);
+
+            let priority = if bg.color_id == 0 || !self.lcdc.get_bg_enable() {
+                Priority::Object
+            } else if !bg.bg_priority {
+                Priority::OamAttribute
+            } else {
+                Priority::Background
+            };
+
+            (self.cgb_color(raw_color), priority)
+        } else {
+            let color_index = Color::apply_dmg_palette(bg.color_id, self.bgp);
+            let raw_color = self.bg_cram.get_color_rgb555(0, color_index);
+
+            let priority = if bg.color_id == 0 {
+                Priority::Object
+            } else {
+                Priority::Background
+            };
+
+            (self.cgb_color(raw_color), priority)
+        };
+
+        if obj.color_id == 0 {
+            return bg_color;
+        }
+
+        if cgb_mode {
+            if !(priority == Priority::Object
+                || (priority == Priority::OamAttribute && !obj.bg_priority))
+            {
+                return bg_color;
+            }
+
+            let raw_color = self
+                .obj_cram
+                .get_color_rgb555(obj.palette_number, obj.color_id);
+
+            self.cgb_color(raw_color)
+        } else {
+            if priority == Priority::Background && obj.bg_priority {
+                return bg_color;
+            }
+
+            let selected_palette = if obj.obp1_selected {
+                self.obp1
+            } else {
+                self.obp0
+            };
+
+            let color_index = Color::apply_dmg_palette(obj.color_id, selected_palette);
+            let raw_color = self.obj_cram.get_color_rgb555(0, color_index);
+
+            self.cgb_color(raw_color)
+        }
+    }
+}
+
+impl SaveState for BgPixel {
+    fn save_state(&self, writer: &mut StateWriter) {
+        writer.write_u8(self.color_id);
+        writer.write_u8(self.palette_number);
+        writer.write_bool(self.bg_priority);
+        writer.write_bool(self.window);
+    }
+
+    fn load_state(&mut self, reader: &mut StateReader) -> Result<(), SaveStateError> {
+        self.color_id = reader.read_u8()? & 0b11;
+        self.palette_number = reader.read_u8()? & 0b111;
+        self.bg_priority = reader.read_bool()?;
+        self.window = reader.read_bool()?;
+
+        Ok(())
+    }
+}
+
+impl SaveState for ObjPixel {
+    fn save_state(&self, writer: &mut StateWriter) {
+        writer.write_u8(self.color_id);
+        writer.write_u8(self.palette_number);
+        writer.write_bool(self.obp1_selected);
+        writer.write_bool(self.bg_priority);
+        writer.write_u8(self.index);
+    }
+
+    fn load_state(&mut self, reader: &mut StateReader) -> Result<(), SaveStateError> {
+        self.color_id = reader.read_u8()? & 0b11;
+        self.palette_number = reader.read_u8()? & 0b111;
+        self.obp1_selected = reader.read_bool()?;
+        self.bg_priority = reader.read_bool()?;
+        self.index = reader.read_u8()?;
+
+        Ok(())
+    }
+}
+
+impl SaveState for PixelFifo {
+    fn save_state(&self, writer: &mut StateWriter) {
+        writer.write_bool(self.active);
+        writer.write_u16(self.dots);
+        writer.write_u8(self.x);
+        writer.write_u8(self.startup_dots);
+        writer.write_u8(self.discard);
+        writer.write_u8(self.stall_dots);
+        writer.write_bool(self.wy_triggered);
+        writer.write_bool(self.in_window);
+
+        writer.write_u8(self.sprites.len() as u8);
+        for sprite in &self.sprites {
+            writer.write_raw(sprite);
+        }
+        writer.write_u16(self.fetched_sprites);
+
+        match self.last_penalized_tile {
+            None => writer.write_u8(0),
+            Some((in_window, tile)) => {
+                writer.write_u8(1 + in_window as u8);
+                writer.write_u8(tile);
+            }
+        }
+
+        let fetcher = &self.fetcher;
+        writer.write_u8(fetcher.step as u8);
+        writer.write_bool(fetcher.second_dot);
+        writer.write_u8(fetcher.tile_x);
+        writer.write_u8(fetcher.row);
+        writer.write_u8(fetcher.tile_index);
+        writer.write_u8(fetcher.attributes);
+        writer.write_u8(fetcher.data_lo);
+        writer.write_u8(fetcher.data_hi);
+
+        for pixel in &self.bg_fifo {
+            pixel.save_state(writer);
+        }
+        writer.write_u8(self.bg_len);
+
+        for pixel in &self.obj_fifo {
+            pixel.save_state(writer);
+        }
+    }
+
+    fn load_state(&mut self, reader: &mut StateReader) -> Result<(), SaveStateError> {
+        self.active = reader.read_bool()?;
+        self.dots = reader.read_u16()?;
+        self.x = reader.read_u8()?;
+        self.startup_dots = reader.read_u8()?;
+        self.discard = reader.read_u8()?;
+        self.stall_dots = reader.read_u8()?;
+        self.wy_triggered = reader.read_bool()?;
+        self.in_window = reader.read_bool()?;
+
+        if self.x as usize >= SCREEN_WIDTH {
+            return Err(SaveStateError::InvalidData("FIFO X position"));
+        }
+
+        let sprites = reader.read_u8()? as usize;
+
+        if sprites > MAX_SPRITES_PER_LINE {
+            return Err(SaveStateError::InvalidData("FIFO sprite count"));
+        }
+
+        self.sprites.clear();
+        for _ in 0..sprites {
+            self.sprites.push(reader.read_array()?);
+        }
+        self.fetched_sprites = reader.read_u16()?;
+
+        self.last_penalized_tile = match reader.read_u8()? {
+            0 => None,
+            tag @ (1 | 2) => Some((tag == 2, reader.read_u8()?)),
+            _ => return Err(SaveStateError::InvalidData("FIFO penalized tile")),
+        };
+
+        let fetcher = &mut self.fetcher;
+        fetcher.step = match reader.read_u8()? {
+            0 => FetcherStep::Tile,
+            1 => FetcherStep::DataLow,
+            2 => FetcherStep::DataHigh,
+            3 => FetcherStep::Push,
+            _ => return Err(SaveStateError::InvalidData("FIFO fetcher step")),
+        };
+        fetcher.second_dot = reader.read_bool()?;
+        fetcher.tile_x = reader.read_u8()?;
+        fetcher.row = reader.read_u8()? & 0b111;
+        fetcher.tile_index = reader.read_u8()?;
+        fetcher.attributes = reader.read_u8()?;
+        fetcher.data_lo = reader.read_u8()?;
+        fetcher.data_hi = reader.read_u8()?;
+
+        for pixel in &mut self.bg_fifo {
+            pixel.load_state(reader)?;
+        }
+        self.bg_len = reader.read_u8()?;
+
+        if self.bg_len > 8 {
+            return Err(SaveStateError::InvalidData("FIFO length"));
+        }
+
+        for pixel in &mut self.obj_fifo {
+            pixel.load_state(reader)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        DeviceModel,
+        components::ppu::lcd_status::StatusMode,
+        constants::{SCREEN_PIXELS_SIZE, ScreenPixels},
+        utils::events::Events,
+    };
+
+    const DOTS_PER_FRAME: usize = 70224;
+
+    /// Tiles with distinct rows, a tile map using all of them, a window and a few sprites.
+    fn test_ppu(device_model: DeviceModel, renderer: Renderer) -> Ppu {
+        let mut ppu = Ppu::with_device_model(device_model);
+        ppu.set_renderer(renderer);
+
+        for address in 0x8000..0x9800 {
+            let value = (address as u8).wrapping_mul(37) ^ (address >> 4) as u8;
+            ppu.vram.write(address, value);
+        }
+
+        for address in 0x9800..0xA000 {
+            ppu.vram.write(address, (address as u8).wrapping_mul(7));
+        }
+
+        if device_model.is_cgb() {
+            ppu.vram.write_vbk(1);
+
+            for address in 0x8000..0x9800 {
+                ppu.vram
+                    .write(address, (address as u8).wrapping_mul(59) ^ 0xA5);
+            }
+
+            // Tile attributes.
+            for address in 0x9800..0xA000 {
+                ppu.vram.write(address, (address as u8).wrapping_mul(3));
+            }
+
+            ppu.vram.write_vbk(0);
+        }
+
+        // The scanline renderer lets a lower priority sprite show through a higher priority
+        // sprite hidden behind the background, so overlapping sprites don't use that flag.
+        let sprites: [[u8; 4]; 7] = [
+            [16, 8, 1, 0b0000_0000],
+            [20, 12, 2, 0b0010_0000],
+            [40, 4, 3, 0b0001_0000],
+            [40, 80, 4, 0b0100_0000],
+            [40, 80, 5, 0b0000_0000],
+            [60, 120, 6, 0b1000_1001],
+            [100, 160, 7, 0b0001_1010],
+        ];
+
+        for (i, sprite) in sprites.iter().enumerate() {
+            for (j, value) in sprite.iter().enumerate() {
+                ppu.oam.write(0xFE00 + (i * 4 + j) as u16, *value);
+            }
+        }
+
+        for i in 0..64 {
+            ppu.bg_cram.write(i, i.wrapping_mul(11));
+            ppu.obj_cram.write(i, i.wrapping_mul(13));
+        }
+
+        ppu.write_scx(3);
+        ppu.write_scy(5);
+        ppu.write_bgp(0b1110_0100);
+        ppu.write_obp0(0b1101_0010);
+        ppu.write_obp1(0b0011_1001);
+        ppu.write_wy(60);
+        ppu.write_wx(87);
+        ppu.write_lcdc(0b1111_0011);
+
+        ppu
+    }
+
+    fn run_frame(ppu: &mut Ppu) -> Box<ScreenPixels> {
+        let mut events = Events::empty();
+
+        for _ in 0..DOTS_PER_FRAME {
+            ppu.tick(&mut events);
+        }
+
+        let mut frame: Box<ScreenPixels> = vec![0; SCREEN_PIXELS_SIZE]
+            .into_boxed_slice()
+            .try_into()
+            .unwrap();
+        ppu.screen().draw_into_frame_rgba8888(&mut frame);
+
+        frame
+    }
+
+    fn mode_3_length(ppu: &mut Ppu) -> usize {
+        let mut events = Events::empty();
+
+        while ppu.mode != StatusMode::Drawing {
+            ppu.tick(&mut events);
+        }
+
+        let mut dots = 0;
+
+        while ppu.mode == StatusMode::Drawing {
+            ppu.tick(&mut events);
+            dots += 1;
+        }
+
+        dots
+    }
+
+    #[test]
+    fn test_same_output_as_scanline() {
+        for device_model in [DeviceModel::Dmg, DeviceModel::Cgb] {
+            let mut scanline = test_ppu(device_model, Renderer::Scanline);
+            let mut fifo = test_ppu(device_model, Renderer::Fifo);
+
+            for _ in 0..2 {
+                assert!(run_frame(&mut scanline) == run_frame(&mut fifo));
+            }
+        }
+    }
+
+    #[test]
+    fn test_mode_3_length() {
+        let mut ppu = Ppu::with_device_model(DeviceModel::Dmg);
+        ppu.set_renderer(Renderer::Fifo);
+        ppu.write_lcdc(0b1000_0001);
+
+        assert_eq!(mode_3_length(&mut ppu), 172);
+
+        ppu.write_scx(3);
+        assert_eq!(mode_3_length(&mut ppu), 175);
+
+        // A sprite at X = 0 (OAM X = 8).
+        ppu.write_scx(0);
+        ppu.write_lcdc(0b1000_0011);
+        ppu.oam.write(0xFE00, 16 + 2);
+        ppu.oam.write(0xFE01, 8);
+
+        assert_eq!(mode_3_length(&mut ppu), 172 + 11);
+
+        // The window starts in the middle of the line.
+        ppu.oam.write(0xFE00, 0);
+        ppu.write_wy(0);
+        ppu.write_wx(87);
+        ppu.write_lcdc(0b1010_0001);
+
+        assert_eq!(mode_3_length(&mut ppu), 172 + 6);
+    }
+
+    #[test]
+    fn test_mid_scanline_write() {
+        let mut ppu = Ppu::with_device_model(DeviceModel::Dmg);
+        ppu.set_renderer(Renderer::Fifo);
+
+        // Every tile is filled with color 3.
+        for address in 0x8000..0x9000 {
+            ppu.vram.write(address, 0xFF);
+        }
+
+        ppu.write_bgp(0b1100_0000);
+        ppu.write_lcdc(0b1001_0001);
+
+        let mut events = Events::empty();
+
+        while ppu.mode != StatusMode::Drawing {
+            ppu.tick(&mut events);
+        }
+
+        for _ in 0..92 {
+            ppu.tick(&mut events);
+        }
+
+        ppu.write_bgp(0b0000_0000);
+
+        while ppu.ly == 0 {
+            ppu.tick(&mut events);
+        }
+
+        let line = &ppu.internal_screen.pixels[..SCREEN_WIDTH];
+
+        let first = line[0].to_rgb555();
+        let last = line[SCREEN_WIDTH - 1].to_rgb555();
+
+        assert_eq!(first, Color::from_dmg_color_id(3).to_rgb555());
+        assert_eq!(last, Color::from_dmg_color_id(0).to_rgb555());
+    }
+
+    #[test]
+    fn test_scanline_mode_3_length() {
+        let mut ppu = Ppu::with_device_model(DeviceModel::Dmg);
+        ppu.write_lcdc(0b1000_0011);
+        ppu.write_scx(3);
+        ppu.oam.write(0xFE00, 16);
+        ppu.oam.write(0xFE01, 8);
+
+        assert_eq!(mode_3_length(&mut ppu), 172);
+    }
+}
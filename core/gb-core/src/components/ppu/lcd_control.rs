@@ -1,7 +1,6 @@
 use bitflags::bitflags;
 
-use super::{Ppu, lcd_status::StatusMode};
-use crate::utils::{color::Color, macros::device_is_cgb};
+use super::{Ppu, fifo::PixelFifo, lcd_status::StatusMode};
 
 bitflags!(
     /// FF40 — LCDC: LCD control
@@ -121,12 +120,10 @@ impl Ppu {
         if lcd_enable && !new_lcd_enable {
             self.mode = StatusMode::Hblank;
             self.ly = 0;
+            self.fifo = PixelFifo::default();
 
-            if device_is_cgb!(self) {
-                self.internal_screen.pixels.fill(Color::CGB_SYSTEM_DEFAULT);
-            } else {
-                self.internal_screen.pixels.fill(Color::DMG_SYSTEM_DEFAULT);
-            }
+            let blank_color = self.blank_color();
+            self.internal_screen.pixels.fill(blank_color);
         }
 
         self.lcdc = new_lcdc;
@@ -13,7 +13,7 @@ pub enum StatusMode {
 
 impl StatusMode {
     /// Warning: using min length Drawing mode and max length H-Blank mode.
-    /// Mode length extensions are not supported yet.
+    /// Only the FIFO renderer extends Drawing (and shortens H-Blank) as needed.
     #[must_use]
     pub const fn dots(self) -> usize {
         match self {
@@ -1,6 +1,7 @@
 use arrayvec::ArrayVec;
 
 use super::{Ppu, lcd_status::StatusMode, sprite::SpriteObject};
+use crate::save_state::{SaveState, SaveStateError, StateReader, StateWriter};
 
 const OAM_SIZE: usize = 0xA0;
 
@@ -55,6 +56,16 @@ impl Oam {
         &self.sprite_buffer
     }
 
+    /// Returns the raw OAM entries of the sprites in the line, in OAM order.
+    pub fn get_raw_sprites_in_line(&self, ly: u8, obj_height: u8) -> ArrayVec<[u8; 4], 10> {
+        self.data
+            .chunks_exact(4)
+            .filter(|chunk| ly.wrapping_sub(chunk[0].wrapping_sub(16)) < obj_height)
+            .map(|chunk| [chunk[0], chunk[1], chunk[2], chunk[3]])
+            .take(10)
+            .collect()
+    }
+
     fn update_sprite_buffer(&mut self, ly: u8, obj_height: u8) {
         self.sprite_buffer = self
             .data
@@ -102,3 +113,14 @@ impl Ppu {
         self.oam.write(address, value);
     }
 }
+
+impl SaveState for Oam {
+    fn save_state(&self, writer: &mut StateWriter) {
+        writer.write_bytes(&self.data);
+    }
+
+    fn load_state(&mut self, reader: &mut StateReader) -> Result<(), SaveStateError> {
+        // The sprite buffer is rebuilt on every line.
+        reader.read_bytes_into(&mut self.data)
+    }
+}
@@ -1,3 +1,5 @@
+use crate::save_state::{SaveState, SaveStateError, StateReader, StateWriter};
+
 #[derive(Debug, Default, PartialEq, Eq)]
 enum Status {
     #[default]
@@ -61,6 +63,11 @@ impl OamDma {
         }
     }
 
+    /// No transfer is running or about to start.
+    pub fn is_idle(&self) -> bool {
+        self.status == Status::Idle
+    }
+
     pub fn read(&self) -> u8 {
         self.dma
     }
@@ -170,6 +177,64 @@ impl OamDma {
     }
 }
 
+impl SaveState for OamDma {
+    fn save_state(&self, writer: &mut StateWriter) {
+        writer.write_u8(self.dma);
+
+        let (status, fields) = match self.status {
+            Status::Idle => (0, [0, 0, 0]),
+            Status::Requested { base_source } => (1, [base_source, 0, 0]),
+            Status::Active {
+                base_source,
+                offset,
+            } => (2, [base_source, offset, 0]),
+            Status::Restarting {
+                next_base_source,
+                current_base_source,
+                current_offset,
+            } => (3, [next_base_source, current_base_source, current_offset]),
+            Status::ActiveFirstStep { base_source } => (4, [base_source, 0, 0]),
+            Status::RestartedFirstStep { base_source } => (5, [base_source, 0, 0]),
+        };
+
+        writer.write_u8(status);
+
+        for field in fields {
+            writer.write_u16(field);
+        }
+    }
+
+    fn load_state(&mut self, reader: &mut StateReader) -> Result<(), SaveStateError> {
+        self.dma = reader.read_u8()?;
+
+        let status = reader.read_u8()?;
+        let [first, second, third] = [reader.read_u16()?, reader.read_u16()?, reader.read_u16()?];
+
+        self.status = match status {
+            0 => Status::Idle,
+            1 => Status::Requested { base_source: first },
+            2 => {
+                Status::Active {
+                    base_source: first,
+                    offset: second,
+                }
+            }
+            3 => {
+                Status::Restarting {
+                    next_base_source: first,
+                    current_base_source: second,
+                    current_offset: third,
+                }
+            }
+            4 => Status::ActiveFirstStep { base_source: first },
+            5 => Status::RestartedFirstStep { base_source: first },
+            _ => return Err(SaveStateError::InvalidData("OAM DMA status")),
+        };
+
+        Ok(())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -0,0 +1,31 @@
+use std::fmt;
+
+/// How the PPU turns the VRAM contents into pixels.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Renderer {
+    /// Draws the whole line when mode 3 ends, and mode 3 has a fixed length.
+    ///
+    /// Faster, but mid-scanline register writes are not visible.
+    #[default]
+    Scanline,
+
+    /// Dot-based background/sprite fetcher and pixel FIFO.
+    ///
+    /// The length of mode 3 depends on SCX, the window and the sprites in the line.
+    Fifo,
+}
+
+impl Renderer {
+    pub const ALL_CASES: [Self; 2] = [Self::Scanline, Self::Fifo];
+}
+
+impl fmt::Display for Renderer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::Scanline => "scanline",
+            Self::Fifo => "fifo",
+        };
+
+        write!(f, "{name}")
+    }
+}
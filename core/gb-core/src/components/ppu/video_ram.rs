@@ -2,6 +2,7 @@ use super::{Ppu, lcd_status::StatusMode};
 use crate::{
     DeviceModel,
     constants::{TILE_DATA_FRAME_WIDTH_CGB, TILES_PER_LINE, TileDataFrameCgb},
+    save_state::{SaveState, SaveStateError, StateReader, StateWriter},
     utils::{
         color::Color,
         macros::{device_is_cgb, in_cgb_mode},
@@ -41,6 +42,14 @@ impl VideoRam {
         }
     }
 
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    pub fn data_mut(&mut self) -> &mut [u8] {
+        &mut self.data
+    }
+
     pub fn set_cgb_mode(&mut self, value: bool) {
         self.cgb_mode = value;
     }
@@ -177,6 +186,22 @@ impl Ppu {
     }
 }
 
+impl SaveState for VideoRam {
+    fn save_state(&self, writer: &mut StateWriter) {
+        writer.write_bytes(&self.data);
+        writer.write_u8(self.vbk);
+        writer.write_bool(self.cgb_mode);
+    }
+
+    fn load_state(&mut self, reader: &mut StateReader) -> Result<(), SaveStateError> {
+        reader.read_bytes_into(&mut self.data)?;
+        self.vbk = reader.read_u8()?;
+        self.cgb_mode = reader.read_bool()?;
+
+        Ok(())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1,4 +1,8 @@
-use crate::{constants::DeviceModel, utils::macros::in_cgb_mode};
+use crate::{
+    constants::DeviceModel,
+    save_state::{SaveState, SaveStateError, StateReader, StateWriter},
+    utils::macros::in_cgb_mode,
+};
 
 #[derive(Debug, Default, PartialEq, Eq)]
 pub enum DmaMode {
@@ -185,6 +189,14 @@ impl VramDma {
         Some(base_offset..(base_offset + length))
     }
 
+    /// No transfer will happen before the PPU enters H-Blank again.
+    pub fn is_waiting(&self) -> bool {
+        matches!(
+            self.mode,
+            DmaMode::Idle | DmaMode::Hblank { active: false, .. }
+        )
+    }
+
     pub fn resume_hdma(&mut self) {
         if let DmaMode::Hblank {
             active: false,
@@ -199,6 +211,63 @@ impl VramDma {
     }
 }
 
+impl SaveState for VramDma {
+    fn save_state(&self, writer: &mut StateWriter) {
+        writer.write_u8(self.steps);
+
+        writer.write_u8(self.hdma1);
+        writer.write_u8(self.hdma2);
+        writer.write_u8(self.hdma3);
+        writer.write_u8(self.hdma4);
+        writer.write_u8(self.hdma5);
+
+        let (mode, active, remaining_steps) = match self.mode {
+            DmaMode::Idle => (0, false, 0),
+            DmaMode::General => (1, false, 0),
+            DmaMode::Hblank {
+                active,
+                remaining_steps,
+            } => (2, active, remaining_steps),
+        };
+
+        writer.write_u8(mode);
+        writer.write_bool(active);
+        writer.write_u8(remaining_steps);
+
+        writer.write_bool(self.cgb_mode);
+    }
+
+    fn load_state(&mut self, reader: &mut StateReader) -> Result<(), SaveStateError> {
+        self.steps = reader.read_u8()?;
+
+        self.hdma1 = reader.read_u8()?;
+        self.hdma2 = reader.read_u8()?;
+        self.hdma3 = reader.read_u8()?;
+        self.hdma4 = reader.read_u8()?;
+        self.hdma5 = reader.read_u8()?;
+
+        let mode = reader.read_u8()?;
+        let active = reader.read_bool()?;
+        let remaining_steps = reader.read_u8()?;
+
+        self.mode = match mode {
+            0 => DmaMode::Idle,
+            1 => DmaMode::General,
+            2 => {
+                DmaMode::Hblank {
+                    active,
+                    remaining_steps,
+                }
+            }
+            _ => return Err(SaveStateError::InvalidData("VRAM DMA mode")),
+        };
+
+        self.cgb_mode = reader.read_bool()?;
+
+        Ok(())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
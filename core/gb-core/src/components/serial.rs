@@ -1,10 +1,13 @@
-// TODO: callbacks and stuff.
-
 use std::sync::mpsc;
 
-use bitflags::{Flags, bitflags};
+use bitflags::bitflags;
 
-use crate::{constants::DeviceModel, utils::macros::in_cgb_mode};
+use self::link::LinkPort;
+use crate::{
+    constants::DeviceModel,
+    save_state::{SaveState, SaveStateError, StateReader, StateWriter},
+    utils::macros::in_cgb_mode,
+};
 
 #[derive(Debug, Default)]
 pub struct Serial {
@@ -16,9 +19,18 @@ pub struct Serial {
     cgb_mode: bool,
     device_model: DeviceModel,
 
+    remaining_bits: u8,
+    cycles: u16, // Cycles since the last clock pulse (internal clock)
+    outgoing: u8,
+
     sender: Option<mpsc::Sender<u8>>,
+    link: Option<Box<dyn LinkPort>>,
 }
 
+/// 8192 Hz, or 262144 Hz with the CGB fast clock. Both are doubled in double speed mode.
+const CYCLES_PER_BIT: u16 = 512;
+const CYCLES_PER_BIT_FAST: u16 = 16;
+
 bitflags! {
     #[derive(Debug, Default)]
     struct Control: u8 {
@@ -49,6 +61,14 @@ impl Serial {
 
     pub fn write_sb(&mut self, value: u8) {
         self.sb = value;
+
+        if self.waiting_for_external_clock() {
+            self.outgoing = value;
+
+            if let Some(link) = self.link.as_mut() {
+                link.set_ready(Some(value));
+            }
+        }
     }
 
     #[must_use]
@@ -69,24 +89,211 @@ impl Serial {
         };
         self.sc = Control::from_bits_truncate(value & mask);
 
-        // (SC & 0x81) == 0x81
-        if self
-            .sc
-            .contains(Control::TRANSFER_ENABLE | Control::CLOCK_SELECT)
-        {
-            if let Some(sender) = self.sender.as_mut() {
-                sender.send(self.sb).unwrap();
+        self.remaining_bits = if self.sc.contains(Control::TRANSFER_ENABLE) {
+            8
+        } else {
+            0
+        };
+        self.cycles = 0;
+        self.outgoing = self.sb;
+
+        if let Some(link) = self.link.as_mut() {
+            let ready = self.sc.contains(Control::TRANSFER_ENABLE)
+                && !self.sc.contains(Control::CLOCK_SELECT);
+
+            link.set_ready(ready.then_some(self.sb));
+        }
+    }
+
+    /// Called every M-cycle, `cycles` are the elapsed single speed T-cycles for the link.
+    ///
+    /// The transfer itself is clocked by the CPU, so it's twice as fast in double speed mode.
+    pub fn tick(&mut self, cycles: u32) {
+        if let Some(link) = self.link.as_mut() {
+            link.tick(cycles);
+        }
+
+        if self.remaining_bits == 0 {
+            return;
+        }
+
+        let received = if self.sc.contains(Control::CLOCK_SELECT) {
+            let cycles_per_bit = if self.sc.contains(Control::CLOCK_SPEED) {
+                CYCLES_PER_BIT_FAST
             } else {
-                // Print serial output for tests.
-                // print!("{}", self.sb as char);
+                CYCLES_PER_BIT
+            };
+
+            self.cycles += 4;
+
+            if self.cycles < cycles_per_bit {
+                return;
             }
 
-            self.irq = true;
-            self.sc.clear();
+            self.cycles -= cycles_per_bit;
+
+            let bit = self.sb & 0x80 != 0;
+
+            // Nothing connected: the line is pulled up.
+            self.link.as_mut().is_none_or(|link| link.transfer_bit(bit))
+        } else {
+            let Some(received) = self.link.as_mut().and_then(|link| link.receive_bit()) else {
+                return;
+            };
+
+            received
+        };
+
+        self.sb = (self.sb << 1) | received as u8;
+        self.remaining_bits -= 1;
+
+        if self.remaining_bits == 0 {
+            self.finish_transfer();
         }
     }
 
+    fn finish_transfer(&mut self) {
+        if let Some(sender) = self.sender.as_mut() {
+            // Only fails if the receiver was dropped, in which case nobody is listening.
+            let _ = sender.send(self.outgoing);
+        }
+
+        if let Some(link) = self.link.as_mut()
+            && !self.sc.contains(Control::CLOCK_SELECT)
+        {
+            link.set_ready(None);
+        }
+
+        self.irq = true;
+        self.sc.remove(Control::TRANSFER_ENABLE);
+    }
+
+    /// Nothing happens when ticking, so any amount of time can pass at once.
+    ///
+    /// Without a link, an externally clocked transfer never progresses.
+    #[must_use]
+    pub fn is_idle(&self) -> bool {
+        self.link.is_none()
+            && (self.remaining_bits == 0 || !self.sc.contains(Control::CLOCK_SELECT))
+    }
+
+    fn waiting_for_external_clock(&self) -> bool {
+        self.remaining_bits == 8 && !self.sc.contains(Control::CLOCK_SELECT)
+    }
+
     pub fn add_sender(&mut self, sender: mpsc::Sender<u8>) {
         self.sender = Some(sender);
     }
+
+    pub fn take_sender(&mut self) -> Option<mpsc::Sender<u8>> {
+        self.sender.take()
+    }
+
+    pub fn connect_link(&mut self, mut link: Box<dyn LinkPort>) {
+        link.set_ready(self.waiting_for_external_clock().then_some(self.sb));
+
+        self.link = Some(link);
+    }
+
+    /// See [`LinkPort::poll`], always ready without a link.
+    pub fn poll_link(&mut self) -> bool {
+        self.link.as_mut().is_none_or(|link| link.poll())
+    }
+
+    pub fn take_link(&mut self) -> Option<Box<dyn LinkPort>> {
+        let mut link = self.link.take()?;
+        link.set_ready(None);
+
+        Some(link)
+    }
+}
+
+impl SaveState for Serial {
+    fn save_state(&self, writer: &mut StateWriter) {
+        writer.write_u8(self.sb);
+        writer.write_u8(self.sc.bits());
+        writer.write_bool(self.irq);
+        writer.write_bool(self.cgb_mode);
+        writer.write_u8(self.remaining_bits);
+        writer.write_u16(self.cycles);
+        writer.write_u8(self.outgoing);
+    }
+
+    fn load_state(&mut self, reader: &mut StateReader) -> Result<(), SaveStateError> {
+        self.sb = reader.read_u8()?;
+        self.sc = Control::from_bits_truncate(reader.read_u8()?);
+        self.irq = reader.read_bool()?;
+        self.cgb_mode = reader.read_bool()?;
+        self.remaining_bits = reader.read_u8()?;
+        self.cycles = reader.read_u16()?;
+        self.outgoing = reader.read_u8()?;
+
+        if self.remaining_bits > 8 || self.cycles >= CYCLES_PER_BIT {
+            return Err(SaveStateError::InvalidData("serial transfer"));
+        }
+
+        Ok(())
+    }
+}
+
+pub mod link;
+pub mod printer;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{GameBoy, components::memory::MemoryInterface as _, test_rom::TestRom};
+
+    fn ticks_until_irq(serial: &mut Serial) -> usize {
+        (1..=4096)
+            .find(|_| {
+                serial.tick(4);
+                serial.irq
+            })
+            .unwrap()
+    }
+
+    #[test]
+    fn test_internal_clock_speed() {
+        let mut serial = Serial::with_device_model(DeviceModel::Cgb);
+
+        serial.write_sb(0x42);
+        serial.write_sc(0x81);
+        assert_eq!(ticks_until_irq(&mut serial), 1024);
+        assert_eq!(serial.read_sb(), 0xFF);
+        assert_eq!(serial.read_sc() & 0x80, 0);
+
+        serial.irq = false;
+        serial.write_sc(0x83);
+        assert_eq!(ticks_until_irq(&mut serial), 32);
+
+        // No fast clock in DMG mode.
+        serial.irq = false;
+        serial.set_cgb_mode(false);
+        serial.write_sc(0x83);
+        assert_eq!(ticks_until_irq(&mut serial), 1024);
+    }
+
+    #[test]
+    fn test_serial_without_partner() {
+        let mut master = GameBoy::new(DeviceModel::Dmg);
+        let mut slave = GameBoy::new(DeviceModel::Dmg);
+
+        master
+            .load(None, TestRom::serial(0x42, 0x81).build())
+            .unwrap();
+        slave
+            .load(None, TestRom::serial(0x99, 0x80).build())
+            .unwrap();
+
+        master.run_frame();
+        slave.run_frame();
+
+        // The line is pulled up, and the external clock never comes.
+        assert_eq!(master.memory().read(0xFF01), 0xFF);
+        assert_ne!(master.memory().read(0xFF0F) & 0b1000, 0);
+
+        assert_eq!(slave.memory().read(0xFF01), 0x99);
+        assert_eq!(slave.memory().read(0xFF0F) & 0b1000, 0);
+    }
 }
@@ -0,0 +1,221 @@
+use std::{
+    collections::VecDeque,
+    fmt,
+    sync::{Arc, Mutex},
+};
+
+/// One end of a link cable, as seen from the serial port of a Game Boy.
+///
+/// The side with the internal clock drives the transfer by calling [`LinkPort::transfer_bit`]
+/// once per clock pulse. The side with the external clock announces that it is waiting with
+/// [`LinkPort::set_ready`] and polls the pulses with [`LinkPort::receive_bit`].
+pub trait LinkPort: fmt::Debug + Send + Sync {
+    /// Sends `bit` on a clock pulse generated by this side and returns the bit sent back.
+    ///
+    /// A partner that is not waiting for a transfer answers with `true` (the line is pulled up).
+    fn transfer_bit(&mut self, bit: bool) -> bool;
+
+    /// Announces that this side is waiting for the partner's clock with `data` in SB,
+    /// or that it stopped waiting (`None`).
+    fn set_ready(&mut self, data: Option<u8>);
+
+    /// Returns the next bit clocked in by the partner, if any.
+    fn receive_bit(&mut self) -> Option<bool>;
+
+    /// Called every M-cycle with the elapsed single speed T-cycles.
+    ///
+    /// Transports that have to stay in sync with a remote partner can block here,
+    /// see [`LinkPort::poll`].
+    fn tick(&mut self, _cycles: u32) {}
+
+    /// Called between frames, also while the emulation is paused.
+    ///
+    /// Returns whether the partner is far enough along for the next frame to run without
+    /// blocking in [`LinkPort::tick`]. Remote transports also keep the connection alive here.
+    fn poll(&mut self) -> bool {
+        true
+    }
+}
+
+/// The shift register of a side that is waiting for the partner's clock.
+#[derive(Debug, Default)]
+struct ShiftRegister {
+    ready: bool,
+    data: u8,
+    remaining_bits: u8,
+}
+
+impl ShiftRegister {
+    fn new(data: Option<u8>) -> Self {
+        Self {
+            ready: data.is_some(),
+            data: data.unwrap_or_default(),
+            remaining_bits: 8,
+        }
+    }
+
+    /// Shifts in a bit sent by the partner and returns the bit that was shifted out,
+    /// or `None` if this side was not waiting for a transfer.
+    fn clock(&mut self, bit: bool) -> Option<bool> {
+        if !self.ready {
+            return None;
+        }
+
+        let sent = self.data & 0x80 != 0;
+
+        self.data = (self.data << 1) | bit as u8;
+        self.remaining_bits -= 1;
+
+        if self.remaining_bits == 0 {
+            self.ready = false;
+        }
+
+        Some(sent)
+    }
+}
+
+#[derive(Debug, Default)]
+struct Side {
+    /// What SB will look like once the pending bits are shifted in.
+    register: ShiftRegister,
+    pending: VecDeque<bool>,
+}
+
+impl Side {
+    fn new(data: Option<u8>) -> Self {
+        Self {
+            register: ShiftRegister::new(data),
+            pending: VecDeque::new(),
+        }
+    }
+
+    fn clock(&mut self, bit: bool) -> Option<bool> {
+        let sent = self.register.clock(bit)?;
+        self.pending.push_back(bit);
+
+        Some(sent)
+    }
+}
+
+/// A link cable between two Game Boys running in the same process.
+///
+/// The master shifts a copy of the partner's SB, so the exchanged bits don't depend on how far
+/// apart both cores are, as long as they are run in lockstep (see [`crate::GameBoy::run_linked_frame`]).
+pub struct LinkCable {
+    sides: Arc<Mutex<[Side; 2]>>,
+    index: usize,
+}
+
+impl LinkCable {
+    /// Creates both ends of the cable.
+    #[must_use]
+    pub fn pair() -> (Self, Self) {
+        let sides = Arc::new(Mutex::new([Side::default(), Side::default()]));
+
+        let first = Self {
+            sides: sides.clone(),
+            index: 0,
+        };
+        let second = Self { sides, index: 1 };
+
+        (first, second)
+    }
+}
+
+impl fmt::Debug for LinkCable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LinkCable")
+            .field("index", &self.index)
+            .finish_non_exhaustive()
+    }
+}
+
+impl LinkPort for LinkCable {
+    fn transfer_bit(&mut self, bit: bool) -> bool {
+        // Nobody waiting on the other side: the line is pulled up.
+        self.sides.lock().unwrap()[1 - self.index]
+            .clock(bit)
+            .unwrap_or(true)
+    }
+
+    fn set_ready(&mut self, data: Option<u8>) {
+        self.sides.lock().unwrap()[self.index] = Side::new(data);
+    }
+
+    fn receive_bit(&mut self) -> Option<bool> {
+        self.sides.lock().unwrap()[self.index].pending.pop_front()
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub mod tcp;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        GameBoy,
+        components::memory::MemoryInterface as _,
+        constants::DeviceModel,
+        test_rom::TestRom,
+    };
+
+    fn transfer_byte(port: &mut impl LinkPort, mut data: u8) -> u8 {
+        for _ in 0..8 {
+            let received = port.transfer_bit(data & 0x80 != 0);
+            data = (data << 1) | received as u8;
+        }
+
+        data
+    }
+
+    #[test]
+    fn test_partner_not_ready() {
+        let (mut master, _slave) = LinkCable::pair();
+
+        assert_eq!(transfer_byte(&mut master, 0x42), 0xFF);
+    }
+
+    #[test]
+    fn test_exchange() {
+        let (mut master, mut slave) = LinkCable::pair();
+
+        slave.set_ready(Some(0x99));
+        assert_eq!(transfer_byte(&mut master, 0x42), 0x99);
+
+        let mut received = 0;
+        while let Some(bit) = slave.receive_bit() {
+            received = (received << 1) | bit as u8;
+        }
+        assert_eq!(received, 0x42);
+
+        // The slave has to get ready again for the next byte.
+        assert_eq!(transfer_byte(&mut master, 0x42), 0xFF);
+        assert_eq!(slave.receive_bit(), None);
+    }
+
+    #[test]
+    fn test_link_cable_exchange() {
+        for (device_model, sc) in [(DeviceModel::Dmg, 0x81), (DeviceModel::Cgb, 0x83)] {
+            let mut master = GameBoy::new(device_model);
+            let mut slave = GameBoy::new(device_model);
+
+            master
+                .load(None, TestRom::serial(0x42, sc).build())
+                .unwrap();
+            slave
+                .load(None, TestRom::serial(0x99, 0x80).build())
+                .unwrap();
+            master.connect_link_cable(&mut slave);
+
+            master.run_linked_frame(&mut slave);
+
+            assert_eq!(master.memory().read(0xFF01), 0x99);
+            assert_eq!(slave.memory().read(0xFF01), 0x42);
+
+            // The serial interrupt is requested on both sides.
+            assert_ne!(master.memory().read(0xFF0F) & 0b1000, 0);
+            assert_ne!(slave.memory().read(0xFF0F) & 0b1000, 0);
+        }
+    }
+}
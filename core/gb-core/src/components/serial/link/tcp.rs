@@ -0,0 +1,506 @@
+//! Link cable over TCP.
+//!
+//! Both sides count the T-cycles since the connection was made, and every message carries the
+//! time it was sent at. A message only takes effect on the other side `LOOKAHEAD` cycles later,
+//! and a side never runs more than `LOOKAHEAD` cycles ahead of what it has heard from its
+//! partner. This way both sides see every event at the same emulated time regardless of the
+//! network latency, and either of them can be the clock master.
+//!
+//! A side that is paused keeps sending its time every [`KEEP_ALIVE_INTERVAL`], so its partner
+//! waits for it instead of dropping the connection.
+//!
+//! Every message is 11 bytes long:
+//!
+//! | Offset | Size | Field                                              |
+//! | ------ | ---- | -------------------------------------------------- |
+//! | 0x00   | 1    | 0: sync, 1: ready, 2: clock                        |
+//! | 0x01   | 8    | Time, little endian                                |
+//! | 0x09   | 1    | Ready: 1 if waiting. Clock: the bit                |
+//! | 0x0A   | 1    | Ready: SB. Clock: 1 if the partner was waiting     |
+
+use std::{
+    collections::VecDeque,
+    fmt,
+    io::{self, BufReader, BufWriter, Read as _, Write as _},
+    net::{Shutdown, TcpListener, TcpStream, ToSocketAddrs},
+    sync::{
+        Mutex,
+        mpsc::{self, TryRecvError},
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use super::{LinkPort, ShiftRegister, Side};
+
+const MAGIC: [u8; 4] = *b"GBLK";
+const PROTOCOL_VERSION: u8 = 1;
+
+/// One frame.
+const LOOKAHEAD: u64 = 70224;
+const SYNC_INTERVAL: u64 = LOOKAHEAD / 4;
+
+/// How long to wait for the partner before considering it gone.
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How often a side that is not running lets its partner know that it's still there.
+const KEEP_ALIVE_INTERVAL: Duration = Duration::from_secs(1);
+
+const MESSAGE_SIZE: usize = 11;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Message {
+    Sync,
+    Ready(Option<u8>),
+    Clock { bit: bool, accepted: bool },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Packet {
+    time: u64,
+    message: Message,
+}
+
+impl Packet {
+    fn encode(self) -> [u8; MESSAGE_SIZE] {
+        let (tag, a, b) = match self.message {
+            Message::Sync => (0, 0, 0),
+            Message::Ready(data) => (1, data.is_some() as u8, data.unwrap_or_default()),
+            Message::Clock { bit, accepted } => (2, bit as u8, accepted as u8),
+        };
+
+        let mut buffer = [0; MESSAGE_SIZE];
+
+        buffer[0x00] = tag;
+        buffer[0x01..0x09].copy_from_slice(&self.time.to_le_bytes());
+        buffer[0x09] = a;
+        buffer[0x0A] = b;
+
+        buffer
+    }
+
+    fn decode(buffer: &[u8; MESSAGE_SIZE]) -> Option<Self> {
+        let time = u64::from_le_bytes(buffer[0x01..0x09].try_into().unwrap());
+        let (a, b) = (buffer[0x09], buffer[0x0A]);
+
+        let message = match buffer[0x00] {
+            0 => Message::Sync,
+            1 => Message::Ready((a != 0).then_some(b)),
+            2 => {
+                Message::Clock {
+                    bit: a != 0,
+                    accepted: b != 0,
+                }
+            }
+
+            _ => return None,
+        };
+
+        Some(Self { time, message })
+    }
+}
+
+/// A link cable to a Game Boy running in another process, possibly on another machine.
+///
+/// Each side blocks in [`LinkPort::tick`] when it gets too far ahead of the other one.
+/// Frontends should only run a frame once [`LinkPort::poll`] returns `true`, so that it rarely
+/// happens. If nothing is heard from the partner for a few seconds, the cable is considered
+/// unplugged.
+pub struct TcpLink {
+    stream: TcpStream,
+    writer: Option<BufWriter<TcpStream>>,
+    receiver: Mutex<mpsc::Receiver<Packet>>,
+
+    /// Received, but not due yet.
+    queue: VecDeque<Packet>,
+
+    cycles: u64,
+    partner_cycles: u64,
+    next_sync: u64,
+
+    /// The time of the last message sent, and when it was sent.
+    sent_cycles: u64,
+    sent_at: Instant,
+    heard_at: Instant,
+
+    local: Side,
+    partner: ShiftRegister,
+}
+
+impl TcpLink {
+    /// Waits for a partner to connect to `address`.
+    pub fn host(address: impl ToSocketAddrs) -> io::Result<Self> {
+        let listener = TcpListener::bind(address)?;
+        let (stream, _) = listener.accept()?;
+
+        Self::from_stream(stream)
+    }
+
+    /// Connects to a partner that is hosting on `address`.
+    pub fn join(address: impl ToSocketAddrs) -> io::Result<Self> {
+        Self::from_stream(TcpStream::connect(address)?)
+    }
+
+    /// Performs the handshake on an already connected stream.
+    pub fn from_stream(mut stream: TcpStream) -> io::Result<Self> {
+        stream.set_nodelay(true)?;
+
+        stream.write_all(&MAGIC)?;
+        stream.write_all(&[PROTOCOL_VERSION])?;
+
+        let mut handshake = [0; 5];
+        stream.set_read_timeout(Some(TIMEOUT))?;
+        stream.read_exact(&mut handshake)?;
+        stream.set_read_timeout(None)?;
+
+        if handshake[0..4] != MAGIC {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "the partner is not a Game Boy link",
+            ));
+        }
+
+        if handshake[4] != PROTOCOL_VERSION {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("unsupported link protocol version: {}", handshake[4]),
+            ));
+        }
+
+        let (sender, receiver) = mpsc::channel();
+        let mut reader = BufReader::new(stream.try_clone()?);
+
+        thread::spawn(move || {
+            let mut buffer = [0; MESSAGE_SIZE];
+
+            while reader.read_exact(&mut buffer).is_ok() {
+                let Some(packet) = Packet::decode(&buffer) else {
+                    break;
+                };
+
+                if sender.send(packet).is_err() {
+                    break;
+                }
+            }
+        });
+
+        Ok(Self {
+            writer: Some(BufWriter::new(stream.try_clone()?)),
+            stream,
+            receiver: Mutex::new(receiver),
+            queue: VecDeque::new(),
+            cycles: 0,
+            partner_cycles: 0,
+            next_sync: SYNC_INTERVAL,
+            sent_cycles: 0,
+            sent_at: Instant::now(),
+            heard_at: Instant::now(),
+            local: Side::default(),
+            partner: ShiftRegister::default(),
+        })
+    }
+
+    #[must_use]
+    pub fn is_connected(&self) -> bool {
+        self.writer.is_some()
+    }
+
+    fn send(&mut self, message: Message) {
+        let packet = Packet {
+            time: self.cycles,
+            message,
+        };
+
+        self.sent_cycles = self.cycles;
+        self.sent_at = Instant::now();
+
+        if let Some(writer) = self.writer.as_mut()
+            && writer.write_all(&packet.encode()).is_err()
+        {
+            self.disconnect();
+        }
+    }
+
+    fn flush(&mut self) {
+        if let Some(writer) = self.writer.as_mut()
+            && writer.flush().is_err()
+        {
+            self.disconnect();
+        }
+    }
+
+    fn disconnect(&mut self) {
+        self.writer = None;
+        self.partner = ShiftRegister::default();
+
+        // Also stops the reader thread.
+        let _ = self.stream.shutdown(Shutdown::Both);
+    }
+
+    fn enqueue(&mut self, packet: Packet) {
+        self.partner_cycles = packet.time;
+        self.heard_at = Instant::now();
+        self.queue.push_back(packet);
+    }
+
+    /// Takes everything received so far, without blocking.
+    fn receive(&mut self) {
+        loop {
+            match self.receiver.get_mut().unwrap().try_recv() {
+                Ok(packet) => self.enqueue(packet),
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    self.disconnect();
+                    break;
+                }
+            }
+        }
+    }
+
+    /// Blocks until everything the partner sent before `cycles - LOOKAHEAD` has been received.
+    fn wait_for_partner(&mut self) {
+        self.receive();
+
+        if self.partner_cycles + LOOKAHEAD >= self.cycles {
+            return;
+        }
+
+        // Let the partner know how far we are, or both sides could end up waiting.
+        self.send(Message::Sync);
+        self.flush();
+
+        while self.is_connected() && self.partner_cycles + LOOKAHEAD < self.cycles {
+            match self.receiver.get_mut().unwrap().recv_timeout(TIMEOUT) {
+                Ok(packet) => self.enqueue(packet),
+                Err(_) => self.disconnect(),
+            }
+        }
+    }
+
+    fn apply_due_packets(&mut self) {
+        while let Some(packet) = self.queue.front()
+            && packet.time + LOOKAHEAD <= self.cycles
+        {
+            match packet.message {
+                Message::Sync => {}
+                Message::Ready(data) => self.partner = ShiftRegister::new(data),
+                Message::Clock { bit, accepted } => {
+                    if accepted {
+                        self.local.clock(bit);
+                    }
+                }
+            }
+
+            self.queue.pop_front();
+        }
+    }
+}
+
+impl Drop for TcpLink {
+    fn drop(&mut self) {
+        self.flush();
+        let _ = self.stream.shutdown(Shutdown::Both);
+    }
+}
+
+impl fmt::Debug for TcpLink {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TcpLink")
+            .field("connected", &self.is_connected())
+            .field("cycles", &self.cycles)
+            .field("partner_cycles", &self.partner_cycles)
+            .finish_non_exhaustive()
+    }
+}
+
+impl LinkPort for TcpLink {
+    fn transfer_bit(&mut self, bit: bool) -> bool {
+        let sent = self.partner.clock(bit);
+
+        self.send(Message::Clock {
+            bit,
+            accepted: sent.is_some(),
+        });
+
+        sent.unwrap_or(true)
+    }
+
+    fn set_ready(&mut self, data: Option<u8>) {
+        self.local = Side::new(data);
+        self.send(Message::Ready(data));
+    }
+
+    fn receive_bit(&mut self) -> Option<bool> {
+        self.local.pending.pop_front()
+    }
+
+    fn tick(&mut self, cycles: u32) {
+        if !self.is_connected() {
+            return;
+        }
+
+        self.cycles += cycles as u64;
+
+        if self.cycles >= self.next_sync {
+            self.send(Message::Sync);
+            self.flush();
+            self.next_sync += SYNC_INTERVAL;
+        }
+
+        self.wait_for_partner();
+        self.apply_due_packets();
+    }
+
+    /// Ready once the partner caught up, then a whole frame fits in the lookahead.
+    fn poll(&mut self) -> bool {
+        if !self.is_connected() {
+            return true;
+        }
+
+        self.receive();
+
+        let ready = self.partner_cycles >= self.cycles;
+
+        if !ready && self.heard_at.elapsed() > TIMEOUT {
+            self.disconnect();
+            return true;
+        }
+
+        // The partner might be waiting for this side to catch up too.
+        if self.sent_cycles != self.cycles || self.sent_at.elapsed() >= KEEP_ALIVE_INTERVAL {
+            self.send(Message::Sync);
+        }
+
+        self.flush();
+
+        ready
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::{
+        GameBoy,
+        components::memory::MemoryInterface as _,
+        constants::DeviceModel,
+        test_rom::TestRom,
+    };
+
+    /// Waits for `delay` iterations of a 7 M-cycle loop, loads SB and SC, then loops forever.
+    fn serial_rom(delay: u16, sb: u8, sc: u8) -> Arc<[u8]> {
+        let [delay_low, delay_high] = delay.to_le_bytes();
+
+        // LD BC, delay; loop: DEC BC; LD A, B; OR C; JR NZ, loop
+        // LD A, sb; LDH (0x01), A; LD A, sc; LDH (0x02), A; JR -2
+        TestRom::new()
+            .program(&[
+                0x01, delay_low, delay_high, 0x0B, 0x78, 0xB1, 0x20, 0xFB, 0x3E, sb, 0xE0, 0x01,
+                0x3E, sc, 0xE0, 0x02, 0x18, 0xFE,
+            ])
+            .build()
+    }
+
+    fn run_linked(rom: Arc<[u8]>, link: TcpLink) -> u8 {
+        let mut gb = GameBoy::new(DeviceModel::Dmg);
+        gb.load(None, rom).unwrap();
+        gb.connect_link_port(Box::new(link));
+
+        for _ in 0..15 {
+            while !gb.poll_link_port() {
+                thread::sleep(Duration::from_millis(1));
+            }
+
+            gb.run_frame();
+        }
+
+        gb.memory().read(0xFF01)
+    }
+
+    #[test]
+    fn test_packet_encoding() {
+        let messages = [
+            Message::Sync,
+            Message::Ready(None),
+            Message::Ready(Some(0x42)),
+            Message::Clock {
+                bit: true,
+                accepted: false,
+            },
+        ];
+
+        for message in messages {
+            let packet = Packet {
+                time: 0x0123_4567_89AB,
+                message,
+            };
+
+            assert_eq!(Packet::decode(&packet.encode()), Some(packet));
+        }
+    }
+
+    #[test]
+    fn test_loopback() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+
+        let slave = thread::spawn(move || {
+            let link = TcpLink::join(address).unwrap();
+            run_linked(serial_rom(0x0001, 0x99, 0x80), link)
+        });
+
+        let (stream, _) = listener.accept().unwrap();
+        let link = TcpLink::from_stream(stream).unwrap();
+
+        // The master waits for a few frames, so the slave is surely ready by then.
+        assert_eq!(run_linked(serial_rom(0x4000, 0x42, 0x81), link), 0x99);
+        assert_eq!(slave.join().unwrap(), 0x42);
+    }
+
+    #[test]
+    fn test_poll() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+
+        let joining = thread::spawn(move || TcpLink::join(address).unwrap());
+        let mut host = TcpLink::from_stream(listener.accept().unwrap().0).unwrap();
+        let mut partner = joining.join().unwrap();
+
+        assert!(host.poll());
+        assert!(partner.poll());
+
+        // A frame ahead, the host has to wait.
+        host.tick(LOOKAHEAD as u32);
+        assert!(!host.poll());
+        assert!(partner.poll());
+
+        partner.tick(LOOKAHEAD as u32);
+
+        let start = Instant::now();
+
+        while !host.poll() {
+            assert!(start.elapsed() < TIMEOUT);
+            thread::sleep(Duration::from_millis(1));
+        }
+
+        assert!(host.is_connected());
+    }
+
+    #[test]
+    fn test_handshake_rejects_other_protocols() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+
+        let client = thread::spawn(move || {
+            let mut stream = TcpStream::connect(address).unwrap();
+            stream.write_all(b"HTTP/").unwrap();
+        });
+
+        let (stream, _) = listener.accept().unwrap();
+        assert!(TcpLink::from_stream(stream).is_err());
+
+        client.join().unwrap();
+    }
+}
@@ -0,0 +1,554 @@
+use std::{fmt, sync::mpsc};
+
+use bitflags::bitflags;
+
+use super::link::LinkPort;
+use crate::constants::SCREEN_WIDTH;
+
+const MAGIC: [u8; 2] = [0x88, 0x33];
+
+/// Sent while the Game Boy transfers the first byte after the checksum.
+const ALIVE: u8 = 0x81;
+
+const COMMAND_INIT: u8 = 0x01;
+const COMMAND_PRINT: u8 = 0x02;
+const COMMAND_DATA: u8 = 0x04;
+const COMMAND_STATUS: u8 = 0x0F;
+
+/// 20x2 tiles per data packet, and up to 9 of them (160x144 pixels) per print.
+const BYTES_PER_TILE_ROW: usize = 20 * 16;
+const BUFFER_SIZE: usize = 9 * 2 * BYTES_PER_TILE_ROW;
+
+/// How long the printer stays busy for each printed line.
+const CYCLES_PER_LINE: u32 = 4096;
+
+/// Shades of the thermal paper, from white to black.
+const SHADES: [u8; 4] = [0xFF, 0xAA, 0x55, 0x00];
+
+bitflags! {
+    #[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+    pub struct Status: u8 {
+        const CHECKSUM_ERROR = 1 << 0;
+        const PRINTING = 1 << 1;
+        const IMAGE_DATA_FULL = 1 << 2;
+        const UNPROCESSED_DATA = 1 << 3;
+        const PACKET_ERROR = 1 << 4;
+        const PAPER_JAM = 1 << 5;
+        const OTHER_ERROR = 1 << 6;
+        const LOW_BATTERY = 1 << 7;
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+enum PacketState {
+    #[default]
+    Magic0,
+    Magic1,
+    Command,
+    Compression,
+    LengthLow,
+    LengthHigh,
+    Data,
+    ChecksumLow,
+    ChecksumHigh,
+    Alive,
+    Status,
+}
+
+#[derive(Debug, Default)]
+struct Packet {
+    command: u8,
+    compressed: bool,
+    length: u16,
+    data: Vec<u8>,
+    checksum: u16,
+}
+
+impl Packet {
+    fn expected_checksum(&self) -> u16 {
+        let [length_low, length_high] = self.length.to_le_bytes();
+        let header = [self.command, self.compressed as u8, length_low, length_high];
+
+        header
+            .iter()
+            .chain(&self.data)
+            .fold(0u16, |sum, &byte| sum.wrapping_add(byte as u16))
+    }
+}
+
+/// A strip of paper that came out of the printer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PrintedImage {
+    pub width: usize,
+    pub height: usize,
+
+    /// RGBA8888.
+    pub pixels: Vec<u8>,
+
+    /// Blank lines fed before and after the image, in units of 4 (from the print command).
+    pub margin_before: u8,
+    pub margin_after: u8,
+}
+
+#[cfg(feature = "printer-png")]
+impl PrintedImage {
+    pub fn save_png(&self, path: impl AsRef<std::path::Path>) -> image::ImageResult<()> {
+        image::save_buffer_with_format(
+            path,
+            &self.pixels,
+            self.width as u32,
+            self.height as u32,
+            image::ColorType::Rgba8,
+            image::ImageFormat::Png,
+        )
+    }
+}
+
+/// The Game Boy Printer, to be plugged in with [`crate::GameBoy::connect_link_port`].
+///
+/// Printed images are sent to the receiver returned by [`GameBoyPrinter::new`].
+pub struct GameBoyPrinter {
+    status: Status,
+    state: PacketState,
+    packet: Packet,
+
+    buffer: Vec<u8>,
+    printing_cycles: u32,
+
+    received: u8,
+    received_bits: u8,
+    response: u8,
+
+    sender: mpsc::Sender<PrintedImage>,
+}
+
+impl GameBoyPrinter {
+    #[must_use]
+    pub fn new() -> (Self, mpsc::Receiver<PrintedImage>) {
+        let (sender, receiver) = mpsc::channel();
+
+        let printer = Self {
+            status: Status::empty(),
+            state: PacketState::default(),
+            packet: Packet::default(),
+            buffer: Vec::with_capacity(BUFFER_SIZE),
+            printing_cycles: 0,
+            received: 0,
+            received_bits: 0,
+            response: 0,
+            sender,
+        };
+
+        (printer, receiver)
+    }
+
+    #[must_use]
+    pub fn status(&self) -> Status {
+        self.status
+    }
+
+    fn receive_byte(&mut self, value: u8) {
+        self.state = match self.state {
+            PacketState::Magic0 if value == MAGIC[0] => PacketState::Magic1,
+            PacketState::Magic1 if value == MAGIC[1] => PacketState::Command,
+            PacketState::Magic0 | PacketState::Magic1 => PacketState::Magic0,
+
+            PacketState::Command => {
+                self.packet = Packet {
+                    command: value,
+                    ..Packet::default()
+                };
+
+                PacketState::Compression
+            }
+
+            PacketState::Compression => {
+                self.packet.compressed = value & 0b1 != 0;
+                PacketState::LengthLow
+            }
+
+            PacketState::LengthLow => {
+                self.packet.length = value as u16;
+                PacketState::LengthHigh
+            }
+
+            PacketState::LengthHigh => {
+                self.packet.length |= (value as u16) << 8;
+
+                if self.packet.length == 0 {
+                    PacketState::ChecksumLow
+                } else {
+                    PacketState::Data
+                }
+            }
+
+            PacketState::Data => {
+                self.packet.data.push(value);
+
+                if self.packet.data.len() == self.packet.length as usize {
+                    PacketState::ChecksumLow
+                } else {
+                    PacketState::Data
+                }
+            }
+
+            PacketState::ChecksumLow => {
+                self.packet.checksum = value as u16;
+                PacketState::ChecksumHigh
+            }
+
+            PacketState::ChecksumHigh => {
+                self.packet.checksum |= (value as u16) << 8;
+                self.handle_packet();
+
+                PacketState::Alive
+            }
+
+            PacketState::Alive => PacketState::Status,
+            PacketState::Status => PacketState::Magic0,
+        };
+
+        self.response = match self.state {
+            PacketState::Alive => ALIVE,
+            PacketState::Status => self.status.bits(),
+            _ => 0x00,
+        };
+    }
+
+    fn handle_packet(&mut self) {
+        let checksum_error = self.packet.checksum != self.packet.expected_checksum();
+        self.status.set(Status::CHECKSUM_ERROR, checksum_error);
+
+        if checksum_error {
+            return;
+        }
+
+        self.status.remove(Status::PACKET_ERROR);
+
+        match self.packet.command {
+            COMMAND_INIT => {
+                self.buffer.clear();
+                self.printing_cycles = 0;
+                self.status = Status::empty();
+            }
+
+            COMMAND_DATA => {
+                if self.packet.data.is_empty() {
+                    // End of data, ready to print.
+                    self.status.insert(Status::IMAGE_DATA_FULL);
+                    return;
+                }
+
+                let data = std::mem::take(&mut self.packet.data);
+
+                if self.packet.compressed {
+                    self.decompress_into_buffer(&data);
+                } else {
+                    self.append_to_buffer(&data);
+                }
+
+                self.status.insert(Status::UNPROCESSED_DATA);
+
+                if self.buffer.len() == BUFFER_SIZE {
+                    self.status.insert(Status::IMAGE_DATA_FULL);
+                }
+            }
+
+            COMMAND_PRINT => self.print(),
+
+            COMMAND_STATUS => {}
+
+            _ => self.status.insert(Status::PACKET_ERROR),
+        }
+    }
+
+    fn append_to_buffer(&mut self, data: &[u8]) {
+        let available = BUFFER_SIZE - self.buffer.len();
+        self.buffer
+            .extend_from_slice(&data[..data.len().min(available)]);
+    }
+
+    /// Run-length encoding: a control byte with bit 7 set repeats the next byte
+    /// `(control & 0x7F) + 2` times, otherwise the next `control + 1` bytes are copied as-is.
+    fn decompress_into_buffer(&mut self, data: &[u8]) {
+        let mut bytes = data.iter().copied();
+
+        while let Some(control) = bytes.next() {
+            if control & 0x80 != 0 {
+                let Some(value) = bytes.next() else {
+                    break;
+                };
+
+                let length = (control & 0x7F) as usize + 2;
+                self.append_to_buffer(&vec![value; length]);
+            } else {
+                let literal: Vec<u8> = bytes.by_ref().take(control as usize + 1).collect();
+                self.append_to_buffer(&literal);
+            }
+        }
+    }
+
+    fn print(&mut self) {
+        let [_sheets, margins, palette, _exposure] = self.packet.data[..] else {
+            self.status.insert(Status::PACKET_ERROR);
+            return;
+        };
+
+        let image = self.render(palette, margins);
+        self.printing_cycles = CYCLES_PER_LINE * image.height.max(1) as u32;
+
+        self.buffer.clear();
+        self.status.remove(Status::UNPROCESSED_DATA);
+        self.status
+            .insert(Status::PRINTING | Status::IMAGE_DATA_FULL);
+
+        // Only fails if the receiver was dropped, in which case nobody is watching the paper.
+        let _ = self.sender.send(image);
+    }
+
+    fn render(&self, palette: u8, margins: u8) -> PrintedImage {
+        // Some games send a zero palette, the printer treats it as the default one.
+        let palette = if palette == 0 { 0b1110_0100 } else { palette };
+
+        let width = SCREEN_WIDTH;
+        let height = self.buffer.len() / BYTES_PER_TILE_ROW * 8;
+        let mut pixels = Vec::with_capacity(width * height * 4);
+
+        for y in 0..height {
+            for x in 0..width {
+                let tile = (y / 8) * (width / 8) + x / 8;
+                let address = tile * 16 + (y % 8) * 2;
+
+                let bit = 7 - (x % 8);
+                let low = (self.buffer[address] >> bit) & 0b1;
+                let high = (self.buffer[address + 1] >> bit) & 0b1;
+                let color_id = (high << 1) | low;
+
+                let shade = SHADES[((palette >> (color_id * 2)) & 0b11) as usize];
+                pixels.extend_from_slice(&[shade, shade, shade, 0xFF]);
+            }
+        }
+
+        PrintedImage {
+            width,
+            height,
+            pixels,
+            margin_before: margins >> 4,
+            margin_after: margins & 0x0F,
+        }
+    }
+}
+
+impl fmt::Debug for GameBoyPrinter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("GameBoyPrinter")
+            .field("status", &self.status)
+            .field("state", &self.state)
+            .field("buffer_len", &self.buffer.len())
+            .finish_non_exhaustive()
+    }
+}
+
+impl LinkPort for GameBoyPrinter {
+    fn transfer_bit(&mut self, bit: bool) -> bool {
+        let sent = self.response & 0x80 != 0;
+
+        self.response <<= 1;
+        self.received = (self.received << 1) | bit as u8;
+        self.received_bits += 1;
+
+        if self.received_bits == 8 {
+            self.received_bits = 0;
+            self.receive_byte(self.received);
+        }
+
+        sent
+    }
+
+    /// The printer never drives the clock.
+    fn set_ready(&mut self, _data: Option<u8>) {}
+
+    fn receive_bit(&mut self) -> Option<bool> {
+        None
+    }
+
+    fn tick(&mut self, cycles: u32) {
+        if self.printing_cycles == 0 {
+            return;
+        }
+
+        self.printing_cycles = self.printing_cycles.saturating_sub(cycles);
+
+        if self.printing_cycles == 0 {
+            self.status
+                .remove(Status::PRINTING | Status::IMAGE_DATA_FULL);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn transfer_byte(printer: &mut GameBoyPrinter, mut value: u8) -> u8 {
+        for _ in 0..8 {
+            let received = printer.transfer_bit(value & 0x80 != 0);
+            value = (value << 1) | received as u8;
+        }
+
+        value
+    }
+
+    /// Sends a whole packet and returns the two bytes answered after the checksum.
+    fn send_packet(
+        printer: &mut GameBoyPrinter,
+        command: u8,
+        compressed: bool,
+        data: &[u8],
+    ) -> [u8; 2] {
+        let packet = Packet {
+            command,
+            compressed,
+            length: data.len() as u16,
+            data: data.to_vec(),
+            checksum: 0,
+        };
+        let checksum = packet.expected_checksum();
+
+        let mut bytes = vec![MAGIC[0], MAGIC[1], command, compressed as u8];
+        bytes.extend_from_slice(&packet.length.to_le_bytes());
+        bytes.extend_from_slice(data);
+        bytes.extend_from_slice(&checksum.to_le_bytes());
+
+        for byte in bytes {
+            assert_eq!(transfer_byte(printer, byte), 0x00);
+        }
+
+        [transfer_byte(printer, 0x00), transfer_byte(printer, 0x00)]
+    }
+
+    #[test]
+    fn test_print() {
+        let (mut printer, receiver) = GameBoyPrinter::new();
+
+        assert_eq!(
+            send_packet(&mut printer, COMMAND_INIT, false, &[]),
+            [ALIVE, 0x00]
+        );
+
+        // Two tile rows: the first one in color 3, the second one in color 1.
+        let mut data = vec![0xFF; BYTES_PER_TILE_ROW];
+        data.extend([0xFF, 0x00].repeat(BYTES_PER_TILE_ROW / 2));
+
+        assert_eq!(
+            send_packet(&mut printer, COMMAND_DATA, false, &data),
+            [ALIVE, 0x08]
+        );
+        assert_eq!(
+            send_packet(&mut printer, COMMAND_DATA, false, &[]),
+            [ALIVE, 0x0C]
+        );
+        assert_eq!(
+            send_packet(
+                &mut printer,
+                COMMAND_PRINT,
+                false,
+                &[0x01, 0x13, 0xE4, 0x40]
+            ),
+            [ALIVE, 0x06]
+        );
+
+        let image = receiver.try_recv().unwrap();
+        assert_eq!((image.width, image.height), (160, 16));
+        assert_eq!((image.margin_before, image.margin_after), (1, 3));
+        assert_eq!(image.pixels[0..4], [0x00, 0x00, 0x00, 0xFF]);
+        assert_eq!(
+            image.pixels[160 * 8 * 4..160 * 8 * 4 + 4],
+            [0xAA, 0xAA, 0xAA, 0xFF]
+        );
+
+        printer.tick(CYCLES_PER_LINE * 16);
+        assert_eq!(
+            send_packet(&mut printer, COMMAND_STATUS, false, &[]),
+            [ALIVE, 0x00]
+        );
+    }
+
+    #[test]
+    fn test_compression() {
+        let (mut printer, _receiver) = GameBoyPrinter::new();
+
+        // 3 literal bytes, then 0x42 repeated 5 times.
+        send_packet(
+            &mut printer,
+            COMMAND_DATA,
+            true,
+            &[0x02, 0x01, 0x02, 0x03, 0x83, 0x42],
+        );
+
+        assert_eq!(
+            printer.buffer,
+            [0x01, 0x02, 0x03, 0x42, 0x42, 0x42, 0x42, 0x42]
+        );
+    }
+
+    #[test]
+    fn test_errors() {
+        let (mut printer, _receiver) = GameBoyPrinter::new();
+
+        // Bad checksum.
+        for byte in [
+            MAGIC[0],
+            MAGIC[1],
+            COMMAND_INIT,
+            0x00,
+            0x00,
+            0x00,
+            0xFF,
+            0xFF,
+        ] {
+            transfer_byte(&mut printer, byte);
+        }
+        assert_eq!(transfer_byte(&mut printer, 0x00), ALIVE);
+        assert_eq!(transfer_byte(&mut printer, 0x00), 0x01);
+
+        // Unknown command.
+        assert_eq!(send_packet(&mut printer, 0x42, false, &[]), [ALIVE, 0x10]);
+
+        // Garbage between packets is ignored.
+        transfer_byte(&mut printer, 0x12);
+        assert_eq!(
+            send_packet(&mut printer, COMMAND_STATUS, false, &[]),
+            [ALIVE, 0x00]
+        );
+    }
+
+    #[cfg(feature = "printer-png")]
+    #[test]
+    fn test_save_png() {
+        let (mut printer, receiver) = GameBoyPrinter::new();
+
+        send_packet(
+            &mut printer,
+            COMMAND_DATA,
+            false,
+            &[0x00; 2 * BYTES_PER_TILE_ROW],
+        );
+        send_packet(
+            &mut printer,
+            COMMAND_PRINT,
+            false,
+            &[0x01, 0x00, 0xE4, 0x40],
+        );
+
+        let image = receiver.try_recv().unwrap();
+        let path = std::env::temp_dir().join(format!("gb-printer-{}.png", std::process::id()));
+
+        image.save_png(&path).unwrap();
+        let decoded = image::open(&path).unwrap().into_rgba8();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(decoded.dimensions(), (160, 16));
+        assert_eq!(decoded.into_raw(), image.pixels);
+    }
+}
@@ -1,3 +1,5 @@
+use crate::save_state::{SaveState, SaveStateError, StateReader, StateWriter};
+
 #[derive(Debug, Default, PartialEq, Eq)]
 enum TimaState {
     #[default]
@@ -50,6 +52,48 @@ impl Timer {
         }
     }
 
+    /// Same as calling [`Timer::tick`] `cycles` times, skipping over the cycles where nothing happens.
+    pub fn advance(&mut self, mut cycles: u64) {
+        while cycles > 0 {
+            if self.tima_state != TimaState::Running {
+                self.tick();
+                cycles -= 1;
+                continue;
+            }
+
+            let until_increment = self.cycles_until_increment();
+
+            if cycles < until_increment {
+                self.system_counter = self.system_counter.wrapping_add(cycles as u16);
+                return;
+            }
+
+            // The last one is handled by `tick`, which increments TIMA.
+            self.system_counter = self
+                .system_counter
+                .wrapping_add((until_increment - 1) as u16);
+            self.tick();
+
+            cycles -= until_increment;
+        }
+    }
+
+    /// Cycles until TIMA overflows and the interrupt has to be requested.
+    #[must_use]
+    pub fn cycles_until_event(&self) -> u64 {
+        if self.tima_state != TimaState::Running {
+            return 1;
+        }
+
+        if !self.timer_enable() {
+            return u64::MAX;
+        }
+
+        let increments_left = (0xFF - self.tima) as u64;
+
+        self.cycles_until_increment() + (increments_left * self.input_clock() as u64)
+    }
+
     #[must_use]
     pub fn read(&self, address: u16) -> u8 {
         match address {
@@ -181,6 +225,21 @@ impl Timer {
         }
     }
 
+    /// TIMA is incremented when the counter reaches a multiple of the input clock.
+    fn cycles_until_increment(&self) -> u64 {
+        if !self.timer_enable() {
+            return u64::MAX;
+        }
+
+        let clock = self.input_clock();
+
+        (clock - (self.system_counter & (clock - 1))) as u64
+    }
+
+    pub(crate) fn system_counter(&self) -> u16 {
+        self.system_counter
+    }
+
     fn timer_enable(&self) -> bool {
         (self.tac & 0b0100) != 0
     }
@@ -197,6 +256,47 @@ impl Timer {
     }
 }
 
+impl SaveState for Timer {
+    fn save_state(&self, writer: &mut StateWriter) {
+        writer.write_u16(self.system_counter);
+        writer.write_u8(self.tima);
+        writer.write_u8(self.tma);
+        writer.write_u8(self.tac);
+
+        let (state, count) = match self.tima_state {
+            TimaState::Running => (0, 0),
+            TimaState::Overflow(count) => (1, count),
+            TimaState::Loading(count) => (2, count),
+        };
+
+        writer.write_u8(state);
+        writer.write_u8(count);
+
+        writer.write_bool(self.irq);
+    }
+
+    fn load_state(&mut self, reader: &mut StateReader) -> Result<(), SaveStateError> {
+        self.system_counter = reader.read_u16()?;
+        self.tima = reader.read_u8()?;
+        self.tma = reader.read_u8()?;
+        self.tac = reader.read_u8()?;
+
+        let state = reader.read_u8()?;
+        let count = reader.read_u8()?;
+
+        self.tima_state = match state {
+            0 => TimaState::Running,
+            1 => TimaState::Overflow(count),
+            2 => TimaState::Loading(count),
+            _ => return Err(SaveStateError::InvalidData("TIMA state")),
+        };
+
+        self.irq = reader.read_bool()?;
+
+        Ok(())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -296,5 +396,48 @@ mod tests {
         assert_eq!(timer.tima, 0xB0);
     }
 
+    #[test]
+    fn test_advance_matches_tick() {
+        let mut ticked = Timer::default();
+        let mut advanced = Timer::default();
+
+        for timer in [&mut ticked, &mut advanced] {
+            timer.write_tima(0xF0);
+            timer.write_tma(0xE0);
+            timer.write_tac(0b101);
+        }
+
+        for cycles in [1, 3, 17, 250, 1000, 4096] {
+            for _ in 0..cycles {
+                ticked.tick();
+            }
+
+            advanced.advance(cycles);
+
+            assert_eq!(advanced.system_counter, ticked.system_counter);
+            assert_eq!(advanced.tima, ticked.tima);
+            assert_eq!(advanced.tima_state, ticked.tima_state);
+            assert_eq!(advanced.irq, ticked.irq);
+        }
+    }
+
+    #[test]
+    fn test_cycles_until_event() {
+        let mut timer = Timer::default();
+        assert_eq!(timer.cycles_until_event(), u64::MAX);
+
+        timer.write_tima(0xFE);
+        timer.write_tac(0b101);
+
+        // Two increments, every 16 cycles.
+        assert_eq!(timer.cycles_until_event(), 32);
+
+        timer.advance(31);
+        assert_eq!(timer.tima, 0xFF);
+
+        timer.advance(1);
+        assert_eq!(timer.tima_state, TimaState::Overflow(3));
+    }
+
     // TODO: test overflows and interrupts
 }
@@ -0,0 +1,540 @@
+//! Breakpoints and watchpoints, see [`crate::GameBoy::run_until_break`].
+
+use std::ops::RangeInclusive;
+
+use bitflags::bitflags;
+
+pub use self::condition::{Condition, ConditionError};
+use crate::{
+    components::{
+        apu::Apu,
+        cpu::Cpu,
+        memory::{Memory, MemoryInterface, interrupts::Interrupts, key1::Key1},
+    },
+    utils::events::Events,
+};
+
+bitflags! {
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub struct Access: u8 {
+        const READ = 1 << 0;
+        const WRITE = 1 << 1;
+        const EXECUTE = 1 << 2;
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BreakpointKind {
+    /// Stops before the instruction at `address` is executed.
+    ///
+    /// With a `bank`, only stops if that ROM bank is mapped to `address`.
+    Pc { address: u16, bank: Option<usize> },
+
+    /// Stops before an instruction with this opcode is executed, e.g. `LD B,B` (0x40).
+    Opcode(u8),
+
+    /// Stops after an instruction accesses an address in `range`.
+    ///
+    /// Reads include instruction fetches. Execution is checked against the start of each
+    /// instruction, before it runs.
+    Watch {
+        range: RangeInclusive<u16>,
+        access: Access,
+    },
+
+    /// Stops after a write to an I/O register (0xFF00 ~ 0xFF7F and 0xFFFF), or to any of them.
+    IoWrite { address: Option<u16> },
+
+    /// Stops after an interrupt is dispatched.
+    ///
+    /// `mask` uses the layout of IF and IE (bit 0 for V-Blank up to bit 4 for the joypad).
+    Interrupt { mask: u8 },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Breakpoint {
+    pub kind: BreakpointKind,
+
+    /// Evaluated when the breakpoint is hit, after the instruction for accesses and interrupts.
+    pub condition: Option<Condition>,
+    pub enabled: bool,
+}
+
+impl Breakpoint {
+    #[must_use]
+    pub fn new(kind: BreakpointKind) -> Self {
+        Self {
+            kind,
+            condition: None,
+            enabled: true,
+        }
+    }
+
+    #[must_use]
+    pub fn with_condition(mut self, condition: Condition) -> Self {
+        self.condition = Some(condition);
+        self
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct BreakpointId(u32);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StopReason {
+    /// A [`BreakpointKind::Pc`], [`BreakpointKind::Opcode`] or execute watchpoint was hit.
+    /// `pc` points to the instruction that is about to be executed.
+    Breakpoint {
+        id: BreakpointId,
+        pc: u16,
+    },
+
+    Watchpoint {
+        id: BreakpointId,
+        address: u16,
+        value: u8,
+        access: Access,
+    },
+
+    IoWrite {
+        id: BreakpointId,
+        address: u16,
+        value: u8,
+    },
+
+    Interrupt {
+        id: BreakpointId,
+        vector: u16,
+    },
+
+    /// Nothing was hit during the frame.
+    FrameEnd,
+}
+
+#[derive(Debug, Default)]
+pub struct Debugger {
+    breakpoints: Vec<(BreakpointId, Breakpoint)>,
+    next_id: u32,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct MemoryAccess {
+    address: u16,
+    value: u8,
+    access: Access,
+}
+
+/// Forwards everything to [`Memory`], keeping track of the CPU reads and writes.
+struct TracedMemory<'a> {
+    memory: &'a mut Memory,
+    accesses: Vec<MemoryAccess>,
+}
+
+impl MemoryInterface for TracedMemory<'_> {
+    fn cycle(&mut self) {
+        MemoryInterface::cycle(self.memory);
+    }
+
+    fn read(&self, address: u16) -> u8 {
+        MemoryInterface::read(self.memory, address)
+    }
+
+    fn write(&mut self, address: u16, value: u8) {
+        MemoryInterface::write(self.memory, address, value);
+    }
+
+    fn read_cycle(&mut self, address: u16) -> u8 {
+        let value = MemoryInterface::read_cycle(self.memory, address);
+
+        self.accesses.push(MemoryAccess {
+            address,
+            value,
+            access: Access::READ,
+        });
+
+        value
+    }
+
+    fn write_cycle(&mut self, address: u16, value: u8) {
+        self.accesses.push(MemoryAccess {
+            address,
+            value,
+            access: Access::WRITE,
+        });
+
+        MemoryInterface::write_cycle(self.memory, address, value);
+    }
+
+    fn events(&self) -> &Events {
+        MemoryInterface::events(self.memory)
+    }
+
+    fn events_mut(&mut self) -> &mut Events {
+        MemoryInterface::events_mut(self.memory)
+    }
+
+    fn process_speed_switch(&mut self) {
+        MemoryInterface::process_speed_switch(self.memory);
+    }
+
+    fn apu(&self) -> &Apu {
+        MemoryInterface::apu(self.memory)
+    }
+
+    fn apu_mut(&mut self) -> &mut Apu {
+        MemoryInterface::apu_mut(self.memory)
+    }
+
+    fn key1(&self) -> &Key1 {
+        MemoryInterface::key1(self.memory)
+    }
+
+    fn key1_mut(&mut self) -> &mut Key1 {
+        MemoryInterface::key1_mut(self.memory)
+    }
+
+    fn interrupts(&self) -> &Interrupts {
+        MemoryInterface::interrupts(self.memory)
+    }
+
+    fn interrupts_mut(&mut self) -> &mut Interrupts {
+        MemoryInterface::interrupts_mut(self.memory)
+    }
+
+    fn sync(&mut self) {
+        MemoryInterface::sync(self.memory);
+    }
+
+    fn elapsed_cycles(&self) -> u64 {
+        MemoryInterface::elapsed_cycles(self.memory)
+    }
+
+    fn rom_bank(&self, address: u16) -> Option<usize> {
+        MemoryInterface::rom_bank(self.memory, address)
+    }
+}
+
+fn is_io_register(address: u16) -> bool {
+    matches!(address, 0xFF00..=0xFF7F | 0xFFFF)
+}
+
+impl Debugger {
+    pub fn add(&mut self, breakpoint: Breakpoint) -> BreakpointId {
+        let id = BreakpointId(self.next_id);
+        self.next_id += 1;
+
+        self.breakpoints.push((id, breakpoint));
+
+        id
+    }
+
+    pub fn remove(&mut self, id: BreakpointId) -> Option<Breakpoint> {
+        let index = self
+            .breakpoints
+            .iter()
+            .position(|(breakpoint_id, _)| *breakpoint_id == id)?;
+
+        Some(self.breakpoints.remove(index).1)
+    }
+
+    #[must_use]
+    pub fn get(&self, id: BreakpointId) -> Option<&Breakpoint> {
+        self.breakpoints
+            .iter()
+            .find_map(|(breakpoint_id, breakpoint)| (*breakpoint_id == id).then_some(breakpoint))
+    }
+
+    pub fn get_mut(&mut self, id: BreakpointId) -> Option<&mut Breakpoint> {
+        self.breakpoints
+            .iter_mut()
+            .find_map(|(breakpoint_id, breakpoint)| (*breakpoint_id == id).then_some(breakpoint))
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (BreakpointId, &Breakpoint)> {
+        self.breakpoints
+            .iter()
+            .map(|(id, breakpoint)| (*id, breakpoint))
+    }
+
+    pub fn clear(&mut self) {
+        self.breakpoints.clear();
+    }
+
+    fn enabled(&self) -> impl Iterator<Item = (BreakpointId, &Breakpoint)> {
+        self.iter().filter(|(_, breakpoint)| breakpoint.enabled)
+    }
+
+    fn condition_holds(breakpoint: &Breakpoint, cpu: &Cpu, memory: &Memory) -> bool {
+        breakpoint
+            .condition
+            .as_ref()
+            .is_none_or(|condition| condition.evaluate(cpu.registers(), memory))
+    }
+
+    /// Checks the instruction that is about to be executed.
+    pub(crate) fn check_execution(&self, cpu: &Cpu, memory: &Memory) -> Option<StopReason> {
+        let pc = cpu.registers().pc;
+
+        self.enabled().find_map(|(id, breakpoint)| {
+            let hit = match &breakpoint.kind {
+                BreakpointKind::Pc { address, bank } => {
+                    *address == pc && bank.is_none_or(|bank| memory.rom_bank(pc) == Some(bank))
+                }
+
+                BreakpointKind::Opcode(opcode) => memory.peek(pc) == *opcode,
+
+                BreakpointKind::Watch { range, access } => {
+                    access.contains(Access::EXECUTE) && range.contains(&pc)
+                }
+
+                BreakpointKind::IoWrite { .. } | BreakpointKind::Interrupt { .. } => false,
+            };
+
+            (hit && Self::condition_holds(breakpoint, cpu, memory))
+                .then_some(StopReason::Breakpoint { id, pc })
+        })
+    }
+
+    fn traces_memory(&self) -> bool {
+        self.enabled().any(|(_, breakpoint)| {
+            match &breakpoint.kind {
+                BreakpointKind::Watch { access, .. } => {
+                    access.intersects(Access::READ | Access::WRITE)
+                }
+                BreakpointKind::IoWrite { .. } => true,
+                _ => false,
+            }
+        })
+    }
+
+    /// Runs a single CPU step, then checks the memory accesses and interrupts it caused.
+    pub(crate) fn step(&self, cpu: &mut Cpu, memory: &mut Memory) -> Option<StopReason> {
+        let accesses = if self.traces_memory() {
+            let mut traced = TracedMemory {
+                memory,
+                accesses: Vec::new(),
+            };
+
+            cpu.step(&mut traced);
+            traced.accesses
+        } else {
+            cpu.step(memory);
+            Vec::new()
+        };
+
+        memory.sync();
+
+        if let Some(vector) = cpu.dispatched_interrupt() {
+            let interrupt = vector
+                .checked_sub(0x40)
+                .map_or(0, |offset| 1u8.checked_shl(offset as u32 / 8).unwrap_or(0));
+
+            let reason = self.enabled().find_map(|(id, breakpoint)| {
+                let BreakpointKind::Interrupt { mask } = breakpoint.kind else {
+                    return None;
+                };
+
+                (mask & interrupt != 0 && Self::condition_holds(breakpoint, cpu, memory))
+                    .then_some(StopReason::Interrupt { id, vector })
+            });
+
+            if reason.is_some() {
+                return reason;
+            }
+        }
+
+        accesses.into_iter().find_map(|memory_access| {
+            self.enabled().find_map(|(id, breakpoint)| {
+                let MemoryAccess {
+                    address,
+                    value,
+                    access,
+                } = memory_access;
+
+                let reason = match &breakpoint.kind {
+                    BreakpointKind::Watch {
+                        range,
+                        access: watched,
+                    } if watched.contains(access) && range.contains(&address) => {
+                        StopReason::Watchpoint {
+                            id,
+                            address,
+                            value,
+                            access,
+                        }
+                    }
+
+                    BreakpointKind::IoWrite { address: watched }
+                        if access == Access::WRITE
+                            && is_io_register(address)
+                            && watched.is_none_or(|watched| watched == address) =>
+                    {
+                        StopReason::IoWrite { id, address, value }
+                    }
+
+                    _ => return None,
+                };
+
+                Self::condition_holds(breakpoint, cpu, memory).then_some(reason)
+            })
+        })
+    }
+}
+
+mod condition;
+pub mod stop;
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::{GameBoy, constants::DeviceModel, test_rom::TestRom};
+
+    /// `LD A, 0x42; LD (0xC000), A; LD B, B; LD A, (0xC000); LDH (0x80), A; EI; JR -2`, with a
+    /// V-Blank handler at 0x40 (`RETI`) and the LCD turned on.
+    fn test_rom() -> Arc<[u8]> {
+        TestRom::new()
+            .code(0x0040, &[0xD9])
+            // LD A, 0x01; LDH (0xFF), A; JP 0x0150
+            .code(0x0100, &[0x3E, 0x01, 0xE0, 0xFF, 0xC3, 0x50, 0x01])
+            .code(
+                0x0150,
+                &[
+                    0x3E, 0x42, 0xEA, 0x00, 0xC0, 0x40, 0xFA, 0x00, 0xC0, 0xE0, 0x80, 0xFB, 0x18,
+                    0xFE,
+                ],
+            )
+            .build()
+    }
+
+    fn run(debugger: &Debugger) -> (GameBoy, StopReason) {
+        let mut gb = GameBoy::new(DeviceModel::Dmg);
+        gb.load(None, test_rom()).unwrap();
+
+        let reason = gb.run_until_break(debugger);
+
+        (gb, reason)
+    }
+
+    #[test]
+    fn test_pc_breakpoint() {
+        let mut debugger = Debugger::default();
+        let id = debugger.add(Breakpoint::new(BreakpointKind::Pc {
+            address: 0x0155,
+            bank: None,
+        }));
+
+        let (mut gb, reason) = run(&debugger);
+        assert_eq!(reason, StopReason::Breakpoint { id, pc: 0x0155 });
+
+        // Resuming executes the instruction at the breakpoint.
+        debugger.get_mut(id).unwrap().enabled = false;
+        assert_eq!(gb.run_until_break(&debugger), StopReason::FrameEnd);
+
+        // Wrong bank.
+        debugger.clear();
+        debugger.add(Breakpoint::new(BreakpointKind::Pc {
+            address: 0x0155,
+            bank: Some(1),
+        }));
+        assert_eq!(run(&debugger).1, StopReason::FrameEnd);
+    }
+
+    #[test]
+    fn test_opcode_breakpoint() {
+        let mut debugger = Debugger::default();
+        let id = debugger.add(Breakpoint::new(BreakpointKind::Opcode(0x40)));
+
+        assert_eq!(run(&debugger).1, StopReason::Breakpoint { id, pc: 0x0155 });
+    }
+
+    #[test]
+    fn test_watchpoints() {
+        let mut debugger = Debugger::default();
+        let read = debugger.add(Breakpoint::new(BreakpointKind::Watch {
+            range: 0xC000..=0xC0FF,
+            access: Access::READ,
+        }));
+        let write = debugger.add(Breakpoint::new(BreakpointKind::Watch {
+            range: 0xC000..=0xC0FF,
+            access: Access::WRITE,
+        }));
+
+        let (mut gb, reason) = run(&debugger);
+        assert_eq!(
+            reason,
+            StopReason::Watchpoint {
+                id: write,
+                address: 0xC000,
+                value: 0x42,
+                access: Access::WRITE,
+            }
+        );
+
+        assert_eq!(
+            gb.run_until_break(&debugger),
+            StopReason::Watchpoint {
+                id: read,
+                address: 0xC000,
+                value: 0x42,
+                access: Access::READ,
+            }
+        );
+    }
+
+    #[test]
+    fn test_io_write_and_interrupt() {
+        let mut debugger = Debugger::default();
+        let io = debugger.add(Breakpoint::new(BreakpointKind::IoWrite {
+            address: Some(0xFF80),
+        }));
+
+        // HRAM is not an I/O register.
+        assert_eq!(run(&debugger).1, StopReason::FrameEnd);
+
+        debugger.get_mut(io).unwrap().kind = BreakpointKind::IoWrite { address: None };
+        assert_eq!(
+            run(&debugger).1,
+            StopReason::IoWrite {
+                id: io,
+                address: 0xFFFF,
+                value: 0x01,
+            }
+        );
+
+        debugger.clear();
+        let vblank = debugger.add(Breakpoint::new(BreakpointKind::Interrupt { mask: 0b1 }));
+        let (gb, reason) = run(&debugger);
+
+        assert_eq!(
+            reason,
+            StopReason::Interrupt {
+                id: vblank,
+                vector: 0x40
+            }
+        );
+        assert_eq!(gb.cpu().registers().pc, 0x40);
+    }
+
+    #[test]
+    fn test_conditions() {
+        let mut debugger = Debugger::default();
+        debugger.add(
+            Breakpoint::new(BreakpointKind::Opcode(0x40))
+                .with_condition(Condition::parse("a != 0x42").unwrap()),
+        );
+
+        assert_eq!(run(&debugger).1, StopReason::FrameEnd);
+
+        let id = debugger.add(
+            Breakpoint::new(BreakpointKind::Watch {
+                range: 0x0000..=0xFFFF,
+                access: Access::EXECUTE,
+            })
+            .with_condition(Condition::parse("[0xC000] == 0x42 && pc > 0x0155").unwrap()),
+        );
+
+        assert_eq!(run(&debugger).1, StopReason::Breakpoint { id, pc: 0x0156 });
+    }
+}
@@ -0,0 +1,451 @@
+use std::{fmt, str::FromStr};
+
+use thiserror::Error;
+
+use crate::components::{cpu::registers::Registers, memory::Memory};
+
+#[derive(Debug, Error, Clone, PartialEq, Eq)]
+pub enum ConditionError {
+    #[error("Unexpected character '{0}' at position {1}.")]
+    UnexpectedCharacter(char, usize),
+
+    #[error("Invalid number: {0}.")]
+    InvalidNumber(String),
+
+    #[error("Unknown register: {0}.")]
+    UnknownRegister(String),
+
+    #[error("Expected {0}.")]
+    Expected(&'static str),
+
+    #[error("Unexpected trailing input.")]
+    TrailingInput,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Register {
+    A,
+    F,
+    B,
+    C,
+    D,
+    E,
+    H,
+    L,
+    Af,
+    Bc,
+    De,
+    Hl,
+    Sp,
+    Pc,
+}
+
+impl Register {
+    fn parse(name: &str) -> Option<Self> {
+        let register = match name.to_ascii_lowercase().as_str() {
+            "a" => Self::A,
+            "f" => Self::F,
+            "b" => Self::B,
+            "c" => Self::C,
+            "d" => Self::D,
+            "e" => Self::E,
+            "h" => Self::H,
+            "l" => Self::L,
+            "af" => Self::Af,
+            "bc" => Self::Bc,
+            "de" => Self::De,
+            "hl" => Self::Hl,
+            "sp" => Self::Sp,
+            "pc" => Self::Pc,
+
+            _ => return None,
+        };
+
+        Some(register)
+    }
+
+    fn read(self, registers: &Registers) -> u16 {
+        match self {
+            Self::A => registers.a as u16,
+            Self::F => registers.f.bits() as u16,
+            Self::B => registers.b as u16,
+            Self::C => registers.c as u16,
+            Self::D => registers.d as u16,
+            Self::E => registers.e as u16,
+            Self::H => registers.h as u16,
+            Self::L => registers.l as u16,
+            Self::Af => registers.get_af(),
+            Self::Bc => registers.get_bc(),
+            Self::De => registers.get_de(),
+            Self::Hl => registers.get_hl(),
+            Self::Sp => registers.sp,
+            Self::Pc => registers.pc,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum BinaryOp {
+    Or,
+    And,
+    Equal,
+    NotEqual,
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+    BitOr,
+    BitXor,
+    BitAnd,
+    Add,
+    Sub,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Expr {
+    Number(u32),
+    Register(Register),
+    /// `[address]`, a byte read without side effects (0xFF where nothing is mapped).
+    Memory(Box<Self>),
+    Not(Box<Self>),
+    Binary(BinaryOp, Box<Self>, Box<Self>),
+}
+
+impl Expr {
+    fn evaluate(&self, registers: &Registers, memory: &Memory) -> u32 {
+        match self {
+            Self::Number(value) => *value,
+            Self::Register(register) => register.read(registers) as u32,
+            Self::Memory(address) => memory.peek(address.evaluate(registers, memory) as u16) as u32,
+            Self::Not(value) => (value.evaluate(registers, memory) == 0) as u32,
+
+            Self::Binary(op, lhs, rhs) => {
+                let lhs = lhs.evaluate(registers, memory);
+
+                // Short-circuit, like the usual languages.
+                match op {
+                    BinaryOp::Or if lhs != 0 => return 1,
+                    BinaryOp::And if lhs == 0 => return 0,
+                    _ => {}
+                }
+
+                let rhs = rhs.evaluate(registers, memory);
+
+                match op {
+                    BinaryOp::Or | BinaryOp::And => (rhs != 0) as u32,
+                    BinaryOp::Equal => (lhs == rhs) as u32,
+                    BinaryOp::NotEqual => (lhs != rhs) as u32,
+                    BinaryOp::Less => (lhs < rhs) as u32,
+                    BinaryOp::LessEqual => (lhs <= rhs) as u32,
+                    BinaryOp::Greater => (lhs > rhs) as u32,
+                    BinaryOp::GreaterEqual => (lhs >= rhs) as u32,
+                    BinaryOp::BitOr => lhs | rhs,
+                    BinaryOp::BitXor => lhs ^ rhs,
+                    BinaryOp::BitAnd => lhs & rhs,
+                    BinaryOp::Add => lhs.wrapping_add(rhs),
+                    BinaryOp::Sub => lhs.wrapping_sub(rhs),
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Number(u32),
+    Identifier(String),
+    Operator(&'static str),
+}
+
+const OPERATORS: [&str; 17] = [
+    "||", "&&", "==", "!=", "<=", ">=", "<", ">", "|", "^", "&", "+", "-", "!", "(", ")", "[",
+];
+
+fn tokenize(source: &str) -> Result<Vec<Token>, ConditionError> {
+    let mut tokens = Vec::new();
+    let mut rest = source;
+
+    while let Some(ch) = rest.chars().next() {
+        let position = source.len() - rest.len();
+
+        if ch.is_whitespace() {
+            rest = &rest[ch.len_utf8()..];
+            continue;
+        }
+
+        if ch == ']' {
+            tokens.push(Token::Operator("]"));
+            rest = &rest[1..];
+            continue;
+        }
+
+        if let Some(operator) = OPERATORS
+            .iter()
+            .find(|operator| rest.starts_with(**operator))
+        {
+            tokens.push(Token::Operator(operator));
+            rest = &rest[operator.len()..];
+            continue;
+        }
+
+        if ch.is_ascii_alphanumeric() || ch == '$' || ch == '_' {
+            let length = rest
+                .char_indices()
+                .skip(1)
+                .find(|(_, ch)| !(ch.is_ascii_alphanumeric() || *ch == '_'))
+                .map_or(rest.len(), |(index, _)| index);
+
+            let word = &rest[..length];
+            rest = &rest[length..];
+
+            tokens.push(
+                if ch.is_ascii_digit() || ch == '$' {
+                    Token::Number(parse_number(word)?)
+                } else {
+                    Token::Identifier(word.to_owned())
+                },
+            );
+
+            continue;
+        }
+
+        return Err(ConditionError::UnexpectedCharacter(ch, position));
+    }
+
+    Ok(tokens)
+}
+
+/// Decimal, `0x` or `$` for hexadecimal, `0b` for binary.
+fn parse_number(word: &str) -> Result<u32, ConditionError> {
+    let lowercase = word.to_ascii_lowercase();
+
+    let result = if let Some(hex) = lowercase.strip_prefix("0x") {
+        u32::from_str_radix(hex, 16)
+    } else if let Some(hex) = lowercase.strip_prefix('$') {
+        u32::from_str_radix(hex, 16)
+    } else if let Some(binary) = lowercase.strip_prefix("0b") {
+        u32::from_str_radix(binary, 2)
+    } else {
+        lowercase.parse()
+    };
+
+    result.map_err(|_| ConditionError::InvalidNumber(word.to_owned()))
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+}
+
+impl Parser {
+    /// Binary operators from the lowest to the highest precedence.
+    const PRECEDENCE: [&[(&str, BinaryOp)]; 7] = [
+        &[("||", BinaryOp::Or)],
+        &[("&&", BinaryOp::And)],
+        &[
+            ("==", BinaryOp::Equal),
+            ("!=", BinaryOp::NotEqual),
+            ("<", BinaryOp::Less),
+            ("<=", BinaryOp::LessEqual),
+            (">", BinaryOp::Greater),
+            (">=", BinaryOp::GreaterEqual),
+        ],
+        &[("|", BinaryOp::BitOr)],
+        &[("^", BinaryOp::BitXor)],
+        &[("&", BinaryOp::BitAnd)],
+        &[("+", BinaryOp::Add), ("-", BinaryOp::Sub)],
+    ];
+
+    fn peek_operator(&self) -> Option<&'static str> {
+        match self.tokens.get(self.position) {
+            Some(Token::Operator(operator)) => Some(operator),
+            _ => None,
+        }
+    }
+
+    fn expect(&mut self, operator: &'static str) -> Result<(), ConditionError> {
+        if self.peek_operator() != Some(operator) {
+            return Err(ConditionError::Expected(operator));
+        }
+
+        self.position += 1;
+        Ok(())
+    }
+
+    fn binary(&mut self, level: usize) -> Result<Expr, ConditionError> {
+        let Some(operators) = Self::PRECEDENCE.get(level) else {
+            return self.unary();
+        };
+
+        let mut lhs = self.binary(level + 1)?;
+
+        while let Some(&(_, op)) = self
+            .peek_operator()
+            .and_then(|token| operators.iter().find(|(operator, _)| *operator == token))
+        {
+            self.position += 1;
+
+            let rhs = self.binary(level + 1)?;
+            lhs = Expr::Binary(op, Box::new(lhs), Box::new(rhs));
+        }
+
+        Ok(lhs)
+    }
+
+    fn unary(&mut self) -> Result<Expr, ConditionError> {
+        let Some(token) = self.tokens.get(self.position).cloned() else {
+            return Err(ConditionError::Expected("a value"));
+        };
+
+        self.position += 1;
+
+        match token {
+            Token::Number(value) => Ok(Expr::Number(value)),
+
+            Token::Identifier(name) => {
+                Register::parse(&name)
+                    .map(Expr::Register)
+                    .ok_or(ConditionError::UnknownRegister(name))
+            }
+
+            Token::Operator("!") => Ok(Expr::Not(Box::new(self.unary()?))),
+
+            Token::Operator("(") => {
+                let expr = self.binary(0)?;
+                self.expect(")")?;
+
+                Ok(expr)
+            }
+
+            Token::Operator("[") => {
+                let expr = self.binary(0)?;
+                self.expect("]")?;
+
+                Ok(Expr::Memory(Box::new(expr)))
+            }
+
+            Token::Operator(_) => Err(ConditionError::Expected("a value")),
+        }
+    }
+}
+
+/// An expression over the CPU registers and memory, e.g. `a == 0x42 && [hl] != 0`.
+///
+/// Supports the 8-bit and 16-bit registers, memory reads (`[address]`), the `||`, `&&`,
+/// comparison, `|`, `^`, `&`, `+`, `-` and `!` operators, and parentheses.
+/// The condition holds if the expression is not zero.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Condition {
+    source: String,
+    expr: Expr,
+}
+
+impl Condition {
+    pub fn parse(source: &str) -> Result<Self, ConditionError> {
+        let mut parser = Parser {
+            tokens: tokenize(source)?,
+            position: 0,
+        };
+
+        let expr = parser.binary(0)?;
+
+        if parser.position != parser.tokens.len() {
+            return Err(ConditionError::TrailingInput);
+        }
+
+        Ok(Self {
+            source: source.to_owned(),
+            expr,
+        })
+    }
+
+    #[must_use]
+    pub fn evaluate(&self, registers: &Registers, memory: &Memory) -> bool {
+        self.expr.evaluate(registers, memory) != 0
+    }
+}
+
+impl FromStr for Condition {
+    type Err = ConditionError;
+
+    fn from_str(source: &str) -> Result<Self, Self::Err> {
+        Self::parse(source)
+    }
+}
+
+impl fmt::Display for Condition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.source)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{components::memory::MemoryInterface as _, constants::DeviceModel};
+
+    fn evaluate(source: &str) -> bool {
+        let mut registers = Registers {
+            a: 0x42,
+            ..Default::default()
+        };
+        registers.set_hl(0xC000);
+
+        let mut memory = Memory::with_device_model(DeviceModel::Dmg);
+        memory.write(0xC000, 0x10);
+
+        Condition::parse(source)
+            .unwrap()
+            .evaluate(&registers, &memory)
+    }
+
+    #[test]
+    fn test_evaluate() {
+        assert!(evaluate("a == 0x42"));
+        assert!(evaluate("A == $42 && hl == 49152"));
+        assert!(evaluate("[hl] == 0x10"));
+        assert!(evaluate("[hl + 1] == 0 || a == 0"));
+        assert!(evaluate("(a & 0b11) == 2"));
+        assert!(evaluate("!(a < 0x40) && h >= 0xC0"));
+        assert!(evaluate("a - 0x43 > 0xFF"));
+
+        assert!(!evaluate("a != 0x42"));
+        assert!(!evaluate("[0xC000] ^ 0x10"));
+    }
+
+    #[test]
+    fn test_evaluate_unmapped_memory() {
+        // The prohibited area, and the cartridge RAM without a cartridge.
+        assert!(evaluate("[0xFEA0] == 0xFF"));
+        assert!(evaluate("[hl - 0x2000] == 0xFF"));
+    }
+
+    #[test]
+    fn test_precedence() {
+        assert!(evaluate("1 + 1 == 2"));
+        assert!(evaluate("0 && 0 || 1"));
+        assert!(evaluate("2 | 1 == 3"));
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(
+            Condition::parse("a == ?"),
+            Err(ConditionError::UnexpectedCharacter('?', 5))
+        );
+        assert_eq!(
+            Condition::parse("x == 1"),
+            Err(ConditionError::UnknownRegister("x".to_owned()))
+        );
+        assert_eq!(
+            Condition::parse("0xZZ"),
+            Err(ConditionError::InvalidNumber("0xZZ".to_owned()))
+        );
+        assert_eq!(Condition::parse("[hl"), Err(ConditionError::Expected("]")));
+        assert_eq!(
+            Condition::parse("a =="),
+            Err(ConditionError::Expected("a value"))
+        );
+        assert_eq!(Condition::parse("a b"), Err(ConditionError::TrailingInput));
+    }
+}
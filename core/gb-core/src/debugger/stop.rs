@@ -0,0 +1,343 @@
+//! Conditions that end a run, shared by the headless runner and the test suites.
+
+use std::{fmt, sync::mpsc};
+
+use super::{Breakpoint, BreakpointKind, Condition, Debugger, StopReason};
+use crate::{GameBoy, components::memory::Memory};
+
+/// `JR -2`, which jumps to itself.
+pub const INFINITE_LOOP: [u8; 2] = [0x18, 0xFE];
+
+/// Bytes expected in memory, e.g. the `DE B0 61` signature of the blargg memory output.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MemoryPattern {
+    pub address: u16,
+    pub bytes: Vec<u8>,
+}
+
+impl MemoryPattern {
+    /// Unmapped memory reads as 0xFF, see [`Memory::peek`].
+    #[must_use]
+    pub fn matches(&self, memory: &Memory) -> bool {
+        self.bytes.iter().enumerate().all(|(offset, byte)| {
+            let address = self.address.wrapping_add(offset as u16);
+            memory.peek(address) == *byte
+        })
+    }
+}
+
+/// Any of them stops the run. Serial, memory and expression conditions are checked
+/// at the end of each frame, the others before the instruction runs.
+#[derive(Debug, Clone, Default)]
+pub struct StopConditions {
+    /// Texts to look for in the serial output.
+    pub serial: Vec<String>,
+    pub pc: Vec<u16>,
+    pub opcodes: Vec<u8>,
+    pub memory: Vec<MemoryPattern>,
+    pub expressions: Vec<Condition>,
+    /// Stops at `JR -2`, see [`INFINITE_LOOP`].
+    pub infinite_loop: bool,
+}
+
+impl StopConditions {
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.serial.is_empty()
+            && self.pc.is_empty()
+            && self.opcodes.is_empty()
+            && self.memory.is_empty()
+            && self.expressions.is_empty()
+            && !self.infinite_loop
+    }
+}
+
+/// Why the run stopped.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Outcome {
+    Serial(String),
+    Pc(u16),
+    Opcode { opcode: u8, pc: u16 },
+    Memory(u16),
+    Expression(Condition),
+    InfiniteLoop(u16),
+    FrameLimit,
+}
+
+impl Outcome {
+    /// Whether the run ended because one of the conditions was met.
+    #[must_use]
+    pub fn is_match(&self) -> bool {
+        *self != Self::FrameLimit
+    }
+}
+
+impl fmt::Display for Outcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Serial(text) => write!(f, "the serial output contains \"{text}\""),
+            Self::Pc(pc) => write!(f, "reached PC ${pc:04X}"),
+            Self::Opcode { opcode, pc } => write!(f, "reached opcode ${opcode:02X} at ${pc:04X}"),
+            Self::Memory(address) => write!(f, "the memory at ${address:04X} matched"),
+            Self::Expression(condition) => write!(f, "`{condition}` held"),
+            Self::InfiniteLoop(pc) => write!(f, "infinite loop at ${pc:04X}"),
+            Self::FrameLimit => write!(f, "frame limit reached"),
+        }
+    }
+}
+
+/// Runs a Game Boy frame by frame until one of the [`StopConditions`] is met.
+pub struct ConditionRunner {
+    conditions: StopConditions,
+    debugger: Debugger,
+
+    serial_receiver: mpsc::Receiver<u8>,
+    serial_output: Vec<u8>,
+
+    frames: u64,
+}
+
+impl ConditionRunner {
+    /// Takes over the serial output of `gb`, see [`ConditionRunner::serial_output`].
+    pub fn new(gb: &mut GameBoy, conditions: StopConditions) -> Self {
+        let (serial_sender, serial_receiver) = mpsc::channel();
+        gb.add_serial_channel(serial_sender);
+
+        let mut debugger = Debugger::default();
+
+        for &address in &conditions.pc {
+            debugger.add(Breakpoint::new(BreakpointKind::Pc {
+                address,
+                bank: None,
+            }));
+        }
+
+        let mut opcodes = conditions.opcodes.clone();
+
+        if conditions.infinite_loop {
+            opcodes.push(INFINITE_LOOP[0]);
+        }
+
+        opcodes.sort_unstable();
+        opcodes.dedup();
+
+        for opcode in opcodes {
+            debugger.add(Breakpoint::new(BreakpointKind::Opcode(opcode)));
+        }
+
+        Self {
+            conditions,
+            debugger,
+            serial_receiver,
+            serial_output: Vec::new(),
+            frames: 0,
+        }
+    }
+
+    /// Frames run to the end so far.
+    #[must_use]
+    pub const fn frames(&self) -> u64 {
+        self.frames
+    }
+
+    #[must_use]
+    pub fn serial_output(&self) -> &[u8] {
+        &self.serial_output
+    }
+
+    /// Runs until one of the conditions is met, or until `max_frames` frames have run in total.
+    pub fn run(&mut self, gb: &mut GameBoy, max_frames: u64) -> Outcome {
+        while self.frames < max_frames {
+            if let Some(outcome) = self.run_frame(gb) {
+                return outcome;
+            }
+        }
+
+        Outcome::FrameLimit
+    }
+
+    /// Runs a frame, or until a condition is met before it ends.
+    pub fn run_frame(&mut self, gb: &mut GameBoy) -> Option<Outcome> {
+        // The debugger slows down the emulation, only use it when needed.
+        if self.debugger.iter().next().is_none() {
+            gb.run_frame();
+        } else {
+            loop {
+                match gb.run_until_break(&self.debugger) {
+                    StopReason::FrameEnd => break,
+                    StopReason::Breakpoint { pc, .. } => {
+                        if let Some(outcome) = self.check_breakpoint(gb, pc) {
+                            return Some(outcome);
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        self.frames += 1;
+        self.serial_output.extend(self.serial_receiver.try_iter());
+
+        let output = String::from_utf8_lossy(&self.serial_output);
+
+        if let Some(text) = self
+            .conditions
+            .serial
+            .iter()
+            .find(|text| output.contains(text.as_str()))
+        {
+            return Some(Outcome::Serial(text.clone()));
+        }
+
+        let memory = gb.memory();
+
+        if let Some(pattern) = self
+            .conditions
+            .memory
+            .iter()
+            .find(|pattern| pattern.matches(memory))
+        {
+            return Some(Outcome::Memory(pattern.address));
+        }
+
+        self.conditions
+            .expressions
+            .iter()
+            .find(|condition| condition.evaluate(gb.cpu().registers(), memory))
+            .map(|condition| Outcome::Expression(condition.clone()))
+    }
+
+    /// Both PC and opcode breakpoints stop at `pc`, so this sorts out which condition it was.
+    fn check_breakpoint(&self, gb: &GameBoy, pc: u16) -> Option<Outcome> {
+        let memory = gb.memory();
+        let opcode = memory.peek(pc);
+
+        if self.conditions.pc.contains(&pc) {
+            return Some(Outcome::Pc(pc));
+        }
+
+        if self.conditions.opcodes.contains(&opcode) {
+            return Some(Outcome::Opcode { opcode, pc });
+        }
+
+        // Other relative jumps are not loops.
+        if self.conditions.infinite_loop
+            && [opcode, memory.peek(pc.wrapping_add(1))] == INFINITE_LOOP
+        {
+            return Some(Outcome::InfiniteLoop(pc));
+        }
+
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::{constants::DeviceModel, test_rom::TestRom};
+
+    /// `LD A, 0x42; LD (0xC000), A; LDH (0x01), A; LD A, 0x81; LDH (0x02), A; LD B, B; JR -2`,
+    /// which sends 'B' through the serial port.
+    fn test_rom() -> Arc<[u8]> {
+        TestRom::new()
+            .program(&[
+                0x3E, 0x42, 0xEA, 0x00, 0xC0, 0xE0, 0x01, 0x3E, 0x81, 0xE0, 0x02, 0x40, 0x18, 0xFE,
+            ])
+            .build()
+    }
+
+    fn run(conditions: StopConditions) -> (ConditionRunner, Outcome) {
+        let mut gb = GameBoy::new(DeviceModel::Dmg);
+        gb.load(None, test_rom()).unwrap();
+
+        let mut runner = ConditionRunner::new(&mut gb, conditions);
+        let outcome = runner.run(&mut gb, 10);
+
+        (runner, outcome)
+    }
+
+    #[test]
+    fn test_execution_conditions() {
+        let (_, outcome) = run(StopConditions {
+            pc: vec![0x015B],
+            ..Default::default()
+        });
+        assert_eq!(outcome, Outcome::Pc(0x015B));
+
+        let (_, outcome) = run(StopConditions {
+            opcodes: vec![0x40],
+            ..Default::default()
+        });
+        assert_eq!(
+            outcome,
+            Outcome::Opcode {
+                opcode: 0x40,
+                pc: 0x015B
+            }
+        );
+
+        let (runner, outcome) = run(StopConditions {
+            infinite_loop: true,
+            ..Default::default()
+        });
+        assert_eq!(outcome, Outcome::InfiniteLoop(0x015C));
+        assert_eq!(runner.frames(), 0);
+    }
+
+    #[test]
+    fn test_frame_conditions() {
+        let (runner, outcome) = run(StopConditions {
+            serial: vec!["B".to_owned()],
+            ..Default::default()
+        });
+        assert_eq!(outcome, Outcome::Serial("B".to_owned()));
+        assert_eq!(runner.serial_output(), b"B");
+
+        let (_, outcome) = run(StopConditions {
+            memory: vec![MemoryPattern {
+                address: 0xC000,
+                bytes: vec![0x42],
+            }],
+            ..Default::default()
+        });
+        assert_eq!(outcome, Outcome::Memory(0xC000));
+
+        let condition = Condition::parse("[0xC000] == 0x42 && a == 0x81").unwrap();
+        let (_, outcome) = run(StopConditions {
+            expressions: vec![condition.clone()],
+            ..Default::default()
+        });
+        assert_eq!(outcome, Outcome::Expression(condition));
+    }
+
+    #[test]
+    fn test_unmapped_memory_pattern() {
+        // The prohibited area reads as an open bus instead of panicking.
+        let (_, outcome) = run(StopConditions {
+            memory: vec![MemoryPattern {
+                address: 0xFEA0,
+                bytes: vec![0xFF, 0xFF],
+            }],
+            ..Default::default()
+        });
+        assert_eq!(outcome, Outcome::Memory(0xFEA0));
+    }
+
+    #[test]
+    fn test_frame_limit() {
+        let (runner, outcome) = run(StopConditions {
+            serial: vec!["Passed".to_owned()],
+            memory: vec![MemoryPattern {
+                address: 0xC000,
+                bytes: vec![0x43],
+            }],
+            ..Default::default()
+        });
+
+        assert_eq!(outcome, Outcome::FrameLimit);
+        assert!(!outcome.is_match());
+        assert_eq!(runner.frames(), 10);
+    }
+}
@@ -1,6 +1,9 @@
 use thiserror::Error;
 
-use crate::components::{cartridge::error::CartridgeError, memory::bootrom::BootromError};
+use crate::{
+    components::{cartridge::error::CartridgeError, memory::bootrom::BootromError},
+    save_state::SaveStateError,
+};
 
 #[derive(Debug, Error)]
 pub enum GameBoyError {
@@ -9,4 +12,7 @@ pub enum GameBoyError {
 
     #[error("Failed to load the cartridge: {0}.")]
     CartridgeError(#[from] CartridgeError),
+
+    #[error("Failed to load the save state: {0}.")]
+    SaveStateError(#[from] SaveStateError),
 }
@@ -0,0 +1,120 @@
+use std::sync::mpsc;
+
+use crate::{
+    components::{
+        apu::{Callback, Channels, ChannelsCallback, vgm::VgmRecorder},
+        cpu::{Cpu, tracer::Tracer},
+        memory::Memory,
+        ppu::renderer::Renderer,
+        serial::link::LinkPort,
+    },
+    utils::color::{ColorCorrection, DmgPalette},
+};
+
+/// What the frontend plugged into the system and its settings, which are not a part of the
+/// emulated state. They are carried over whenever the CPU and the memory are rebuilt, by a power
+/// cycle or when loading a state.
+pub struct FrontendHooks {
+    audio_callback: Option<Box<Callback>>,
+    channels_callback: Option<Box<ChannelsCallback>>,
+    vgm_recorder: Option<VgmRecorder>,
+    sample_rate: usize,
+    rate_adjustment: f64,
+    ui_channel_overrides: Channels,
+
+    serial_sender: Option<mpsc::Sender<u8>>,
+    link: Option<Box<dyn LinkPort>>,
+
+    renderer: Renderer,
+    dmg_palette: DmgPalette,
+    color_correction: ColorCorrection,
+
+    tracer: Option<Tracer>,
+    idle_skipping: bool,
+}
+
+impl FrontendHooks {
+    pub fn take(cpu: &mut Cpu, memory: &mut Memory) -> Self {
+        Self {
+            audio_callback: memory.apu.take_callback(),
+            channels_callback: memory.apu.take_channels_callback(),
+            vgm_recorder: memory.take_vgm_recorder(),
+            sample_rate: memory.apu.sample_rate(),
+            rate_adjustment: memory.apu.rate_adjustment(),
+            ui_channel_overrides: memory.apu.ui_channel_overrides,
+            serial_sender: memory.serial.take_sender(),
+            link: memory.serial.take_link(),
+            renderer: memory.ppu.renderer(),
+            dmg_palette: memory.ppu.dmg_palette(),
+            color_correction: memory.ppu.color_correction(),
+            tracer: cpu.take_tracer(),
+            idle_skipping: cpu.idle_skipping(),
+        }
+    }
+
+    pub fn restore(self, cpu: &mut Cpu, memory: &mut Memory) {
+        cpu.set_idle_skipping(self.idle_skipping);
+
+        if let Some(tracer) = self.tracer {
+            cpu.set_tracer(tracer);
+        }
+
+        memory.ppu.set_renderer(self.renderer);
+        memory.ppu.set_dmg_palette(self.dmg_palette);
+        memory.ppu.set_color_correction(self.color_correction);
+
+        // The channel taps resample at the current rate, so it goes first.
+        memory.apu.set_sample_rate(self.sample_rate);
+        memory.apu.set_rate_adjustment(self.rate_adjustment);
+        memory.apu.ui_channel_overrides = self.ui_channel_overrides;
+
+        if let Some(callback) = self.audio_callback {
+            memory.apu.add_callback(callback);
+        }
+
+        if let Some(callback) = self.channels_callback {
+            memory.apu.add_channels_callback(callback);
+        }
+
+        if let Some(recorder) = self.vgm_recorder {
+            memory.continue_vgm_recording(recorder);
+        }
+
+        if let Some(sender) = self.serial_sender {
+            memory.serial.add_sender(sender);
+        }
+
+        if let Some(link) = self.link {
+            memory.serial.connect_link(link);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{GameBoy, constants::DeviceModel, test_rom::TestRom};
+
+    #[test]
+    fn test_frontend_hooks() {
+        let (sender, receiver) = mpsc::channel();
+
+        let mut gb = GameBoy::new(DeviceModel::Dmg);
+        gb.load(None, TestRom::serial(0x42, 0x81).build()).unwrap();
+        gb.add_serial_channel(sender);
+        gb.set_audio_sample_rate(48000);
+        gb.set_dmg_palette(DmgPalette::Grey);
+        gb.set_ppu_renderer(Renderer::Fifo);
+
+        let state = gb.save_state();
+        gb.power_cycle();
+        gb.load_state(&state).unwrap();
+
+        assert_eq!(gb.audio_sample_rate(), 48000);
+        assert_eq!(gb.dmg_palette(), DmgPalette::Grey);
+        assert_eq!(gb.ppu_renderer(), Renderer::Fifo);
+
+        gb.run_frame();
+        assert_eq!(receiver.try_recv(), Ok(0x42));
+    }
+}
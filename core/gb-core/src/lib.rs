@@ -1,16 +1,38 @@
 use std::sync::{Arc, mpsc};
 
-use components::{cartridge::error::CartridgeError, cpu::Cpu, memory::Memory};
+use bitflags::Flags as _;
+use components::{
+    cartridge::{Cartridge, info::Info},
+    cpu::{Cpu, tracer::Tracer},
+    memory::{Memory, MemoryInterface as _, bootrom::Bootrom},
+    ppu::renderer::Renderer,
+    serial::link::{LinkCable, LinkPort},
+};
 use constants::{DeviceModel, ScreenPixels};
-use utils::{button::Button, screen::Screen};
+use debugger::{Debugger, StopReason};
+use error::GameBoyError;
+use hooks::FrontendHooks;
+use movie::{Movie, MovieError, MovieFrame, MovieSession, MovieStart, RecordingStart};
+use rewind::{RewindBuffer, RewindConfig};
+use save_state::{Header, RomIdentity, SaveState as _, SaveStateError, StateReader, StateWriter};
+use utils::{
+    button::Button,
+    color::{ColorCorrection, DmgPalette},
+    events::Events,
+    screen::Screen,
+};
 
 pub struct GameBoy {
     cpu: Cpu,
     memory: Memory,
 
     rom: Option<Arc<[u8]>>,
+    rom_identity: RomIdentity,
     bootrom: Option<Arc<[u8]>>,
 
+    rewind: Option<RewindBuffer>,
+    movie: Option<MovieSession>,
+
     pub device_model: DeviceModel,
 }
 
@@ -24,17 +46,41 @@ impl GameBoy {
             cpu,
             memory,
             rom: None,
+            rom_identity: RomIdentity::default(),
             bootrom: None,
+            rewind: None,
+            movie: None,
             device_model,
         }
     }
 
+    /// Reboots the system while keeping the cartridge RAM, like a soft reset.
+    ///
+    /// Unsaved in-game progress is preserved, see [`GameBoy::power_cycle`] for a cold start.
     pub fn reset(&mut self) {
-        let audio_callback = self.memory.apu.take_callback();
+        if let Some(MovieSession::Recording { reset, .. }) = &mut self.movie {
+            *reset = true;
+        }
+
+        // The clock keeps running, without going through the wall clock time of a battery file.
+        let previous = self.memory.cartridge.take();
+
+        self.power_cycle();
+
+        if let (Some(previous), Some(cartridge)) = (previous, self.memory.cartridge.as_mut()) {
+            cartridge.keep_battery(previous);
+        }
+    }
+
+    /// Turns the system off and on again. Anything that was not saved by the frontend is lost.
+    pub fn power_cycle(&mut self) {
+        let hooks = FrontendHooks::take(&mut self.cpu, &mut self.memory);
 
         self.cpu = Cpu::with_device_model(self.device_model);
         self.memory = Memory::with_device_model(self.device_model);
 
+        hooks.restore(&mut self.cpu, &mut self.memory);
+
         let Some(rom) = &self.rom else {
             return;
         };
@@ -44,47 +90,54 @@ impl GameBoy {
         if self.bootrom.is_none() {
             self.cpu.skip_bootrom();
         }
-
-        if let Some(callback) = audio_callback {
-            self.add_audio_callback(callback);
-        }
     }
 
-    pub fn load(
-        &mut self,
-        bootrom: Option<Arc<[u8]>>,
-        rom: Arc<[u8]>,
-    ) -> Result<(), CartridgeError> {
-        self.rom = Some(rom);
-
+    /// Inserts a cartridge and powers the system on.
+    ///
+    /// Nothing changes if the ROM or the bootrom are rejected.
+    pub fn load(&mut self, bootrom: Option<Arc<[u8]>>, rom: Arc<[u8]>) -> Result<(), GameBoyError> {
+        // Use the provided bootrom by the frontend
         #[cfg(feature = "bundled-bootrom")]
-        {
-            // Use the provided bootrom by the frontend
-            self.bootrom = bootrom.or_else(|| {
-                // Or use the bundled bootrom if the feature is enabled
-                let bootrom = match self.device_model {
-                    DeviceModel::Dmg => {
-                        include_bytes!("../../../roms/bootrom/dmg_boot.bin").as_slice()
-                    }
-                    DeviceModel::Cgb => {
-                        include_bytes!("../../../roms/bootrom/cgb_boot.bin").as_slice()
-                    }
-                };
+        let bootrom = bootrom.or_else(|| {
+            // Or use the bundled bootrom if the feature is enabled
+            let bootrom = match self.device_model {
+                DeviceModel::Dmg => include_bytes!("../../../roms/bootrom/dmg_boot.bin").as_slice(),
+                DeviceModel::Cgb => include_bytes!("../../../roms/bootrom/cgb_boot.bin").as_slice(),
+            };
+
+            Some(bootrom.into())
+        });
+
+        Cartridge::new(rom.clone())?;
 
-                Some(bootrom.into())
-            });
+        if let Some(bootrom) = &bootrom {
+            Bootrom::try_new(self.device_model, bootrom.clone())?;
         }
 
-        #[cfg(not(feature = "bundled-bootrom"))]
-        {
-            self.bootrom = bootrom;
+        self.rom_identity = RomIdentity::from_rom(&rom);
+        self.rom = Some(rom);
+        self.bootrom = bootrom;
+
+        if let Some(rewind) = &mut self.rewind {
+            rewind.clear();
         }
 
-        self.reset();
+        self.movie = None;
+
+        self.power_cycle();
 
         Ok(())
     }
 
+    /// The header of the inserted cartridge.
+    #[must_use]
+    pub fn cartridge_info(&self) -> Option<&Info> {
+        self.memory
+            .cartridge
+            .as_ref()
+            .map(|cartridge| &cartridge.info)
+    }
+
     #[must_use]
     pub fn cpu(&self) -> &Cpu {
         &self.cpu
@@ -123,24 +176,330 @@ impl GameBoy {
         }
     }
 
+    /// Serializes the whole machine state. See [`save_state`] for the format.
+    #[must_use]
+    pub fn save_state(&self) -> Vec<u8> {
+        let mut writer = StateWriter::default();
+
+        Header::new(self.device_model, self.rom_identity).write(&mut writer);
+
+        self.cpu.save_state(&mut writer);
+        self.memory.save_state(&mut writer);
+
+        writer.into_inner()
+    }
+
+    /// Restores a state created by [`GameBoy::save_state`].
+    ///
+    /// The current state is left untouched if the save state is rejected.
+    pub fn load_state(&mut self, state: &[u8]) -> Result<(), SaveStateError> {
+        let Some(rom) = &self.rom else {
+            return Err(SaveStateError::NoCartridge);
+        };
+
+        let mut reader = StateReader::new(state);
+
+        let header = Header::read(&mut reader)?;
+        header.validate(self.device_model, &self.rom_identity)?;
+
+        let mut cpu = Cpu::with_device_model(self.device_model);
+        let mut memory = Memory::with_device_model(self.device_model);
+
+        memory
+            .load(rom.clone(), self.bootrom.clone())
+            .expect("The ROM and the bootrom should have been validated already");
+
+        cpu.load_state(&mut reader)?;
+        memory.load_state(&mut reader)?;
+
+        if !reader.is_empty() {
+            return Err(SaveStateError::InvalidData("trailing data"));
+        }
+
+        // Frontend hooks are not a part of the state.
+        FrontendHooks::take(&mut self.cpu, &mut self.memory).restore(&mut cpu, &mut memory);
+
+        self.cpu = cpu;
+        self.memory = memory;
+
+        Ok(())
+    }
+
     pub fn step(&mut self) {
         self.cpu.step(&mut self.memory);
+        self.memory.sync();
     }
 
     pub fn run_frame(&mut self) {
         self.cpu.run_frame(&mut self.memory);
+        self.end_frame();
+    }
+
+    /// Runs until a breakpoint from `debugger` is hit, or until the end of the frame.
+    ///
+    /// The instruction at the current PC is always executed, so calling this again resumes
+    /// from a breakpoint instead of stopping at it again.
+    pub fn run_until_break(&mut self, debugger: &Debugger) -> StopReason {
+        let mut resuming = true;
+
+        loop {
+            if !resuming
+                && !self.cpu.halted()
+                && let Some(reason) = debugger.check_execution(&self.cpu, &self.memory)
+            {
+                return reason;
+            }
+
+            resuming = false;
+
+            if let Some(reason) = debugger.step(&mut self.cpu, &mut self.memory) {
+                return reason;
+            }
+
+            if self.memory.events().contains(Events::VBLANK) {
+                self.memory.events_mut().clear();
+                self.end_frame();
+
+                return StopReason::FrameEnd;
+            }
+        }
+    }
+
+    /// Runs a frame of this Game Boy and keeps `partner` in lockstep with it.
+    ///
+    /// Meant for two cores connected with [`GameBoy::connect_link_cable`].
+    /// Only the frame of `self` is tracked, `partner` just runs for the same amount of time.
+    pub fn run_linked_frame(&mut self, partner: &mut Self) {
+        let start = self.memory.elapsed_cycles();
+        let partner_start = partner.memory.elapsed_cycles();
+
+        while !self.memory.events().contains(Events::VBLANK) {
+            let elapsed = self.memory.elapsed_cycles() - start;
+            let partner_elapsed = partner.memory.elapsed_cycles() - partner_start;
+
+            if elapsed <= partner_elapsed {
+                self.step();
+            } else {
+                partner.step();
+            }
+        }
+
+        self.memory.events_mut().clear();
+        partner.memory.events_mut().clear();
+
+        self.end_frame();
+        partner.end_frame();
+    }
+
+    /// Starts keeping snapshots for [`GameBoy::rewind_frames`], replacing any previous history.
+    pub fn enable_rewind(&mut self, config: RewindConfig) {
+        self.rewind = Some(RewindBuffer::new(config));
+    }
+
+    pub fn disable_rewind(&mut self) {
+        self.rewind = None;
+    }
+
+    #[must_use]
+    pub fn rewind_buffer(&self) -> Option<&RewindBuffer> {
+        self.rewind.as_ref()
+    }
+
+    /// Goes back in time by up to `frames` frames, limited by the snapshot interval and the
+    /// history available. Returns how many frames were actually rewound.
+    ///
+    /// The history is cleared if a snapshot is rejected, which happens when the device model
+    /// was changed since it was taken.
+    pub fn rewind_frames(&mut self, frames: usize) -> Result<usize, SaveStateError> {
+        let Some((rewound, state)) = self
+            .rewind
+            .as_mut()
+            .and_then(|rewind| rewind.rewind(frames as u64))
+        else {
+            return Ok(0);
+        };
+
+        if let Err(error) = self.load_state(&state) {
+            if let Some(rewind) = &mut self.rewind {
+                rewind.clear();
+            }
+
+            return Err(error);
+        }
+
+        let rewound = rewound as usize;
+
+        // Recording again from an earlier frame overwrites what came after it.
+        match &mut self.movie {
+            Some(MovieSession::Recording { movie, reset }) => {
+                let frames = movie.frames.len().saturating_sub(rewound);
+                movie.frames.truncate(frames);
+                *reset = false;
+            }
+            Some(MovieSession::Playing { movie, frame }) => {
+                *frame = frame.saturating_sub(rewound);
+
+                // The snapshots are taken before the input of the next frame is applied.
+                if let Some(next) = movie.frames.get(*frame).copied() {
+                    self.apply_movie_frame(next);
+                }
+            }
+            None => {}
+        }
+
+        Ok(rewound)
+    }
+
+    /// Starts recording the input of every frame, see [`movie`].
+    pub fn start_recording(&mut self, start: RecordingStart) -> Result<(), MovieError> {
+        if self.rom.is_none() {
+            return Err(MovieError::NoCartridge);
+        }
+
+        let start = match start {
+            RecordingStart::PowerOn => {
+                // The power cycle would lose whatever the game saved since the battery was loaded.
+                let ram = self.get_battery().map(<[u8]>::to_vec);
+
+                self.power_cycle();
+
+                match ram {
+                    Some(ram) => {
+                        if let Some(cartridge) = self.memory.cartridge.as_mut() {
+                            cartridge.restore_battery(ram.clone());
+                        }
+
+                        MovieStart::CartridgeRam(ram)
+                    }
+                    None => MovieStart::PowerOn,
+                }
+            }
+            RecordingStart::CurrentState => MovieStart::State(self.save_state()),
+        };
+
+        let movie = Movie {
+            device_model: self.device_model,
+            rom: Some(self.rom_identity),
+            start,
+            frames: Vec::new(),
+        };
+
+        self.movie = Some(MovieSession::Recording {
+            movie,
+            reset: false,
+        });
+
+        Ok(())
+    }
+
+    /// Restores the starting point of `movie` and plays it back from the next frame on.
+    ///
+    /// The frontend input is ignored until the movie ends or [`GameBoy::stop_movie`] is called.
+    pub fn play_movie(&mut self, movie: Movie) -> Result<(), MovieError> {
+        if self.rom.is_none() {
+            return Err(MovieError::NoCartridge);
+        }
+
+        movie.validate(self.device_model, &self.rom_identity)?;
+
+        match &movie.start {
+            MovieStart::PowerOn => self.power_cycle(),
+            MovieStart::CartridgeRam(ram) => {
+                self.power_cycle();
+
+                // The clock starts as recorded, whenever the movie is played back.
+                if let Some(cartridge) = self.memory.cartridge.as_mut() {
+                    cartridge.restore_battery(ram.clone());
+                }
+            }
+            MovieStart::State(state) => self.load_state(state)?,
+        }
+
+        self.movie = None;
+
+        if let Some(first) = movie.frames.first().copied() {
+            self.apply_movie_frame(first);
+            self.movie = Some(MovieSession::Playing { movie, frame: 0 });
+        }
+
+        Ok(())
+    }
+
+    /// Stops recording or playing back, returning the movie.
+    pub fn stop_movie(&mut self) -> Option<Movie> {
+        self.movie.take().map(MovieSession::into_movie)
+    }
+
+    #[must_use]
+    pub fn is_recording_movie(&self) -> bool {
+        matches!(self.movie, Some(MovieSession::Recording { .. }))
+    }
+
+    #[must_use]
+    pub fn is_playing_movie(&self) -> bool {
+        matches!(self.movie, Some(MovieSession::Playing { .. }))
+    }
+
+    fn apply_movie_frame(&mut self, frame: MovieFrame) {
+        if frame.reset {
+            self.reset();
+        }
+
+        self.memory.joypad.set_buttons(frame.buttons);
+    }
+
+    fn end_frame(&mut self) {
+        // Anything that looks at the system between frames expects it to be up to date.
+        self.memory.sync();
+
+        if let Some(mut rewind) = self.rewind.take() {
+            rewind.end_frame(|| self.save_state());
+            self.rewind = Some(rewind);
+        }
+
+        match &mut self.movie {
+            Some(MovieSession::Recording { movie, reset }) => {
+                movie.frames.push(MovieFrame {
+                    buttons: self.memory.joypad.buttons(),
+                    reset: std::mem::take(reset),
+                });
+            }
+            Some(MovieSession::Playing { movie, frame }) => {
+                *frame += 1;
+
+                if let Some(next) = movie.frames.get(*frame).copied() {
+                    self.apply_movie_frame(next);
+                } else {
+                    self.movie = None;
+                }
+            }
+            None => {}
+        }
     }
 
     pub fn set_joypad_button(&mut self, button: Button, value: bool) {
-        self.memory.joypad.set_joypad_button(button, value);
+        if !self.is_playing_movie() {
+            self.memory.joypad.set_joypad_button(button, value);
+        }
+    }
+
+    /// Sets every button at once, one bit per [`Button`].
+    pub fn set_joypad_buttons(&mut self, buttons: u8) {
+        if !self.is_playing_movie() {
+            self.memory.joypad.set_buttons(buttons);
+        }
     }
 
     pub fn joypad_button_down(&mut self, key: Button) {
-        self.memory.joypad.joypad_button_down(key);
+        if !self.is_playing_movie() {
+            self.memory.joypad.joypad_button_down(key);
+        }
     }
 
     pub fn joypad_button_up(&mut self, key: Button) {
-        self.memory.joypad.joypad_button_up(key);
+        if !self.is_playing_movie() {
+            self.memory.joypad.joypad_button_up(key);
+        }
     }
 
     #[must_use]
@@ -160,12 +519,160 @@ impl GameBoy {
         self.memory.serial.add_sender(channel);
     }
 
+    /// Plugs a link cable into the serial port, replacing the current one.
+    pub fn connect_link_port(&mut self, link: Box<dyn LinkPort>) {
+        self.memory.serial.connect_link(link);
+    }
+
+    /// Unplugs the link cable, the serial port behaves as if nothing is connected.
+    pub fn disconnect_link_port(&mut self) -> Option<Box<dyn LinkPort>> {
+        self.memory.serial.take_link()
+    }
+
+    /// Whether the next frame can run without waiting for the link partner, see [`LinkPort::poll`].
+    ///
+    /// Frontends with a remote link should call it between frames, also while paused,
+    /// and hold the frame back until it returns `true`.
+    pub fn poll_link_port(&mut self) -> bool {
+        self.memory.serial.poll_link()
+    }
+
+    /// Connects both Game Boys with an in-process [`LinkCable`].
+    pub fn connect_link_cable(&mut self, other: &mut Self) {
+        let (first, second) = LinkCable::pair();
+
+        self.connect_link_port(Box::new(first));
+        other.connect_link_port(Box::new(second));
+    }
+
+    /// Starts logging every executed instruction, replacing the previous tracer.
+    pub fn set_tracer(&mut self, tracer: Tracer) {
+        self.cpu.set_tracer(tracer);
+    }
+
+    /// Stops logging and hands back the tracer, so that it can be flushed.
+    pub fn take_tracer(&mut self) -> Option<Tracer> {
+        self.cpu.take_tracer()
+    }
+
+    #[must_use]
+    pub fn idle_skipping(&self) -> bool {
+        self.cpu.idle_skipping()
+    }
+
+    /// Lets [`GameBoy::run_frame`] skip the time spent in HALT or in loops that only wait
+    /// for an interrupt or a register to change. The results are the same either way.
+    ///
+    /// Enabled by default.
+    pub fn set_idle_skipping(&mut self, value: bool) {
+        self.cpu.set_idle_skipping(value);
+    }
+
+    #[must_use]
+    pub fn ppu_renderer(&self) -> Renderer {
+        self.memory.ppu.renderer()
+    }
+
+    /// Selects the PPU renderer, see [`Renderer`].
+    pub fn set_ppu_renderer(&mut self, renderer: Renderer) {
+        self.memory.ppu.set_renderer(renderer);
+    }
+
+    #[must_use]
+    pub fn dmg_palette(&self) -> DmgPalette {
+        self.memory.ppu.dmg_palette()
+    }
+
+    /// Selects the shades used by the DMG, CGB colors are not affected.
+    pub fn set_dmg_palette(&mut self, dmg_palette: DmgPalette) {
+        self.memory.ppu.set_dmg_palette(dmg_palette);
+    }
+
+    #[must_use]
+    pub fn color_correction(&self) -> ColorCorrection {
+        self.memory.ppu.color_correction()
+    }
+
+    /// Selects how the CGB colors are converted, DMG colors are not affected.
+    pub fn set_color_correction(&mut self, color_correction: ColorCorrection) {
+        self.memory.ppu.set_color_correction(color_correction);
+    }
+
     pub fn add_audio_callback(&mut self, callback: Box<components::apu::Callback>) {
         self.memory.apu.add_callback(callback);
     }
+
+    /// Receives the output of each channel before panning and mixing, e.g. to record stems.
+    ///
+    /// The samples come at the same rate as the mixed ones, see [`components::apu::ChannelsCallback`].
+    pub fn add_audio_channels_callback(
+        &mut self,
+        callback: Box<components::apu::ChannelsCallback>,
+    ) {
+        self.memory.apu.add_channels_callback(callback);
+    }
+
+    /// Stops sending the output of each channel, which also stops resampling them.
+    pub fn remove_audio_channels_callback(&mut self) {
+        self.memory.apu.take_channels_callback();
+    }
+
+    /// Starts logging the writes to the APU as a VGM file, which chiptune players can play.
+    ///
+    /// The recording goes on across resets and loaded states until [`GameBoy::stop_vgm_recording`].
+    pub fn start_vgm_recording(&mut self) {
+        self.memory.start_vgm_recording();
+    }
+
+    /// Makes the VGM players loop back to this point once they reach the end of the recording.
+    pub fn mark_vgm_loop(&mut self) {
+        self.memory.mark_vgm_loop();
+    }
+
+    /// Returns the VGM file, if it was recording.
+    pub fn stop_vgm_recording(&mut self) -> Option<Vec<u8>> {
+        self.memory.stop_vgm_recording()
+    }
+
+    #[must_use]
+    pub fn is_recording_vgm(&self) -> bool {
+        self.memory.is_recording_vgm()
+    }
+
+    #[must_use]
+    pub fn audio_sample_rate(&self) -> usize {
+        self.memory.apu.sample_rate()
+    }
+
+    /// Changes the rate of the samples sent to the audio callback, 44.1kHz by default.
+    /// A sample rate of 0 is ignored.
+    pub fn set_audio_sample_rate(&mut self, sample_rate: usize) {
+        self.memory.apu.set_sample_rate(sample_rate);
+    }
+
+    /// Nudges the audio sample rate by a small fraction, see [`components::apu::Apu::set_rate_adjustment`].
+    pub fn set_audio_rate_adjustment(&mut self, adjustment: f64) {
+        self.memory.apu.set_rate_adjustment(adjustment);
+    }
+
+    /// Sends the pending samples to the audio callback instead of waiting for its buffer to fill.
+    ///
+    /// Calling this after every frame makes the callback receive exactly the samples of that frame.
+    pub fn flush_audio(&mut self) {
+        self.memory.apu.flush();
+    }
 }
 
 pub mod components;
 pub mod constants;
+pub mod debugger;
 pub mod error;
+mod hooks;
+pub mod movie;
+#[cfg(feature = "pool")]
+pub mod pool;
+pub mod rewind;
+pub mod save_state;
+#[cfg(any(test, feature = "test-rom"))]
+pub mod test_rom;
 pub mod utils;
@@ -0,0 +1,353 @@
+//! Input movies.
+//!
+//! A movie is the joypad state of every frame, along with the state the emulation started from.
+//! The emulation is deterministic, so playing a movie back reproduces the exact same frames and
+//! audio as when it was recorded.
+//!
+//! | Field           | Size | Notes                                                   |
+//! | --------------- | ---- | ------------------------------------------------------- |
+//! | Magic           | 4    | `GBMV`                                                  |
+//! | Version         | 2    | [`MOVIE_VERSION`], little endian                        |
+//! | Device model    | 1    | 0: DMG, 1: CGB                                          |
+//! | Has ROM         | 1    | 0 for imported movies, which don't identify the ROM     |
+//! | ROM identity    | 27   | Only if "Has ROM" is 1, same layout as the save states  |
+//! | Start           | 1    | 0: power on, 1: power on with cartridge RAM, 2: state   |
+//! | Start data      | -    | Byte array with the cartridge RAM or the save state     |
+//! | Frame count     | 4    |                                                         |
+//! | Frames          | 2    | Per frame, see below                                    |
+//!
+//! Each frame is a `u16` where bits 0-7 are the pressed buttons, in the same order as
+//! [`Button`](crate::utils::button::Button) (A, B, Select, Start, Right, Left, Up, Down),
+//! and bit 8 is set when the system was soft reset right before the frame.
+//!
+//! Every multi-byte value is stored in little endian.
+//! Byte arrays are prefixed by their length (`u32`).
+
+pub use self::error::MovieError;
+use crate::{
+    constants::DeviceModel,
+    save_state::{RomIdentity, SaveStateError, StateReader, StateWriter, device_model_to_u8},
+};
+
+pub const MOVIE_MAGIC: [u8; 4] = *b"GBMV";
+pub const MOVIE_VERSION: u16 = 1;
+
+const RESET_BIT: u16 = 1 << 8;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MovieStart {
+    /// Power on with cleared cartridge RAM.
+    PowerOn,
+    /// Power on with the provided cartridge RAM.
+    CartridgeRam(Vec<u8>),
+    /// Embedded save state.
+    State(Vec<u8>),
+}
+
+/// Where a new recording starts from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecordingStart {
+    /// Power cycles the system before recording, keeping the cartridge RAM.
+    PowerOn,
+    /// Embeds the current state in the movie.
+    CurrentState,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct MovieFrame {
+    /// Pressed buttons, one bit per [`Button`](crate::utils::button::Button).
+    pub buttons: u8,
+    /// Soft reset right before this frame.
+    pub reset: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Movie {
+    pub device_model: DeviceModel,
+    /// `None` when imported from other emulators.
+    pub rom: Option<RomIdentity>,
+    pub start: MovieStart,
+    pub frames: Vec<MovieFrame>,
+}
+
+impl Movie {
+    #[must_use]
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut writer = StateWriter::default();
+
+        writer.write_raw(&MOVIE_MAGIC);
+        writer.write_u16(MOVIE_VERSION);
+        writer.write_u8(device_model_to_u8(self.device_model));
+
+        writer.write_bool(self.rom.is_some());
+
+        if let Some(rom) = &self.rom {
+            writer.write_raw(&rom.title);
+            writer.write_u8(rom.header_checksum);
+            writer.write_u16(rom.global_checksum);
+            writer.write_u64(rom.hash);
+        }
+
+        match &self.start {
+            MovieStart::PowerOn => writer.write_u8(0),
+            MovieStart::CartridgeRam(ram) => {
+                writer.write_u8(1);
+                writer.write_bytes(ram);
+            }
+            MovieStart::State(state) => {
+                writer.write_u8(2);
+                writer.write_bytes(state);
+            }
+        }
+
+        writer.write_u32(self.frames.len() as u32);
+
+        for frame in &self.frames {
+            let reset = if frame.reset { RESET_BIT } else { 0 };
+            writer.write_u16(frame.buttons as u16 | reset);
+        }
+
+        writer.into_inner()
+    }
+
+    pub fn from_bytes(data: &[u8]) -> Result<Self, MovieError> {
+        Self::read(&mut StateReader::new(data)).map_err(|error| {
+            match error {
+                ReadError::Movie(error) => error,
+                ReadError::Reader(SaveStateError::UnexpectedEof) => MovieError::UnexpectedEof,
+                ReadError::Reader(_) => MovieError::InvalidData("malformed field"),
+            }
+        })
+    }
+
+    fn read(reader: &mut StateReader) -> Result<Self, ReadError> {
+        if reader.read_array::<4>()? != MOVIE_MAGIC {
+            return Err(MovieError::InvalidMagic.into());
+        }
+
+        let version = reader.read_u16()?;
+
+        if version != MOVIE_VERSION {
+            return Err(MovieError::UnsupportedVersion {
+                expected: MOVIE_VERSION,
+                found: version,
+            }
+            .into());
+        }
+
+        let device_model = match reader.read_u8()? {
+            0 => DeviceModel::Dmg,
+            1 => DeviceModel::Cgb,
+            _ => return Err(MovieError::InvalidData("device model").into()),
+        };
+
+        let rom = if reader.read_bool()? {
+            Some(RomIdentity {
+                title: reader.read_array()?,
+                header_checksum: reader.read_u8()?,
+                global_checksum: reader.read_u16()?,
+                hash: reader.read_u64()?,
+            })
+        } else {
+            None
+        };
+
+        let start = match reader.read_u8()? {
+            0 => MovieStart::PowerOn,
+            1 => MovieStart::CartridgeRam(reader.read_bytes()?.to_vec()),
+            2 => MovieStart::State(reader.read_bytes()?.to_vec()),
+            _ => return Err(MovieError::InvalidData("start").into()),
+        };
+
+        let frame_count = reader.read_u32()? as usize;
+        let mut frames = Vec::with_capacity(frame_count.min(reader.remaining() / 2));
+
+        for _ in 0..frame_count {
+            let frame = reader.read_u16()?;
+
+            frames.push(MovieFrame {
+                buttons: frame as u8,
+                reset: frame & RESET_BIT != 0,
+            });
+        }
+
+        if !reader.is_empty() {
+            return Err(MovieError::InvalidData("trailing data").into());
+        }
+
+        Ok(Self {
+            device_model,
+            rom,
+            start,
+            frames,
+        })
+    }
+
+    /// Rejects movies recorded with a different ROM or device model.
+    pub(crate) fn validate(
+        &self,
+        device_model: DeviceModel,
+        rom: &RomIdentity,
+    ) -> Result<(), MovieError> {
+        if self.device_model != device_model {
+            return Err(MovieError::DeviceModelMismatch {
+                expected: device_model,
+                found: self.device_model,
+            });
+        }
+
+        if let Some(movie_rom) = &self.rom
+            && movie_rom != rom
+        {
+            return Err(MovieError::RomMismatch {
+                expected: rom.title_lossy(),
+                found: movie_rom.title_lossy(),
+            });
+        }
+
+        Ok(())
+    }
+}
+
+enum ReadError {
+    Movie(MovieError),
+    Reader(SaveStateError),
+}
+
+impl From<MovieError> for ReadError {
+    fn from(error: MovieError) -> Self {
+        Self::Movie(error)
+    }
+}
+
+impl From<SaveStateError> for ReadError {
+    fn from(error: SaveStateError) -> Self {
+        Self::Reader(error)
+    }
+}
+
+/// A movie being recorded or played back by a [`GameBoy`](crate::GameBoy).
+#[derive(Debug)]
+pub(crate) enum MovieSession {
+    Recording {
+        movie: Movie,
+        /// Soft reset since the last frame.
+        reset: bool,
+    },
+    Playing {
+        movie: Movie,
+        /// Frame currently being played.
+        frame: usize,
+    },
+}
+
+impl MovieSession {
+    pub(crate) fn into_movie(self) -> Movie {
+        match self {
+            Self::Recording { movie, .. } | Self::Playing { movie, .. } => movie,
+        }
+    }
+}
+
+#[cfg(feature = "movie-import")]
+pub mod bk2;
+mod error;
+pub mod vbm;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        GameBoy,
+        components::memory::MemoryInterface as _,
+        test_rom::TestRom,
+        utils::button::Button,
+    };
+
+    #[test]
+    fn test_round_trip() {
+        let movie = Movie {
+            device_model: DeviceModel::Cgb,
+            rom: Some(RomIdentity::from_rom(&vec![0x42; 0x8000])),
+            start: MovieStart::CartridgeRam(vec![1, 2, 3]),
+            frames: vec![
+                MovieFrame {
+                    buttons: 0b1000_0001,
+                    reset: false,
+                },
+                MovieFrame {
+                    buttons: 0,
+                    reset: true,
+                },
+            ],
+        };
+
+        let bytes = movie.to_bytes();
+
+        assert_eq!(Movie::from_bytes(&bytes).unwrap(), movie);
+        assert!(matches!(
+            Movie::from_bytes(&bytes[..bytes.len() - 1]),
+            Err(MovieError::UnexpectedEof)
+        ));
+        assert!(matches!(
+            Movie::from_bytes(b"GBSS"),
+            Err(MovieError::InvalidMagic)
+        ));
+    }
+
+    #[test]
+    fn test_movie_playback() {
+        let mut gb = GameBoy::new(DeviceModel::Cgb);
+        gb.load(None, TestRom::serial(0x42, 0x81).build()).unwrap();
+        gb.start_recording(RecordingStart::PowerOn).unwrap();
+
+        for frame in 0..10 {
+            if frame == 3 {
+                gb.joypad_button_down(Button::A);
+            }
+
+            if frame == 6 {
+                gb.reset();
+            }
+
+            gb.run_frame();
+        }
+
+        let state = gb.save_state();
+        let movie = gb.stop_movie().unwrap();
+        assert_eq!(movie.frames.len(), 10);
+        assert!(movie.frames[6].reset);
+
+        gb.play_movie(movie).unwrap();
+
+        // Inputs are ignored during the playback.
+        gb.joypad_button_down(Button::B);
+
+        for _ in 0..10 {
+            gb.run_frame();
+        }
+
+        assert_eq!(gb.save_state(), state);
+        assert!(!gb.is_playing_movie());
+    }
+
+    #[test]
+    fn test_recording_keeps_cartridge_ram() {
+        // MBC1+RAM+BATTERY, 8 KiB of RAM
+        let rom = TestRom::new().mbc(0x03).ram_size(0x02).build();
+
+        let mut gb = GameBoy::new(DeviceModel::Cgb);
+        gb.load(None, rom).unwrap();
+
+        // Enables the cartridge RAM and writes to it, without saving the battery.
+        let memory = gb.memory_mut();
+        memory.write(0x0000, 0x0A);
+        memory.write(0xA000, 0x42);
+
+        gb.start_recording(RecordingStart::PowerOn).unwrap();
+        assert_eq!(gb.get_battery().unwrap()[0], 0x42);
+
+        let movie = gb.stop_movie().unwrap();
+        assert!(matches!(movie.start, MovieStart::CartridgeRam(ram) if ram[0] == 0x42));
+    }
+}
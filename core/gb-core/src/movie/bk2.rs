@@ -0,0 +1,230 @@
+//! `BizHawk` movies (`.bk2`).
+//!
+//! A `.bk2` is a zip archive. The relevant entries are `Header.txt`, with one `Key Value` pair
+//! per line, and `Input Log.txt`, where every frame is a line like `|.|U.......|`. The
+//! `LogKey` line names the buttons of each `|` separated group, in order. Any character other
+//! than `.` or a space means that the button is pressed.
+
+use std::io::{Cursor, Read};
+
+use zip::ZipArchive;
+
+use super::{Movie, MovieError, MovieFrame, MovieStart};
+use crate::{constants::DeviceModel, utils::button::Button};
+
+const HEADER_ENTRY: &str = "Header.txt";
+const INPUT_LOG_ENTRY: &str = "Input Log.txt";
+const SAVE_RAM_ENTRY: &str = "SaveRam";
+
+/// Used when the input log has no `LogKey` line.
+const DEFAULT_LOG_KEY: &str = "#Up|Down|Left|Right|Start|Select|B|A|Power|";
+
+#[derive(Clone, Copy)]
+enum Key {
+    Button(Button),
+    Reset,
+    Ignored,
+}
+
+impl Key {
+    fn from_name(name: &str) -> Self {
+        // Multiplayer cores prefix the buttons with the player.
+        let name = name.strip_prefix("P1 ").unwrap_or(name);
+
+        match name {
+            "A" => Self::Button(Button::A),
+            "B" => Self::Button(Button::B),
+            "Select" => Self::Button(Button::Select),
+            "Start" => Self::Button(Button::Start),
+            "Right" => Self::Button(Button::Right),
+            "Left" => Self::Button(Button::Left),
+            "Up" => Self::Button(Button::Up),
+            "Down" => Self::Button(Button::Down),
+            "Power" | "Reset" | "Hard Reset" => Self::Reset,
+            _ => Self::Ignored,
+        }
+    }
+}
+
+/// Converts a `BizHawk` movie, which does not identify the ROM with the same hash as ours.
+pub fn import(data: &[u8]) -> Result<Movie, MovieError> {
+    let mut archive = ZipArchive::new(Cursor::new(data))?;
+
+    let header = read_entry(&mut archive, HEADER_ENTRY)?;
+    let header_value = |key: &str| {
+        header.lines().find_map(|line| {
+            let (line_key, value) = line.split_once(' ').unwrap_or((line, ""));
+            (line_key == key).then(|| value.trim())
+        })
+    };
+    let is_true = |value: Option<&str>| value.is_some_and(|value| matches!(value, "True" | "1"));
+
+    if is_true(header_value("StartsFromSavestate")) {
+        return Err(MovieError::Unsupported("starts from a BizHawk save state"));
+    }
+
+    let device_model =
+        if header_value("Platform") == Some("GBC") || is_true(header_value("IsCGBMode")) {
+            DeviceModel::Cgb
+        } else {
+            DeviceModel::Dmg
+        };
+
+    let start = if is_true(header_value("StartsFromSaveRam")) {
+        let mut ram = Vec::new();
+        archive
+            .by_name(SAVE_RAM_ENTRY)
+            .map_err(|_| MovieError::MissingEntry(SAVE_RAM_ENTRY))?
+            .read_to_end(&mut ram)?;
+
+        MovieStart::CartridgeRam(ram)
+    } else {
+        MovieStart::PowerOn
+    };
+
+    let input_log = read_entry(&mut archive, INPUT_LOG_ENTRY)?;
+
+    Ok(Movie {
+        device_model,
+        rom: None,
+        start,
+        frames: parse_input_log(&input_log)?,
+    })
+}
+
+fn read_entry(
+    archive: &mut ZipArchive<Cursor<&[u8]>>,
+    name: &'static str,
+) -> Result<String, MovieError> {
+    let mut entry = archive
+        .by_name(name)
+        .map_err(|_| MovieError::MissingEntry(name))?;
+
+    let mut contents = String::new();
+    entry.read_to_string(&mut contents)?;
+
+    Ok(contents)
+}
+
+fn parse_log_key(log_key: &str) -> Vec<Vec<Key>> {
+    log_key
+        .split('#')
+        .skip(1)
+        .map(|group| {
+            group
+                .split('|')
+                .filter(|name| !name.is_empty())
+                .map(Key::from_name)
+                .collect()
+        })
+        .collect()
+}
+
+fn parse_input_log(input_log: &str) -> Result<Vec<MovieFrame>, MovieError> {
+    let log_key = input_log
+        .lines()
+        .find_map(|line| line.strip_prefix("LogKey:"))
+        .unwrap_or(DEFAULT_LOG_KEY);
+
+    let groups = parse_log_key(log_key);
+
+    let mut frames = Vec::new();
+
+    for line in input_log.lines().filter(|line| line.starts_with('|')) {
+        let mut frame = MovieFrame::default();
+
+        let line_groups = line.trim_matches('|').split('|');
+
+        for (keys, group) in groups.iter().zip(line_groups) {
+            if group.chars().count() != keys.len() {
+                return Err(MovieError::InvalidData("input log line"));
+            }
+
+            for (key, mnemonic) in keys.iter().zip(group.chars()) {
+                if matches!(mnemonic, '.' | ' ') {
+                    continue;
+                }
+
+                match key {
+                    Key::Button(button) => frame.buttons |= *button as u8,
+                    Key::Reset => frame.reset = true,
+                    Key::Ignored => {}
+                }
+            }
+        }
+
+        frames.push(frame);
+    }
+
+    Ok(frames)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write as _;
+
+    use zip::{ZipWriter, write::SimpleFileOptions};
+
+    use super::*;
+
+    fn archive(entries: &[(&str, &str)]) -> Vec<u8> {
+        let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+
+        for (name, contents) in entries {
+            writer
+                .start_file(*name, SimpleFileOptions::default())
+                .unwrap();
+            writer.write_all(contents.as_bytes()).unwrap();
+        }
+
+        writer.finish().unwrap().into_inner()
+    }
+
+    #[test]
+    fn test_import() {
+        let data = archive(&[
+            ("Header.txt", "MovieVersion BizHawk v2.0\nPlatform GBC\n"),
+            (
+                "Input Log.txt",
+                "[Input]\n\
+                 LogKey:#Up|Down|Left|Right|Start|Select|B|A|Power|\n\
+                 |U......A.|\n\
+                 |........P|\n\
+                 |...R..B..|\n\
+                 [/Input]\n",
+            ),
+        ]);
+
+        let movie = import(&data).unwrap();
+
+        assert_eq!(movie.device_model, DeviceModel::Cgb);
+        assert_eq!(movie.start, MovieStart::PowerOn);
+        assert_eq!(
+            movie.frames,
+            [
+                MovieFrame {
+                    buttons: Button::Up as u8 | Button::A as u8,
+                    reset: false,
+                },
+                MovieFrame {
+                    buttons: 0,
+                    reset: true,
+                },
+                MovieFrame {
+                    buttons: Button::Right as u8 | Button::B as u8,
+                    reset: false,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_unsupported_start() {
+        let data = archive(&[
+            ("Header.txt", "StartsFromSavestate True\n"),
+            ("Input Log.txt", ""),
+        ]);
+
+        assert!(matches!(import(&data), Err(MovieError::Unsupported(_))));
+    }
+}
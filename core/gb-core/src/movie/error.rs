@@ -0,0 +1,49 @@
+use thiserror::Error;
+
+use crate::{constants::DeviceModel, save_state::SaveStateError};
+
+#[derive(Debug, Error)]
+pub enum MovieError {
+    #[error("Not a movie.")]
+    InvalidMagic,
+
+    #[error("Unsupported movie version (expected = {expected}, found = {found}).")]
+    UnsupportedVersion { expected: u16, found: u16 },
+
+    #[error("Device model mismatch (expected = {expected:?}, found = {found:?}).")]
+    DeviceModelMismatch {
+        expected: DeviceModel,
+        found: DeviceModel,
+    },
+
+    #[error(
+        "The movie belongs to a different ROM (expected = \"{expected}\", found = \"{found}\")."
+    )]
+    RomMismatch { expected: String, found: String },
+
+    #[error("No cartridge is loaded.")]
+    NoCartridge,
+
+    #[error("Unexpected end of the movie.")]
+    UnexpectedEof,
+
+    #[error("Invalid movie data ({0}).")]
+    InvalidData(&'static str),
+
+    #[error("Unsupported movie ({0}).")]
+    Unsupported(&'static str),
+
+    #[cfg(feature = "movie-import")]
+    #[error("The movie archive has no \"{0}\" entry.")]
+    MissingEntry(&'static str),
+
+    #[cfg(feature = "movie-import")]
+    #[error("Failed to read the movie archive: {0}.")]
+    Archive(#[from] zip::result::ZipError),
+
+    #[error("Failed to read the movie: {0}.")]
+    Io(#[from] std::io::Error),
+
+    #[error("Failed to load the initial state: {0}.")]
+    State(#[from] SaveStateError),
+}
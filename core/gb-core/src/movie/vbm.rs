@@ -0,0 +1,178 @@
+//! `VisualBoyAdvance` movies (`.vbm`).
+//!
+//! | Offset | Size | Notes                                                         |
+//! | ------ | ---- | ------------------------------------------------------------- |
+//! | 0x00   | 4    | `VBM\x1A`                                                     |
+//! | 0x04   | 4    | Version (1)                                                   |
+//! | 0x0C   | 4    | Frame count                                                   |
+//! | 0x14   | 1    | Start flags, bit 0: save state, bit 1: SRAM                   |
+//! | 0x15   | 1    | Controller flags, bits 0-3: controllers 1-4 enabled           |
+//! | 0x16   | 1    | System flags, bit 0: GBA, bit 1: GBC, bit 2: GB, bit 3: SGB   |
+//! | 0x38   | 4    | Offset of the save state or SRAM                              |
+//! | 0x3C   | 4    | Offset of the controller data                                 |
+//!
+//! Every frame has a `u16` per enabled controller. Bits 0-7 match our button order,
+//! bits 10 and 11 are resets (the former from older versions).
+//! Every multi-byte value is stored in little endian.
+
+use super::{Movie, MovieError, MovieFrame, MovieStart};
+use crate::constants::DeviceModel;
+
+const MAGIC: [u8; 4] = *b"VBM\x1A";
+const VERSION: u32 = 1;
+const HEADER_SIZE: usize = 0x40;
+
+const START_FROM_STATE: u8 = 1 << 0;
+const START_FROM_SRAM: u8 = 1 << 1;
+
+const SYSTEM_GBC: u8 = 1 << 1;
+
+const RESET_MASK: u16 = 0x0C00;
+
+/// Converts a VBA movie, which does not identify the ROM with the same hash as ours.
+pub fn import(data: &[u8]) -> Result<Movie, MovieError> {
+    if data.len() < HEADER_SIZE {
+        return Err(MovieError::UnexpectedEof);
+    }
+
+    if data[0x00..0x04] != MAGIC {
+        return Err(MovieError::InvalidMagic);
+    }
+
+    let version = read_u32(data, 0x04);
+
+    if version != VERSION {
+        return Err(MovieError::Unsupported("VBM version"));
+    }
+
+    let frame_count = read_u32(data, 0x0C) as usize;
+    let start_flags = data[0x14];
+    let controller_flags = data[0x15];
+    let system_flags = data[0x16];
+    let start_offset = read_u32(data, 0x38) as usize;
+    let controller_offset = read_u32(data, 0x3C) as usize;
+
+    if start_flags & START_FROM_STATE != 0 {
+        return Err(MovieError::Unsupported("starts from a VBA save state"));
+    }
+
+    let device_model = if system_flags & SYSTEM_GBC != 0 {
+        DeviceModel::Cgb
+    } else {
+        DeviceModel::Dmg
+    };
+
+    let start = if start_flags & START_FROM_SRAM != 0 {
+        let ram = data
+            .get(start_offset..controller_offset)
+            .ok_or(MovieError::InvalidData("SRAM offset"))?;
+
+        MovieStart::CartridgeRam(ram.to_vec())
+    } else {
+        MovieStart::PowerOn
+    };
+
+    // Only the first controller is used.
+    let controllers = (controller_flags & 0b1111).count_ones() as usize;
+
+    if controllers == 0 {
+        return Err(MovieError::InvalidData("no controllers"));
+    }
+
+    let stride = controllers * 2;
+
+    let input = data
+        .get(controller_offset..)
+        .filter(|input| input.len() >= frame_count * stride)
+        .ok_or(MovieError::UnexpectedEof)?;
+
+    let frames = input
+        .chunks_exact(stride)
+        .take(frame_count)
+        .map(|frame| {
+            let frame = u16::from_le_bytes([frame[0], frame[1]]);
+
+            MovieFrame {
+                buttons: frame as u8,
+                reset: frame & RESET_MASK != 0,
+            }
+        })
+        .collect();
+
+    Ok(Movie {
+        device_model,
+        rom: None,
+        start,
+        frames,
+    })
+}
+
+fn read_u32(data: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes([
+        data[offset],
+        data[offset + 1],
+        data[offset + 2],
+        data[offset + 3],
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::button::Button;
+
+    fn vbm(start_flags: u8, sram: &[u8], input: &[u16]) -> Vec<u8> {
+        let mut data = vec![0; HEADER_SIZE];
+
+        data[0x00..0x04].copy_from_slice(&MAGIC);
+        data[0x04..0x08].copy_from_slice(&VERSION.to_le_bytes());
+        data[0x0C..0x10].copy_from_slice(&(input.len() as u32).to_le_bytes());
+        data[0x14] = start_flags;
+        data[0x15] = 0b0001;
+        data[0x16] = SYSTEM_GBC;
+
+        let start_offset = data.len() as u32;
+        let controller_offset = start_offset + sram.len() as u32;
+        data[0x38..0x3C].copy_from_slice(&start_offset.to_le_bytes());
+        data[0x3C..0x40].copy_from_slice(&controller_offset.to_le_bytes());
+
+        data.extend_from_slice(sram);
+
+        for frame in input {
+            data.extend_from_slice(&frame.to_le_bytes());
+        }
+
+        data
+    }
+
+    #[test]
+    fn test_import() {
+        let data = vbm(START_FROM_SRAM, &[0xAA; 4], &[0x0041, 0x0800, 0x0000]);
+        let movie = import(&data).unwrap();
+
+        assert_eq!(movie.device_model, DeviceModel::Cgb);
+        assert_eq!(movie.start, MovieStart::CartridgeRam(vec![0xAA; 4]));
+        assert_eq!(
+            movie.frames,
+            [
+                MovieFrame {
+                    buttons: Button::A as u8 | Button::Up as u8,
+                    reset: false,
+                },
+                MovieFrame {
+                    buttons: 0,
+                    reset: true,
+                },
+                MovieFrame::default(),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_truncated_input() {
+        let mut data = vbm(0, &[], &[0x0001, 0x0002]);
+        data.pop();
+
+        assert!(matches!(import(&data), Err(MovieError::UnexpectedEof)));
+    }
+}
@@ -0,0 +1,178 @@
+//! Runs many independent Game Boys in parallel, for batch jobs like agent training and fuzzing.
+//!
+//! Every call to [`GameBoyPool::run_frame`] steps all the instances by one frame on a thread
+//! pool, then gathers their screens into one contiguous buffer.
+
+use rayon::{ThreadPool, ThreadPoolBuildError, ThreadPoolBuilder, prelude::*};
+use thiserror::Error;
+
+use crate::{
+    GameBoy,
+    constants::{SCREEN_PIXELS_SIZE, ScreenPixels},
+};
+
+#[derive(Debug, Error)]
+pub enum PoolError {
+    #[error("Failed to start the thread pool: {0}.")]
+    ThreadPool(#[from] ThreadPoolBuildError),
+
+    #[error("Expected one input per instance ({expected}), got {actual}.")]
+    InputCount { expected: usize, actual: usize },
+}
+
+pub struct GameBoyPool {
+    instances: Vec<GameBoy>,
+    /// RGBA8888, one screen per instance.
+    frames: Vec<u8>,
+
+    thread_pool: ThreadPool,
+}
+
+impl GameBoyPool {
+    /// Spawns `threads` worker threads, or one per CPU core if `threads` is 0.
+    pub fn new(instances: Vec<GameBoy>, threads: usize) -> Result<Self, PoolError> {
+        let thread_pool = ThreadPoolBuilder::new().num_threads(threads).build()?;
+        let frames = vec![0; instances.len() * SCREEN_PIXELS_SIZE];
+
+        Ok(Self {
+            instances,
+            frames,
+            thread_pool,
+        })
+    }
+
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.instances.len()
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.instances.is_empty()
+    }
+
+    pub fn push(&mut self, gb: GameBoy) {
+        self.instances.push(gb);
+        self.frames
+            .resize(self.instances.len() * SCREEN_PIXELS_SIZE, 0);
+    }
+
+    #[must_use]
+    pub fn instances(&self) -> &[GameBoy] {
+        &self.instances
+    }
+
+    pub fn instances_mut(&mut self) -> &mut [GameBoy] {
+        &mut self.instances
+    }
+
+    #[must_use]
+    pub fn into_instances(self) -> Vec<GameBoy> {
+        self.instances
+    }
+
+    /// Runs every instance for one frame, holding `inputs[i]` on the instance `i`.
+    ///
+    /// Each input has one bit per [`Button`](crate::utils::button::Button), same as
+    /// [`GameBoy::set_joypad_buttons`].
+    pub fn run_frame(&mut self, inputs: &[u8]) -> Result<(), PoolError> {
+        if inputs.len() != self.instances.len() {
+            return Err(PoolError::InputCount {
+                expected: self.instances.len(),
+                actual: inputs.len(),
+            });
+        }
+
+        let instances = &mut self.instances;
+        let frames = &mut self.frames;
+
+        self.thread_pool.install(|| {
+            instances
+                .par_iter_mut()
+                .zip(frames.par_chunks_exact_mut(SCREEN_PIXELS_SIZE))
+                .zip(inputs.par_iter())
+                .for_each(|((gb, frame), &buttons)| {
+                    gb.set_joypad_buttons(buttons);
+                    gb.run_frame();
+                    gb.draw_into_frame_rgba8888(frame.try_into().unwrap());
+                });
+        });
+
+        Ok(())
+    }
+
+    /// The screens after the last [`GameBoyPool::run_frame`], back to back in RGBA8888.
+    #[must_use]
+    pub fn frames(&self) -> &[u8] {
+        &self.frames
+    }
+
+    #[must_use]
+    pub fn frame(&self, index: usize) -> &ScreenPixels {
+        let start = index * SCREEN_PIXELS_SIZE;
+
+        self.frames[start..start + SCREEN_PIXELS_SIZE]
+            .try_into()
+            .unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        components::memory::MemoryInterface as _,
+        constants::DeviceModel,
+        test_rom::TestRom,
+        utils::button::Button,
+    };
+
+    fn new_instance() -> GameBoy {
+        let mut gb = GameBoy::new(DeviceModel::Cgb);
+        gb.load(None, TestRom::new().build()).unwrap();
+
+        // Select the action buttons.
+        gb.memory_mut().write(0xFF00, 0x10);
+
+        gb
+    }
+
+    #[test]
+    fn test_run_frame() {
+        let instances = (0..3).map(|_| new_instance()).collect();
+        let mut pool = GameBoyPool::new(instances, 2).unwrap();
+
+        pool.run_frame(&[0, Button::A as u8, Button::B as u8])
+            .unwrap();
+
+        let joyp = pool
+            .instances()
+            .iter()
+            .map(|gb| gb.memory().read(0xFF00) & 0b11)
+            .collect::<Vec<_>>();
+
+        assert_eq!(joyp, [0b11, 0b10, 0b01]);
+        assert_eq!(pool.frames().len(), 3 * SCREEN_PIXELS_SIZE);
+
+        #[allow(clippy::large_stack_arrays)]
+        let mut frame = [0; SCREEN_PIXELS_SIZE];
+
+        for (index, gb) in pool.instances().iter().enumerate() {
+            gb.draw_into_frame_rgba8888(&mut frame);
+            assert_eq!(pool.frame(index), &frame);
+        }
+    }
+
+    #[test]
+    fn test_input_count() {
+        let mut pool = GameBoyPool::new(vec![new_instance()], 1).unwrap();
+
+        assert!(matches!(
+            pool.run_frame(&[0, 0]),
+            Err(PoolError::InputCount {
+                expected: 1,
+                actual: 2,
+            })
+        ));
+    }
+}
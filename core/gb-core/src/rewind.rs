@@ -0,0 +1,357 @@
+//! Rewind buffer.
+//!
+//! Snapshots are regular save states taken at the end of a frame. Only the newest snapshot is
+//! kept as is, every older one is stored as the XOR against its successor with the zero runs
+//! compressed away. Consecutive frames share most of their state, so these deltas are tiny.
+//!
+//! Going back one snapshot XORs the newest snapshot with the last delta, which makes the
+//! restored snapshot the newest one. Dropping the oldest snapshot only drops its delta.
+
+use std::collections::VecDeque;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RewindConfig {
+    /// Frames between two snapshots.
+    pub interval: u32,
+    /// Maximum number of snapshots kept, the oldest ones are dropped first.
+    pub capacity: usize,
+}
+
+impl Default for RewindConfig {
+    fn default() -> Self {
+        // One minute of history at full granularity.
+        Self {
+            interval: 1,
+            capacity: 60 * 60,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Snapshot {
+    frame: u64,
+    state: Vec<u8>,
+}
+
+#[derive(Debug)]
+struct Delta {
+    frame: u64,
+    length: usize,
+    data: Vec<u8>,
+}
+
+impl Delta {
+    /// Encodes `older` as a delta against `newer`.
+    fn encode(frame: u64, older: &[u8], newer: &[u8]) -> Self {
+        let xor = older
+            .iter()
+            .enumerate()
+            .map(|(i, byte)| byte ^ newer.get(i).copied().unwrap_or(0));
+
+        Self {
+            frame,
+            length: older.len(),
+            data: compress_zero_runs(xor),
+        }
+    }
+
+    /// Rebuilds the older snapshot from the newer one.
+    fn apply(&self, newer: &[u8]) -> Vec<u8> {
+        let mut older = decompress_zero_runs(&self.data, self.length);
+
+        for (byte, newer) in older.iter_mut().zip(newer) {
+            *byte ^= newer;
+        }
+
+        older
+    }
+}
+
+#[derive(Debug)]
+pub struct RewindBuffer {
+    config: RewindConfig,
+
+    /// Frames run since the buffer was created, rewinding moves it back.
+    frame: u64,
+
+    newest: Option<Snapshot>,
+    /// Older snapshots, oldest first.
+    deltas: VecDeque<Delta>,
+}
+
+impl RewindBuffer {
+    #[must_use]
+    pub fn new(config: RewindConfig) -> Self {
+        Self {
+            config: RewindConfig {
+                interval: config.interval.max(1),
+                capacity: config.capacity.max(1),
+            },
+            frame: 0,
+            newest: None,
+            deltas: VecDeque::new(),
+        }
+    }
+
+    #[must_use]
+    pub fn config(&self) -> RewindConfig {
+        self.config
+    }
+
+    /// Number of snapshots currently stored.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.deltas.len() + usize::from(self.newest.is_some())
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.newest.is_none()
+    }
+
+    /// Approximate memory used by the snapshots, in bytes.
+    #[must_use]
+    pub fn memory_usage(&self) -> usize {
+        let newest = self
+            .newest
+            .as_ref()
+            .map_or(0, |snapshot| snapshot.state.len());
+        let deltas = self
+            .deltas
+            .iter()
+            .map(|delta| delta.data.len())
+            .sum::<usize>();
+
+        newest + deltas
+    }
+
+    pub fn clear(&mut self) {
+        self.frame = 0;
+        self.newest = None;
+        self.deltas.clear();
+    }
+
+    /// Called at the end of every frame, `save_state` is only called when a snapshot is due.
+    pub(crate) fn end_frame(&mut self, save_state: impl FnOnce() -> Vec<u8>) {
+        self.frame += 1;
+
+        let due = self
+            .newest
+            .as_ref()
+            .is_none_or(|newest| self.frame - newest.frame >= self.config.interval as u64);
+
+        if due {
+            self.push(save_state());
+        }
+    }
+
+    fn push(&mut self, state: Vec<u8>) {
+        if let Some(previous) = self.newest.take() {
+            let delta = Delta::encode(previous.frame, &previous.state, &state);
+            self.deltas.push_back(delta);
+
+            // The new snapshot takes one of the slots.
+            if self.deltas.len() >= self.config.capacity {
+                self.deltas.pop_front();
+            }
+        }
+
+        self.newest = Some(Snapshot {
+            frame: self.frame,
+            state,
+        });
+    }
+
+    /// Goes back to the newest snapshot taken at least `frames` frames ago, or to the oldest one
+    /// if the history is not long enough.
+    ///
+    /// Returns the number of frames actually rewound along with the state to be loaded.
+    pub(crate) fn rewind(&mut self, frames: u64) -> Option<(u64, Vec<u8>)> {
+        let target = self.frame.saturating_sub(frames);
+
+        let mut newest = self.newest.take()?;
+
+        while newest.frame > target {
+            let Some(delta) = self.deltas.pop_back() else {
+                break;
+            };
+
+            newest = Snapshot {
+                frame: delta.frame,
+                state: delta.apply(&newest.state),
+            };
+        }
+
+        let rewound = self.frame - newest.frame;
+        let state = newest.state.clone();
+
+        self.frame = newest.frame;
+        self.newest = Some(newest);
+
+        Some((rewound, state))
+    }
+}
+
+/// Encodes the bytes as runs of `(zero count, literal count, literals)`, with LEB128 counts.
+fn compress_zero_runs(bytes: impl Iterator<Item = u8>) -> Vec<u8> {
+    let mut output = Vec::new();
+    let mut literals = Vec::new();
+    let mut zeros = 0;
+
+    for byte in bytes {
+        if byte == 0 {
+            if !literals.is_empty() {
+                write_run(&mut output, zeros, &literals);
+                literals.clear();
+                zeros = 0;
+            }
+
+            zeros += 1;
+        } else {
+            literals.push(byte);
+        }
+    }
+
+    if zeros != 0 || !literals.is_empty() {
+        write_run(&mut output, zeros, &literals);
+    }
+
+    output
+}
+
+fn decompress_zero_runs(mut data: &[u8], length: usize) -> Vec<u8> {
+    let mut output = Vec::with_capacity(length);
+
+    while !data.is_empty() {
+        let zeros = read_leb128(&mut data);
+        let literals = read_leb128(&mut data);
+
+        output.resize(output.len() + zeros, 0);
+        output.extend_from_slice(&data[..literals]);
+        data = &data[literals..];
+    }
+
+    output.resize(length, 0);
+    output
+}
+
+fn write_run(output: &mut Vec<u8>, zeros: usize, literals: &[u8]) {
+    write_leb128(output, zeros);
+    write_leb128(output, literals.len());
+    output.extend_from_slice(literals);
+}
+
+fn write_leb128(output: &mut Vec<u8>, mut value: usize) {
+    loop {
+        let byte = (value & 0x7F) as u8;
+        value >>= 7;
+
+        if value == 0 {
+            output.push(byte);
+            return;
+        }
+
+        output.push(byte | 0x80);
+    }
+}
+
+fn read_leb128(data: &mut &[u8]) -> usize {
+    let mut value = 0;
+    let mut shift = 0;
+
+    while let Some((&byte, rest)) = data.split_first() {
+        *data = rest;
+        value |= ((byte & 0x7F) as usize) << shift;
+        shift += 7;
+
+        if byte & 0x80 == 0 {
+            break;
+        }
+    }
+
+    value
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{GameBoy, constants::DeviceModel, save_state::SaveStateError, test_rom::TestRom};
+
+    #[test]
+    fn test_zero_run_compression() {
+        let mut bytes = vec![0; 1000];
+        bytes[3] = 1;
+        bytes[4] = 2;
+        bytes[999] = 3;
+
+        let compressed = compress_zero_runs(bytes.iter().copied());
+
+        assert!(compressed.len() < 16);
+        assert_eq!(decompress_zero_runs(&compressed, bytes.len()), bytes);
+    }
+
+    #[test]
+    fn test_rewind_across_snapshots() {
+        let mut buffer = RewindBuffer::new(RewindConfig {
+            interval: 2,
+            capacity: 3,
+        });
+
+        let state = |frame: u8| vec![frame; 4 + frame as usize];
+
+        for frame in 1..=8 {
+            buffer.end_frame(|| state(frame));
+        }
+
+        // Snapshots of frames 3, 5 and 7 are left.
+        assert_eq!(buffer.len(), 3);
+
+        assert_eq!(buffer.rewind(1), Some((1, state(7))));
+        assert_eq!(buffer.rewind(100), Some((4, state(3))));
+        assert_eq!(buffer.rewind(1), Some((0, state(3))));
+        assert_eq!(buffer.len(), 1);
+    }
+
+    #[test]
+    fn test_rewind_frames() {
+        let mut gb = GameBoy::new(DeviceModel::Cgb);
+        gb.load(None, TestRom::serial(0x42, 0x81).build()).unwrap();
+        gb.enable_rewind(RewindConfig::default());
+
+        for _ in 0..5 {
+            gb.run_frame();
+        }
+
+        let snapshot = gb.save_state();
+
+        for _ in 0..5 {
+            gb.run_frame();
+        }
+
+        assert_eq!(gb.rewind_frames(5).unwrap(), 5);
+        assert_eq!(gb.save_state(), snapshot);
+
+        // Only 4 frames of history are left before the first snapshot.
+        assert_eq!(gb.rewind_frames(10).unwrap(), 4);
+    }
+
+    #[test]
+    fn test_rewind_after_device_model_change() {
+        let mut gb = GameBoy::new(DeviceModel::Cgb);
+        gb.load(None, TestRom::serial(0x42, 0x81).build()).unwrap();
+        gb.enable_rewind(RewindConfig::default());
+
+        for _ in 0..10 {
+            gb.run_frame();
+        }
+
+        gb.device_model = DeviceModel::Dmg;
+
+        assert!(matches!(
+            gb.rewind_frames(5),
+            Err(SaveStateError::DeviceModelMismatch { .. })
+        ));
+        assert!(gb.rewind_buffer().unwrap().is_empty());
+        assert_eq!(gb.rewind_frames(5).unwrap(), 0);
+    }
+}
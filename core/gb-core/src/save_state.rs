@@ -0,0 +1,505 @@
+//! Save states.
+//!
+//! A save state is a header followed by the state of each component, in a fixed order.
+//!
+//! | Field           | Size | Notes                                       |
+//! | --------------- | ---- | ------------------------------------------- |
+//! | Magic           | 4    | `GBSS`                                      |
+//! | Version         | 2    | [`SAVE_STATE_VERSION`], little endian       |
+//! | Device model    | 1    | 0: DMG, 1: CGB                              |
+//! | Title           | 16   | 0x0134 ~ 0x0143                             |
+//! | Header checksum | 1    | 0x014D                                      |
+//! | Global checksum | 2    | 0x014E ~ 0x014F                             |
+//! | ROM hash        | 8    | FNV-1a (64 bits) of the whole ROM           |
+//! | Body            | -    | CPU, then memory (and all its components)   |
+//!
+//! Every multi-byte value is stored in little endian.
+//! Byte arrays are prefixed by their length (`u32`).
+
+pub use self::error::SaveStateError;
+use crate::constants::DeviceModel;
+
+pub const SAVE_STATE_MAGIC: [u8; 4] = *b"GBSS";
+
+/// Has to be bumped whenever the layout of any component changes.
+pub const SAVE_STATE_VERSION: u16 = 5;
+
+pub(crate) trait SaveState {
+    fn save_state(&self, writer: &mut StateWriter);
+    fn load_state(&mut self, reader: &mut StateReader) -> Result<(), SaveStateError>;
+}
+
+const TITLE_ADDRESS_BEGIN: usize = 0x0134;
+const TITLE_ADDRESS_END: usize = 0x0143;
+const HEADER_CHECKSUM_ADDRESS: usize = 0x014D;
+const GLOBAL_CHECKSUM_ADDRESS: usize = 0x014E;
+
+/// Identifies the ROM a save state was created with.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct RomIdentity {
+    pub title: [u8; 16],
+    pub header_checksum: u8,
+    pub global_checksum: u16,
+    pub hash: u64,
+}
+
+impl RomIdentity {
+    #[must_use]
+    pub fn from_rom(rom: &[u8]) -> Self {
+        let mut title = [0; 16];
+
+        if let Some(bytes) = rom.get(TITLE_ADDRESS_BEGIN..=TITLE_ADDRESS_END) {
+            title.copy_from_slice(bytes);
+        }
+
+        let header_checksum = rom.get(HEADER_CHECKSUM_ADDRESS).copied().unwrap_or(0);
+
+        let global_checksum = {
+            let high = rom.get(GLOBAL_CHECKSUM_ADDRESS).copied().unwrap_or(0) as u16;
+            let low = rom.get(GLOBAL_CHECKSUM_ADDRESS + 1).copied().unwrap_or(0) as u16;
+
+            (high << 8) | low
+        };
+
+        Self {
+            title,
+            header_checksum,
+            global_checksum,
+            hash: fnv1a(rom),
+        }
+    }
+
+    pub(crate) fn title_lossy(&self) -> String {
+        String::from_utf8_lossy(&self.title)
+            .trim_end_matches('\0')
+            .trim()
+            .to_string()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Header {
+    pub version: u16,
+    pub device_model: DeviceModel,
+    pub rom: RomIdentity,
+}
+
+impl Header {
+    pub(crate) fn new(device_model: DeviceModel, rom: RomIdentity) -> Self {
+        Self {
+            version: SAVE_STATE_VERSION,
+            device_model,
+            rom,
+        }
+    }
+
+    pub(crate) fn write(&self, writer: &mut StateWriter) {
+        writer.write_raw(&SAVE_STATE_MAGIC);
+        writer.write_u16(self.version);
+        writer.write_u8(device_model_to_u8(self.device_model));
+        writer.write_raw(&self.rom.title);
+        writer.write_u8(self.rom.header_checksum);
+        writer.write_u16(self.rom.global_checksum);
+        writer.write_u64(self.rom.hash);
+    }
+
+    pub(crate) fn read(reader: &mut StateReader) -> Result<Self, SaveStateError> {
+        if reader.read_array::<4>()? != SAVE_STATE_MAGIC {
+            return Err(SaveStateError::InvalidMagic);
+        }
+
+        let version = reader.read_u16()?;
+
+        if version != SAVE_STATE_VERSION {
+            return Err(SaveStateError::UnsupportedVersion {
+                expected: SAVE_STATE_VERSION,
+                found: version,
+            });
+        }
+
+        let device_model = match reader.read_u8()? {
+            0 => DeviceModel::Dmg,
+            1 => DeviceModel::Cgb,
+            _ => return Err(SaveStateError::InvalidData("device model")),
+        };
+
+        let rom = RomIdentity {
+            title: reader.read_array()?,
+            header_checksum: reader.read_u8()?,
+            global_checksum: reader.read_u16()?,
+            hash: reader.read_u64()?,
+        };
+
+        Ok(Self {
+            version,
+            device_model,
+            rom,
+        })
+    }
+
+    /// Rejects save states created for a different ROM or device model.
+    pub(crate) fn validate(
+        &self,
+        device_model: DeviceModel,
+        rom: &RomIdentity,
+    ) -> Result<(), SaveStateError> {
+        if self.device_model != device_model {
+            return Err(SaveStateError::DeviceModelMismatch {
+                expected: device_model,
+                found: self.device_model,
+            });
+        }
+
+        if self.rom != *rom {
+            return Err(SaveStateError::RomMismatch {
+                expected: rom.title_lossy(),
+                found: self.rom.title_lossy(),
+            });
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct StateWriter {
+    buffer: Vec<u8>,
+}
+
+impl StateWriter {
+    pub fn into_inner(self) -> Vec<u8> {
+        self.buffer
+    }
+
+    pub fn write_u8(&mut self, value: u8) {
+        self.buffer.push(value);
+    }
+
+    pub fn write_bool(&mut self, value: bool) {
+        self.write_u8(value as u8);
+    }
+
+    pub fn write_u16(&mut self, value: u16) {
+        self.buffer.extend_from_slice(&value.to_le_bytes());
+    }
+
+    pub fn write_u32(&mut self, value: u32) {
+        self.buffer.extend_from_slice(&value.to_le_bytes());
+    }
+
+    pub fn write_u64(&mut self, value: u64) {
+        self.buffer.extend_from_slice(&value.to_le_bytes());
+    }
+
+    pub fn write_usize(&mut self, value: usize) {
+        self.write_u64(value as u64);
+    }
+
+    pub fn write_f32(&mut self, value: f32) {
+        self.write_u32(value.to_bits());
+    }
+
+    /// Length-prefixed.
+    pub fn write_bytes(&mut self, bytes: &[u8]) {
+        self.write_u32(bytes.len() as u32);
+        self.write_raw(bytes);
+    }
+
+    pub fn write_raw(&mut self, bytes: &[u8]) {
+        self.buffer.extend_from_slice(bytes);
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct StateReader<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl<'a> StateReader<'a> {
+    pub fn new(data: &'a [u8]) -> Self {
+        Self { data, position: 0 }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.position >= self.data.len()
+    }
+
+    pub fn remaining(&self) -> usize {
+        self.data.len().saturating_sub(self.position)
+    }
+
+    pub fn read_u8(&mut self) -> Result<u8, SaveStateError> {
+        Ok(self.read_raw(1)?[0])
+    }
+
+    pub fn read_bool(&mut self) -> Result<bool, SaveStateError> {
+        match self.read_u8()? {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(SaveStateError::InvalidData("boolean")),
+        }
+    }
+
+    pub fn read_u16(&mut self) -> Result<u16, SaveStateError> {
+        Ok(u16::from_le_bytes(self.read_array()?))
+    }
+
+    pub fn read_u32(&mut self) -> Result<u32, SaveStateError> {
+        Ok(u32::from_le_bytes(self.read_array()?))
+    }
+
+    pub fn read_u64(&mut self) -> Result<u64, SaveStateError> {
+        Ok(u64::from_le_bytes(self.read_array()?))
+    }
+
+    pub fn read_usize(&mut self) -> Result<usize, SaveStateError> {
+        usize::try_from(self.read_u64()?).map_err(|_| SaveStateError::InvalidData("size"))
+    }
+
+    pub fn read_f32(&mut self) -> Result<f32, SaveStateError> {
+        Ok(f32::from_bits(self.read_u32()?))
+    }
+
+    pub fn read_array<const N: usize>(&mut self) -> Result<[u8; N], SaveStateError> {
+        let bytes = self.read_raw(N)?;
+
+        Ok(bytes.try_into().expect("length should be N"))
+    }
+
+    /// Length-prefixed.
+    pub fn read_bytes(&mut self) -> Result<&'a [u8], SaveStateError> {
+        let length = self.read_u32()? as usize;
+
+        self.read_raw(length)
+    }
+
+    /// Length-prefixed. The stored length has to match the destination's length.
+    pub fn read_bytes_into(&mut self, destination: &mut [u8]) -> Result<(), SaveStateError> {
+        let bytes = self.read_bytes()?;
+
+        if bytes.len() != destination.len() {
+            return Err(SaveStateError::InvalidData("byte array length"));
+        }
+
+        destination.copy_from_slice(bytes);
+
+        Ok(())
+    }
+
+    pub fn read_raw(&mut self, length: usize) -> Result<&'a [u8], SaveStateError> {
+        let end = self
+            .position
+            .checked_add(length)
+            .ok_or(SaveStateError::UnexpectedEof)?;
+
+        let bytes = self
+            .data
+            .get(self.position..end)
+            .ok_or(SaveStateError::UnexpectedEof)?;
+
+        self.position = end;
+
+        Ok(bytes)
+    }
+}
+
+pub(crate) fn device_model_to_u8(device_model: DeviceModel) -> u8 {
+    match device_model {
+        DeviceModel::Dmg => 0,
+        DeviceModel::Cgb => 1,
+    }
+}
+
+/// FNV-1a (64 bits).
+fn fnv1a(bytes: &[u8]) -> u64 {
+    const OFFSET_BASIS: u64 = 0xCBF2_9CE4_8422_2325;
+    const PRIME: u64 = 0x0000_0100_0000_01B3;
+
+    bytes.iter().fold(OFFSET_BASIS, |hash, byte| {
+        (hash ^ (*byte as u64)).wrapping_mul(PRIME)
+    })
+}
+
+mod error;
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::{GameBoy, test_rom::TestRom};
+
+    #[test]
+    fn test_round_trip() {
+        let mut writer = StateWriter::default();
+
+        writer.write_u8(0xAB);
+        writer.write_bool(true);
+        writer.write_u16(0x1234);
+        writer.write_u32(0xDEAD_BEEF);
+        writer.write_u64(0x0123_4567_89AB_CDEF);
+        writer.write_usize(42);
+        writer.write_f32(0.5);
+        writer.write_bytes(&[1, 2, 3]);
+
+        let buffer = writer.into_inner();
+        let mut reader = StateReader::new(&buffer);
+
+        assert_eq!(reader.read_u8().unwrap(), 0xAB);
+        assert!(reader.read_bool().unwrap());
+        assert_eq!(reader.read_u16().unwrap(), 0x1234);
+        assert_eq!(reader.read_u32().unwrap(), 0xDEAD_BEEF);
+        assert_eq!(reader.read_u64().unwrap(), 0x0123_4567_89AB_CDEF);
+        assert_eq!(reader.read_usize().unwrap(), 42);
+        assert!((reader.read_f32().unwrap() - 0.5).abs() < f32::EPSILON);
+
+        let mut bytes = [0; 3];
+        reader.read_bytes_into(&mut bytes).unwrap();
+        assert_eq!(bytes, [1, 2, 3]);
+
+        assert!(reader.is_empty());
+        assert!(matches!(
+            reader.read_u8(),
+            Err(SaveStateError::UnexpectedEof)
+        ));
+    }
+
+    #[test]
+    fn test_length_mismatch() {
+        let mut writer = StateWriter::default();
+        writer.write_bytes(&[1, 2, 3]);
+
+        let buffer = writer.into_inner();
+        let mut reader = StateReader::new(&buffer);
+
+        let mut bytes = [0; 4];
+
+        assert!(matches!(
+            reader.read_bytes_into(&mut bytes),
+            Err(SaveStateError::InvalidData(_))
+        ));
+    }
+
+    #[test]
+    fn test_header() {
+        let rom = RomIdentity::from_rom(&vec![0; 0x8000]);
+        let header = Header::new(DeviceModel::Cgb, rom);
+
+        let mut writer = StateWriter::default();
+        header.write(&mut writer);
+
+        let buffer = writer.into_inner();
+        let mut reader = StateReader::new(&buffer);
+
+        let read_header = Header::read(&mut reader).unwrap();
+
+        assert_eq!(read_header, header);
+        assert!(read_header.validate(DeviceModel::Cgb, &rom).is_ok());
+
+        assert!(matches!(
+            read_header.validate(DeviceModel::Dmg, &rom),
+            Err(SaveStateError::DeviceModelMismatch { .. })
+        ));
+
+        let other_rom = RomIdentity::from_rom(&vec![1; 0x8000]);
+
+        assert!(matches!(
+            read_header.validate(DeviceModel::Cgb, &other_rom),
+            Err(SaveStateError::RomMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn test_invalid_header() {
+        let mut reader = StateReader::new(b"NOPE");
+
+        assert!(matches!(
+            Header::read(&mut reader),
+            Err(SaveStateError::InvalidMagic)
+        ));
+
+        let mut writer = StateWriter::default();
+        writer.write_raw(&SAVE_STATE_MAGIC);
+        writer.write_u16(SAVE_STATE_VERSION + 1);
+
+        let buffer = writer.into_inner();
+        let mut reader = StateReader::new(&buffer);
+
+        assert!(matches!(
+            Header::read(&mut reader),
+            Err(SaveStateError::UnsupportedVersion { .. })
+        ));
+    }
+
+    /// A 32 KiB ROM without a MBC which keeps writing to WRAM.
+    fn test_rom(title: &[u8]) -> Arc<[u8]> {
+        // LD HL, 0xC000; loop: INC A; LD (HL), A; INC L; JR loop
+        TestRom::new()
+            .title(title)
+            .program(&[0x21, 0x00, 0xC0, 0x3C, 0x77, 0x2C, 0x18, 0xFB])
+            .build()
+    }
+
+    fn run_frames(gb: &mut GameBoy, frames: usize) {
+        for _ in 0..frames {
+            gb.run_frame();
+        }
+    }
+
+    #[test]
+    fn test_game_boy_round_trip() {
+        let mut gb = GameBoy::new(DeviceModel::Cgb);
+        gb.load(None, test_rom(b"TEST")).unwrap();
+
+        run_frames(&mut gb, 10);
+        let state = gb.save_state();
+
+        run_frames(&mut gb, 10);
+        let expected = gb.save_state();
+
+        gb.load_state(&state).unwrap();
+        assert_eq!(gb.save_state(), state);
+
+        run_frames(&mut gb, 10);
+        assert_eq!(gb.save_state(), expected);
+    }
+
+    #[test]
+    fn test_game_boy_rejects_invalid_states() {
+        let mut gb = GameBoy::new(DeviceModel::Cgb);
+
+        assert!(matches!(
+            gb.load_state(&[]),
+            Err(SaveStateError::NoCartridge)
+        ));
+
+        gb.load(None, test_rom(b"TEST")).unwrap();
+        run_frames(&mut gb, 1);
+
+        let state = gb.save_state();
+        let before = gb.save_state();
+
+        assert!(matches!(
+            gb.load_state(&state[..state.len() - 1]),
+            Err(SaveStateError::UnexpectedEof)
+        ));
+        assert_eq!(gb.save_state(), before);
+
+        let mut other = GameBoy::new(DeviceModel::Cgb);
+        other.load(None, test_rom(b"OTHER")).unwrap();
+
+        assert!(matches!(
+            other.load_state(&state),
+            Err(SaveStateError::RomMismatch { .. })
+        ));
+
+        let mut dmg = GameBoy::new(DeviceModel::Dmg);
+        dmg.load(None, test_rom(b"TEST")).unwrap();
+
+        assert!(matches!(
+            dmg.load_state(&state),
+            Err(SaveStateError::DeviceModelMismatch { .. })
+        ));
+    }
+}
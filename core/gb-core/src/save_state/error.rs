@@ -0,0 +1,35 @@
+use thiserror::Error;
+
+use crate::constants::DeviceModel;
+
+#[derive(Debug, Error)]
+pub enum SaveStateError {
+    #[error("Not a save state.")]
+    InvalidMagic,
+
+    #[error("Unsupported save state version (expected = {expected}, found = {found}).")]
+    UnsupportedVersion { expected: u16, found: u16 },
+
+    #[error("Device model mismatch (expected = {expected:?}, found = {found:?}).")]
+    DeviceModelMismatch {
+        expected: DeviceModel,
+        found: DeviceModel,
+    },
+
+    #[error(
+        "The save state belongs to a different ROM (expected = \"{expected}\", found = \"{found}\")."
+    )]
+    RomMismatch { expected: String, found: String },
+
+    #[error("No cartridge is loaded.")]
+    NoCartridge,
+
+    #[error("The bootrom is still mapped in the save state, but no bootrom was provided.")]
+    MissingBootrom,
+
+    #[error("Unexpected end of the save state.")]
+    UnexpectedEof,
+
+    #[error("Invalid save state data ({0}).")]
+    InvalidData(&'static str),
+}
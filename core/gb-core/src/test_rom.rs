@@ -0,0 +1,90 @@
+//! Synthetic ROMs for the tests, see [`TestRom`].
+//!
+//! Also available to the tests of the frontends through the `test-rom` feature.
+
+use std::sync::Arc;
+
+use crate::components::cartridge::info::{
+    mbc_type::MBC_TYPE_ADDRESS,
+    ram_banks::RAM_BANKS_CODE_ADDRESS,
+    title::TITLE_ADDRESS_BEGIN,
+};
+
+/// Builds a 32 KiB ROM, without a mapper or RAM unless set.
+///
+/// The header is left empty apart from the title, the cartridge type and the RAM size, which is
+/// enough for the cartridge to be accepted. By default, the entry point loops forever (`JR -2`).
+#[derive(Debug, Clone)]
+pub struct TestRom {
+    rom: Vec<u8>,
+}
+
+impl Default for TestRom {
+    fn default() -> Self {
+        let mut rom = vec![0; 0x8000];
+
+        // JR -2
+        rom[0x0100..0x0102].copy_from_slice(&[0x18, 0xFE]);
+
+        Self { rom }
+    }
+}
+
+impl TestRom {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Runs `program` from 0x0150, right after the header, with the interrupts disabled.
+    #[must_use]
+    pub fn program(self, program: &[u8]) -> Self {
+        // DI; JP 0x0150
+        self.code(0x0100, &[0xF3, 0xC3, 0x50, 0x01])
+            .code(0x0150, program)
+    }
+
+    /// Loads SB and SC, then loops forever.
+    #[must_use]
+    pub fn serial(sb: u8, sc: u8) -> Self {
+        // LD A, sb; LDH (0x01), A; LD A, sc; LDH (0x02), A; JR -2
+        Self::new().program(&[0x3E, sb, 0xE0, 0x01, 0x3E, sc, 0xE0, 0x02, 0x18, 0xFE])
+    }
+
+    /// Places `code` at `address`, e.g. at the entry point or at an interrupt vector.
+    #[must_use]
+    pub fn code(mut self, address: u16, code: &[u8]) -> Self {
+        let address = address as usize;
+        self.rom[address..address + code.len()].copy_from_slice(code);
+
+        self
+    }
+
+    #[must_use]
+    pub fn title(mut self, title: &[u8]) -> Self {
+        self.rom[TITLE_ADDRESS_BEGIN..TITLE_ADDRESS_BEGIN + title.len()].copy_from_slice(title);
+
+        self
+    }
+
+    /// The cartridge type in the header, e.g. 0x03 for MBC1+RAM+BATTERY.
+    #[must_use]
+    pub fn mbc(mut self, cartridge_type: u8) -> Self {
+        self.rom[MBC_TYPE_ADDRESS] = cartridge_type;
+
+        self
+    }
+
+    /// The RAM size code in the header, e.g. 0x02 for 8 KiB.
+    #[must_use]
+    pub fn ram_size(mut self, ram_size: u8) -> Self {
+        self.rom[RAM_BANKS_CODE_ADDRESS] = ram_size;
+
+        self
+    }
+
+    #[must_use]
+    pub fn build(self) -> Arc<[u8]> {
+        self.rom.into()
+    }
+}
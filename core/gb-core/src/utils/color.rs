@@ -80,6 +80,18 @@ impl Color {
         Self::new(red, green, blue)
     }
 
+    /// Scales each component to 8 bits, without any correction.
+    #[must_use]
+    pub const fn from_rgb555_scaled(value: u16) -> Self {
+        let raw = Self::from_rgb555(value);
+
+        Self::new(
+            (raw.red << 3) | (raw.red >> 2),
+            (raw.green << 3) | (raw.green >> 2),
+            (raw.blue << 3) | (raw.blue >> 2),
+        )
+    }
+
     #[must_use]
     pub const fn from_rgb555_accurate(value: u16) -> Self {
         let raw_red = value & 0b1_1111;
@@ -113,3 +125,45 @@ impl Color {
         }
     }
 }
+
+/// Shades used for the DMG, from color 0 (lightest) to color 3 (darkest).
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum DmgPalette {
+    #[default]
+    Green,
+    Grey,
+}
+
+impl DmgPalette {
+    pub const ALL_CASES: [Self; 2] = [Self::Green, Self::Grey];
+
+    #[must_use]
+    pub const fn colors(self) -> [Color; 4] {
+        match self {
+            Self::Green => Color::DMG_GREEN_PALETTE,
+            Self::Grey => Color::DMG_GREY_PALETTE,
+        }
+    }
+}
+
+/// How the CGB colors (RGB555) are converted to the output colors.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ColorCorrection {
+    /// Approximates the washed-out colors of the CGB screen.
+    #[default]
+    Accurate,
+    /// Raw colors, more saturated than on hardware.
+    Disabled,
+}
+
+impl ColorCorrection {
+    pub const ALL_CASES: [Self; 2] = [Self::Accurate, Self::Disabled];
+
+    #[must_use]
+    pub const fn convert(self, value: u16) -> Color {
+        match self {
+            Self::Accurate => Color::from_rgb555_accurate(value),
+            Self::Disabled => Color::from_rgb555_scaled(value),
+        }
+    }
+}
@@ -3,4 +3,5 @@ pub mod button;
 pub mod color;
 pub mod events;
 pub mod macros;
+pub mod scheduler;
 pub mod screen;
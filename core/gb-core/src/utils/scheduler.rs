@@ -0,0 +1,186 @@
+/// Components that are not ticked every T-cycle, but caught up when needed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Event {
+    /// TIMA overflows, on the CPU clock.
+    Timer,
+    /// PPU mode changes and STAT interrupts, on the dot clock.
+    Ppu,
+    /// Audio samples, on the dot clock. Nothing else in the APU is observable without a register access.
+    Apu,
+}
+
+impl Event {
+    const COUNT: usize = 3;
+
+    const fn index(self) -> usize {
+        self as usize
+    }
+
+    const fn runs_on_cpu_clock(self) -> bool {
+        matches!(self, Self::Timer)
+    }
+}
+
+/// Keeps the time of both clocks and when each component has to be caught up next.
+///
+/// The CPU clock (4 T-cycles per M-cycle) drives the timer,
+/// while the dot clock (2 T-cycles per M-cycle in double speed mode) drives the PPU and the APU.
+#[derive(Debug, Default)]
+pub struct Scheduler {
+    cpu_cycles: u64,
+    dots: u64,
+
+    /// When each component was caught up last, in its own clock.
+    synced: [u64; Event::COUNT],
+    /// When each component has to be caught up, in its own clock.
+    deadlines: [u64; Event::COUNT],
+
+    /// The earliest deadline of each clock.
+    next_cpu_deadline: u64,
+    next_dot_deadline: u64,
+}
+
+impl Scheduler {
+    pub const NEVER: u64 = u64::MAX;
+
+    /// Single speed T-cycles since the system was turned on.
+    #[must_use]
+    pub fn dots(&self) -> u64 {
+        self.dots
+    }
+
+    /// M-cycles since the system was turned on.
+    #[must_use]
+    pub fn m_cycles(&self) -> u64 {
+        self.cpu_cycles / 4
+    }
+
+    /// Advances both clocks by an M-cycle.
+    pub fn advance(&mut self, double_speed: bool) {
+        self.advance_by(1, double_speed);
+    }
+
+    /// Advances both clocks by `m_cycles` M-cycles at once.
+    pub fn advance_by(&mut self, m_cycles: u64, double_speed: bool) {
+        self.cpu_cycles += 4 * m_cycles;
+        self.dots += Self::dots_per_m_cycle(double_speed) * m_cycles;
+    }
+
+    /// M-cycles that can pass before any component reaches its deadline.
+    #[must_use]
+    pub fn idle_m_cycles(&self, double_speed: bool) -> u64 {
+        let until_cpu_deadline = self
+            .next_cpu_deadline
+            .saturating_sub(self.cpu_cycles)
+            .div_ceil(4);
+
+        let until_dot_deadline = self
+            .next_dot_deadline
+            .saturating_sub(self.dots)
+            .div_ceil(Self::dots_per_m_cycle(double_speed));
+
+        // The M-cycle that reaches the deadline has to run normally.
+        until_cpu_deadline.min(until_dot_deadline).saturating_sub(1)
+    }
+
+    /// Whether any component has reached its deadline.
+    #[must_use]
+    pub fn pending(&self) -> bool {
+        self.cpu_cycles >= self.next_cpu_deadline || self.dots >= self.next_dot_deadline
+    }
+
+    #[must_use]
+    pub fn is_due(&self, event: Event) -> bool {
+        self.now(event) >= self.deadlines[event.index()]
+    }
+
+    /// Cycles elapsed since `event` was caught up last, and marks it as caught up.
+    pub fn take_elapsed(&mut self, event: Event) -> u64 {
+        let now = self.now(event);
+        let elapsed = now - self.synced[event.index()];
+        self.synced[event.index()] = now;
+
+        elapsed
+    }
+
+    /// Wakes `event` up `cycles` from now, or never with [`Scheduler::NEVER`].
+    pub fn schedule(&mut self, event: Event, cycles: u64) {
+        self.deadlines[event.index()] = self.now(event).saturating_add(cycles);
+        self.update_next_deadlines();
+    }
+
+    const fn dots_per_m_cycle(double_speed: bool) -> u64 {
+        if double_speed { 2 } else { 4 }
+    }
+
+    fn now(&self, event: Event) -> u64 {
+        if event.runs_on_cpu_clock() {
+            self.cpu_cycles
+        } else {
+            self.dots
+        }
+    }
+
+    fn update_next_deadlines(&mut self) {
+        self.next_cpu_deadline = self.deadlines[Event::Timer.index()];
+        self.next_dot_deadline =
+            self.deadlines[Event::Ppu.index()].min(self.deadlines[Event::Apu.index()]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_deadlines() {
+        let mut scheduler = Scheduler::default();
+        scheduler.schedule(Event::Timer, Scheduler::NEVER);
+        scheduler.schedule(Event::Ppu, 6);
+        scheduler.schedule(Event::Apu, Scheduler::NEVER);
+
+        scheduler.advance(false);
+        assert!(!scheduler.pending());
+
+        scheduler.advance(false);
+        assert!(scheduler.pending());
+        assert!(scheduler.is_due(Event::Ppu));
+        assert!(!scheduler.is_due(Event::Timer));
+
+        assert_eq!(scheduler.take_elapsed(Event::Ppu), 8);
+        assert_eq!(scheduler.take_elapsed(Event::Ppu), 0);
+    }
+
+    #[test]
+    fn test_double_speed_clocks() {
+        let mut scheduler = Scheduler::default();
+
+        scheduler.advance(true);
+        scheduler.advance(true);
+
+        assert_eq!(scheduler.take_elapsed(Event::Timer), 8);
+        assert_eq!(scheduler.take_elapsed(Event::Apu), 4);
+        assert_eq!(scheduler.dots(), 4);
+    }
+
+    #[test]
+    fn test_idle_m_cycles() {
+        let mut scheduler = Scheduler::default();
+        scheduler.schedule(Event::Timer, 64);
+        scheduler.schedule(Event::Ppu, 30);
+        scheduler.schedule(Event::Apu, Scheduler::NEVER);
+
+        // The PPU is due in the 8th M-cycle.
+        assert_eq!(scheduler.idle_m_cycles(false), 7);
+        // In double speed mode, it is due in the 15th M-cycle, but the timer is due in the 16th.
+        assert_eq!(scheduler.idle_m_cycles(true), 14);
+
+        scheduler.advance_by(7, false);
+        assert!(!scheduler.pending());
+        assert_eq!(scheduler.idle_m_cycles(false), 0);
+
+        scheduler.advance(false);
+        assert!(scheduler.pending());
+        assert_eq!(scheduler.idle_m_cycles(false), 0);
+    }
+}
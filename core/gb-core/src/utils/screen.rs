@@ -1,5 +1,8 @@
 use super::color::Color;
-use crate::constants::{SCREEN_HEIGHT, SCREEN_WIDTH, ScreenPixels};
+use crate::{
+    constants::{SCREEN_HEIGHT, SCREEN_WIDTH, ScreenPixels},
+    save_state::{SaveState, SaveStateError, StateReader, StateWriter},
+};
 
 pub struct Screen {
     pub pixels: Box<[Color; SCREEN_WIDTH * SCREEN_HEIGHT]>,
@@ -40,3 +43,26 @@ impl Screen {
         }
     }
 }
+
+impl SaveState for Screen {
+    fn save_state(&self, writer: &mut StateWriter) {
+        for pixel in self.pixels.iter() {
+            writer.write_raw(&[pixel.red, pixel.green, pixel.blue, pixel.alpha]);
+        }
+    }
+
+    fn load_state(&mut self, reader: &mut StateReader) -> Result<(), SaveStateError> {
+        for pixel in self.pixels.iter_mut() {
+            let [red, green, blue, alpha] = reader.read_array()?;
+
+            *pixel = Color {
+                alpha,
+                blue,
+                green,
+                red,
+            };
+        }
+
+        Ok(())
+    }
+}
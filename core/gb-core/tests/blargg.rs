@@ -19,8 +19,6 @@ testcases_blargg_serial! {
     // cpu_instrs_all("cpu_instrs/cpu_instrs.gb"); // Very slow
 
     instr_timing("instr_timing/instr_timing.gb");
-
-    // mem_timing("mem_timing/mem_timing.gb"); // TODO: no programmatic way of stopping
 }
 
 testcases_blargg_memory! {
@@ -56,6 +54,21 @@ testcases_blargg_memory! {
     // cgb_sound_12_wave("cgb_sound/rom_singles/12-wave.gb");
 }
 
+/// Stops on whichever output the ROM reports through, or once it loops forever.
+#[test]
+fn mem_timing() {
+    let path = concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/../../",
+        "external/gameboy-test-roms/blargg/",
+        "mem_timing/mem_timing.gb"
+    );
+
+    let rom = std::fs::read(path).unwrap();
+
+    run_for_model!(common::blargg::run_any, rom);
+}
+
 #[test]
 fn test_interrupt_time() {
     let name = "blargg_interrupt_time";
@@ -1,5 +1,5 @@
 use common::{runners::run_until_break, validators::validate_screenshot};
-use gb_core::{GameBoy, constants::DeviceModel};
+use gb_core::{GameBoy, components::ppu::renderer::Renderer, constants::DeviceModel};
 
 mod common;
 
@@ -21,3 +21,23 @@ fn test_cgb_acid2() {
     run_until_break(&mut gb).unwrap();
     validate_screenshot(&gb, name).unwrap();
 }
+
+#[test]
+fn test_cgb_acid2_fifo() {
+    let name = "cgb-acid2";
+
+    let path = concat!(
+        env!("CARGO_MANIFEST_DIR"),
+        "/../../",
+        "external/gameboy-test-roms/",
+        "cgb-acid2.gbc"
+    );
+    let rom = std::fs::read(path).unwrap();
+
+    let mut gb = GameBoy::new(DeviceModel::Cgb);
+    gb.load(None, rom.into()).unwrap();
+    gb.set_ppu_renderer(Renderer::Fifo);
+
+    run_until_break(&mut gb).unwrap();
+    validate_screenshot(&gb, name).unwrap();
+}
@@ -2,7 +2,12 @@ use gb_core::constants::DeviceModel;
 
 use super::{
     error::Error,
-    runners::{run_test, run_until_memory_status, run_until_serial_passed},
+    runners::{
+        run_test,
+        run_until_blargg_result,
+        run_until_memory_status,
+        run_until_serial_passed,
+    },
 };
 
 pub fn run_serial(model: DeviceModel, rom: &[u8]) -> Result<(), Error> {
@@ -20,3 +25,11 @@ pub fn run_memory(model: DeviceModel, rom: &[u8]) -> Result<(), Error> {
         Ok(())
     })
 }
+
+pub fn run_any(model: DeviceModel, rom: &[u8]) -> Result<(), Error> {
+    run_test(model, rom, |gb| {
+        run_until_blargg_result(gb)?;
+
+        Ok(())
+    })
+}
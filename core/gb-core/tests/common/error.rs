@@ -1,4 +1,4 @@
-use gb_core::components::cartridge::error::CartridgeError;
+use gb_core::error::GameBoyError;
 use thiserror::Error;
 
 #[derive(Debug, Error)]
@@ -13,8 +13,8 @@ pub enum Error {
     FibonacciValidationFailure,
     #[error("Assertion failed. The snapshot does not match the expected one.")]
     SnapshotMismatch,
-    #[error("Cartridge error: {0:?}")]
-    CartridgeError(#[from] CartridgeError),
+    #[error("Load error: {0:?}")]
+    LoadError(#[from] GameBoyError),
     #[error("Internal image error: {0:?}")]
     ImageError(#[from] image::ImageError),
 }
@@ -20,9 +20,17 @@ macro_rules! run_for_model {
 
 #[macro_export]
 macro_rules! testcases_mooneye {
-    ($name:ident($path:literal $(, $model:ident)?);) => {
+    (renderer: $renderer:ident; $name:ident($path:literal $(, $model:ident)?);) => {
         #[test]
         fn $name() {
+            fn run(
+                model: gb_core::constants::DeviceModel,
+                rom: &[u8],
+            ) -> Result<(), common::error::Error> {
+                let renderer = gb_core::components::ppu::renderer::Renderer::$renderer;
+                common::mooneye::run(model, rom, renderer)
+            }
+
             let path = concat!(
                 env!("CARGO_MANIFEST_DIR"),
                 "/../../",
@@ -32,16 +40,21 @@ macro_rules! testcases_mooneye {
             );
             let rom = std::fs::read(path).unwrap();
 
-            run_for_model!($($model, )? common::mooneye::run, rom);
+            run_for_model!($($model, )? run, rom);
         }
     };
 
     (
+        renderer: $renderer:ident;
         $name:ident($path:literal $(, $model:ident)?);
         $($names:ident($paths:literal $(, $models:ident)?);)+
     ) => {
-        testcases_mooneye! { $name($path $(, $model)?); }
-        testcases_mooneye! { $($names($paths $(, $models)?);)+ }
+        testcases_mooneye! { renderer: $renderer; $name($path $(, $model)?); }
+        testcases_mooneye! { renderer: $renderer; $($names($paths $(, $models)?);)+ }
+    };
+
+    ($($names:ident($paths:literal $(, $models:ident)?);)+) => {
+        testcases_mooneye! { renderer: Scanline; $($names($paths $(, $models)?);)+ }
     };
 }
 
@@ -1,4 +1,4 @@
-use gb_core::constants::DeviceModel;
+use gb_core::{components::ppu::renderer::Renderer, constants::DeviceModel};
 
 use super::{
     error::Error,
@@ -6,8 +6,9 @@ use super::{
     validators::validate_fibonacci,
 };
 
-pub fn run(model: DeviceModel, rom: &[u8]) -> Result<(), Error> {
+pub fn run(model: DeviceModel, rom: &[u8], renderer: Renderer) -> Result<(), Error> {
     run_test(model, rom, |gb| {
+        gb.set_ppu_renderer(renderer);
         run_until_break(gb)?;
         validate_fibonacci(gb)?;
 
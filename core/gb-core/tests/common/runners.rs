@@ -1,15 +1,23 @@
-use std::{
-    sync::mpsc,
-    time::{Duration, Instant},
+use std::time::{Duration, Instant};
+
+use gb_core::{
+    GameBoy,
+    constants::DeviceModel,
+    debugger::{
+        Condition,
+        stop::{ConditionRunner, Outcome, StopConditions},
+    },
 };
 
-use gb_core::{GameBoy, components::memory::MemoryInterface as _, constants::DeviceModel};
-
 use super::error::Error;
 
 const TIMEOUT: Duration = Duration::from_secs(20);
 const BREAK_OPCODE: u8 = 0x40; // LD B,B
 
+/// The blargg memory output is done: the signature is there and the status is no longer 0x80.
+const BLARGG_MEMORY_STATUS: &str =
+    "[0xA001] == 0xDE && [0xA002] == 0xB0 && [0xA003] == 0x61 && [0xA000] != 0x80";
+
 pub fn run_test<F>(device_model: DeviceModel, rom: &[u8], runner: F) -> Result<(), Error>
 where
     F: FnOnce(&mut GameBoy) -> Result<(), Error>,
@@ -22,81 +30,103 @@ where
     Ok(())
 }
 
-pub fn run_until_break(gb: &mut GameBoy) -> Result<(), Error> {
+/// Runs until one of the `conditions` is met, or fails after [`TIMEOUT`].
+pub fn run_until(
+    gb: &mut GameBoy,
+    conditions: StopConditions,
+) -> Result<(ConditionRunner, Outcome), Error> {
+    let mut runner = ConditionRunner::new(gb, conditions);
     let start_time = Instant::now();
 
-    while gb.memory().read(gb.cpu().registers().pc) != BREAK_OPCODE {
-        gb.step();
+    loop {
+        if let Some(outcome) = runner.run_frame(gb) {
+            return Ok((runner, outcome));
+        }
 
         if start_time.elapsed() > TIMEOUT {
             return Err(Error::Timeout);
         }
     }
+}
+
+pub fn run_until_break(gb: &mut GameBoy) -> Result<(), Error> {
+    run_until(
+        gb,
+        StopConditions {
+            opcodes: vec![BREAK_OPCODE],
+            ..Default::default()
+        },
+    )?;
 
     Ok(())
 }
 
 pub fn run_until_serial_passed(gb: &mut GameBoy) -> Result<(), Error> {
-    let (sender, receiver) = mpsc::channel::<u8>();
-
-    let mut output = String::default();
-    gb.add_serial_channel(sender);
-
-    let start_time = Instant::now();
-
-    loop {
-        for _ in 0..30 {
-            gb.run_frame();
-        }
-
-        while let Ok(ch) = receiver.try_recv() {
-            output.push(ch as char);
-        }
-
-        if output.contains("Passed") {
-            return Ok(());
-        }
-
-        if output.contains("Failed") {
-            return Err(Error::SerialOutputFailure(output));
-        }
-
-        if start_time.elapsed() > TIMEOUT {
-            return Err(Error::Timeout);
-        }
-    }
+    let (runner, _) = run_until(
+        gb,
+        StopConditions {
+            serial: vec!["Passed".to_owned(), "Failed".to_owned()],
+            ..Default::default()
+        },
+    )?;
+
+    check_serial_output(&runner)
 }
 
 pub fn run_until_memory_status(gb: &mut GameBoy) -> Result<(), Error> {
-    let start_time = Instant::now();
+    run_until(
+        gb,
+        StopConditions {
+            expressions: vec![blargg_memory_status()],
+            ..Default::default()
+        },
+    )?;
+
+    check_memory_output(gb)
+}
 
-    loop {
-        for _ in 0..30 {
-            gb.run_frame();
-        }
+/// For the blargg ROMs that might report through either output,
+/// or just stop in an infinite loop once they're done.
+pub fn run_until_blargg_result(gb: &mut GameBoy) -> Result<(), Error> {
+    let (runner, outcome) = run_until(
+        gb,
+        StopConditions {
+            serial: vec!["Passed".to_owned(), "Failed".to_owned()],
+            expressions: vec![blargg_memory_status()],
+            infinite_loop: true,
+            ..Default::default()
+        },
+    )?;
+
+    match outcome {
+        Outcome::Expression(_) => check_memory_output(gb),
+        _ => check_serial_output(&runner),
+    }
+}
 
-        let valid = [(0xA001, 0xDE), (0xA002, 0xB0), (0xA003, 0x61)]
-            .into_iter()
-            .all(|(address, value)| gb.memory().read(address) == value);
+fn blargg_memory_status() -> Condition {
+    Condition::parse(BLARGG_MEMORY_STATUS).unwrap()
+}
 
-        if valid && gb.memory().read(0xA000) != 0x80 {
-            break;
-        }
+fn check_serial_output(runner: &ConditionRunner) -> Result<(), Error> {
+    let output = String::from_utf8_lossy(runner.serial_output()).into_owned();
 
-        if start_time.elapsed() > TIMEOUT {
-            return Err(Error::Timeout);
-        }
+    if output.contains("Passed") {
+        Ok(())
+    } else {
+        Err(Error::SerialOutputFailure(output))
     }
+}
 
+/// The text is null-terminated, starting at 0xA004.
+fn check_memory_output(gb: &GameBoy) -> Result<(), Error> {
+    let memory = gb.memory();
     let output = (0xA004..=0xBFFF)
+        .map(|address| memory.peek(address))
         .take_while(|value| *value != 0)
-        .map(|address| gb.memory().read(address) as char)
+        .map(char::from)
         .collect::<String>();
 
-    if output.contains("Passed") {
-        return Ok(());
-    }
-
     if output.contains("Failed") {
         return Err(Error::MemoryOutputFailure(output));
     }
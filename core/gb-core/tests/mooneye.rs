@@ -59,11 +59,11 @@ testcases_mooneye! {
     oam_dma_basic("acceptance/oam_dma/basic.gb");
     oam_dma_reg_read("acceptance/oam_dma/reg_read.gb");
     oam_dma_sources_gs("acceptance/oam_dma/sources-GS.gb"); // ! This should fail on CGB
-    // ppu_hblank_ly_scx_timing_gs("acceptance/ppu/hblank_ly_scx_timing-GS.gb");
+    // ppu_hblank_ly_scx_timing_gs("acceptance/ppu/hblank_ly_scx_timing-GS.gb"); // FIFO only
     ppu_intr_1_2_timing_gs("acceptance/ppu/intr_1_2_timing-GS.gb"); // ! This should fail on CGB
     ppu_intr_2_0_timing("acceptance/ppu/intr_2_0_timing.gb");
     ppu_intr_2_mode0_timing("acceptance/ppu/intr_2_mode0_timing.gb");
-    // ppu_intr_2_mode0_timing_sprites("acceptance/ppu/intr_2_mode0_timing_sprites.gb");
+    // ppu_intr_2_mode0_timing_sprites("acceptance/ppu/intr_2_mode0_timing_sprites.gb"); // FIFO only
     ppu_intr_2_mode3_timing("acceptance/ppu/intr_2_mode3_timing.gb");
     ppu_intr_2_oam_ok_timing("acceptance/ppu/intr_2_oam_ok_timing.gb");
     // ppu_lcdon_timing_gs("acceptance/ppu/lcdon_timing-GS.gb");
@@ -87,6 +87,26 @@ testcases_mooneye! {
     timer_tma_write_reloading("acceptance/timer/tma_write_reloading.gb");
 }
 
+// PPU, with the FIFO renderer
+testcases_mooneye! {
+    renderer: Fifo;
+
+    ppu_hblank_ly_scx_timing_gs_fifo("acceptance/ppu/hblank_ly_scx_timing-GS.gb");
+    ppu_intr_1_2_timing_gs_fifo("acceptance/ppu/intr_1_2_timing-GS.gb");
+    ppu_intr_2_0_timing_fifo("acceptance/ppu/intr_2_0_timing.gb");
+    ppu_intr_2_mode0_timing_fifo("acceptance/ppu/intr_2_mode0_timing.gb");
+    ppu_intr_2_mode0_timing_sprites_fifo("acceptance/ppu/intr_2_mode0_timing_sprites.gb");
+    ppu_intr_2_mode3_timing_fifo("acceptance/ppu/intr_2_mode3_timing.gb");
+    ppu_intr_2_oam_ok_timing_fifo("acceptance/ppu/intr_2_oam_ok_timing.gb");
+    ppu_vblank_stat_intr_gs_fifo("acceptance/ppu/vblank_stat_intr-GS.gb");
+
+    // Still failing, these don't depend on the renderer:
+    // ppu_lcdon_timing_gs_fifo("acceptance/ppu/lcdon_timing-GS.gb"); // The shorter first line after turning the LCD on
+    // ppu_lcdon_write_timing_gs_fifo("acceptance/ppu/lcdon_write_timing-GS.gb"); // Same
+    // ppu_stat_irq_blocking_fifo("acceptance/ppu/stat_irq_blocking.gb"); // The STAT interrupt line is not shared by its sources
+    // ppu_stat_lyc_onoff_fifo("acceptance/ppu/stat_lyc_onoff.gb"); // LY=LYC is not updated when the LCD is turned on/off
+}
+
 // MBC
 testcases_mooneye! {
     // MBC1
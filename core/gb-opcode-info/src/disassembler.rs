@@ -0,0 +1,351 @@
+use std::fmt;
+
+use crate::{globals::opcodes, opcodes::Opcode};
+
+const CB_PREFIX: u8 = 0xCB;
+const MAX_INSTRUCTION_LENGTH: usize = 3;
+
+const BRANCH_MNEMONICS: [&str; 4] = ["JR", "JP", "CALL", "RET"];
+const CONDITIONS: [&str; 4] = ["NZ", "Z", "NC", "C"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operand {
+    /// 8-bit or 16-bit register (`A`, `HL`, `SP`, ...).
+    Register(&'static str),
+    /// Memory pointed by a register (`(HL)`, `(HL+)`, `(BC)`, ...).
+    Indirect(&'static str),
+    /// Branch condition (`NZ`, `Z`, `NC`, `C`).
+    Condition(&'static str),
+    /// Bit index of the CB-prefixed bit operations.
+    Bit(u8),
+    Immediate8(u8),
+    Immediate16(u16),
+    /// Signed immediate (`ADD SP,i8`).
+    Signed8(i8),
+    /// `SP` plus a signed immediate (`LD HL,SP+i8`).
+    SpOffset(i8),
+    /// Memory at an absolute address (`(u16)`).
+    Absolute(u16),
+    /// Memory at `0xFF00` plus an immediate (`(FF00+u8)`).
+    HighPage(u8),
+    /// Memory at `0xFF00` plus `C` (`(FF00+C)`).
+    HighPageC,
+    /// Resolved destination of a jump, call or restart.
+    Target(u16),
+}
+
+impl Operand {
+    /// Address referenced by this operand, if it is known without the CPU state.
+    #[must_use]
+    pub fn address(&self) -> Option<u16> {
+        match *self {
+            Self::Absolute(address) | Self::Target(address) => Some(address),
+            Self::HighPage(offset) => Some(0xFF00 | offset as u16),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Operand {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Self::Register(name) | Self::Condition(name) => write!(f, "{name}"),
+            Self::Indirect(name) => write!(f, "({name})"),
+            Self::Bit(bit) => write!(f, "{bit}"),
+            Self::Immediate8(value) => write!(f, "${value:02X}"),
+            Self::Immediate16(value) | Self::Target(value) => write!(f, "${value:04X}"),
+            Self::Signed8(value) => write!(f, "{value:+}"),
+            Self::SpOffset(value) => write!(f, "SP{value:+}"),
+            Self::Absolute(address) => write!(f, "(${address:04X})"),
+            Self::HighPage(offset) => write!(f, "($FF{offset:02X})"),
+            Self::HighPageC => write!(f, "($FF00+C)"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Instruction {
+    pub address: u16,
+    pub bytes: Vec<u8>,
+    pub mnemonic: &'static str,
+    pub operands: Vec<Operand>,
+    pub opcode: &'static Opcode,
+}
+
+impl Instruction {
+    #[must_use]
+    pub fn length(&self) -> u16 {
+        self.bytes.len() as u16
+    }
+
+    #[must_use]
+    pub fn next_address(&self) -> u16 {
+        self.address.wrapping_add(self.length())
+    }
+
+    /// Whether the opcode exists on the SM83.
+    #[must_use]
+    pub fn is_valid(&self) -> bool {
+        self.mnemonic != "UNUSED"
+    }
+
+    /// Whether the instruction may transfer control somewhere other than the next instruction.
+    #[must_use]
+    pub fn is_branch(&self) -> bool {
+        BRANCH_MNEMONICS.contains(&self.mnemonic) || matches!(self.mnemonic, "RETI" | "RST")
+    }
+
+    /// Statically known destination of a jump, call or restart.
+    #[must_use]
+    pub fn target(&self) -> Option<u16> {
+        self.operands.iter().find_map(|operand| {
+            match *operand {
+                Operand::Target(address) => Some(address),
+                _ => None,
+            }
+        })
+    }
+}
+
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if !self.is_valid() {
+            return write!(f, "DB ${:02X}", self.bytes[0]);
+        }
+
+        write!(f, "{}", self.mnemonic)?;
+
+        for (index, operand) in self.operands.iter().enumerate() {
+            let separator = if index == 0 { " " } else { "," };
+            write!(f, "{separator}{operand}")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Decodes the instruction at `address`, fetching its bytes through `read`.
+pub fn decode(address: u16, read: impl Fn(u16) -> u8) -> Instruction {
+    let table = opcodes();
+
+    let first = read(address);
+
+    let (opcode, prefix_length) = if first == CB_PREFIX {
+        let second = read(address.wrapping_add(1));
+        (&table.cb_prefixed[second as usize], 2)
+    } else {
+        (&table.unprefixed[first as usize], 1)
+    };
+
+    let length = u16::try_from(opcode.length)
+        .unwrap_or_default()
+        .max(prefix_length);
+    let bytes = (0..length)
+        .map(|offset| read(address.wrapping_add(offset)))
+        .collect::<Vec<_>>();
+
+    let (mnemonic, operands) = match opcode.name.split_once(' ') {
+        Some((mnemonic, operands)) => (mnemonic, operands.split(',').collect()),
+        None => (opcode.name.as_str(), Vec::new()),
+    };
+
+    let operands = operands
+        .iter()
+        .enumerate()
+        .map(|(index, operand)| parse_operand(mnemonic, index, operand, address, &bytes))
+        .collect();
+
+    Instruction {
+        address,
+        bytes,
+        mnemonic,
+        operands,
+        opcode,
+    }
+}
+
+/// Decodes `count` consecutive instructions starting at `address`.
+pub fn disassemble(address: u16, count: usize, read: impl Fn(u16) -> u8) -> Vec<Instruction> {
+    let mut instructions = Vec::with_capacity(count);
+    let mut current = address;
+
+    for _ in 0..count {
+        let instruction = decode(current, &read);
+        current = instruction.next_address();
+        instructions.push(instruction);
+    }
+
+    instructions
+}
+
+/// Decodes up to `before` instructions leading to `address`, the instruction at `address`
+/// and `after` instructions following it.
+///
+/// Instruction boundaries can't be known when walking backwards, so the start is chosen as the
+/// furthest address whose linear decoding lands exactly on `address`.
+pub fn disassemble_around(
+    address: u16,
+    before: usize,
+    after: usize,
+    read: impl Fn(u16) -> u8,
+) -> Vec<Instruction> {
+    let mut instructions = preceding(address, before, &read);
+    instructions.extend(disassemble(address, after + 1, &read));
+
+    instructions
+}
+
+fn preceding(address: u16, count: usize, read: impl Fn(u16) -> u8) -> Vec<Instruction> {
+    let max_distance = (count * MAX_INSTRUCTION_LENGTH).min(address as usize);
+
+    let mut best = Vec::new();
+
+    for distance in (1..=max_distance).rev() {
+        let mut current = address as usize - distance;
+        let mut instructions = Vec::new();
+
+        while current < address as usize {
+            let instruction = decode(current as u16, &read);
+            current += instruction.length() as usize;
+            instructions.push(instruction);
+        }
+
+        if current == address as usize && instructions.len() > best.len() {
+            best = instructions;
+
+            if best.len() >= count {
+                break;
+            }
+        }
+    }
+
+    let excess = best.len().saturating_sub(count);
+    best.drain(..excess);
+
+    best
+}
+
+fn parse_operand(
+    mnemonic: &str,
+    index: usize,
+    operand: &'static str,
+    address: u16,
+    bytes: &[u8],
+) -> Operand {
+    let immediate8 = || bytes[1];
+    let immediate16 = || u16::from_le_bytes([bytes[1], bytes[2]]);
+
+    match operand {
+        "u8" => Operand::Immediate8(immediate8()),
+        "u16" if matches!(mnemonic, "JP" | "CALL") => Operand::Target(immediate16()),
+        "u16" => Operand::Immediate16(immediate16()),
+        "i8" if mnemonic == "JR" => {
+            let offset = immediate8() as i8;
+            let target = address
+                .wrapping_add(bytes.len() as u16)
+                .wrapping_add_signed(offset as i16);
+
+            Operand::Target(target)
+        }
+        "i8" => Operand::Signed8(immediate8() as i8),
+        "SP+i8" => Operand::SpOffset(immediate8() as i8),
+        "(u16)" => Operand::Absolute(immediate16()),
+        "(FF00+u8)" => Operand::HighPage(immediate8()),
+        "(FF00+C)" => Operand::HighPageC,
+        _ if mnemonic == "RST" => {
+            let vector = operand.trim_end_matches('h');
+            Operand::Target(u16::from_str_radix(vector, 16).unwrap_or_default())
+        }
+        _ if index == 0 && matches!(mnemonic, "BIT" | "RES" | "SET") => {
+            Operand::Bit(operand.parse().unwrap_or_default())
+        }
+        _ if BRANCH_MNEMONICS.contains(&mnemonic) && CONDITIONS.contains(&operand) => {
+            Operand::Condition(operand)
+        }
+        _ if operand.starts_with('(') => Operand::Indirect(&operand[1..operand.len() - 1]),
+        _ => Operand::Register(operand),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reader(memory: &[u8]) -> impl Fn(u16) -> u8 + '_ {
+        |address| memory.get(address as usize).copied().unwrap_or(0xFF)
+    }
+
+    #[test]
+    fn test_decode_operands() {
+        let memory = [
+            0x3E, 0x42, // LD A,$42
+            0xEA, 0x00, 0xC0, // LD ($C000),A
+            0xE0, 0x44, // LD ($FF44),A
+            0xF8, 0xFE, // LD HL,SP-2
+            0xCB, 0x7C, // BIT 7,H
+            0x22, // LD (HL+),A
+            0xD3, // Unused
+        ];
+
+        let instructions = disassemble(0, 7, reader(&memory));
+        let text = instructions
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            text,
+            [
+                "LD A,$42",
+                "LD ($C000),A",
+                "LD ($FF44),A",
+                "LD HL,SP-2",
+                "BIT 7,H",
+                "LD (HL+),A",
+                "DB $D3",
+            ]
+        );
+
+        assert_eq!(instructions[2].operands[0].address(), Some(0xFF44));
+        assert_eq!(instructions[4].length(), 2);
+        assert!(!instructions[6].is_valid());
+    }
+
+    #[test]
+    fn test_branch_targets() {
+        let mut memory = vec![0; 0x200];
+        memory[0x150..0x158].copy_from_slice(&[
+            0x20, 0xFE, // JR NZ,$0150
+            0xC3, 0x00, 0x01, // JP $0100
+            0xD8, // RET C
+            0xFF, // RST $38
+            0xE9, // JP HL
+        ]);
+
+        let instructions = disassemble(0x150, 5, reader(&memory));
+
+        assert_eq!(instructions[0].to_string(), "JR NZ,$0150");
+        assert_eq!(instructions[0].target(), Some(0x150));
+        assert_eq!(instructions[1].target(), Some(0x100));
+        assert_eq!(instructions[2].operands, [Operand::Condition("C")]);
+        assert_eq!(instructions[3].target(), Some(0x38));
+        assert_eq!(instructions[4].target(), None);
+        assert!(instructions.iter().all(Instruction::is_branch));
+    }
+
+    #[test]
+    fn test_disassemble_around() {
+        let memory = [
+            0x00, // NOP
+            0x01, 0x34, 0x12, // LD BC,$1234
+            0x3E, 0x01, // LD A,$01
+            0xCB, 0x37, // SWAP A
+            0xC9, // RET
+        ];
+
+        let instructions = disassemble_around(6, 2, 1, reader(&memory));
+        let addresses = instructions.iter().map(|i| i.address).collect::<Vec<_>>();
+
+        assert_eq!(addresses, [1, 4, 6, 8]);
+    }
+}
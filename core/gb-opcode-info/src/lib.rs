@@ -1,4 +1,6 @@
 pub use globals::opcodes;
+pub use opcodes::{Opcode, OpcodeTable};
 
+pub mod disassembler;
 mod globals;
 mod opcodes;
@@ -1,6 +1,6 @@
 use serde::Deserialize;
 
-#[derive(Deserialize)]
+#[derive(Debug, Deserialize)]
 pub struct OpcodeTable {
     #[serde(rename = "Unprefixed")]
     pub unprefixed: Vec<Opcode>,
@@ -9,7 +9,7 @@ pub struct OpcodeTable {
     pub cb_prefixed: Vec<Opcode>,
 }
 
-#[derive(Deserialize)]
+#[derive(Debug, Deserialize)]
 pub struct Opcode {
     #[serde(rename = "Name")]
     pub name: String,